@@ -14,6 +14,25 @@ mod m20251018_204207_add_project_note_history;
 mod m20251027_205543_create_project_note_parts;
 mod m20251027_212252_create_project_note_tag;
 mod m20251112_175958_add_notifcation_settings_table;
+mod m20261019_101500_add_point_scale_to_project;
+mod m20261019_120000_create_estimation_sessions;
+mod m20261019_130000_add_recurrence_to_issue;
+mod m20261019_140000_add_notification_channels;
+mod m20261019_150000_create_notification_preferences;
+mod m20261019_160000_create_notification_digest;
+mod m20261019_170000_create_mentions;
+mod m20261019_180000_add_notification_archiving;
+mod m20261019_190000_create_notification_deliveries;
+mod m20261019_200000_create_webhooks;
+mod m20261019_210000_create_repository_integrations;
+mod m20261019_220000_create_domain_events;
+mod m20261019_230000_add_file_upload_checksums;
+mod m20261019_233000_create_upload_sessions;
+mod m20261019_234000_create_direct_uploads;
+mod m20261019_235000_add_file_upload_thumbnails;
+mod m20261019_235500_create_upload_quarantine;
+mod m20261019_235600_add_active_estimation_session_index;
+mod m20261019_235700_add_upload_session_write_lease;
 
 pub struct Migrator;
 
@@ -35,6 +54,25 @@ impl MigratorTrait for Migrator {
             Box::new(m20251027_205543_create_project_note_parts::Migration),
             Box::new(m20251027_212252_create_project_note_tag::Migration),
             Box::new(m20251112_175958_add_notifcation_settings_table::Migration),
+            Box::new(m20261019_101500_add_point_scale_to_project::Migration),
+            Box::new(m20261019_120000_create_estimation_sessions::Migration),
+            Box::new(m20261019_130000_add_recurrence_to_issue::Migration),
+            Box::new(m20261019_140000_add_notification_channels::Migration),
+            Box::new(m20261019_150000_create_notification_preferences::Migration),
+            Box::new(m20261019_160000_create_notification_digest::Migration),
+            Box::new(m20261019_170000_create_mentions::Migration),
+            Box::new(m20261019_180000_add_notification_archiving::Migration),
+            Box::new(m20261019_190000_create_notification_deliveries::Migration),
+            Box::new(m20261019_200000_create_webhooks::Migration),
+            Box::new(m20261019_210000_create_repository_integrations::Migration),
+            Box::new(m20261019_220000_create_domain_events::Migration),
+            Box::new(m20261019_230000_add_file_upload_checksums::Migration),
+            Box::new(m20261019_233000_create_upload_sessions::Migration),
+            Box::new(m20261019_234000_create_direct_uploads::Migration),
+            Box::new(m20261019_235000_add_file_upload_thumbnails::Migration),
+            Box::new(m20261019_235500_create_upload_quarantine::Migration),
+            Box::new(m20261019_235600_add_active_estimation_session_index::Migration),
+            Box::new(m20261019_235700_add_upload_session_write_lease::Migration),
         ]
     }
 }
@@ -0,0 +1,201 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(EstimationSession::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EstimationSession::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationSession::IssueId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationSession::ProjectId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationSession::CreatedById)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationSession::State)
+                            .string()
+                            .not_null()
+                            .default("open"),
+                    )
+                    .col(ColumnDef::new(EstimationSession::FinalPoints).integer())
+                    .col(
+                        ColumnDef::new(EstimationSession::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationSession::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_estimation_session_issue")
+                            .from(EstimationSession::Table, EstimationSession::IssueId)
+                            .to(Issue::Table, Issue::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_estimation_session_project")
+                            .from(EstimationSession::Table, EstimationSession::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_estimation_session_created_by")
+                            .from(EstimationSession::Table, EstimationSession::CreatedById)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_estimation_session_issue_id")
+                    .table(EstimationSession::Table)
+                    .col(EstimationSession::IssueId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(EstimationVote::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EstimationVote::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationVote::SessionId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(EstimationVote::UserId).integer().not_null())
+                    .col(ColumnDef::new(EstimationVote::Points).integer().not_null())
+                    .col(
+                        ColumnDef::new(EstimationVote::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EstimationVote::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_estimation_vote_session")
+                            .from(EstimationVote::Table, EstimationVote::SessionId)
+                            .to(EstimationSession::Table, EstimationSession::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_estimation_vote_user")
+                            .from(EstimationVote::Table, EstimationVote::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // One vote per member per round; re-voting updates the existing row
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_estimation_vote_session_user_unique")
+                    .table(EstimationVote::Table)
+                    .col(EstimationVote::SessionId)
+                    .col(EstimationVote::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EstimationVote::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(EstimationSession::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EstimationSession {
+    Table,
+    Id,
+    IssueId,
+    ProjectId,
+    CreatedById,
+    State,
+    FinalPoints,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EstimationVote {
+    Table,
+    Id,
+    SessionId,
+    UserId,
+    Points,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Issue {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,230 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // A project can now have several channels, so its settings rows are no longer unique
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_settings_application_project_unique")
+                    .table(NotificationSettings::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_settings_project_id")
+                    .table(NotificationSettings::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_settings_project_id")
+                    .table(NotificationSettings::Table)
+                    .col(NotificationSettings::ProjectId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One column per statement keeps the migration compatible with SQLite
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationSettings::Table)
+                    .add_column(
+                        ColumnDef::new(NotificationSettings::Channel)
+                            .string()
+                            .not_null()
+                            .default("gotify"),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationSettings::Table)
+                    .add_column(ColumnDef::new(NotificationSettings::Config).text().null())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationSettings::Table)
+                    .add_column(
+                        ColumnDef::new(NotificationSettings::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(UserNotificationChannel::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::Channel)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::Config)
+                            .text()
+                            .null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UserNotificationChannel::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_user_notification_channel_user")
+                            .from(
+                                UserNotificationChannel::Table,
+                                UserNotificationChannel::UserId,
+                            )
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_user_notification_channel_user_id")
+                    .table(UserNotificationChannel::Table)
+                    .col(UserNotificationChannel::UserId)
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(UserNotificationChannel::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        for column in [
+            NotificationSettings::Enabled,
+            NotificationSettings::Config,
+            NotificationSettings::Channel,
+        ] {
+            manager
+                .alter_table(
+                    Table::alter()
+                        .table(NotificationSettings::Table)
+                        .drop_column(column)
+                        .to_owned(),
+                )
+                .await?;
+        }
+
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_settings_project_id")
+                    .table(NotificationSettings::Table)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_settings_project_id")
+                    .table(NotificationSettings::Table)
+                    .col(NotificationSettings::ProjectId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_settings_application_project_unique")
+                    .table(NotificationSettings::Table)
+                    .col(NotificationSettings::ApplicationId)
+                    .col(NotificationSettings::ProjectId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationSettings {
+    Table,
+    ProjectId,
+    ApplicationId,
+    Channel,
+    Config,
+    Enabled,
+}
+
+#[derive(DeriveIden)]
+enum UserNotificationChannel {
+    Table,
+    Id,
+    UserId,
+    Channel,
+    Config,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
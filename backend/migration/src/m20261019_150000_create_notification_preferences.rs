@@ -0,0 +1,230 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationPreference::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NotificationPreference::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::ProjectId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::Comments)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::StatusChanges)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::IssueUpdates)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::Blockers)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::TaskUpdates)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(ColumnDef::new(NotificationPreference::Channels).text())
+                    .col(
+                        ColumnDef::new(NotificationPreference::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationPreference::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notification_preference_user")
+                            .from(
+                                NotificationPreference::Table,
+                                NotificationPreference::UserId,
+                            )
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notification_preference_project")
+                            .from(
+                                NotificationPreference::Table,
+                                NotificationPreference::ProjectId,
+                            )
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_preference_user_project_unique")
+                    .table(NotificationPreference::Table)
+                    .col(NotificationPreference::UserId)
+                    .col(NotificationPreference::ProjectId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(IssueSubscription::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IssueSubscription::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueSubscription::IssueId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueSubscription::UserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueSubscription::Watching)
+                            .boolean()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueSubscription::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_issue_subscription_issue")
+                            .from(IssueSubscription::Table, IssueSubscription::IssueId)
+                            .to(Issue::Table, Issue::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_issue_subscription_user")
+                            .from(IssueSubscription::Table, IssueSubscription::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // A user either watches or has muted an issue, never both
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_issue_subscription_issue_user_unique")
+                    .table(IssueSubscription::Table)
+                    .col(IssueSubscription::IssueId)
+                    .col(IssueSubscription::UserId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IssueSubscription::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(
+                Table::drop()
+                    .table(NotificationPreference::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreference {
+    Table,
+    Id,
+    UserId,
+    ProjectId,
+    Comments,
+    StatusChanges,
+    IssueUpdates,
+    Blockers,
+    TaskUpdates,
+    Channels,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum IssueSubscription {
+    Table,
+    Id,
+    IssueId,
+    UserId,
+    Watching,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Issue {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
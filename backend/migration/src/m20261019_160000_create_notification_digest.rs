@@ -0,0 +1,114 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(NotificationDigest::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(NotificationDigest::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationDigest::UserId)
+                            .integer()
+                            .not_null()
+                            .unique_key(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationDigest::Frequency)
+                            .string()
+                            .not_null()
+                            .default("daily"),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationDigest::Timezone)
+                            .string()
+                            .not_null()
+                            .default("UTC"),
+                    )
+                    .col(ColumnDef::new(NotificationDigest::LastSentAt).timestamp_with_time_zone())
+                    .col(
+                        ColumnDef::new(NotificationDigest::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(NotificationDigest::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_notification_digest_user")
+                            .from(NotificationDigest::Table, NotificationDigest::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Lets a project be left out of the digest without muting it in-app
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreference::Table)
+                    .add_column(
+                        ColumnDef::new(NotificationPreference::Digest)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(NotificationPreference::Table)
+                    .drop_column(NotificationPreference::Digest)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(NotificationDigest::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum NotificationDigest {
+    Table,
+    Id,
+    UserId,
+    Frequency,
+    Timezone,
+    LastSentAt,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum NotificationPreference {
+    Table,
+    Digest,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
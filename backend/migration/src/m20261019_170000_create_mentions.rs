@@ -0,0 +1,163 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(Mention::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(Mention::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(Mention::ProjectId).integer().not_null())
+                    .col(ColumnDef::new(Mention::IssueId).integer().not_null())
+                    // Null when the mention is in the issue description
+                    .col(ColumnDef::new(Mention::CommentId).integer())
+                    .col(ColumnDef::new(Mention::UserId).integer().not_null())
+                    .col(ColumnDef::new(Mention::MentionedById).integer().not_null())
+                    .col(
+                        ColumnDef::new(Mention::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mention_project")
+                            .from(Mention::Table, Mention::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mention_issue")
+                            .from(Mention::Table, Mention::IssueId)
+                            .to(Issue::Table, Issue::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mention_comment")
+                            .from(Mention::Table, Mention::CommentId)
+                            .to(Comment::Table, Comment::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mention_user")
+                            .from(Mention::Table, Mention::UserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_mention_mentioned_by")
+                            .from(Mention::Table, Mention::MentionedById)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mention_user_project")
+                    .table(Mention::Table)
+                    .col(Mention::UserId)
+                    .col(Mention::ProjectId)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_mention_issue_id")
+                    .table(Mention::Table)
+                    .col(Mention::IssueId)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Lets clients tell mentions and assignments apart from ordinary updates
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Notification::Table)
+                    .add_column(
+                        ColumnDef::new(Notification::Kind)
+                            .string()
+                            .not_null()
+                            .default("issue_update"),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Notification::Table)
+                    .drop_column(Notification::Kind)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .drop_table(Table::drop().table(Mention::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Mention {
+    Table,
+    Id,
+    ProjectId,
+    IssueId,
+    CommentId,
+    UserId,
+    MentionedById,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Notification {
+    Table,
+    Kind,
+}
+
+#[derive(DeriveIden)]
+enum Comment {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Issue {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
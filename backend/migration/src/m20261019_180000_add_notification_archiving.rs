@@ -0,0 +1,64 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Notification::Table)
+                    .add_column(
+                        ColumnDef::new(Notification::Archived)
+                            .boolean()
+                            .not_null()
+                            .default(false),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // Backs the unread count and the filtered inbox
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_notification_targeted_user_project_read")
+                    .table(Notification::Table)
+                    .col(Notification::TargetedUserId)
+                    .col(Notification::ProjectId)
+                    .col(Notification::Read)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_notification_targeted_user_project_read")
+                    .table(Notification::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(Notification::Table)
+                    .drop_column(Notification::Archived)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum Notification {
+    Table,
+    TargetedUserId,
+    ProjectId,
+    Read,
+    Archived,
+}
@@ -0,0 +1,203 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(RepositoryIntegration::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::ProjectId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::Provider)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::Secret)
+                            .string()
+                            .not_null(),
+                    )
+                    // Status that closing references move an issue to; null leaves status alone
+                    .col(ColumnDef::new(RepositoryIntegration::MergeStatus).integer())
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::Enabled)
+                            .boolean()
+                            .not_null()
+                            .default(true),
+                    )
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(RepositoryIntegration::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_repository_integration_project")
+                            .from(
+                                RepositoryIntegration::Table,
+                                RepositoryIntegration::ProjectId,
+                            )
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_table(
+                Table::create()
+                    .table(IssueRepositoryLink::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::IssueId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::IntegrationId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::Kind)
+                            .string()
+                            .not_null(),
+                    )
+                    // Commit sha or pull request number
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::ExternalId)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(IssueRepositoryLink::Title).text().not_null())
+                    .col(ColumnDef::new(IssueRepositoryLink::Url).text())
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::State)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(IssueRepositoryLink::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_issue_repository_link_issue")
+                            .from(IssueRepositoryLink::Table, IssueRepositoryLink::IssueId)
+                            .to(Issue::Table, Issue::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_issue_repository_link_integration")
+                            .from(
+                                IssueRepositoryLink::Table,
+                                IssueRepositoryLink::IntegrationId,
+                            )
+                            .to(RepositoryIntegration::Table, RepositoryIntegration::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        // The same commit pushed to several branches is linked once
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_issue_repository_link_unique")
+                    .table(IssueRepositoryLink::Table)
+                    .col(IssueRepositoryLink::IssueId)
+                    .col(IssueRepositoryLink::IntegrationId)
+                    .col(IssueRepositoryLink::Kind)
+                    .col(IssueRepositoryLink::ExternalId)
+                    .unique()
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(IssueRepositoryLink::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(RepositoryIntegration::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum RepositoryIntegration {
+    Table,
+    Id,
+    ProjectId,
+    Provider,
+    Secret,
+    MergeStatus,
+    Enabled,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum IssueRepositoryLink {
+    Table,
+    Id,
+    IssueId,
+    IntegrationId,
+    Kind,
+    ExternalId,
+    Title,
+    Url,
+    State,
+    CreatedAt,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum Issue {
+    Table,
+    Id,
+}
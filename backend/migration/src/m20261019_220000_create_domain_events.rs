@@ -0,0 +1,123 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .create_table(
+                Table::create()
+                    .table(DomainEvent::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DomainEvent::Sequence)
+                            .big_integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DomainEvent::ProjectId).integer().not_null())
+                    // Set on events meant for a single user
+                    .col(ColumnDef::new(DomainEvent::UserId).integer())
+                    .col(ColumnDef::new(DomainEvent::EventType).string().not_null())
+                    .col(ColumnDef::new(DomainEvent::Data).text().not_null())
+                    .col(
+                        ColumnDef::new(DomainEvent::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_domain_event_project")
+                            .from(DomainEvent::Table, DomainEvent::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_domain_event_project_sequence")
+                    .table(DomainEvent::Table)
+                    .col(DomainEvent::ProjectId)
+                    .col(DomainEvent::Sequence)
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_domain_event_created_at")
+                    .table(DomainEvent::Table)
+                    .col(DomainEvent::CreatedAt)
+                    .to_owned(),
+            )
+            .await?;
+
+        // Where each server-side consumer has read up to, so it resumes after a restart
+        manager
+            .create_table(
+                Table::create()
+                    .table(EventConsumer::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(EventConsumer::Name)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(EventConsumer::Sequence)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(EventConsumer::UpdatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(EventConsumer::Table).to_owned())
+            .await?;
+        manager
+            .drop_table(Table::drop().table(DomainEvent::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DomainEvent {
+    Table,
+    Sequence,
+    ProjectId,
+    UserId,
+    EventType,
+    Data,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum EventConsumer {
+    Table,
+    Name,
+    Sequence,
+    UpdatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
@@ -0,0 +1,96 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Uploads stored before this migration keep their own object and no checksum
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FileUpload::Table)
+                    .add_column(ColumnDef::new(FileUpload::Sha256).string())
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_file_upload_sha256")
+                    .table(FileUpload::Table)
+                    .col(FileUpload::Sha256)
+                    .to_owned(),
+            )
+            .await?;
+
+        // One stored object per distinct content, shared by every upload with that checksum
+        manager
+            .create_table(
+                Table::create()
+                    .table(FileBlob::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(FileBlob::Sha256)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(FileBlob::Path).string().not_null())
+                    .col(ColumnDef::new(FileBlob::SizeBytes).big_integer().not_null())
+                    .col(
+                        ColumnDef::new(FileBlob::RefCount)
+                            .integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(FileBlob::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(FileBlob::Table).to_owned())
+            .await?;
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_file_upload_sha256")
+                    .table(FileUpload::Table)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FileUpload::Table)
+                    .drop_column(FileUpload::Sha256)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FileUpload {
+    Table,
+    Sha256,
+}
+
+#[derive(DeriveIden)]
+enum FileBlob {
+    Table,
+    Sha256,
+    Path,
+    SizeBytes,
+    RefCount,
+    CreatedAt,
+}
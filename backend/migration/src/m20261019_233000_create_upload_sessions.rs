@@ -0,0 +1,122 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Resumable uploads in progress; the received bytes are kept in UPLOAD_TMP_DIR
+        manager
+            .create_table(
+                Table::create()
+                    .table(UploadSession::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UploadSession::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadSession::ProjectId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UploadSession::IssueId).integer())
+                    .col(ColumnDef::new(UploadSession::ProjectNoteId).integer())
+                    .col(
+                        ColumnDef::new(UploadSession::UploaderUserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadSession::OriginalFilename)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UploadSession::MimeType).string().not_null())
+                    .col(
+                        ColumnDef::new(UploadSession::SizeBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadSession::OffsetBytes)
+                            .big_integer()
+                            .not_null()
+                            .default(0),
+                    )
+                    .col(
+                        ColumnDef::new(UploadSession::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadSession::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_upload_session_project")
+                            .from(UploadSession::Table, UploadSession::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_upload_session_user")
+                            .from(UploadSession::Table, UploadSession::UploaderUserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_upload_session_expires_at")
+                    .table(UploadSession::Table)
+                    .col(UploadSession::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UploadSession::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UploadSession {
+    Table,
+    Id,
+    ProjectId,
+    IssueId,
+    ProjectNoteId,
+    UploaderUserId,
+    OriginalFilename,
+    MimeType,
+    SizeBytes,
+    OffsetBytes,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
@@ -0,0 +1,113 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Uploads the client sends straight to S3 with a presigned PUT, waiting to be finalized
+        manager
+            .create_table(
+                Table::create()
+                    .table(DirectUpload::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(DirectUpload::Id)
+                            .string()
+                            .not_null()
+                            .primary_key(),
+                    )
+                    .col(ColumnDef::new(DirectUpload::ProjectId).integer().not_null())
+                    .col(ColumnDef::new(DirectUpload::IssueId).integer())
+                    .col(ColumnDef::new(DirectUpload::ProjectNoteId).integer())
+                    .col(
+                        ColumnDef::new(DirectUpload::UploaderUserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DirectUpload::OriginalFilename)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DirectUpload::MimeType).string().not_null())
+                    .col(
+                        ColumnDef::new(DirectUpload::SizeBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(DirectUpload::Sha256).string().not_null())
+                    .col(
+                        ColumnDef::new(DirectUpload::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(DirectUpload::ExpiresAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_direct_upload_project")
+                            .from(DirectUpload::Table, DirectUpload::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_direct_upload_user")
+                            .from(DirectUpload::Table, DirectUpload::UploaderUserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_direct_upload_expires_at")
+                    .table(DirectUpload::Table)
+                    .col(DirectUpload::ExpiresAt)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(DirectUpload::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum DirectUpload {
+    Table,
+    Id,
+    ProjectId,
+    IssueId,
+    ProjectNoteId,
+    UploaderUserId,
+    OriginalFilename,
+    MimeType,
+    SizeBytes,
+    Sha256,
+    CreatedAt,
+    ExpiresAt,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
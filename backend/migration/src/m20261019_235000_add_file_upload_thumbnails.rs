@@ -0,0 +1,36 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Set by the thumbnail job once a preview has been rendered
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FileUpload::Table)
+                    .add_column(ColumnDef::new(FileUpload::ThumbnailPath).string())
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(FileUpload::Table)
+                    .drop_column(FileUpload::ThumbnailPath)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum FileUpload {
+    Table,
+    ThumbnailPath,
+}
@@ -0,0 +1,130 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Uploads the virus scanner flagged, kept aside in the store instead of being attached
+        manager
+            .create_table(
+                Table::create()
+                    .table(UploadQuarantine::Table)
+                    .if_not_exists()
+                    .col(
+                        ColumnDef::new(UploadQuarantine::Id)
+                            .integer()
+                            .not_null()
+                            .auto_increment()
+                            .primary_key(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::ProjectId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UploadQuarantine::IssueId).integer())
+                    .col(ColumnDef::new(UploadQuarantine::ProjectNoteId).integer())
+                    .col(
+                        ColumnDef::new(UploadQuarantine::UploaderUserId)
+                            .integer()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::OriginalFilename)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::MimeType)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::SizeBytes)
+                            .big_integer()
+                            .not_null(),
+                    )
+                    .col(ColumnDef::new(UploadQuarantine::Sha256).string().not_null())
+                    .col(ColumnDef::new(UploadQuarantine::Path).string().not_null())
+                    .col(
+                        ColumnDef::new(UploadQuarantine::Scanner)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::Signature)
+                            .string()
+                            .not_null(),
+                    )
+                    .col(
+                        ColumnDef::new(UploadQuarantine::CreatedAt)
+                            .timestamp_with_time_zone()
+                            .not_null(),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_upload_quarantine_project")
+                            .from(UploadQuarantine::Table, UploadQuarantine::ProjectId)
+                            .to(Project::Table, Project::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .foreign_key(
+                        ForeignKey::create()
+                            .name("fk_upload_quarantine_user")
+                            .from(UploadQuarantine::Table, UploadQuarantine::UploaderUserId)
+                            .to(User::Table, User::Id)
+                            .on_delete(ForeignKeyAction::Cascade),
+                    )
+                    .to_owned(),
+            )
+            .await?;
+
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_upload_quarantine_project_id")
+                    .table(UploadQuarantine::Table)
+                    .col(UploadQuarantine::ProjectId)
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_table(Table::drop().table(UploadQuarantine::Table).to_owned())
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UploadQuarantine {
+    Table,
+    Id,
+    ProjectId,
+    IssueId,
+    ProjectNoteId,
+    UploaderUserId,
+    OriginalFilename,
+    MimeType,
+    SizeBytes,
+    Sha256,
+    Path,
+    Scanner,
+    Signature,
+    CreatedAt,
+}
+
+#[derive(DeriveIden)]
+enum Project {
+    Table,
+    Id,
+}
+
+#[derive(DeriveIden)]
+enum User {
+    Table,
+    Id,
+}
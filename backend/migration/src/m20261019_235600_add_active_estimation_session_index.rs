@@ -0,0 +1,40 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // At most one round per issue may be open or revealed at a time
+        manager
+            .create_index(
+                Index::create()
+                    .name("idx_estimation_session_active_issue_unique")
+                    .table(EstimationSession::Table)
+                    .col(EstimationSession::IssueId)
+                    .unique()
+                    .and_where(Expr::col(EstimationSession::State).is_in(["open", "revealed"]))
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .drop_index(
+                Index::drop()
+                    .name("idx_estimation_session_active_issue_unique")
+                    .table(EstimationSession::Table)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum EstimationSession {
+    Table,
+    IssueId,
+    State,
+}
@@ -0,0 +1,56 @@
+use sea_orm_migration::prelude::*;
+
+#[derive(DeriveMigrationName)]
+pub struct Migration;
+
+#[async_trait::async_trait]
+impl MigrationTrait for Migration {
+    async fn up(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        // Held by the request currently streaming into the upload's part file
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UploadSession::Table)
+                    .add_column(ColumnDef::new(UploadSession::WriteLease).string())
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UploadSession::Table)
+                    .add_column(
+                        ColumnDef::new(UploadSession::WriteLeaseExpiresAt)
+                            .timestamp_with_time_zone(),
+                    )
+                    .to_owned(),
+            )
+            .await
+    }
+
+    async fn down(&self, manager: &SchemaManager) -> Result<(), DbErr> {
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UploadSession::Table)
+                    .drop_column(UploadSession::WriteLeaseExpiresAt)
+                    .to_owned(),
+            )
+            .await?;
+        manager
+            .alter_table(
+                Table::alter()
+                    .table(UploadSession::Table)
+                    .drop_column(UploadSession::WriteLease)
+                    .to_owned(),
+            )
+            .await
+    }
+}
+
+#[derive(DeriveIden)]
+enum UploadSession {
+    Table,
+    WriteLease,
+    WriteLeaseExpiresAt,
+}
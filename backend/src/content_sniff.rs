@@ -0,0 +1,291 @@
+// Works out what an upload really is from its first bytes, so the type a client declares
+// can be checked before the file is stored and served back under it.
+
+// How much of a file is looked at
+pub const SNIFF_LEN: usize = 512;
+
+// Types whose files always start with a signature; declaring one of these for content
+// without it is rejected even when nothing else is recognized
+const SIGNED_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/bmp",
+    "image/tiff",
+    "application/pdf",
+    "application/zip",
+    "application/gzip",
+    "application/x-7z-compressed",
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "video/mp4",
+    "video/webm",
+    "audio/ogg",
+    "audio/flac",
+    "audio/wav",
+    "font/woff",
+    "font/woff2",
+];
+
+// Formats stored inside a ZIP or an OLE compound file, which is all their signature shows
+const ZIP_CONTAINERS: &[&str] = &[
+    "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+    "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+    "application/vnd.oasis.opendocument.text",
+    "application/vnd.oasis.opendocument.spreadsheet",
+    "application/vnd.oasis.opendocument.presentation",
+    "application/epub+zip",
+    "application/java-archive",
+    "application/x-zip-compressed",
+    "model/3mf",
+];
+const OLE_CONTAINERS: &[&str] = &[
+    "application/msword",
+    "application/vnd.ms-excel",
+    "application/vnd.ms-powerpoint",
+    "application/vnd.ms-outlook",
+];
+
+// Markup a browser would run if it were served inline
+const ACTIVE_CONTENT: &[&str] = &[
+    "text/html",
+    "application/xhtml+xml",
+    "image/svg+xml",
+    "text/xml",
+    "application/xml",
+    "text/xsl",
+    "text/javascript",
+    "application/javascript",
+    "application/x-javascript",
+];
+
+// Tags that make a document HTML when it opens with them (per the WHATWG sniffing rules)
+const HTML_TAGS: &[&str] = &[
+    "<!doctype html",
+    "<html",
+    "<head",
+    "<script",
+    "<iframe",
+    "<h1",
+    "<div",
+    "<font",
+    "<table",
+    "<a",
+    "<style",
+    "<title",
+    "<b",
+    "<body",
+    "<br",
+    "<p",
+];
+
+/// The type the content's first bytes show, or None for plain text and anything unrecognized.
+pub fn sniff(head: &[u8]) -> Option<&'static str> {
+    let starts = |magic: &[u8]| head.starts_with(magic);
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+
+    if starts(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if starts(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if starts(b"GIF87a") || starts(b"GIF89a") {
+        Some("image/gif")
+    } else if starts(b"RIFF") && at(8, b"WEBP") {
+        Some("image/webp")
+    } else if starts(b"RIFF") && at(8, b"WAVE") {
+        Some("audio/wav")
+    } else if starts(b"RIFF") && at(8, b"AVI ") {
+        Some("video/x-msvideo")
+    } else if starts(b"BM") && at(6, b"\0\0\0\0") {
+        Some("image/bmp")
+    } else if starts(b"II*\0") || starts(b"MM\0*") {
+        Some("image/tiff")
+    } else if starts(b"\0\0\x01\0") {
+        Some("image/x-icon")
+    } else if starts(b"%PDF-") {
+        Some("application/pdf")
+    } else if starts(b"PK\x03\x04") || starts(b"PK\x05\x06") {
+        Some("application/zip")
+    } else if starts(b"\xd0\xcf\x11\xe0\xa1\xb1\x1a\xe1") {
+        Some("application/x-ole-storage")
+    } else if starts(b"\x1f\x8b") {
+        Some("application/gzip")
+    } else if starts(b"7z\xbc\xaf\x27\x1c") {
+        Some("application/x-7z-compressed")
+    } else if starts(b"Rar!\x1a\x07") {
+        Some("application/vnd.rar")
+    } else if at(4, b"ftyp") {
+        Some("video/mp4")
+    } else if starts(b"\x1a\x45\xdf\xa3") {
+        Some("video/webm")
+    } else if starts(b"OggS") {
+        Some("audio/ogg")
+    } else if starts(b"fLaC") {
+        Some("audio/flac")
+    } else if starts(b"ID3") {
+        Some("audio/mpeg")
+    } else if starts(b"wOFF") {
+        Some("font/woff")
+    } else if starts(b"wOF2") {
+        Some("font/woff2")
+    } else if (starts(b"MZ") && head.contains(&0)) || starts(b"\x7fELF") {
+        Some("application/x-executable")
+    } else {
+        sniff_markup(head)
+    }
+}
+
+fn sniff_markup(head: &[u8]) -> Option<&'static str> {
+    let text = String::from_utf8_lossy(head.strip_prefix(b"\xef\xbb\xbf").unwrap_or(head));
+    let text = text.trim_start().to_ascii_lowercase();
+    let opens_with = |tag: &str| {
+        text.strip_prefix(tag)
+            .and_then(|rest| rest.chars().next())
+            .is_some_and(|c| c == '>' || c.is_ascii_whitespace())
+    };
+    if text.starts_with("<svg") || (text.starts_with("<?xml") && text.contains("<svg")) {
+        Some("image/svg+xml")
+    } else if text.starts_with("<?xml") {
+        Some("application/xml")
+    } else if HTML_TAGS.iter().any(|tag| opens_with(tag)) {
+        Some("text/html")
+    } else {
+        None
+    }
+}
+
+/// Checks the declared type against the content; the error says what it looks like instead.
+pub fn check_content_type(declared: &str, head: &[u8]) -> Result<(), String> {
+    let declared = essence(declared);
+    match sniff(head) {
+        Some(sniffed) if !compatible(&declared, sniffed) => {
+            Err(format!("content looks like {}, not {}", sniffed, declared))
+        }
+        None if SIGNED_TYPES.contains(&canonical(&declared)) => {
+            Err(format!("content is not {}", declared))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Types that are sent as attachments and under a locked-down CSP, never rendered inline.
+pub fn is_active_content(mime_type: &str) -> bool {
+    let mime_type = essence(mime_type);
+    ACTIVE_CONTENT.contains(&mime_type.as_str()) || mime_type.ends_with("+xml")
+}
+
+fn compatible(declared: &str, sniffed: &str) -> bool {
+    let declared = canonical(declared);
+    if declared == sniffed || declared == "application/octet-stream" {
+        return true;
+    }
+    match sniffed {
+        "application/zip" => ZIP_CONTAINERS.contains(&declared),
+        "application/x-ole-storage" => OLE_CONTAINERS.contains(&declared),
+        "video/mp4" => matches!(
+            declared,
+            "video/quicktime"
+                | "audio/mp4"
+                | "audio/x-m4a"
+                | "video/3gpp"
+                | "image/avif"
+                | "image/heic"
+                | "image/heif"
+        ),
+        "video/webm" => matches!(declared, "audio/webm" | "video/x-matroska"),
+        "audio/ogg" => matches!(declared, "video/ogg" | "application/ogg" | "audio/opus"),
+        "application/gzip" => declared == "application/x-compressed-tar",
+        "application/vnd.rar" => declared == "application/x-rar-compressed",
+        "text/html" => matches!(
+            declared,
+            "application/xhtml+xml" | "text/plain" | "text/markdown"
+        ),
+        "image/svg+xml" | "application/xml" => {
+            declared == "text/xml"
+                || declared == "text/html"
+                || declared.ends_with("+xml")
+                || matches!(declared, "text/plain" | "text/markdown")
+        }
+        _ => false,
+    }
+}
+
+// Lowercase type/subtype without parameters
+fn essence(mime_type: &str) -> String {
+    mime_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
+
+// Folds the aliases clients send onto the names `sniff` returns
+fn canonical(mime_type: &str) -> &str {
+    match mime_type {
+        "image/jpg" | "image/pjpeg" => "image/jpeg",
+        "image/x-ms-bmp" | "image/x-bmp" => "image/bmp",
+        "image/vnd.microsoft.icon" => "image/x-icon",
+        "application/x-gzip" => "application/gzip",
+        "audio/wave" | "audio/x-wav" | "audio/vnd.wave" => "audio/wav",
+        "audio/mp3" => "audio/mpeg",
+        "audio/x-flac" => "audio/flac",
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markup_labeled_as_an_image_is_rejected() {
+        assert_eq!(
+            check_content_type("image/png", b"<html><script>alert(1)</script></html>"),
+            Err("content looks like text/html, not image/png".to_string())
+        );
+        assert_eq!(
+            check_content_type(
+                "image/png",
+                b"<?xml version=\"1.0\"?>\n<svg onload=\"x()\"/>"
+            ),
+            Err("content looks like image/svg+xml, not image/png".to_string())
+        );
+        assert_eq!(
+            check_content_type("image/jpeg", b"just some text"),
+            Err("content is not image/jpeg".to_string())
+        );
+    }
+
+    #[test]
+    fn test_matching_and_container_types_are_accepted() {
+        assert!(check_content_type("image/png", b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").is_ok());
+        assert!(check_content_type("IMAGE/JPG; q=1", b"\xff\xd8\xff\xe0\0\x10JFIF").is_ok());
+        assert!(check_content_type(
+            "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+            b"PK\x03\x04\x14\0\x06\0"
+        )
+        .is_ok());
+        assert!(check_content_type("text/plain", b"plain notes\nline two").is_ok());
+        assert!(check_content_type("text/markdown", b"<div align=\"center\">\n# Title").is_ok());
+        assert!(check_content_type("text/plain", b"MZ\x90\0").is_err());
+    }
+
+    #[test]
+    fn test_active_content_is_recognized() {
+        assert!(is_active_content("image/svg+xml"));
+        assert!(is_active_content("Text/HTML; charset=utf-8"));
+        assert!(is_active_content("application/rss+xml"));
+        assert!(!is_active_content("image/png"));
+        assert!(!is_active_content("text/plain"));
+    }
+}
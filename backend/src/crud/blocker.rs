@@ -1,9 +1,9 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
 use crate::crud::history::HistoryCrud;
 use crate::crud::issue::IssueCrud;
+use crate::error::AppError;
 
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::entities::blocker;
 use crate::AppState;
 use sea_orm::*;
@@ -18,7 +18,11 @@ impl BlockerCrud {
         Self { app_state }
     }
 
-    pub async fn create(&self, blocker_id: i32, blocked_id: i32) -> Result<blocker::Model, DbErr> {
+    pub async fn create(
+        &self,
+        blocker_id: i32,
+        blocked_id: i32,
+    ) -> Result<blocker::Model, AppError> {
         let model = self.find_by_ids(blocker_id, blocked_id).await?;
         if let Some(model) = model {
             return Ok(model);
@@ -68,6 +72,7 @@ impl BlockerCrud {
         );
         let _ = notification_crud
             .notify_issue_assignees(
+                NotificationKind::Blocker,
                 blocker_id,
                 format!("Issue Now Blocking: {}", blocked_issue.title),
                 blocker_description,
@@ -83,6 +88,7 @@ impl BlockerCrud {
         );
         let _ = notification_crud
             .notify_issue_assignees(
+                NotificationKind::Blocker,
                 blocked_id,
                 format!("Issue Blocked: {}", blocked_issue.title),
                 blocked_description,
@@ -91,13 +97,10 @@ impl BlockerCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({ "id": blocker_id }),
-        );
+        // Both sides show the relationship
+        let issue_crud = IssueCrud::new(self.app_state.clone());
+        issue_crud.publish_updated(blocker_id).await?;
+        issue_crud.publish_updated(blocked_id).await?;
 
         Ok(result)
     }
@@ -106,29 +109,38 @@ impl BlockerCrud {
         &self,
         blocker_id: i32,
         blocked_id: i32,
-    ) -> Result<Option<blocker::Model>, DbErr> {
+    ) -> Result<Option<blocker::Model>, AppError> {
         blocker::Entity::find()
             .filter(blocker::Column::BlockerId.eq(blocker_id))
             .filter(blocker::Column::BlockedId.eq(blocked_id))
             .one(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_blocker_id(&self, blocker_id: i32) -> Result<Vec<blocker::Model>, DbErr> {
+    pub async fn find_by_blocker_id(
+        &self,
+        blocker_id: i32,
+    ) -> Result<Vec<blocker::Model>, AppError> {
         blocker::Entity::find()
             .filter(blocker::Column::BlockerId.eq(blocker_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_blocked_id(&self, blocked_id: i32) -> Result<Vec<blocker::Model>, DbErr> {
+    pub async fn find_by_blocked_id(
+        &self,
+        blocked_id: i32,
+    ) -> Result<Vec<blocker::Model>, AppError> {
         blocker::Entity::find()
             .filter(blocker::Column::BlockedId.eq(blocked_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete(&self, blocker_id: i32, blocked_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, blocker_id: i32, blocked_id: i32) -> Result<DeleteResult, AppError> {
         let issue_crud = IssueCrud::new(self.app_state.clone());
         let blocker_issue = issue_crud.find_by_id(blocker_id).await?.unwrap();
         let blocked_issue = issue_crud.find_by_id(blocked_id).await?.unwrap();
@@ -172,6 +184,7 @@ impl BlockerCrud {
         );
         let _ = notification_crud
             .notify_issue_assignees(
+                NotificationKind::Blocker,
                 blocker_id,
                 format!("Issue No Longer Blocking: {}", blocked_issue.title),
                 blocker_description,
@@ -187,6 +200,7 @@ impl BlockerCrud {
         );
         let _ = notification_crud
             .notify_issue_assignees(
+                NotificationKind::Blocker,
                 blocked_id,
                 format!("Issue No Longer Blocked: {}", blocked_issue.title),
                 blocked_description,
@@ -195,25 +209,29 @@ impl BlockerCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({ "id": blocker_id }),
-        );
+        // Both sides show the relationship
+        let issue_crud = IssueCrud::new(self.app_state.clone());
+        issue_crud.publish_updated(blocker_id).await?;
+        issue_crud.publish_updated(blocked_id).await?;
 
         Ok(result)
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
         let result = blocker::Entity::delete_many()
             .filter(
                 Condition::any()
                     .add(blocker::Column::BlockerId.eq(issue_id))
                     .add(blocker::Column::BlockedId.eq(issue_id)),
             )
-            .exec(&self.app_state.db)
+            .exec(txn)
             .await?;
 
         Ok(result)
@@ -1,10 +1,12 @@
 use crate::crud::comment_file_upload::CommentFileUploadCrud;
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
 use crate::crud::file_upload::FileUploadCrud;
 use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::crud::mention::MentionCrud;
+use crate::error::AppError;
 
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::{entities::comment, AppState};
 use sea_orm::*;
 use tracing::debug;
@@ -24,7 +26,7 @@ impl CommentCrud {
         content: String,
         issue_id: i32,
         user_id: i32,
-    ) -> Result<comment::Model, DbErr> {
+    ) -> Result<comment::Model, AppError> {
         debug!(
             "Creating comment for issue {} by user {}, comment {}",
             issue_id, user_id, content
@@ -36,7 +38,7 @@ impl CommentCrud {
             ..Default::default()
         };
         let result = comment.insert(&self.app_state.db).await?;
-        let comment_id = result.id.clone();
+        let comment_id = result.id;
         debug!("comment created with id {}", comment_id);
 
         // Add history entry
@@ -58,6 +60,7 @@ impl CommentCrud {
 
         let _ = notification_crud
             .notify_issue_assignees_with_context(
+                NotificationKind::Comment,
                 issue_id,
                 "New Comment on Issue: {}",
                 description,
@@ -66,43 +69,53 @@ impl CommentCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"id": user_id}),
-        );
+        // Mentioned users hear about the comment even if they aren't assigned
+        MentionCrud::new(self.app_state.clone())
+            .sync(*project_id, issue_id, Some(comment_id), &content, user_id)
+            .await?;
 
-        return Ok(result);
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
+
+        Ok(result)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<comment::Model>, DbErr> {
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<comment::Model>, AppError> {
         comment::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<comment::Model>, DbErr> {
+    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<comment::Model>, AppError> {
         comment::Entity::find()
             .filter(comment::Column::IssueId.eq(issue_id))
             .order_by(comment::Column::CreatedAt, Order::Asc)
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_user_id(&self, user_id: i32) -> Result<Vec<comment::Model>, DbErr> {
+    pub async fn find_by_user_id(&self, user_id: i32) -> Result<Vec<comment::Model>, AppError> {
         comment::Entity::find()
             .filter(comment::Column::UserId.eq(user_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_by_issue_id_txn(
+        &self,
+        issue_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> Result<DeleteResult, AppError> {
         // Load all comments for this issue, then delete each using the single-comment
         // delete path to ensure associated uploads and mappings are cleaned up.
-        let txn = self.app_state.db.begin().await?;
-        let comments = self.find_by_issue_id(issue_id).await?;
+        let comments = comment::Entity::find()
+            .filter(comment::Column::IssueId.eq(issue_id))
+            .all(txn)
+            .await?;
         let mut rows: u64 = 0;
         for c in comments {
             let comment_id = c.id;
@@ -110,38 +123,44 @@ impl CommentCrud {
             // Find all uploads attached to this comment (use txn-aware variant)
             let cfu_crud = CommentFileUploadCrud::new(self.app_state.clone());
             let uploads = cfu_crud
-                .find_uploads_by_comment_id_txn(comment_id, &txn)
+                .find_uploads_by_comment_id_txn(comment_id, txn)
                 .await?;
 
             // Delete each upload (record + stored file) without creating history, inside the same txn
             let file_crud = FileUploadCrud::new(self.app_state.clone());
             for u in uploads {
-                file_crud.delete_with_no_history_txn(u.id, &txn).await?;
+                file_crud.delete_with_no_history_txn(u.id, txn).await?;
                 // Also remove the specific mapping for this comment (noop if already removed by upload deletion)
-                let _ = cfu_crud.delete_txn(comment_id, u.id, &txn).await?;
+                let _ = cfu_crud.delete_txn(comment_id, u.id, txn).await?;
             }
 
             // Delete all history records that reference this comment (set comment_id to NULL)
             let history_crud = HistoryCrud::new(self.app_state.db.clone());
-            history_crud.delete_by_comment_id(comment_id).await?;
+            history_crud
+                .delete_by_comment_id_with_txn(comment_id, txn)
+                .await?;
+
+            let mention_crud = MentionCrud::new(self.app_state.clone());
+            mention_crud
+                .delete_by_comment_id_with_txn(comment_id, txn)
+                .await?;
 
             // Delete the comment
-            comment::Entity::delete_by_id(comment_id).exec(&txn).await?;
+            comment::Entity::delete_by_id(comment_id).exec(txn).await?;
 
             rows += 1;
         }
-        txn.commit().await?;
         Ok(DeleteResult {
             rows_affected: rows,
         })
     }
 
-    pub async fn update(&self, id: i32, content: String) -> Result<comment::Model, DbErr> {
+    pub async fn update(&self, id: i32, content: String) -> Result<comment::Model, AppError> {
         let txn = self.app_state.db.begin().await?;
         let comment_model = comment::Entity::find_by_id(id)
             .one(&txn)
             .await?
-            .ok_or(DbErr::Custom("Comment not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Comment"))?;
 
         let current_version = comment_model.lock_version;
         let user_id = comment_model.user_id;
@@ -160,7 +179,7 @@ impl CommentCrud {
 
         if result.lock_version != current_version + 1 {
             txn.rollback().await?;
-            return Err(DbErr::Custom("Optimistic lock error".to_owned()));
+            return Err(AppError::OptimisticLock);
         }
 
         txn.commit().await?;
@@ -179,26 +198,28 @@ impl CommentCrud {
                 .await?;
         }
 
-        // Broadcast event
         let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"id": user_id}),
-        );
+        if old_content != content {
+            MentionCrud::new(self.app_state.clone())
+                .sync(*project_id, issue_id, Some(id), &content, user_id)
+                .await?;
+        }
+
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
 
         Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<(), DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<(), AppError> {
         let txn = self.app_state.db.begin().await?;
 
         // Find the comment first to get details for history
         let comment_model = comment::Entity::find_by_id(id)
             .one(&txn)
             .await?
-            .ok_or(DbErr::Custom("Comment not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Comment"))?;
 
         let user_id = comment_model.user_id;
         let issue_id = comment_model.issue_id;
@@ -218,7 +239,10 @@ impl CommentCrud {
 
         // Delete all history records that reference this comment (set comment_id to NULL)
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
-        history_crud.delete_by_comment_id(id).await?;
+        history_crud.delete_by_comment_id_with_txn(id, &txn).await?;
+
+        let mention_crud = MentionCrud::new(self.app_state.clone());
+        mention_crud.delete_by_comment_id_with_txn(id, &txn).await?;
 
         // Delete the comment
         comment::Entity::delete_by_id(id).exec(&txn).await?;
@@ -236,14 +260,9 @@ impl CommentCrud {
             )
             .await?;
 
-        // Broadcast event
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"id": user_id}),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
 
         Ok(())
     }
@@ -1,7 +1,8 @@
-use crate::crud::event_broadcaster::{EventBroadcaster, ISSUE_UPDATED};
 use crate::crud::file_upload::FileUploadCrud;
 use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
 use crate::entities::{comment, comment_file_upload, file_upload};
+use crate::error::AppError;
 use crate::AppState;
 use sea_orm::*;
 
@@ -21,7 +22,7 @@ impl CommentFileUploadCrud {
         &self,
         comment_id: i32,
         file_upload_id: i32,
-    ) -> Result<comment_file_upload::Model, DbErr> {
+    ) -> Result<comment_file_upload::Model, AppError> {
         // If it already exists, return existing (idempotent create)
         if let Some(existing) = self.find_by_ids(comment_id, file_upload_id).await? {
             return Ok(existing);
@@ -32,26 +33,26 @@ impl CommentFileUploadCrud {
             .one(&self.app_state.db)
             .await?
         else {
-            return Err(DbErr::Custom("Comment not found".into()));
+            return Err(AppError::not_found("Comment"));
         };
 
         let Some(file_model) = file_upload::Entity::find_by_id(file_upload_id)
             .one(&self.app_state.db)
             .await?
         else {
-            return Err(DbErr::Custom("File upload not found".into()));
+            return Err(AppError::not_found("File upload"));
         };
 
         // Validation: comments attach to files that are owned by the same issue
         match file_model.issue_id {
             Some(issue_id) if issue_id == comment_model.issue_id => {}
             Some(_) => {
-                return Err(DbErr::Custom(
+                return Err(AppError::Conflict(
                     "File upload belongs to a different issue than the comment".into(),
                 ))
             }
             None => {
-                return Err(DbErr::Custom(
+                return Err(AppError::Conflict(
                     "Cannot attach a project note file upload to an issue comment".into(),
                 ))
             }
@@ -72,7 +73,7 @@ impl CommentFileUploadCrud {
             .user
             .as_ref()
             .map(|u| u.id)
-            .ok_or_else(|| DbErr::Custom("Current user not set on AppState".into()))?;
+            .ok_or(AppError::Unauthorized)?;
         let _ = history_crud
             .create(
                 current_user_id,
@@ -86,15 +87,9 @@ impl CommentFileUploadCrud {
             )
             .await?;
 
-        // Broadcast update
-        if let Some(project) = &self.app_state.project {
-            let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-            broadcaster.broadcast_event(
-                project.id,
-                ISSUE_UPDATED,
-                serde_json::json!({ "id": comment_model.issue_id }),
-            );
-        }
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(comment_model.issue_id)
+            .await?;
 
         Ok(mapping)
     }
@@ -103,29 +98,31 @@ impl CommentFileUploadCrud {
         &self,
         comment_id: i32,
         file_upload_id: i32,
-    ) -> Result<Option<comment_file_upload::Model>, DbErr> {
+    ) -> Result<Option<comment_file_upload::Model>, AppError> {
         comment_file_upload::Entity::find()
             .filter(comment_file_upload::Column::CommentId.eq(comment_id))
             .filter(comment_file_upload::Column::FileUploadId.eq(file_upload_id))
             .one(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn find_by_comment_id(
         &self,
         comment_id: i32,
-    ) -> Result<Vec<comment_file_upload::Model>, DbErr> {
+    ) -> Result<Vec<comment_file_upload::Model>, AppError> {
         comment_file_upload::Entity::find()
             .filter(comment_file_upload::Column::CommentId.eq(comment_id))
             .order_by_asc(comment_file_upload::Column::CreatedAt)
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn find_uploads_by_comment_id(
         &self,
         comment_id: i32,
-    ) -> Result<Vec<file_upload::Model>, DbErr> {
+    ) -> Result<Vec<file_upload::Model>, AppError> {
         let mut uploads = file_upload::Entity::find()
             .join(
                 JoinType::InnerJoin,
@@ -152,7 +149,7 @@ impl CommentFileUploadCrud {
         &self,
         comment_id: i32,
         txn: &DatabaseTransaction,
-    ) -> Result<Vec<file_upload::Model>, DbErr> {
+    ) -> Result<Vec<file_upload::Model>, AppError> {
         file_upload::Entity::find()
             .join(
                 JoinType::InnerJoin,
@@ -162,13 +159,14 @@ impl CommentFileUploadCrud {
             .order_by_asc(file_upload::Column::UploadedAt)
             .all(txn)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn delete(
         &self,
         comment_id: i32,
         file_upload_id: i32,
-    ) -> Result<DeleteResult, DbErr> {
+    ) -> Result<DeleteResult, AppError> {
         // Fetch for history and broadcasting context (best-effort; ignore if missing)
         let comment_model = comment::Entity::find_by_id(comment_id)
             .one(&self.app_state.db)
@@ -198,15 +196,9 @@ impl CommentFileUploadCrud {
                     .await;
             }
 
-            // Broadcast update
-            if let Some(project) = &self.app_state.project {
-                let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-                broadcaster.broadcast_event(
-                    project.id,
-                    ISSUE_UPDATED,
-                    serde_json::json!({ "id": c.issue_id }),
-                );
-            }
+            IssueCrud::new(self.app_state.clone())
+                .publish_updated(c.issue_id)
+                .await?;
         }
 
         Ok(result)
@@ -218,12 +210,13 @@ impl CommentFileUploadCrud {
         comment_id: i32,
         file_upload_id: i32,
         txn: &DatabaseTransaction,
-    ) -> Result<DeleteResult, DbErr> {
+    ) -> Result<DeleteResult, AppError> {
         comment_file_upload::Entity::delete_many()
             .filter(comment_file_upload::Column::CommentId.eq(comment_id))
             .filter(comment_file_upload::Column::FileUploadId.eq(file_upload_id))
             .exec(txn)
             .await
+            .map_err(AppError::from)
     }
 
     // Delete all mappings for a given file upload within an existing transaction
@@ -231,10 +224,11 @@ impl CommentFileUploadCrud {
         &self,
         file_upload_id: i32,
         txn: &DatabaseTransaction,
-    ) -> Result<DeleteResult, DbErr> {
+    ) -> Result<DeleteResult, AppError> {
         comment_file_upload::Entity::delete_many()
             .filter(comment_file_upload::Column::FileUploadId.eq(file_upload_id))
             .exec(txn)
             .await
+            .map_err(AppError::from)
     }
 }
@@ -0,0 +1,98 @@
+use crate::crud::file_upload::{check_upload_size, generate_guid};
+use crate::crud::upload_session::{NewUploadSession, UploadTarget};
+use crate::entities::direct_upload;
+use crate::environment;
+use crate::error::AppError;
+use chrono::{Duration, Utc};
+use sea_orm::*;
+
+#[derive(Clone)]
+pub struct DirectUploadCrud {
+    db: DatabaseConnection,
+}
+
+impl DirectUploadCrud {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn create(
+        &self,
+        new: NewUploadSession,
+        sha256: String,
+    ) -> Result<direct_upload::Model, AppError> {
+        if new.size_bytes <= 0 {
+            return Err(AppError::invalid(
+                "size",
+                "Empty file uploads are not allowed",
+            ));
+        }
+        check_upload_size(new.size_bytes)?;
+        let sha256 = sha256.to_ascii_lowercase();
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(AppError::invalid(
+                "sha256",
+                "must be the hex SHA-256 of the file",
+            ));
+        }
+
+        let (issue_id, project_note_id) = match new.target {
+            UploadTarget::Issue(id) => (Some(id), None),
+            UploadTarget::ProjectNote(id) => (None, Some(id)),
+        };
+        let now = Utc::now();
+        let upload = direct_upload::ActiveModel {
+            id: Set(generate_guid(32)),
+            project_id: Set(new.project_id),
+            issue_id: Set(issue_id),
+            project_note_id: Set(project_note_id),
+            uploader_user_id: Set(new.uploader_user_id),
+            original_filename: Set(new.original_filename),
+            mime_type: Set(new.mime_type),
+            size_bytes: Set(new.size_bytes),
+            sha256: Set(sha256),
+            created_at: Set(now.into()),
+            expires_at: Set(
+                (now + Duration::hours(environment::upload_session_ttl_hours())).into(),
+            ),
+        }
+        .insert(&self.db)
+        .await?;
+        Ok(upload)
+    }
+
+    // Direct uploads are private to the user who started them, in the project they started them in
+    pub async fn find_for_user(
+        &self,
+        id: &str,
+        user_id: i32,
+        project_id: i32,
+    ) -> Result<Option<direct_upload::Model>, AppError> {
+        let upload = direct_upload::Entity::find_by_id(id.to_string())
+            .filter(direct_upload::Column::UploaderUserId.eq(user_id))
+            .filter(direct_upload::Column::ProjectId.eq(project_id))
+            .filter(direct_upload::Column::ExpiresAt.gt(Utc::now()))
+            .one(&self.db)
+            .await?;
+        Ok(upload)
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        direct_upload::Entity::delete_by_id(id.to_string())
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    // Removes expired uploads that were never finalized and returns them
+    pub async fn delete_expired(&self) -> Result<Vec<direct_upload::Model>, AppError> {
+        let expired = direct_upload::Entity::find()
+            .filter(direct_upload::Column::ExpiresAt.lte(Utc::now()))
+            .all(&self.db)
+            .await?;
+        for upload in &expired {
+            self.delete(&upload.id).await?;
+        }
+        Ok(expired)
+    }
+}
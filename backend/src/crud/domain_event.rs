@@ -0,0 +1,519 @@
+use crate::crud::estimation_session::EstimateStats;
+use crate::crud::event_broadcaster::*;
+use crate::entities::{domain_event, event_consumer, issue, tag};
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Any constant works as long as every publisher takes the same locks; the second key is
+// the project
+const OUTBOX_LOCK_CLASS: i32 = 0x5048_4f45; // "PHOE"
+
+// Upper bound on one replay; a client further behind than this should reload instead
+pub const MAX_REPLAY: u64 = 1000;
+
+// How long a gap in the sequence is waited on before the event that would fill it is
+// taken to have been rolled back
+pub const GAP_TIMEOUT: Duration = Duration::from_secs(5);
+// How often a gap is looked at again while nothing new is announced
+pub const GAP_RECHECK: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RevealedVote {
+    pub user_id: i32,
+    pub points: i32,
+}
+
+/// Everything that happens in a project that clients and integrations hear about.
+/// Serialized as `{"event_type": "...", "data": ...}`, which is what WebSocket clients
+/// have always received.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "event_type", content = "data", rename_all = "snake_case")]
+pub enum DomainEvent {
+    IssueCreated(issue::Model),
+    // Also sent when something hanging off the issue changes (comments, tags, tasks...)
+    IssueUpdated(issue::Model),
+    IssueDeleted {
+        id: i32,
+    },
+    TagCreated(tag::Model),
+    TagUpdated(tag::Model),
+    TagDeleted {
+        id: i32,
+    },
+    ProjectNoteCreated {
+        project_note_id: i32,
+    },
+    ProjectNoteUpdated {
+        project_note_id: i32,
+    },
+    ProjectNoteDeleted {
+        project_note_id: i32,
+    },
+    ProjectNotePartUpdated {
+        project_note_id: i32,
+        project_note_part_id: i32,
+    },
+    ReminderDispatched(issue::Model),
+    EstimationStarted {
+        session_id: i32,
+        issue_id: i32,
+    },
+    // The value stays hidden until the round is revealed
+    EstimationVoteCast {
+        session_id: i32,
+        issue_id: i32,
+        user_id: i32,
+        vote_count: u64,
+    },
+    EstimationRevealed {
+        session_id: i32,
+        issue_id: i32,
+        votes: Vec<RevealedVote>,
+        stats: Option<EstimateStats>,
+    },
+    EstimationCommitted {
+        session_id: i32,
+        issue_id: i32,
+        points: i32,
+    },
+    EstimationCancelled {
+        session_id: i32,
+        issue_id: i32,
+    },
+    NotificationCountUpdated {
+        count: i32,
+    },
+    // Tells the user's open sockets to re-check their access to the project
+    MembershipRevoked {
+        user_id: i32,
+    },
+}
+
+impl DomainEvent {
+    pub fn event_type(&self) -> &'static str {
+        match self {
+            DomainEvent::IssueCreated(_) => ISSUE_CREATED,
+            DomainEvent::IssueUpdated(_) => ISSUE_UPDATED,
+            DomainEvent::IssueDeleted { .. } => ISSUE_DELETED,
+            DomainEvent::TagCreated(_) => TAG_CREATED,
+            DomainEvent::TagUpdated(_) => TAG_UPDATED,
+            DomainEvent::TagDeleted { .. } => TAG_DELETED,
+            DomainEvent::ProjectNoteCreated { .. } => PROJECT_NOTE_CREATED,
+            DomainEvent::ProjectNoteUpdated { .. } => PROJECT_NOTE_UPDATED,
+            DomainEvent::ProjectNoteDeleted { .. } => PROJECT_NOTE_DELETED,
+            DomainEvent::ProjectNotePartUpdated { .. } => PROJECT_NOTE_PART_UPDATED,
+            DomainEvent::ReminderDispatched(_) => REMINDER_DISPATCHED,
+            DomainEvent::EstimationStarted { .. } => ESTIMATION_STARTED,
+            DomainEvent::EstimationVoteCast { .. } => ESTIMATION_VOTE_CAST,
+            DomainEvent::EstimationRevealed { .. } => ESTIMATION_REVEALED,
+            DomainEvent::EstimationCommitted { .. } => ESTIMATION_COMMITTED,
+            DomainEvent::EstimationCancelled { .. } => ESTIMATION_CANCELLED,
+            DomainEvent::NotificationCountUpdated { .. } => NOTIFICATION_COUNT_UPDATED,
+            DomainEvent::MembershipRevoked { .. } => MEMBERSHIP_REVOKED,
+        }
+    }
+
+    /// The issue the event is about, if any.
+    pub fn issue_id(&self) -> Option<i32> {
+        match self {
+            DomainEvent::IssueCreated(issue)
+            | DomainEvent::IssueUpdated(issue)
+            | DomainEvent::ReminderDispatched(issue) => Some(issue.id),
+            DomainEvent::IssueDeleted { id } => Some(*id),
+            DomainEvent::EstimationStarted { issue_id, .. }
+            | DomainEvent::EstimationVoteCast { issue_id, .. }
+            | DomainEvent::EstimationRevealed { issue_id, .. }
+            | DomainEvent::EstimationCommitted { issue_id, .. }
+            | DomainEvent::EstimationCancelled { issue_id, .. } => Some(*issue_id),
+            _ => None,
+        }
+    }
+
+    /// The event's `data`, as stored in the outbox.
+    pub fn data(&self) -> Value {
+        match serde_json::to_value(self) {
+            Ok(Value::Object(mut fields)) => fields.remove("data").unwrap_or(Value::Null),
+            _ => Value::Null,
+        }
+    }
+
+    fn from_parts(event_type: &str, data: &str) -> Result<Self, serde_json::Error> {
+        let data: Value = serde_json::from_str(data)?;
+        serde_json::from_value(serde_json::json!({ "event_type": event_type, "data": data }))
+    }
+}
+
+/// A stored event. Within a project, sequences only ever increase in the order events were
+/// committed, so a consumer that remembers the last one it saw can pick up where it left off.
+/// Consumers following every project read through an `OutboxReader`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventEnvelope {
+    pub sequence: i64,
+    pub project_id: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<i32>,
+    #[serde(flatten)]
+    pub event: DomainEvent,
+    pub created_at: DateTime<Utc>,
+}
+
+impl EventEnvelope {
+    /// Whether `user_id` may see this event: everything in the project except other
+    /// users' personal events.
+    pub fn visible_to(&self, user_id: i32) -> bool {
+        self.user_id.is_none_or(|id| id == user_id)
+    }
+}
+
+impl TryFrom<domain_event::Model> for EventEnvelope {
+    type Error = AppError;
+
+    fn try_from(model: domain_event::Model) -> Result<Self, Self::Error> {
+        let event = DomainEvent::from_parts(&model.event_type, &model.data).map_err(|e| {
+            AppError::Internal(format!("Unreadable event {}: {}", model.sequence, e))
+        })?;
+        Ok(EventEnvelope {
+            sequence: model.sequence,
+            project_id: model.project_id,
+            user_id: model.user_id,
+            event,
+            created_at: model.created_at.with_timezone(&Utc),
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct DomainEventCrud {
+    db: DatabaseConnection,
+}
+
+impl DomainEventCrud {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    /// Writes an event to the outbox as part of `txn`, so it is kept exactly when the change
+    /// it describes is. On Postgres the project's lock is held until that commit, so each
+    /// project's sequences become visible in order, even with several servers writing at once.
+    pub async fn append_with_txn<C>(
+        &self,
+        project_id: i32,
+        user_id: Option<i32>,
+        event: DomainEvent,
+        txn: &C,
+    ) -> Result<EventEnvelope, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        lock_project(project_id, txn).await?;
+        let now = Utc::now();
+        let model = domain_event::ActiveModel {
+            project_id: Set(project_id),
+            user_id: Set(user_id),
+            event_type: Set(event.event_type().to_string()),
+            data: Set(event.data().to_string()),
+            created_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(txn)
+        .await?;
+
+        Ok(EventEnvelope {
+            sequence: model.sequence,
+            project_id,
+            user_id,
+            event,
+            created_at: now,
+        })
+    }
+
+    /// Events after `after`, oldest first. `project_ids` of None means every project,
+    /// and personal events are only included for `user_id`.
+    pub async fn find_since(
+        &self,
+        after: i64,
+        project_ids: Option<&[i32]>,
+        user_id: Option<i32>,
+        limit: u64,
+    ) -> Result<Vec<EventEnvelope>, AppError> {
+        let mut query = domain_event::Entity::find()
+            .filter(domain_event::Column::Sequence.gt(after))
+            .order_by_asc(domain_event::Column::Sequence)
+            .limit(limit.min(MAX_REPLAY));
+        if let Some(project_ids) = project_ids {
+            query = query.filter(domain_event::Column::ProjectId.is_in(project_ids.to_vec()));
+        }
+        if let Some(user_id) = user_id {
+            query = query.filter(
+                Condition::any()
+                    .add(domain_event::Column::UserId.is_null())
+                    .add(domain_event::Column::UserId.eq(user_id)),
+            );
+        }
+        query
+            .all(&self.db)
+            .await?
+            .into_iter()
+            .map(EventEnvelope::try_from)
+            .collect()
+    }
+
+    pub async fn latest_sequence(&self) -> Result<i64, AppError> {
+        let latest = domain_event::Entity::find()
+            .order_by_desc(domain_event::Column::Sequence)
+            .one(&self.db)
+            .await?;
+        Ok(latest.map(|e| e.sequence).unwrap_or(0))
+    }
+
+    /// The project's last committed sequence, read under its lock so no event of the
+    /// project with a lower sequence can still be committing. The latest sequence overall
+    /// could already be past such an event, which a subscriber starting there would miss.
+    pub async fn latest_sequence_in_project(&self, project_id: i32) -> Result<i64, AppError> {
+        let txn = self.db.begin().await?;
+        lock_project(project_id, &txn).await?;
+        let latest = domain_event::Entity::find()
+            .filter(domain_event::Column::ProjectId.eq(project_id))
+            .order_by_desc(domain_event::Column::Sequence)
+            .one(&txn)
+            .await?;
+        txn.commit().await?;
+        Ok(latest.map(|e| e.sequence).unwrap_or(0))
+    }
+
+    pub async fn find_cursor(&self, consumer: &str) -> Result<Option<i64>, AppError> {
+        Ok(event_consumer::Entity::find_by_id(consumer.to_string())
+            .one(&self.db)
+            .await?
+            .map(|c| c.sequence))
+    }
+
+    pub async fn save_cursor(&self, consumer: &str, sequence: i64) -> Result<(), AppError> {
+        let cursor = event_consumer::ActiveModel {
+            name: Set(consumer.to_string()),
+            sequence: Set(sequence),
+            updated_at: Set(Utc::now().into()),
+        };
+        event_consumer::Entity::insert(cursor)
+            .on_conflict(
+                sea_query::OnConflict::column(event_consumer::Column::Name)
+                    .update_columns([
+                        event_consumer::Column::Sequence,
+                        event_consumer::Column::UpdatedAt,
+                    ])
+                    .to_owned(),
+            )
+            .exec(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Moves `consumer`'s cursor forward to `sequence` unless it is already there. Only one
+    /// of several instances racing for the same event gets `true`; each event is claimed in
+    /// order, so none is left unclaimed.
+    pub async fn claim(&self, consumer: &str, sequence: i64) -> Result<bool, AppError> {
+        let result = event_consumer::Entity::update_many()
+            .col_expr(event_consumer::Column::Sequence, Expr::value(sequence))
+            .col_expr(event_consumer::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(event_consumer::Column::Name.eq(consumer))
+            .filter(event_consumer::Column::Sequence.lt(sequence))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected > 0)
+    }
+
+    pub async fn delete_older_than(&self, cutoff: DateTime<Utc>) -> Result<u64, AppError> {
+        let result = domain_event::Entity::delete_many()
+            .filter(domain_event::Column::CreatedAt.lt(cutoff))
+            .exec(&self.db)
+            .await?;
+        Ok(result.rows_affected)
+    }
+}
+
+// Held until the transaction ends. SQLite already allows only one writer at a time.
+async fn lock_project<C>(project_id: i32, txn: &C) -> Result<(), AppError>
+where
+    C: ConnectionTrait,
+{
+    if txn.get_database_backend() == DbBackend::Postgres {
+        txn.execute(Statement::from_sql_and_values(
+            DbBackend::Postgres,
+            "SELECT pg_advisory_xact_lock($1, $2)",
+            [OUTBOX_LOCK_CLASS.into(), project_id.into()],
+        ))
+        .await?;
+    }
+    Ok(())
+}
+
+/// Follows the outbox across every project, in sequence order. Sequences are only committed
+/// in order within a project, so a gap can be another project's event that is still being
+/// committed; reading stops there until the gap fills or GAP_TIMEOUT passes.
+pub struct OutboxReader {
+    events: DomainEventCrud,
+    pub cursor: i64,
+    gap_since: Option<Instant>,
+}
+
+impl OutboxReader {
+    pub fn new(db: DatabaseConnection, cursor: i64) -> Self {
+        Self {
+            events: DomainEventCrud::new(db),
+            cursor,
+            gap_since: None,
+        }
+    }
+
+    /// The next events after the cursor, oldest first, which the cursor moves past. Fewer
+    /// than MAX_REPLAY means there are no more for now.
+    pub async fn next_batch(&mut self) -> Result<Vec<EventEnvelope>, AppError> {
+        let batch = self
+            .events
+            .find_since(self.cursor, None, None, MAX_REPLAY)
+            .await?;
+        Ok(self.settle(batch, Instant::now()))
+    }
+
+    /// Whether reading stopped at a gap that may still fill.
+    pub fn waiting(&self) -> bool {
+        self.gap_since.is_some()
+    }
+
+    fn settle(&mut self, batch: Vec<EventEnvelope>, now: Instant) -> Vec<EventEnvelope> {
+        let mut ready = Vec::with_capacity(batch.len());
+        for envelope in batch {
+            if envelope.sequence != self.cursor + 1 {
+                let since = *self.gap_since.get_or_insert(now);
+                if now.duration_since(since) < GAP_TIMEOUT {
+                    break;
+                }
+                warn!(
+                    "Events {} to {} were never committed, moving on",
+                    self.cursor + 1,
+                    envelope.sequence - 1
+                );
+            }
+            self.gap_since = None;
+            self.cursor = envelope.sequence;
+            ready.push(envelope);
+        }
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_round_trip_through_the_outbox_format() {
+        let event = DomainEvent::EstimationCommitted {
+            session_id: 4,
+            issue_id: 9,
+            points: 3,
+        };
+        assert_eq!(event.event_type(), "estimation_committed");
+        assert_eq!(
+            event.data(),
+            serde_json::json!({"session_id": 4, "issue_id": 9, "points": 3})
+        );
+        let stored = DomainEvent::from_parts(event.event_type(), &event.data().to_string());
+        assert_eq!(stored.unwrap(), event);
+    }
+
+    #[test]
+    fn test_envelopes_keep_the_websocket_shape() {
+        let envelope = EventEnvelope {
+            sequence: 12,
+            project_id: 1,
+            user_id: Some(5),
+            event: DomainEvent::NotificationCountUpdated { count: 2 },
+            created_at: Utc::now(),
+        };
+        let json = serde_json::to_value(&envelope).unwrap();
+        assert_eq!(json["event_type"], "notification_count_updated");
+        assert_eq!(json["data"]["count"], 2);
+        assert_eq!(json["sequence"], 12);
+        assert!(envelope.visible_to(5) && !envelope.visible_to(6));
+        let parsed: EventEnvelope = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed, envelope);
+    }
+
+    #[test]
+    fn test_reader_waits_on_gaps_until_they_time_out() {
+        let envelope = |sequence| EventEnvelope {
+            sequence,
+            project_id: 1,
+            user_id: None,
+            event: DomainEvent::TagDeleted { id: 1 },
+            created_at: Utc::now(),
+        };
+        let sequences =
+            |batch: Vec<EventEnvelope>| -> Vec<i64> { batch.iter().map(|e| e.sequence).collect() };
+        let mut reader = OutboxReader::new(DatabaseConnection::Disconnected, 4);
+        let start = Instant::now();
+
+        // 6 may still be committing in another project
+        let ready = reader.settle(vec![envelope(5), envelope(7)], start);
+        assert_eq!(sequences(ready), vec![5]);
+        assert!(reader.waiting());
+
+        let ready = reader.settle(vec![envelope(6), envelope(7)], start);
+        assert_eq!(sequences(ready), vec![6, 7]);
+        assert!(!reader.waiting());
+
+        // 8 never turns up
+        assert!(reader.settle(vec![envelope(9)], start).is_empty());
+        let ready = reader.settle(vec![envelope(9)], start + GAP_TIMEOUT);
+        assert_eq!(sequences(ready), vec![9]);
+        assert_eq!(reader.cursor, 9);
+        assert!(!reader.waiting());
+    }
+
+    async fn store(db: &DatabaseConnection, sequence: i64, project_id: i32) {
+        domain_event::ActiveModel {
+            sequence: Set(sequence),
+            project_id: Set(project_id),
+            user_id: Set(None),
+            event_type: Set(TAG_DELETED.to_string()),
+            data: Set(serde_json::json!({ "id": sequence }).to_string()),
+            created_at: Set(Utc::now().into()),
+        }
+        .insert(db)
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_get_a_lower_sequence_that_commits_late() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let table = Schema::new(backend).create_table_from_entity(domain_event::Entity);
+        db.execute(backend.build(&table)).await.unwrap();
+        let events = DomainEventCrud::new(db.clone());
+
+        store(&db, 1, 1).await;
+        // 2 belongs to project 1 and is still committing when project 2's 3 is in
+        store(&db, 3, 2).await;
+        let start = events.latest_sequence_in_project(1).await.unwrap();
+        assert_eq!(start, 1);
+        assert_eq!(events.latest_sequence().await.unwrap(), 3);
+
+        store(&db, 2, 1).await;
+        let replayed = events
+            .find_since(start, Some(&[1]), None, MAX_REPLAY)
+            .await
+            .unwrap();
+        assert_eq!(
+            replayed.iter().map(|e| e.sequence).collect::<Vec<_>>(),
+            vec![2]
+        );
+    }
+}
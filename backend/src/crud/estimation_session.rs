@@ -0,0 +1,622 @@
+use crate::crud::domain_event::RevealedVote;
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
+use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::crud::point_scale::{self, PointScale};
+use crate::crud::project_user::ProjectUserCrud;
+use crate::entities::{estimation_session, estimation_vote, issue, project};
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+
+pub const SESSION_OPEN: &str = "open";
+pub const SESSION_REVEALED: &str = "revealed";
+pub const SESSION_COMMITTED: &str = "committed";
+pub const SESSION_CANCELLED: &str = "cancelled";
+
+// Votes further apart than this many steps on the project's scale need a discussion
+pub const DISAGREEMENT_STEPS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EstimateStats {
+    pub vote_count: usize,
+    pub min: i32,
+    pub max: i32,
+    pub mean: f64,
+    pub median: f64,
+    // Scale value nearest the median, offered as the default to commit
+    pub suggested: i32,
+    pub consensus: bool,
+    // Distance between the lowest and highest vote in steps of the scale
+    pub spread: usize,
+    pub disagreement: bool,
+}
+
+// Position of `points` on the scale, or of the nearest value if the scale has changed
+fn scale_step(scale: &PointScale, points: i32) -> usize {
+    scale
+        .values
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, v)| (v.value - points).abs())
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+pub fn estimate_stats(scale: &PointScale, votes: &[i32]) -> Option<EstimateStats> {
+    if votes.is_empty() {
+        return None;
+    }
+    let mut sorted = votes.to_vec();
+    sorted.sort_unstable();
+    let count = sorted.len();
+    let min = sorted[0];
+    let max = sorted[count - 1];
+    let mean = sorted.iter().map(|&p| p as f64).sum::<f64>() / count as f64;
+    let median = if count.is_multiple_of(2) {
+        (sorted[count / 2 - 1] + sorted[count / 2]) as f64 / 2.0
+    } else {
+        sorted[count / 2] as f64
+    };
+    // Ties round up: under-estimating hurts the schedule more than over-estimating
+    let suggested = scale
+        .values
+        .iter()
+        .map(|v| v.value)
+        .min_by(|a, b| {
+            let da = (*a as f64 - median).abs();
+            let db = (*b as f64 - median).abs();
+            da.total_cmp(&db).then(b.cmp(a))
+        })
+        .unwrap_or(max);
+    let spread = scale_step(scale, max) - scale_step(scale, min);
+
+    Some(EstimateStats {
+        vote_count: count,
+        min,
+        max,
+        mean,
+        median,
+        suggested,
+        consensus: min == max,
+        spread,
+        disagreement: spread >= DISAGREEMENT_STEPS,
+    })
+}
+
+#[derive(Clone)]
+pub struct EstimationSessionCrud {
+    app_state: AppState,
+}
+
+impl EstimationSessionCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn open(&self, issue_id: i32) -> Result<estimation_session::Model, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let project = self.app_state.current_project()?.clone();
+        let issue = self.find_issue(issue_id, project.id).await?;
+        self.ensure_member(user_id, project.id).await?;
+
+        if !point_scale::is_estimable(&project, issue.work_type) {
+            return Err(AppError::invalid(
+                "issueId",
+                "this issue's work type is not estimated in this project",
+            ));
+        }
+
+        // A partial unique index allows one open or revealed round per issue
+        let now = Utc::now();
+        let txn = self.app_state.db.begin().await?;
+        let session = estimation_session::ActiveModel {
+            issue_id: Set(issue_id),
+            project_id: Set(project.id),
+            created_by_id: Set(user_id),
+            state: Set(SESSION_OPEN.to_string()),
+            final_points: Set(None),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await
+        .map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => AppError::Conflict(
+                "An estimation session is already running for this issue".to_string(),
+            ),
+            _ => AppError::from(e),
+        })?;
+
+        HistoryCrud::new(self.app_state.db.clone())
+            .create_with_txn(
+                user_id,
+                Some(issue_id),
+                None,
+                None,
+                "started a planning poker round".to_string(),
+                &txn,
+            )
+            .await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let started = broadcaster
+            .record(
+                &txn,
+                project.id,
+                DomainEvent::EstimationStarted {
+                    session_id: session.id,
+                    issue_id,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&started).await;
+
+        Ok(session)
+    }
+
+    pub async fn find_by_id(&self, id: i32) -> Result<estimation_session::Model, AppError> {
+        let project_id = self.app_state.current_project()?.id;
+        estimation_session::Entity::find_by_id(id)
+            .filter(estimation_session::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Estimation session"))
+    }
+
+    pub async fn find_by_issue_id(
+        &self,
+        issue_id: i32,
+    ) -> Result<Vec<estimation_session::Model>, AppError> {
+        let project_id = self.app_state.current_project()?.id;
+        estimation_session::Entity::find()
+            .filter(estimation_session::Column::IssueId.eq(issue_id))
+            .filter(estimation_session::Column::ProjectId.eq(project_id))
+            .order_by_desc(estimation_session::Column::CreatedAt)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_votes(
+        &self,
+        session_id: i32,
+    ) -> Result<Vec<estimation_vote::Model>, AppError> {
+        estimation_vote::Entity::find()
+            .filter(estimation_vote::Column::SessionId.eq(session_id))
+            .order_by_asc(estimation_vote::Column::CreatedAt)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    // Re-voting while the round is open replaces the member's previous vote. The session
+    // row is locked while the vote is written, so a reveal or cancel running at the same
+    // time either sees the vote or makes this one fail.
+    pub async fn cast_vote(
+        &self,
+        session_id: i32,
+        points: i32,
+    ) -> Result<estimation_vote::Model, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let session = self.find_by_id(session_id).await?;
+        ensure_state(
+            &session,
+            &[SESSION_OPEN],
+            "Voting has closed for this round",
+        )?;
+        self.ensure_member(user_id, session.project_id).await?;
+
+        let project = self.find_project(session.project_id).await?;
+        let issue = self
+            .find_issue(session.issue_id, session.project_id)
+            .await?;
+        point_scale::check_points(&project, issue.work_type, Some(points))?;
+
+        let now = Utc::now();
+        let txn = self.app_state.db.begin().await?;
+        let locked = estimation_session::Entity::find_by_id(session_id)
+            .lock_exclusive()
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Estimation session"))?;
+        ensure_state(&locked, &[SESSION_OPEN], "Voting has closed for this round")?;
+
+        estimation_vote::Entity::insert(estimation_vote::ActiveModel {
+            session_id: Set(session_id),
+            user_id: Set(user_id),
+            points: Set(points),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        })
+        .on_conflict(
+            sea_query::OnConflict::columns([
+                estimation_vote::Column::SessionId,
+                estimation_vote::Column::UserId,
+            ])
+            .update_columns([
+                estimation_vote::Column::Points,
+                estimation_vote::Column::UpdatedAt,
+            ])
+            .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await
+        .map_err(|e| match e.sql_err() {
+            Some(SqlErr::UniqueConstraintViolation(_)) => {
+                AppError::Conflict("Your vote was changed at the same time".to_string())
+            }
+            _ => AppError::from(e),
+        })?;
+        let vote = estimation_vote::Entity::find()
+            .filter(estimation_vote::Column::SessionId.eq(session_id))
+            .filter(estimation_vote::Column::UserId.eq(user_id))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::Internal("estimation vote missing after insert".into()))?;
+
+        // The value stays hidden until the round is revealed; only announce who voted
+        let vote_count = estimation_vote::Entity::find()
+            .filter(estimation_vote::Column::SessionId.eq(session_id))
+            .count(&txn)
+            .await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let cast = broadcaster
+            .record(
+                &txn,
+                session.project_id,
+                DomainEvent::EstimationVoteCast {
+                    session_id,
+                    issue_id: session.issue_id,
+                    user_id,
+                    vote_count,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&cast).await;
+
+        Ok(vote)
+    }
+
+    pub async fn reveal(
+        &self,
+        session_id: i32,
+    ) -> Result<(estimation_session::Model, Vec<estimation_vote::Model>), AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let session = self.find_by_id(session_id).await?;
+        ensure_state(
+            &session,
+            &[SESSION_OPEN],
+            "This round has already been revealed",
+        )?;
+        self.ensure_member(user_id, session.project_id).await?;
+
+        // Votes are read after the round is claimed, so none can be cast in between
+        let txn = self.app_state.db.begin().await?;
+        let session = self
+            .transition(
+                session,
+                &[SESSION_OPEN],
+                SESSION_REVEALED,
+                "This round has already been revealed",
+                &txn,
+            )
+            .await?;
+        let votes = estimation_vote::Entity::find()
+            .filter(estimation_vote::Column::SessionId.eq(session_id))
+            .order_by_asc(estimation_vote::Column::CreatedAt)
+            .all(&txn)
+            .await?;
+        if votes.is_empty() {
+            return Err(AppError::Conflict(
+                "No votes have been cast in this round".to_string(),
+            ));
+        }
+        let stats = self.stats_for(&session, &votes).await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let revealed = broadcaster
+            .record(
+                &txn,
+                session.project_id,
+                DomainEvent::EstimationRevealed {
+                    session_id: session.id,
+                    issue_id: session.issue_id,
+                    votes: votes
+                        .iter()
+                        .map(|v| RevealedVote {
+                            user_id: v.user_id,
+                            points: v.points,
+                        })
+                        .collect(),
+                    stats,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&revealed).await;
+
+        Ok((session, votes))
+    }
+
+    // Writes the agreed value through IssueCrud so the points change is recorded in the
+    // issue history and broadcast like any other edit. The round is claimed with a
+    // conditional update in the same transaction, so concurrent commits apply it once.
+    pub async fn commit(
+        &self,
+        session_id: i32,
+        points: i32,
+    ) -> Result<estimation_session::Model, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let session = self.find_by_id(session_id).await?;
+        ensure_state(
+            &session,
+            &[SESSION_REVEALED],
+            "Votes must be revealed before an estimate is committed",
+        )?;
+        self.ensure_member(user_id, session.project_id).await?;
+
+        let votes = self.find_votes(session_id).await?;
+        let now = Utc::now();
+        let txn = self.app_state.db.begin().await?;
+        let claimed = estimation_session::Entity::update_many()
+            .col_expr(
+                estimation_session::Column::State,
+                Expr::value(SESSION_COMMITTED),
+            )
+            .col_expr(
+                estimation_session::Column::FinalPoints,
+                Expr::value(Some(points)),
+            )
+            .col_expr(estimation_session::Column::UpdatedAt, Expr::value(now))
+            .filter(estimation_session::Column::Id.eq(session_id))
+            .filter(estimation_session::Column::State.eq(SESSION_REVEALED))
+            .exec(&txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Err(AppError::Conflict(
+                "This round has already finished".to_string(),
+            ));
+        }
+
+        let issue_crud = IssueCrud::new(self.app_state.clone());
+        let pending = issue_crud
+            .update_with_txn(
+                session.issue_id,
+                None,
+                None,
+                None,
+                Some(Some(points)),
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                &txn,
+            )
+            .await?;
+
+        let vote_list = votes
+            .iter()
+            .map(|v| v.points.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        HistoryCrud::new(self.app_state.db.clone())
+            .create_with_txn(
+                user_id,
+                Some(session.issue_id),
+                None,
+                None,
+                format!(
+                    "committed planning poker estimate of {} (votes: {})",
+                    points, vote_list
+                ),
+                &txn,
+            )
+            .await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let committed = broadcaster
+            .record(
+                &txn,
+                session.project_id,
+                DomainEvent::EstimationCommitted {
+                    session_id: session.id,
+                    issue_id: session.issue_id,
+                    points,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+        issue_crud.complete_update(pending).await?;
+        broadcaster.announce(&committed).await;
+
+        Ok(estimation_session::Model {
+            state: SESSION_COMMITTED.to_string(),
+            final_points: Some(points),
+            updated_at: now.into(),
+            ..session
+        })
+    }
+
+    pub async fn cancel(&self, session_id: i32) -> Result<estimation_session::Model, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let session = self.find_by_id(session_id).await?;
+        ensure_state(
+            &session,
+            &[SESSION_OPEN, SESSION_REVEALED],
+            "This round has already finished",
+        )?;
+        self.ensure_member(user_id, session.project_id).await?;
+
+        let txn = self.app_state.db.begin().await?;
+        let session = self
+            .transition(
+                session,
+                &[SESSION_OPEN, SESSION_REVEALED],
+                SESSION_CANCELLED,
+                "This round has already finished",
+                &txn,
+            )
+            .await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let cancelled = broadcaster
+            .record(
+                &txn,
+                session.project_id,
+                DomainEvent::EstimationCancelled {
+                    session_id: session.id,
+                    issue_id: session.issue_id,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&cancelled).await;
+
+        Ok(session)
+    }
+
+    pub async fn stats_for(
+        &self,
+        session: &estimation_session::Model,
+        votes: &[estimation_vote::Model],
+    ) -> Result<Option<EstimateStats>, AppError> {
+        let project = self.find_project(session.project_id).await?;
+        let points: Vec<i32> = votes.iter().map(|v| v.points).collect();
+        Ok(estimate_stats(point_scale::scale_for(&project), &points))
+    }
+
+    // Moves the round on only if it is still in one of the `from` states, so of two
+    // requests racing on the same round one gets a conflict instead of both succeeding
+    async fn transition<C>(
+        &self,
+        session: estimation_session::Model,
+        from: &[&str],
+        to: &str,
+        message: &str,
+        txn: &C,
+    ) -> Result<estimation_session::Model, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        let now = Utc::now();
+        let claimed = estimation_session::Entity::update_many()
+            .col_expr(estimation_session::Column::State, Expr::value(to))
+            .col_expr(estimation_session::Column::UpdatedAt, Expr::value(now))
+            .filter(estimation_session::Column::Id.eq(session.id))
+            .filter(estimation_session::Column::State.is_in(from.iter().copied()))
+            .exec(txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Err(AppError::Conflict(message.to_string()));
+        }
+        Ok(estimation_session::Model {
+            state: to.to_string(),
+            updated_at: now.into(),
+            ..session
+        })
+    }
+
+    async fn ensure_member(&self, user_id: i32, project_id: i32) -> Result<(), AppError> {
+        let is_member = ProjectUserCrud::new(self.app_state.clone())
+            .is_member(user_id, project_id)
+            .await?;
+        if !is_member {
+            return Err(AppError::Forbidden(
+                "Only project members can take part in estimation".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    async fn find_issue(&self, issue_id: i32, project_id: i32) -> Result<issue::Model, AppError> {
+        issue::Entity::find_by_id(issue_id)
+            .filter(issue::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Issue"))
+    }
+
+    async fn find_project(&self, project_id: i32) -> Result<project::Model, AppError> {
+        project::Entity::find_by_id(project_id)
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Project"))
+    }
+}
+
+fn ensure_state(
+    session: &estimation_session::Model,
+    allowed: &[&str],
+    message: &str,
+) -> Result<(), AppError> {
+    if allowed.contains(&session.state.as_str()) {
+        Ok(())
+    } else {
+        Err(AppError::Conflict(message.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::point_scale::{POINT_SCALE_FIBONACCI, POINT_SCALE_MAP, POINT_SCALE_TSHIRT};
+
+    fn fibonacci() -> &'static PointScale {
+        &POINT_SCALE_MAP[POINT_SCALE_FIBONACCI]
+    }
+
+    #[test]
+    fn test_no_votes_have_no_stats() {
+        assert_eq!(estimate_stats(fibonacci(), &[]), None);
+    }
+
+    #[test]
+    fn test_unanimous_votes_reach_consensus() {
+        let stats = estimate_stats(fibonacci(), &[3, 3, 3]).unwrap();
+        assert!(stats.consensus);
+        assert!(!stats.disagreement);
+        assert_eq!(stats.spread, 0);
+        assert_eq!(stats.suggested, 3);
+    }
+
+    #[test]
+    fn test_adjacent_votes_are_not_flagged() {
+        let stats = estimate_stats(fibonacci(), &[3, 5, 5]).unwrap();
+        assert!(!stats.consensus);
+        assert_eq!(stats.spread, 1);
+        assert!(!stats.disagreement);
+        assert_eq!(stats.suggested, 5);
+    }
+
+    #[test]
+    fn test_distant_votes_are_flagged() {
+        let stats = estimate_stats(fibonacci(), &[1, 2, 8]).unwrap();
+        assert_eq!((stats.min, stats.max), (1, 8));
+        assert_eq!(stats.spread, 4);
+        assert!(stats.disagreement);
+        assert_eq!(stats.median, 2.0);
+        assert!((stats.mean - 11.0 / 3.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_suggestion_snaps_median_to_scale() {
+        // Median 4 sits between 3 and 5; ties round up
+        let stats = estimate_stats(fibonacci(), &[3, 5]).unwrap();
+        assert_eq!(stats.median, 4.0);
+        assert_eq!(stats.suggested, 5);
+
+        let tshirt = &POINT_SCALE_MAP[POINT_SCALE_TSHIRT];
+        let stats = estimate_stats(tshirt, &[1, 2, 2, 3]).unwrap();
+        assert_eq!(stats.suggested, 2);
+    }
+}
@@ -1,10 +1,21 @@
-use serde_json::Value;
+use crate::crud::domain_event::{DomainEventCrud, EventEnvelope};
+use crate::error::AppError;
+use crate::event_fanout::EventFanout;
+use crate::AppState;
+use sea_orm::{ConnectionTrait, DatabaseConnection, TransactionTrait};
 use std::sync::Arc;
 use tokio::sync::broadcast::Sender;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, error, warn};
 
+pub use crate::crud::domain_event::DomainEvent;
+
+/// Live events for WebSocket connections and other in-process consumers.
+pub type EventSender = Sender<Arc<EventEnvelope>>;
+
+#[derive(Clone)]
 pub struct EventBroadcaster {
-    tx: Arc<Sender<String>>,
+    db: DatabaseConnection,
+    tx: Arc<EventSender>,
 }
 
 pub const ISSUE_CREATED: &str = "issue_created";
@@ -23,60 +34,127 @@ pub const PROJECT_NOTE_DELETED: &str = "project_note_deleted";
 
 pub const REMINDER_DISPATCHED: &str = "reminder_dispatched";
 
+pub const ESTIMATION_STARTED: &str = "estimation_started";
+pub const ESTIMATION_VOTE_CAST: &str = "estimation_vote_cast";
+pub const ESTIMATION_REVEALED: &str = "estimation_revealed";
+pub const ESTIMATION_COMMITTED: &str = "estimation_committed";
+pub const ESTIMATION_CANCELLED: &str = "estimation_cancelled";
+
+// Sent only to the user it concerns
+pub const NOTIFICATION_COUNT_UPDATED: &str = "notification_count_updated";
+pub const MEMBERSHIP_REVOKED: &str = "membership_revoked";
+
+// Project-wide events a webhook can subscribe to
+pub const WEBHOOK_EVENTS: [&str; 16] = [
+    ISSUE_CREATED,
+    ISSUE_UPDATED,
+    ISSUE_DELETED,
+    TAG_CREATED,
+    TAG_UPDATED,
+    TAG_DELETED,
+    PROJECT_NOTE_PART_UPDATED,
+    PROJECT_NOTE_CREATED,
+    PROJECT_NOTE_UPDATED,
+    PROJECT_NOTE_DELETED,
+    REMINDER_DISPATCHED,
+    ESTIMATION_STARTED,
+    ESTIMATION_VOTE_CAST,
+    ESTIMATION_REVEALED,
+    ESTIMATION_COMMITTED,
+    ESTIMATION_CANCELLED,
+];
+
 impl EventBroadcaster {
-    pub fn new(tx: Arc<Sender<String>>) -> Self {
-        Self { tx }
+    pub fn new(app_state: &AppState) -> Self {
+        Self {
+            db: app_state.db.clone(),
+            tx: app_state.tx.clone(),
+        }
+    }
+
+    /// Records the event in the outbox as part of `txn`, so it is saved exactly when the
+    /// change it describes is. Announce it once the transaction has committed.
+    pub async fn record<C>(
+        &self,
+        txn: &C,
+        project_id: i32,
+        event: DomainEvent,
+    ) -> Result<EventEnvelope, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        DomainEventCrud::new(self.db.clone())
+            .append_with_txn(project_id, None, event, txn)
+            .await
     }
 
-    pub fn broadcast_event(&self, project_id: i32, event_type: &str, data: Value) {
-        let event = serde_json::json!({
-            "project_id": project_id,
-            "event_type": event_type,
-            "data": data
-        });
-
-        let event_json = match serde_json::to_string(&event) {
-            Ok(json) => json,
-            Err(e) => {
-                error!(
-                    "Failed to serialize event for project {}: {:?}",
-                    project_id, e
-                );
-                return;
-            }
-        };
+    /// Like `record`, but only forwarded to `user_id`'s own connections.
+    pub async fn record_for_user<C>(
+        &self,
+        txn: &C,
+        project_id: i32,
+        user_id: i32,
+        event: DomainEvent,
+    ) -> Result<EventEnvelope, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        DomainEventCrud::new(self.db.clone())
+            .append_with_txn(project_id, Some(user_id), event, txn)
+            .await
+    }
 
+    /// Lets every instance know a recorded event has been committed. Each one forwards the
+    /// outbox to its connections in sequence order, so a failure here only delays that.
+    pub async fn announce(&self, envelope: &EventEnvelope) {
         debug!(
-            "Broadcasting event '{}' for project {}: {}",
-            event_type, project_id, event_json
+            "Announcing event '{}' #{} for project {} to {} local subscribers",
+            envelope.event.event_type(),
+            envelope.sequence,
+            envelope.project_id,
+            self.tx.receiver_count()
         );
+        if let Err(e) = EventFanout::configured()
+            .announce(&self.db, envelope.sequence)
+            .await
+        {
+            warn!(
+                "Failed to announce event '{}' in project {}: {:?}",
+                envelope.event.event_type(),
+                envelope.project_id,
+                e
+            );
+        }
+    }
 
-        // Get current subscriber count for monitoring
-        let subscriber_count = self.tx.receiver_count();
-        info!(
-            "Broadcasting {} event to {} subscribers for project {}",
-            event_type, subscriber_count, project_id
-        );
+    /// Records and announces an event on its own, for callers with no transaction of their
+    /// own to record it in.
+    pub async fn publish(&self, project_id: i32, event: DomainEvent) {
+        self.send(project_id, None, event).await;
+    }
+
+    /// Like `publish`, but only forwarded to `user_id`'s own connections.
+    pub async fn publish_to_user(&self, project_id: i32, user_id: i32, event: DomainEvent) {
+        self.send(project_id, Some(user_id), event).await;
+    }
 
-        match self.tx.send(event_json) {
-            Ok(subscriber_count) => {
-                info!(
-                    "Event '{}' broadcast successful to {} subscribers for project {}",
-                    event_type, subscriber_count, project_id
-                );
-            }
-            Err(e) => {
-                warn!(
-                    "Failed to broadcast event '{}' for project {}: {:?} (subscribers: {})",
-                    event_type, project_id, e, subscriber_count
-                );
-
-                error!(
-                    "Broadcast channel is full! Consider increasing WEBSOCKET_BUFFER_SIZE. \
-                    Current subscribers: {}, Event: {} for project {}",
-                    subscriber_count, event_type, project_id
-                );
-            }
+    async fn send(&self, project_id: i32, user_id: Option<i32>, event: DomainEvent) {
+        let event_type = event.event_type();
+        let recorded = async {
+            let txn = self.db.begin().await?;
+            let envelope = DomainEventCrud::new(self.db.clone())
+                .append_with_txn(project_id, user_id, event, &txn)
+                .await?;
+            txn.commit().await?;
+            Ok::<_, AppError>(envelope)
+        }
+        .await;
+        match recorded {
+            Ok(envelope) => self.announce(&envelope).await,
+            Err(e) => error!(
+                "Failed to record '{}' event for project {}: {:?}",
+                event_type, project_id, e
+            ),
         }
     }
 }
@@ -1,24 +1,39 @@
+use crate::content_sniff::{self, SNIFF_LEN};
 use crate::crud::comment_file_upload::CommentFileUploadCrud;
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::history::HistoryCrud;
 use crate::crud::issue::IssueCrud;
 use crate::crud::project_note_history::ProjectNoteHistoryCrud;
-use crate::entities::{comment_file_upload, file_upload};
+use crate::crud::thumbnail;
+use crate::entities::{
+    comment_file_upload, direct_upload, file_blob, file_upload, issue, project_note,
+    upload_quarantine,
+};
 use crate::environment;
+use crate::error::AppError;
+use crate::signed_url;
+use crate::upload_scanner::{self, ScanInput, ScanVerdict};
 use crate::AppState;
+use base64::Engine;
+use futures::{Stream, StreamExt};
 use rand::Rng;
 use sea_orm::sea_query::Expr;
 use sea_orm::*;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{error, warn};
 
 use aws_config::BehaviorVersion;
 use aws_sdk_s3::config::Region;
 use aws_sdk_s3::presigning::PresigningConfig;
-use aws_sdk_s3::primitives::ByteStream;
+use aws_sdk_s3::primitives::{ByteStream, Length};
+use aws_sdk_s3::types::{CompletedMultipartUpload, CompletedPart};
 use aws_sdk_s3::Client as S3Client;
 
 #[derive(Clone)]
@@ -32,14 +47,17 @@ impl FileUploadCrud {
     }
 
     // Internal helpers to populate full_url consistently
-    async fn ensure_full_url(&self, model: &mut file_upload::Model) -> Result<(), DbErr> {
+    async fn ensure_full_url(&self, model: &mut file_upload::Model) -> Result<(), AppError> {
         if model.full_url.is_none() {
             model.full_url = Some(self.generate_browser_url(model).await?);
         }
+        if model.thumbnail_url.is_none() {
+            model.thumbnail_url = self.generate_thumbnail_url(model).await?;
+        }
         Ok(())
     }
 
-    async fn ensure_full_urls(&self, models: &mut [file_upload::Model]) -> Result<(), DbErr> {
+    async fn ensure_full_urls(&self, models: &mut [file_upload::Model]) -> Result<(), AppError> {
         for m in models {
             self.ensure_full_url(m).await?;
         }
@@ -47,9 +65,12 @@ impl FileUploadCrud {
     }
 
     // Generate a browser-accessible URL for a given upload.
-    // - local: backend serves the file via authorized endpoint
+    // - local: a signed, short-lived link to the backend's asset route
     // - aws: return a presigned S3 GET URL
-    pub async fn generate_browser_url(&self, upload: &file_upload::Model) -> Result<String, DbErr> {
+    pub async fn generate_browser_url(
+        &self,
+        upload: &file_upload::Model,
+    ) -> Result<String, AppError> {
         let scheme = environment::file_store_scheme();
         match scheme {
             "local" => {
@@ -57,71 +78,108 @@ impl FileUploadCrud {
                 let base = environment::public_base_url().to_string();
                 // Serve the actual asset via the public API route; include final filename for nicer URLs
                 Ok(format!(
-                    "{}/api/uploads/assets/{}/{}?token={}",
+                    "{}/api/uploads/assets/{}/{}?{}",
                     base.trim_end_matches('/'),
                     upload.id,
                     upload.final_filename,
-                    self.app_state.bearer_token.clone().unwrap()
+                    signed_url::query(signed_url::ASSET, upload.id, link_expiry())
                 ))
             }
             "aws" => {
                 let ttl = environment::s3_presign_ttl_seconds();
                 let store = FileStore::from_env().await?;
+                // Markup is downloaded rather than opened from the bucket's origin
+                let disposition = content_sniff::is_active_content(&upload.mime_type)
+                    .then(|| format!("attachment; filename=\"{}\"", upload.final_filename));
                 let url = store
-                    .presign_get_url(&upload.path, ttl)
+                    .presign_get_url(&upload.path, ttl, disposition)
                     .await
-                    .map_err(to_db_err)?;
+                    .map_err(to_store_err)?;
                 Ok(url)
             }
-            other => Err(DbErr::Custom(format!(
+            other => Err(AppError::Internal(format!(
                 "Unsupported FILE_STORE_SCHEME for URL generation: {}",
                 other
             ))),
         }
     }
 
-    // Create for Issue using in-memory bytes (multipart should assemble into bytes before calling)
-    pub async fn create_for_issue_from_bytes(
+    // Browser URL of the upload's preview, once the thumbnail job has stored one
+    pub async fn generate_thumbnail_url(
+        &self,
+        upload: &file_upload::Model,
+    ) -> Result<Option<String>, AppError> {
+        let Some(thumbnail_path) = upload.thumbnail_path.as_deref() else {
+            return Ok(None);
+        };
+        match environment::file_store_scheme() {
+            "local" => {
+                let base = environment::public_base_url().to_string();
+                Ok(Some(format!(
+                    "{}/api/uploads/thumbnails/{}?{}",
+                    base.trim_end_matches('/'),
+                    upload.id,
+                    signed_url::query(signed_url::THUMBNAIL, upload.id, link_expiry())
+                )))
+            }
+            "aws" => {
+                let ttl = environment::s3_presign_ttl_seconds();
+                let store = FileStore::from_env().await?;
+                let url = store
+                    .presign_get_url(thumbnail_path, ttl, None)
+                    .await
+                    .map_err(to_store_err)?;
+                Ok(Some(url))
+            }
+            other => Err(AppError::Internal(format!(
+                "Unsupported FILE_STORE_SCHEME for URL generation: {}",
+                other
+            ))),
+        }
+    }
+
+    // Create for Issue from a file already spooled to disk (see `spool`)
+    pub async fn create_for_issue(
         &self,
         issue_id: i32,
         uploader_user_id: i32,
         original_filename: String,
         mime_type: String,
-        bytes: Vec<u8>,
-    ) -> Result<file_upload::Model, DbErr> {
+        file: SpooledFile,
+    ) -> Result<file_upload::Model, AppError> {
         self.create_impl(
             Some(issue_id),
             None,
             uploader_user_id,
             original_filename,
             mime_type,
-            bytes,
+            UploadContent::Spooled(&file),
         )
         .await
     }
 
-    // Create for Project Note using in-memory bytes
-    pub async fn create_for_project_note_from_bytes(
+    // Create for Project Note from a spooled file
+    pub async fn create_for_project_note(
         &self,
         project_note_id: i32,
         uploader_user_id: i32,
         original_filename: String,
         mime_type: String,
-        bytes: Vec<u8>,
-    ) -> Result<file_upload::Model, DbErr> {
+        file: SpooledFile,
+    ) -> Result<file_upload::Model, AppError> {
         self.create_impl(
             None,
             Some(project_note_id),
             uploader_user_id,
             original_filename,
             mime_type,
-            bytes,
+            UploadContent::Spooled(&file),
         )
         .await
     }
 
     // Finders
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<file_upload::Model>, DbErr> {
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<file_upload::Model>, AppError> {
         let mut result = file_upload::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await?;
@@ -131,7 +189,10 @@ impl FileUploadCrud {
         Ok(result)
     }
 
-    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<file_upload::Model>, DbErr> {
+    pub async fn find_by_issue_id(
+        &self,
+        issue_id: i32,
+    ) -> Result<Vec<file_upload::Model>, AppError> {
         let mut items = file_upload::Entity::find()
             .filter(file_upload::Column::IssueId.eq(issue_id))
             .order_by_asc(file_upload::Column::UploadedAt)
@@ -145,7 +206,7 @@ impl FileUploadCrud {
     pub async fn find_unattached_by_issue_id(
         &self,
         issue_id: i32,
-    ) -> Result<Vec<file_upload::Model>, DbErr> {
+    ) -> Result<Vec<file_upload::Model>, AppError> {
         let mut items = file_upload::Entity::find()
             .filter(file_upload::Column::IssueId.eq(issue_id))
             .join(
@@ -171,7 +232,7 @@ impl FileUploadCrud {
     pub async fn find_by_project_note_id(
         &self,
         project_note_id: i32,
-    ) -> Result<Vec<file_upload::Model>, DbErr> {
+    ) -> Result<Vec<file_upload::Model>, AppError> {
         let mut items = file_upload::Entity::find()
             .filter(file_upload::Column::ProjectNoteId.eq(project_note_id))
             .order_by_asc(file_upload::Column::UploadedAt)
@@ -181,7 +242,7 @@ impl FileUploadCrud {
         Ok(items)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<(), DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<(), AppError> {
         // Fetch the model first so we can create history and broadcast after deletion
         let model_opt = file_upload::Entity::find_by_id(id)
             .one(&self.app_state.db)
@@ -230,12 +291,9 @@ impl FileUploadCrud {
                     .find_by_id(issue_id)
                     .await?
                 {
-                    let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-                    broadcaster.broadcast_event(
-                        project_id,
-                        ISSUE_UPDATED,
-                        serde_json::json!(issue),
-                    );
+                    EventBroadcaster::new(&self.app_state)
+                        .publish(project_id, DomainEvent::IssueUpdated(issue))
+                        .await;
                 }
             }
         }
@@ -243,7 +301,7 @@ impl FileUploadCrud {
         Ok(())
     }
 
-    pub async fn delete_with_no_history(&self, id: i32) -> Result<(), DbErr> {
+    pub async fn delete_with_no_history(&self, id: i32) -> Result<(), AppError> {
         // Wrapper: create a transaction and call the txn variant to perform work atomically
         let txn = self.app_state.db.begin().await?;
         self.delete_with_no_history_txn(id, &txn).await?;
@@ -256,7 +314,7 @@ impl FileUploadCrud {
         &self,
         id: i32,
         txn: &DatabaseTransaction,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let Some(model) = file_upload::Entity::find_by_id(id).one(txn).await? else {
             return Ok(());
         };
@@ -268,22 +326,35 @@ impl FileUploadCrud {
         // Delete the DB record for the file upload
         file_upload::Entity::delete_by_id(id).exec(txn).await?;
 
-        // Delete the underlying object from storage
-        let store = FileStore::from_env().await?;
-        store.delete(&model.path).await.map_err(to_db_err)?;
+        // Delete the underlying object from storage once nothing else shares it
+        if release_blob(&model, txn).await? {
+            let store = FileStore::from_env().await?;
+            store.delete(&model.path).await.map_err(to_store_err)?;
+            if let Some(thumbnail_path) = model.thumbnail_path.as_deref() {
+                store.delete(thumbnail_path).await.map_err(to_store_err)?;
+            }
+        }
 
         Ok(())
     }
 
     // Internal bulk-delete helper used by various scoped deletions
-    async fn delete_all_by_condition(&self, cond: sea_orm::Condition) -> Result<(), DbErr> {
+    async fn delete_all_by_condition(&self, cond: sea_orm::Condition) -> Result<(), AppError> {
         let txn = self.app_state.db.begin().await?;
+        self.delete_all_by_condition_txn(cond, &txn).await?;
+        txn.commit().await?;
+        Ok(())
+    }
 
+    async fn delete_all_by_condition_txn(
+        &self,
+        cond: sea_orm::Condition,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), AppError> {
         // Load all uploads matching the condition within the transaction
-        let uploads = file_upload::Entity::find().filter(cond).all(&txn).await?;
+        let uploads = file_upload::Entity::find().filter(cond).all(txn).await?;
 
         if uploads.is_empty() {
-            txn.commit().await?;
             return Ok(());
         }
 
@@ -292,35 +363,392 @@ impl FileUploadCrud {
 
         for u in uploads {
             // Remove comment-file mappings for this upload
-            cfu_crud
-                .delete_all_by_file_upload_id_txn(u.id, &txn)
-                .await?;
+            cfu_crud.delete_all_by_file_upload_id_txn(u.id, txn).await?;
 
             // Delete upload record
-            file_upload::Entity::delete_by_id(u.id).exec(&txn).await?;
+            file_upload::Entity::delete_by_id(u.id).exec(txn).await?;
 
-            // Delete the underlying stored file
-            store.delete(&u.path).await.map_err(to_db_err)?;
+            // Delete the underlying stored file unless other uploads share it
+            if release_blob(&u, txn).await? {
+                store.delete(&u.path).await.map_err(to_store_err)?;
+                if let Some(thumbnail_path) = u.thumbnail_path.as_deref() {
+                    store.delete(thumbnail_path).await.map_err(to_store_err)?;
+                }
+            }
         }
 
-        txn.commit().await?;
         Ok(())
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<(), DbErr> {
-        self.delete_all_by_condition(
+    pub async fn delete_all_by_issue_id_txn(
+        &self,
+        issue_id: i32,
+        txn: &DatabaseTransaction,
+    ) -> Result<(), AppError> {
+        self.delete_all_by_condition_txn(
             sea_orm::Condition::all().add(file_upload::Column::IssueId.eq(issue_id)),
+            txn,
         )
         .await
     }
 
-    pub async fn delete_all_by_project_note_id(&self, project_note_id: i32) -> Result<(), DbErr> {
+    pub async fn delete_all_by_project_note_id(
+        &self,
+        project_note_id: i32,
+    ) -> Result<(), AppError> {
         self.delete_all_by_condition(
             sea_orm::Condition::all().add(file_upload::Column::ProjectNoteId.eq(project_note_id)),
         )
         .await
     }
 
+    // Reads an upload's content, refusing to return it if it no longer matches its checksum
+    pub async fn read_verified(&self, upload: &file_upload::Model) -> Result<Vec<u8>, AppError> {
+        let store = FileStore::from_env().await?;
+        let bytes = store.get(&upload.path).await.map_err(to_store_err)?;
+        if let Some(expected) = upload.sha256.as_deref() {
+            if sha256_hex(&bytes) != expected {
+                error!(
+                    "Checksum mismatch for upload {} stored at {}",
+                    upload.id, upload.path
+                );
+                return Err(AppError::Internal(format!(
+                    "stored content of upload {} failed its checksum",
+                    upload.id
+                )));
+            }
+        }
+        Ok(bytes)
+    }
+
+    // Reads the stored preview of an upload
+    pub async fn read_thumbnail(&self, upload: &file_upload::Model) -> Result<Vec<u8>, AppError> {
+        let thumbnail_path = upload
+            .thumbnail_path
+            .as_deref()
+            .ok_or_else(|| AppError::not_found("Thumbnail"))?;
+        let store = FileStore::from_env().await?;
+        match store.get(thumbnail_path).await {
+            Ok(bytes) => Ok(bytes),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                Err(AppError::not_found("Thumbnail"))
+            }
+            Err(e) => Err(to_store_err(e)),
+        }
+    }
+
+    // Renders and stores the preview of an upload, then points it and every upload sharing
+    // its content at it. False when the upload has no preview to show.
+    pub async fn generate_thumbnail(&self, id: i32) -> Result<bool, AppError> {
+        let db = &self.app_state.db;
+        let Some(upload) = file_upload::Entity::find_by_id(id).one(db).await? else {
+            return Ok(false);
+        };
+        if upload.thumbnail_path.is_some() || !thumbnail::can_preview(&upload.mime_type) {
+            return Ok(false);
+        }
+
+        let key = build_thumbnail_key(&upload);
+        let store = FileStore::from_env().await?;
+        // Another upload of the same content may have rendered it already
+        if !store.exists(&key).await.map_err(to_store_err)? {
+            let content = self.read_verified(&upload).await?;
+            let Some(png) = thumbnail::render(&upload.mime_type, content).await else {
+                return Ok(false);
+            };
+            store
+                .put(&key, "image/png", png)
+                .await
+                .map_err(to_store_err)?;
+        }
+
+        let sharing = match upload.sha256.clone() {
+            Some(sha256) => file_upload::Column::Sha256.eq(sha256),
+            None => file_upload::Column::Id.eq(upload.id),
+        };
+        let updated = file_upload::Entity::find()
+            .filter(sharing.clone())
+            .filter(file_upload::Column::ThumbnailPath.is_null())
+            .all(db)
+            .await?;
+        file_upload::Entity::update_many()
+            .col_expr(file_upload::Column::ThumbnailPath, Expr::value(key))
+            .filter(sharing)
+            .filter(file_upload::Column::ThumbnailPath.is_null())
+            .exec(db)
+            .await?;
+
+        // Lets open issues and notes pick up the preview
+        for upload in updated {
+            if let Some(issue_id) = upload.issue_id {
+                IssueCrud::new(self.app_state.clone())
+                    .publish_updated(issue_id)
+                    .await?;
+            } else if let Some(project_note_id) = upload.project_note_id {
+                if let Some(note) = project_note::Entity::find_by_id(project_note_id)
+                    .one(db)
+                    .await?
+                {
+                    EventBroadcaster::new(&self.app_state)
+                        .publish(
+                            note.project_id,
+                            DomainEvent::ProjectNoteUpdated { project_note_id },
+                        )
+                        .await;
+                }
+            }
+        }
+        Ok(true)
+    }
+
+    // Content already attached in the uploader's own project needs no PUT. Anywhere else,
+    // skipping it would let a client attach a file it only knows the checksum of, and
+    // tell whether another project has it.
+    async fn stored_in_project(
+        &self,
+        store: &FileStore,
+        upload: &direct_upload::Model,
+    ) -> Result<bool, AppError> {
+        let issue_ids = issue::Entity::find()
+            .select_only()
+            .column(issue::Column::Id)
+            .filter(issue::Column::ProjectId.eq(upload.project_id))
+            .into_query();
+        let note_ids = project_note::Entity::find()
+            .select_only()
+            .column(project_note::Column::Id)
+            .filter(project_note::Column::ProjectId.eq(upload.project_id))
+            .into_query();
+        let referenced = file_upload::Entity::find()
+            .filter(file_upload::Column::Sha256.eq(upload.sha256.clone()))
+            .filter(
+                Condition::any()
+                    .add(file_upload::Column::IssueId.in_subquery(issue_ids))
+                    .add(file_upload::Column::ProjectNoteId.in_subquery(note_ids)),
+            )
+            .one(&self.app_state.db)
+            .await?
+            .is_some();
+        Ok(referenced
+            && store
+                .exists(&build_blob_key(&upload.sha256))
+                .await
+                .map_err(to_store_err)?)
+    }
+
+    // Presigns the PUT a client uses to send a file straight to S3. None when the same
+    // content is already attached in the project, in which case the client can finalize
+    // right away.
+    pub async fn presign_direct_upload(
+        &self,
+        upload: &direct_upload::Model,
+    ) -> Result<Option<PresignedPut>, AppError> {
+        let store = FileStore::from_env().await?;
+        if self.stored_in_project(&store, upload).await? {
+            return Ok(None);
+        }
+        let presigned = store
+            .presign_put_url(
+                &build_direct_key(&upload.id),
+                &upload.mime_type,
+                upload.size_bytes,
+                &upload.sha256,
+                environment::s3_presign_ttl_seconds(),
+            )
+            .await
+            .map_err(to_store_err)?;
+        Ok(Some(presigned))
+    }
+
+    // Records a file the client put in S3 itself, once the object is there and matches what
+    // was presigned. S3 already refused any body whose checksum differed.
+    pub async fn finalize_direct_upload(
+        &self,
+        upload: &direct_upload::Model,
+    ) -> Result<file_upload::Model, AppError> {
+        let store = FileStore::from_env().await?;
+        let key = if self.stored_in_project(&store, upload).await? {
+            build_blob_key(&upload.sha256)
+        } else {
+            build_direct_key(&upload.id)
+        };
+        if !store.exists(&key).await.map_err(to_store_err)? {
+            return Err(AppError::Conflict(
+                "The file has not been uploaded yet".to_string(),
+            ));
+        }
+        let object = store
+            .stat(&key)
+            .await
+            .map_err(to_store_err)?
+            .ok_or_else(|| AppError::Conflict("The file has not been uploaded yet".to_string()))?;
+        if object.size_bytes != upload.size_bytes {
+            return Err(AppError::invalid(
+                "file",
+                format!(
+                    "Uploaded {} bytes but {} were declared",
+                    object.size_bytes, upload.size_bytes
+                ),
+            ));
+        }
+        if let Some(content_type) = object.content_type.as_deref() {
+            if content_type != upload.mime_type {
+                return Err(AppError::invalid(
+                    "file",
+                    format!(
+                        "Uploaded as {} but {} was declared",
+                        content_type, upload.mime_type
+                    ),
+                ));
+            }
+        }
+
+        self.create_impl(
+            upload.issue_id,
+            upload.project_note_id,
+            upload.uploader_user_id,
+            upload.original_filename.clone(),
+            upload.mime_type.clone(),
+            UploadContent::Direct {
+                key: &key,
+                sha256: &upload.sha256,
+                size_bytes: upload.size_bytes,
+            },
+        )
+        .await
+    }
+
+    // Reads every object the project's uploads point at and checks it against its checksum
+    pub async fn audit(&self, project_id: i32) -> Result<StorageAudit, AppError> {
+        let db = &self.app_state.db;
+        let issue_ids = issue::Entity::find()
+            .select_only()
+            .column(issue::Column::Id)
+            .filter(issue::Column::ProjectId.eq(project_id))
+            .into_tuple::<i32>()
+            .all(db)
+            .await?;
+        let note_ids = project_note::Entity::find()
+            .select_only()
+            .column(project_note::Column::Id)
+            .filter(project_note::Column::ProjectId.eq(project_id))
+            .into_tuple::<i32>()
+            .all(db)
+            .await?;
+        let uploads = file_upload::Entity::find()
+            .filter(
+                Condition::any()
+                    .add(file_upload::Column::IssueId.is_in(issue_ids))
+                    .add(file_upload::Column::ProjectNoteId.is_in(note_ids)),
+            )
+            .order_by_asc(file_upload::Column::Id)
+            .all(db)
+            .await?;
+
+        let store = FileStore::from_env().await?;
+        let mut report = StorageAudit {
+            checked: uploads.len(),
+            ..Default::default()
+        };
+        // Uploads sharing a blob are checked with a single read
+        let mut by_path: HashMap<&str, Vec<&file_upload::Model>> = HashMap::new();
+        for upload in &uploads {
+            by_path
+                .entry(upload.path.as_str())
+                .or_default()
+                .push(upload);
+        }
+        for (path, sharing) in by_path {
+            let ids = sharing.iter().map(|u| u.id);
+            let bytes = match store.get(path).await {
+                Ok(bytes) => bytes,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    report.missing.extend(ids);
+                    continue;
+                }
+                Err(e) => return Err(to_store_err(e)),
+            };
+            let actual = sha256_hex(&bytes);
+            for upload in sharing {
+                match upload.sha256.as_deref() {
+                    None => report.unchecksummed += 1,
+                    Some(expected) if expected == actual => report.verified += 1,
+                    Some(_) => report.corrupted.push(upload.id),
+                }
+            }
+        }
+        report.missing.sort_unstable();
+        report.corrupted.sort_unstable();
+
+        // Each blob's reference count should match the uploads (in any project) using it
+        let checksums: Vec<String> = uploads.iter().filter_map(|u| u.sha256.clone()).collect();
+        let blobs = file_blob::Entity::find()
+            .filter(file_blob::Column::Sha256.is_in(checksums))
+            .all(db)
+            .await?;
+        for blob in blobs {
+            let references = file_upload::Entity::find()
+                .filter(file_upload::Column::Sha256.eq(blob.sha256.clone()))
+                .count(db)
+                .await? as i32;
+            if references != blob.ref_count {
+                report.ref_count_mismatches.push(RefCountMismatch {
+                    sha256: blob.sha256,
+                    ref_count: blob.ref_count,
+                    references,
+                });
+            }
+        }
+        report
+            .ref_count_mismatches
+            .sort_by(|a, b| a.sha256.cmp(&b.sha256));
+
+        Ok(report)
+    }
+
+    // Moves flagged content under quarantine/ and records it, instead of attaching it
+    async fn quarantine(
+        &self,
+        store: &FileStore,
+        content: &UploadContent<'_>,
+        mut record: upload_quarantine::ActiveModel,
+    ) -> Result<(), AppError> {
+        let sha256 = record.sha256.clone().unwrap();
+        let key = format!("quarantine/{}", sha256);
+        match content {
+            UploadContent::Spooled(file) => {
+                let mime_type = record.mime_type.clone().unwrap();
+                store
+                    .put_file(&key, &mime_type, file.size_bytes, &file.path)
+                    .await
+                    .map_err(to_store_err)?;
+            }
+            // Nothing references the object the client put in place, so it is moved as a whole
+            UploadContent::Direct { key: staged, .. } => {
+                store.rename(staged, &key).await.map_err(to_store_err)?;
+            }
+        }
+        record.path = Set(key);
+        let record = record.insert(&self.app_state.db).await?;
+        warn!(
+            "Quarantined upload '{}' from user {}: {} matched {}",
+            record.original_filename, record.uploader_user_id, record.scanner, record.signature
+        );
+        Ok(())
+    }
+
+    // Quarantined uploads of a project, newest first
+    pub async fn find_quarantined(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<upload_quarantine::Model>, AppError> {
+        Ok(upload_quarantine::Entity::find()
+            .filter(upload_quarantine::Column::ProjectId.eq(project_id))
+            .order_by_desc(upload_quarantine::Column::CreatedAt)
+            .all(&self.app_state.db)
+            .await?)
+    }
+
     // Internal create implementation per spec
     async fn create_impl(
         &self,
@@ -329,42 +757,151 @@ impl FileUploadCrud {
         uploader_user_id: i32,
         original_filename: String,
         mime_type: String,
-        bytes: Vec<u8>,
-    ) -> Result<file_upload::Model, DbErr> {
+        content: UploadContent<'_>,
+    ) -> Result<file_upload::Model, AppError> {
+        let (sha256, size_bytes) = match &content {
+            UploadContent::Spooled(file) => (file.sha256.clone(), file.size_bytes),
+            UploadContent::Direct {
+                sha256, size_bytes, ..
+            } => (String::from(*sha256), *size_bytes),
+        };
+
         // Validation
-        if bytes.is_empty() {
-            return Err(DbErr::Custom(
-                "Empty file uploads are not allowed".to_string(),
+        if size_bytes == 0 {
+            return Err(AppError::invalid(
+                "file",
+                "Empty file uploads are not allowed",
             ));
         }
-        let max_mb: i64 = environment::max_upload_size_mb();
-        let max_bytes = max_mb * 1024 * 1024;
-        if (bytes.len() as i64) > max_bytes {
-            return Err(DbErr::Custom(format!(
-                "File exceeds max size of {} MB",
-                max_mb
-            )));
-        }
+        check_upload_size(size_bytes)?;
 
-        // Naming per spec
+        // Naming per spec; the GUID names the upload, the checksum names the stored object
         let guid = generate_guid(10);
         let ext = derive_extension(&original_filename, &mime_type);
         let final_filename = format!("{}.{}", guid, ext);
-        let storage_key = build_storage_key(&guid, &ext);
+        let storage_key = build_blob_key(&sha256);
 
         let store = FileStore::from_env().await?;
 
-        // Handle collisions by regenerating GUID
-        let storage_key = ensure_unique_key(&store, storage_key, &ext, 5).await?;
-        let size_bytes = bytes.len() as i64;
+        // The declared type must match what the content actually is
+        let head = match &content {
+            UploadContent::Spooled(file) => file.head.clone(),
+            UploadContent::Direct { key, .. } => store
+                .read_head(key, SNIFF_LEN)
+                .await
+                .map_err(to_store_err)?,
+        };
+        content_sniff::check_content_type(&mime_type, &head).map_err(|message| {
+            warn!(
+                "Rejected upload '{}' declared as {}: {}",
+                original_filename, mime_type, message
+            );
+            AppError::invalid("file", message)
+        })?;
+
+        // New content is scanned before it is stored; stored content was scanned when it first arrived
+        if let Some(scanner) = upload_scanner::from_env().map_err(AppError::Internal)? {
+            let stored = file_blob::Entity::find_by_id(sha256.clone())
+                .one(&self.app_state.db)
+                .await?
+                .is_some();
+            if !stored {
+                let input: ScanInput = match &content {
+                    UploadContent::Spooled(file) => {
+                        Box::new(fs::File::open(&file.path).await.map_err(to_store_err)?)
+                    }
+                    UploadContent::Direct { key, .. } => {
+                        store.open_read(key).await.map_err(to_store_err)?
+                    }
+                };
+                let verdict = scanner.scan(input).await.map_err(|e| {
+                    error!("Could not scan upload '{}': {}", original_filename, e);
+                    AppError::Upstream("The virus scanner is unavailable".to_string())
+                })?;
+                if let ScanVerdict::Infected(signature) = verdict {
+                    self.quarantine(
+                        &store,
+                        &content,
+                        upload_quarantine::ActiveModel {
+                            project_id: Set(self.app_state.current_project()?.id),
+                            issue_id: Set(issue_id),
+                            project_note_id: Set(project_note_id),
+                            uploader_user_id: Set(uploader_user_id),
+                            original_filename: Set(original_filename),
+                            mime_type: Set(mime_type),
+                            size_bytes: Set(size_bytes),
+                            sha256: Set(sha256),
+                            scanner: Set(scanner.kind().to_string()),
+                            signature: Set(signature),
+                            created_at: Set(chrono::Utc::now().into()),
+                            ..Default::default()
+                        },
+                    )
+                    .await?;
+                    return Err(AppError::invalid(
+                        "file",
+                        "was flagged by the virus scanner and quarantined",
+                    ));
+                }
+            }
+        }
 
-        // Begin pseudo-atomic: write then insert DB; cleanup object if DB insert fails
+        // Begin pseudo-atomic: claim the blob, write it if new, then insert the upload;
+        // cleanup the object if the DB insert fails
         let txn = self.app_state.db.begin().await?;
 
-        store
-            .put(&storage_key, &mime_type, size_bytes, &bytes)
-            .await
-            .map_err(to_db_err)?;
+        file_blob::Entity::insert(file_blob::ActiveModel {
+            sha256: Set(sha256.clone()),
+            path: Set(storage_key.clone()),
+            size_bytes: Set(size_bytes),
+            ref_count: Set(1),
+            created_at: Set(chrono::Utc::now().into()),
+        })
+        .on_conflict(
+            sea_query::OnConflict::column(file_blob::Column::Sha256)
+                .value(
+                    file_blob::Column::RefCount,
+                    Expr::col((file_blob::Entity, file_blob::Column::RefCount)).add(1),
+                )
+                .to_owned(),
+        )
+        .exec_without_returning(&txn)
+        .await?;
+        let blob = file_blob::Entity::find_by_id(sha256.clone())
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::Internal("file blob missing after insert".into()))?;
+
+        // The same content is usually already stored; it is only rewritten if the object went missing.
+        // A direct upload's staged object, checked before getting here, is moved into place.
+        let mut wrote_blob = false;
+        let missing =
+            blob.ref_count == 1 || !store.exists(&blob.path).await.map_err(to_store_err)?;
+        match &content {
+            UploadContent::Spooled(file) if missing => {
+                store
+                    .put_file(&blob.path, &mime_type, size_bytes, &file.path)
+                    .await
+                    .map_err(to_store_err)?;
+                wrote_blob = blob.ref_count == 1;
+            }
+            UploadContent::Direct { key, .. } if missing && *key != blob.path => {
+                store.rename(key, &blob.path).await.map_err(to_store_err)?;
+                wrote_blob = blob.ref_count == 1;
+            }
+            _ => {}
+        }
+
+        // Uploads of content that already has a preview share it
+        let thumbnail_path = file_upload::Entity::find()
+            .select_only()
+            .column(file_upload::Column::ThumbnailPath)
+            .filter(file_upload::Column::Sha256.eq(sha256.clone()))
+            .filter(file_upload::Column::ThumbnailPath.is_not_null())
+            .into_tuple::<Option<String>>()
+            .one(&txn)
+            .await?
+            .flatten();
 
         let result = file_upload::ActiveModel {
             issue_id: Set(issue_id),
@@ -372,25 +909,37 @@ impl FileUploadCrud {
             uploader_user_id: Set(uploader_user_id),
             original_filename: Set(original_filename),
             final_filename: Set(final_filename),
-            path: Set(storage_key.clone()),
+            path: Set(blob.path.clone()),
             mime_type: Set(mime_type),
             size_bytes: Set(size_bytes),
+            sha256: Set(Some(sha256)),
+            thumbnail_path: Set(thumbnail_path),
             ..Default::default()
         }
         .insert(&txn)
         .await
-        .map_err(|e| {
-            // Clean up the file on DB error
-            let store = store.clone();
-            let key = storage_key.clone();
-            tokio::spawn(async move {
-                let _ = store.delete(&key).await;
-            });
-            e
+        .inspect_err(|_e| {
+            // Clean up the file on DB error, unless it was already shared
+            if wrote_blob {
+                let store = store.clone();
+                let key = blob.path.clone();
+                tokio::spawn(async move {
+                    let _ = store.delete(&key).await;
+                });
+            }
         })?;
 
         txn.commit().await?;
 
+        // A staged copy of content that was already stored is no longer needed
+        if let UploadContent::Direct { key, .. } = &content {
+            if *key != blob.path {
+                if let Err(e) = store.delete(key).await {
+                    warn!("Failed to remove staged direct upload '{}': {}", key, e);
+                }
+            }
+        }
+
         // Create a history record for issue-scoped uploads
         if let Some(issue_id) = issue_id {
             if let Some(current_user_id) = self.app_state.user.as_ref().map(|u| u.id) {
@@ -421,13 +970,191 @@ impl FileUploadCrud {
             }
         }
 
-        // Populate browser URL before returning
+        if result.thumbnail_path.is_none() {
+            thumbnail::enqueue(&self.app_state, result.id, &result.mime_type).await;
+        }
+
+        // Populate browser URLs before returning
         let mut model = result;
-        model.full_url = Some(self.generate_browser_url(&model).await?);
+        self.ensure_full_url(&mut model).await?;
         Ok(model)
     }
 }
 
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StorageAudit {
+    pub checked: usize,
+    pub verified: usize,
+    // Uploads stored before checksums were kept; only their presence is checked
+    pub unchecksummed: usize,
+    pub missing: Vec<i32>,
+    pub corrupted: Vec<i32>,
+    pub ref_count_mismatches: Vec<RefCountMismatch>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RefCountMismatch {
+    pub sha256: String,
+    pub ref_count: i32,
+    pub references: i32,
+}
+
+// A presigned request for the client to send as-is, with these headers and the file as the body
+#[derive(Debug, Serialize)]
+pub struct PresignedPut {
+    pub method: String,
+    pub url: String,
+    pub headers: BTreeMap<String, String>,
+}
+
+struct StoredObject {
+    size_bytes: i64,
+    // Not known for the local store
+    content_type: Option<String>,
+}
+
+// Deletes the object an unfinished direct upload may have left behind
+pub async fn remove_staged_direct_upload(upload_id: &str) -> Result<(), AppError> {
+    let store = FileStore::from_env().await?;
+    store
+        .delete(&build_direct_key(upload_id))
+        .await
+        .map_err(to_store_err)
+}
+
+// The bytes behind a new upload
+enum UploadContent<'a> {
+    // Received by the backend; written to the store unless the same content is already there
+    Spooled(&'a SpooledFile),
+    // Already put in the store by the client with a presigned URL, at `key`
+    Direct {
+        key: &'a str,
+        sha256: &'a str,
+        size_bytes: i64,
+    },
+}
+
+// An upload written to UPLOAD_TMP_DIR, with its size and checksum worked out on the way.
+// The file is removed when this is dropped, unless the store has already moved it into place.
+pub struct SpooledFile {
+    pub path: PathBuf,
+    pub size_bytes: i64,
+    pub sha256: String,
+    // The first SNIFF_LEN bytes, for checking the declared type
+    pub head: Vec<u8>,
+}
+
+impl SpooledFile {
+    // Takes over a file that was written some other way, e.g. the parts of a resumable upload
+    pub async fn from_file(path: PathBuf) -> Result<Self, AppError> {
+        let mut file = fs::File::open(&path).await.map_err(to_store_err)?;
+        let mut hasher = Sha256::new();
+        let mut size_bytes = 0i64;
+        let mut head = Vec::new();
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let read = file.read(&mut buf).await.map_err(to_store_err)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            keep_head(&mut head, &buf[..read]);
+            size_bytes += read as i64;
+        }
+        Ok(Self {
+            path,
+            size_bytes,
+            sha256: hex::encode(hasher.finalize()),
+            head,
+        })
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+// Writes a body to a temp file chunk by chunk, so memory use doesn't grow with the file,
+// and stops as soon as it goes over MAX_UPLOAD_SIZE_MB
+pub async fn spool<S, B, E>(stream: S) -> Result<SpooledFile, AppError>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+    E: Display,
+{
+    let dir = environment::upload_tmp_dir();
+    fs::create_dir_all(dir).await.map_err(to_store_err)?;
+    let mut spooled = SpooledFile {
+        path: dir.join(format!(".spool-{}", generate_guid(16))),
+        size_bytes: 0,
+        sha256: String::new(),
+        head: Vec::new(),
+    };
+    let mut file = fs::File::create(&spooled.path)
+        .await
+        .map_err(to_store_err)?;
+    let mut hasher = Sha256::new();
+    let mut stream = std::pin::pin!(stream);
+    while let Some(chunk) = stream.next().await {
+        let chunk =
+            chunk.map_err(|e| AppError::BadRequest(format!("Invalid upload body: {}", e)))?;
+        let chunk = chunk.as_ref();
+        spooled.size_bytes += chunk.len() as i64;
+        check_upload_size(spooled.size_bytes)?;
+        hasher.update(chunk);
+        keep_head(&mut spooled.head, chunk);
+        file.write_all(chunk).await.map_err(to_store_err)?;
+    }
+    file.flush().await.map_err(to_store_err)?;
+    spooled.sha256 = hex::encode(hasher.finalize());
+    Ok(spooled)
+}
+
+fn keep_head(head: &mut Vec<u8>, chunk: &[u8]) {
+    let wanted = SNIFF_LEN.saturating_sub(head.len()).min(chunk.len());
+    head.extend_from_slice(&chunk[..wanted]);
+}
+
+pub fn check_upload_size(size_bytes: i64) -> Result<(), AppError> {
+    let max_mb = environment::max_upload_size_mb();
+    if size_bytes > max_mb * 1024 * 1024 {
+        return Err(AppError::invalid(
+            "file",
+            format!("File exceeds max size of {} MB", max_mb),
+        ));
+    }
+    Ok(())
+}
+
+// Drops one reference to the upload's blob; true when the stored object should be deleted.
+// Uploads without a checksum own their object outright.
+async fn release_blob(
+    upload: &file_upload::Model,
+    txn: &DatabaseTransaction,
+) -> Result<bool, AppError> {
+    let Some(sha256) = upload.sha256.clone() else {
+        return Ok(true);
+    };
+    file_blob::Entity::update_many()
+        .col_expr(
+            file_blob::Column::RefCount,
+            Expr::col(file_blob::Column::RefCount).sub(1),
+        )
+        .filter(file_blob::Column::Sha256.eq(sha256.clone()))
+        .exec(txn)
+        .await?;
+    let deleted = file_blob::Entity::delete_many()
+        .filter(file_blob::Column::Sha256.eq(sha256))
+        .filter(file_blob::Column::RefCount.lte(0))
+        .exec(txn)
+        .await?;
+    Ok(deleted.rows_affected > 0)
+}
+
 // ---------------- Storage abstraction (single active backend) ----------------
 
 #[derive(Clone)]
@@ -442,12 +1169,12 @@ enum FileStoreInner {
 }
 
 impl FileStore {
-    async fn from_env() -> Result<Self, DbErr> {
+    async fn from_env() -> Result<Self, AppError> {
         let scheme = environment::file_store_scheme().to_string();
         match scheme.as_str() {
             "local" => {
                 let base = environment::base_file_path().cloned().ok_or_else(|| {
-                    DbErr::Custom("BASE_FILE_PATH must be set for local file store".into())
+                    AppError::Internal("BASE_FILE_PATH must be set for local file store".into())
                 })?;
                 Ok(Self {
                     inner: FileStoreInner::Local(LocalFileStore { base_path: base }),
@@ -456,7 +1183,7 @@ impl FileStore {
             "aws" => {
                 let bucket = environment::s3_bucket()
                     .ok_or_else(|| {
-                        DbErr::Custom("S3_BUCKET must be set when using aws store".into())
+                        AppError::Internal("S3_BUCKET must be set when using aws store".into())
                     })?
                     .to_string();
 
@@ -480,7 +1207,7 @@ impl FileStore {
                     inner: FileStoreInner::Aws(AwsS3FileStore { client, bucket }),
                 })
             }
-            other => Err(DbErr::Custom(format!(
+            other => Err(AppError::Internal(format!(
                 "Unsupported FILE_STORE_SCHEME: {} (expected 'local' or 'aws')",
                 other
             ))),
@@ -494,22 +1221,63 @@ impl FileStore {
         }
     }
 
-    async fn put(
+    async fn put_file(
         &self,
         storage_key: &str,
         content_type: &str,
         content_length: i64,
-        bytes: &[u8],
+        source: &Path,
     ) -> Result<(), std::io::Error> {
         match &self.inner {
-            FileStoreInner::Local(s) => s.put(storage_key, bytes).await,
+            FileStoreInner::Local(s) => s.put_file(storage_key, source).await,
             FileStoreInner::Aws(s) => {
-                s.put(storage_key, content_type, content_length, bytes)
+                s.put_file(storage_key, content_type, content_length, source)
                     .await
             }
         }
     }
 
+    async fn put(
+        &self,
+        storage_key: &str,
+        content_type: &str,
+        content: Vec<u8>,
+    ) -> Result<(), std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.put(storage_key, &content).await,
+            FileStoreInner::Aws(s) => s.put(storage_key, content_type, content).await,
+        }
+    }
+
+    async fn get(&self, storage_key: &str) -> Result<Vec<u8>, std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.get(storage_key).await,
+            FileStoreInner::Aws(s) => s.get(storage_key).await,
+        }
+    }
+
+    // Up to the first `len` bytes of an object
+    async fn read_head(&self, storage_key: &str, len: usize) -> Result<Vec<u8>, std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.read_head(storage_key, len).await,
+            FileStoreInner::Aws(s) => s.read_head(storage_key, len).await,
+        }
+    }
+
+    async fn open_read(&self, storage_key: &str) -> Result<ScanInput, std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.open_read(storage_key).await,
+            FileStoreInner::Aws(s) => s.open_read(storage_key).await,
+        }
+    }
+
+    async fn rename(&self, from_key: &str, to_key: &str) -> Result<(), std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.rename(from_key, to_key).await,
+            FileStoreInner::Aws(s) => s.rename(from_key, to_key).await,
+        }
+    }
+
     async fn delete(&self, storage_key: &str) -> Result<(), std::io::Error> {
         match &self.inner {
             FileStoreInner::Local(s) => s.delete(storage_key).await,
@@ -521,17 +1289,50 @@ impl FileStore {
         &self,
         storage_key: &str,
         ttl_secs: u64,
+        content_disposition: Option<String>,
     ) -> Result<String, std::io::Error> {
         match &self.inner {
-            FileStoreInner::Local(_) => Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
+            FileStoreInner::Local(_) => Err(std::io::Error::other(
+                "presign not supported for local store",
+            )),
+            FileStoreInner::Aws(s) => {
+                s.presign_get_url(storage_key, ttl_secs, content_disposition)
+                    .await
+            }
+        }
+    }
+
+    async fn presign_put_url(
+        &self,
+        storage_key: &str,
+        content_type: &str,
+        content_length: i64,
+        sha256: &str,
+        ttl_secs: u64,
+    ) -> Result<PresignedPut, std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(_) => Err(std::io::Error::other(
                 "presign not supported for local store",
             )),
-            FileStoreInner::Aws(s) => s.presign_get_url(storage_key, ttl_secs).await,
+            FileStoreInner::Aws(s) => {
+                s.presign_put_url(storage_key, content_type, content_length, sha256, ttl_secs)
+                    .await
+            }
+        }
+    }
+
+    async fn stat(&self, storage_key: &str) -> Result<Option<StoredObject>, std::io::Error> {
+        match &self.inner {
+            FileStoreInner::Local(s) => s.stat(storage_key).await,
+            FileStoreInner::Aws(s) => s.stat(storage_key).await,
         }
     }
 }
 
+// Files above this go to S3 as a multipart upload, in parts of S3_PART_SIZE
+const S3_MULTIPART_THRESHOLD: u64 = 16 * 1024 * 1024;
+const S3_PART_SIZE: u64 = 8 * 1024 * 1024;
+
 #[derive(Clone)]
 struct LocalFileStore {
     base_path: PathBuf,
@@ -553,22 +1354,23 @@ impl LocalFileStore {
         Ok(fs::metadata(path).await.is_ok())
     }
 
-    async fn put(&self, storage_key: &str, bytes: &[u8]) -> Result<(), std::io::Error> {
+    async fn put_file(&self, storage_key: &str, source: &Path) -> Result<(), std::io::Error> {
         let full_path = self.full_path(storage_key);
         if let Some(parent) = full_path.parent() {
             fs::create_dir_all(parent).await?;
         }
 
-        // Write atomically: write to temp then rename
-        let tmp_path = tmp_path_for(&full_path);
-        if let Some(parent) = tmp_path.parent() {
-            fs::create_dir_all(parent).await?;
+        // Usually on the same filesystem, so the spooled file just moves into place
+        if fs::rename(source, &full_path).await.is_ok() {
+            return Ok(());
         }
 
-        let mut file = fs::File::create(&tmp_path).await?;
-        file.write_all(bytes).await?;
-        file.flush().await?;
-        drop(file);
+        // Otherwise copy atomically: copy to temp then rename
+        let tmp_path = tmp_path_for(&full_path);
+        if let Err(e) = fs::copy(source, &tmp_path).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
 
         // Atomic rename into place
         if let Err(e) = fs::rename(&tmp_path, &full_path).await {
@@ -579,6 +1381,58 @@ impl LocalFileStore {
         Ok(())
     }
 
+    // For small derived objects; written to a temp file and renamed into place
+    async fn put(&self, storage_key: &str, content: &[u8]) -> Result<(), std::io::Error> {
+        let full_path = self.full_path(storage_key);
+        if let Some(parent) = full_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        let tmp_path = tmp_path_for(&full_path);
+        if let Err(e) = fs::write(&tmp_path, content).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+        if let Err(e) = fs::rename(&tmp_path, &full_path).await {
+            let _ = fs::remove_file(&tmp_path).await;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    async fn get(&self, storage_key: &str) -> Result<Vec<u8>, std::io::Error> {
+        fs::read(self.full_path(storage_key)).await
+    }
+
+    async fn read_head(&self, storage_key: &str, len: usize) -> Result<Vec<u8>, std::io::Error> {
+        let file = fs::File::open(self.full_path(storage_key)).await?;
+        let mut head = Vec::with_capacity(len);
+        file.take(len as u64).read_to_end(&mut head).await?;
+        Ok(head)
+    }
+
+    async fn open_read(&self, storage_key: &str) -> Result<ScanInput, std::io::Error> {
+        Ok(Box::new(fs::File::open(self.full_path(storage_key)).await?))
+    }
+
+    async fn rename(&self, from_key: &str, to_key: &str) -> Result<(), std::io::Error> {
+        let to_path = self.full_path(to_key);
+        if let Some(parent) = to_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        fs::rename(self.full_path(from_key), to_path).await
+    }
+
+    async fn stat(&self, storage_key: &str) -> Result<Option<StoredObject>, std::io::Error> {
+        match fs::metadata(self.full_path(storage_key)).await {
+            Ok(meta) => Ok(Some(StoredObject {
+                size_bytes: meta.len() as i64,
+                content_type: None,
+            })),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     async fn delete(&self, storage_key: &str) -> Result<(), std::io::Error> {
         let full_path = self.full_path(storage_key);
         if fs::metadata(&full_path).await.is_ok() {
@@ -611,7 +1465,7 @@ impl AwsS3FileStore {
                     .max_keys(1)
                     .send()
                     .await
-                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+                    .map_err(|e| std::io::Error::other(e.to_string()))?;
                 let objs = list.contents();
                 let found = objs.iter().any(|o| match o.key() {
                     Some(k) => k == storage_key,
@@ -622,27 +1476,201 @@ impl AwsS3FileStore {
         }
     }
 
-    async fn put(
+    async fn put_file(
         &self,
         storage_key: &str,
         content_type: &str,
         content_length: i64,
-        bytes: &[u8],
+        source: &Path,
+    ) -> Result<(), std::io::Error> {
+        if content_length as u64 <= S3_MULTIPART_THRESHOLD {
+            let body = ByteStream::from_path(source)
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            self.client
+                .put_object()
+                .bucket(&self.bucket)
+                .key(storage_key)
+                .content_type(content_type.to_string())
+                .content_length(content_length)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            return Ok(());
+        }
+
+        let created = self
+            .client
+            .create_multipart_upload()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .content_type(content_type.to_string())
+            .send()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let upload_id = created
+            .upload_id()
+            .ok_or_else(|| std::io::Error::other("S3 returned no multipart upload id"))?;
+
+        let result = self
+            .upload_parts(storage_key, upload_id, content_length as u64, source)
+            .await;
+        if result.is_err() {
+            // Don't leave the parts behind to be billed for
+            let _ = self
+                .client
+                .abort_multipart_upload()
+                .bucket(&self.bucket)
+                .key(storage_key)
+                .upload_id(upload_id)
+                .send()
+                .await;
+        }
+        result
+    }
+
+    // Each part is streamed straight from its slice of the file
+    async fn upload_parts(
+        &self,
+        storage_key: &str,
+        upload_id: &str,
+        content_length: u64,
+        source: &Path,
+    ) -> Result<(), std::io::Error> {
+        let mut parts = Vec::new();
+        let mut offset = 0u64;
+        let mut part_number = 1;
+        while offset < content_length {
+            let length = S3_PART_SIZE.min(content_length - offset);
+            let body = ByteStream::read_from()
+                .path(source)
+                .offset(offset)
+                .length(Length::Exact(length))
+                .build()
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            let uploaded = self
+                .client
+                .upload_part()
+                .bucket(&self.bucket)
+                .key(storage_key)
+                .upload_id(upload_id)
+                .part_number(part_number)
+                .content_length(length as i64)
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| std::io::Error::other(e.to_string()))?;
+            parts.push(
+                CompletedPart::builder()
+                    .part_number(part_number)
+                    .set_e_tag(uploaded.e_tag().map(str::to_string))
+                    .build(),
+            );
+            offset += length;
+            part_number += 1;
+        }
+
+        self.client
+            .complete_multipart_upload()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(())
+    }
+
+    async fn put(
+        &self,
+        storage_key: &str,
+        content_type: &str,
+        content: Vec<u8>,
     ) -> Result<(), std::io::Error> {
-        let body = ByteStream::from(bytes.to_vec());
         self.client
             .put_object()
             .bucket(&self.bucket)
             .key(storage_key)
             .content_type(content_type.to_string())
-            .content_length(content_length)
-            .body(body)
+            .body(ByteStream::from(content))
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
         Ok(())
     }
 
+    async fn get(&self, storage_key: &str) -> Result<Vec<u8>, std::io::Error> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .send()
+            .await
+            .map_err(|e| match e.as_service_error() {
+                Some(se) if se.is_no_such_key() => {
+                    std::io::Error::new(std::io::ErrorKind::NotFound, e.to_string())
+                }
+                _ => std::io::Error::other(e.to_string()),
+            })?;
+        let body = object
+            .body
+            .collect()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn read_head(&self, storage_key: &str, len: usize) -> Result<Vec<u8>, std::io::Error> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .range(format!("bytes=0-{}", len.saturating_sub(1)))
+            .send()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let body = object
+            .body
+            .collect()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(body.into_bytes().to_vec())
+    }
+
+    async fn open_read(&self, storage_key: &str) -> Result<ScanInput, std::io::Error> {
+        let object = self
+            .client
+            .get_object()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .send()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        Ok(Box::new(object.body.into_async_read()))
+    }
+
+    // S3 has no rename; the object is copied within the bucket and the original removed
+    async fn rename(&self, from_key: &str, to_key: &str) -> Result<(), std::io::Error> {
+        self.client
+            .copy_object()
+            .bucket(&self.bucket)
+            .copy_source(format!("{}/{}", self.bucket, from_key))
+            .key(to_key)
+            .send()
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        self.delete(from_key).await
+    }
+
     async fn delete(&self, storage_key: &str) -> Result<(), std::io::Error> {
         self.client
             .delete_object()
@@ -650,7 +1678,7 @@ impl AwsS3FileStore {
             .key(storage_key)
             .send()
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
         Ok(())
     }
 
@@ -658,17 +1686,19 @@ impl AwsS3FileStore {
         &self,
         storage_key: &str,
         ttl_secs: u64,
+        content_disposition: Option<String>,
     ) -> Result<String, std::io::Error> {
         let conf = PresigningConfig::expires_in(Duration::from_secs(ttl_secs))
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
         let presigned = self
             .client
             .get_object()
             .bucket(&self.bucket)
             .key(storage_key)
+            .set_response_content_disposition(content_disposition)
             .presigned(conf)
             .await
-            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.to_string()))?;
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
         let mut url = presigned.uri().to_string();
         if let Some(public_ep) = environment::s3_public_endpoint_url() {
             if let Some(new_url) = replace_endpoint(&url, public_ep) {
@@ -677,6 +1707,63 @@ impl AwsS3FileStore {
         }
         Ok(url)
     }
+
+    // Signs the type, length and checksum, so S3 only accepts exactly the declared file
+    async fn presign_put_url(
+        &self,
+        storage_key: &str,
+        content_type: &str,
+        content_length: i64,
+        sha256: &str,
+        ttl_secs: u64,
+    ) -> Result<PresignedPut, std::io::Error> {
+        let digest = hex::decode(sha256).map_err(|e| std::io::Error::other(e.to_string()))?;
+        let conf = PresigningConfig::expires_in(Duration::from_secs(ttl_secs))
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let presigned = self
+            .client
+            .put_object()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .content_type(content_type.to_string())
+            .content_length(content_length)
+            .checksum_sha256(base64::engine::general_purpose::STANDARD.encode(digest))
+            .presigned(conf)
+            .await
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        let mut url = presigned.uri().to_string();
+        if let Some(public_ep) = environment::s3_public_endpoint_url() {
+            if let Some(new_url) = replace_endpoint(&url, public_ep) {
+                url = new_url;
+            }
+        }
+        Ok(PresignedPut {
+            method: presigned.method().to_string(),
+            url,
+            headers: presigned
+                .headers()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        })
+    }
+
+    async fn stat(&self, storage_key: &str) -> Result<Option<StoredObject>, std::io::Error> {
+        let head = self
+            .client
+            .head_object()
+            .bucket(&self.bucket)
+            .key(storage_key)
+            .send()
+            .await;
+        match head {
+            Ok(head) => Ok(Some(StoredObject {
+                size_bytes: head.content_length().unwrap_or_default(),
+                content_type: head.content_type().map(str::to_string),
+            })),
+            Err(e) if e.as_service_error().is_some_and(|se| se.is_not_found()) => Ok(None),
+            Err(e) => Err(std::io::Error::other(e.to_string())),
+        }
+    }
 }
 
 fn tmp_path_for(final_path: &Path) -> PathBuf {
@@ -692,7 +1779,7 @@ fn tmp_path_for(final_path: &Path) -> PathBuf {
 
 // ---------------- Helpers ----------------
 
-fn generate_guid(len: usize) -> String {
+pub(crate) fn generate_guid(len: usize) -> String {
     const CHARSET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
     let mut rng = rand::rng();
     (0..len)
@@ -801,38 +1888,35 @@ fn sanitize_ext(ext: &str) -> String {
     }
 }
 
-async fn ensure_unique_key(
-    store: &FileStore,
-    initial_key: String,
-    ext: &str,
-    max_attempts: usize,
-) -> Result<String, DbErr> {
-    let mut key = initial_key;
-    for attempt in 0..max_attempts {
-        let exists = store.exists(&key).await.map_err(to_db_err)?;
-        if !exists {
-            return Ok(key);
-        }
-        // regenerate
-        let guid = generate_guid(10);
-        key = build_storage_key(&guid, ext);
-        if attempt == max_attempts - 1 {
-            return Err(DbErr::Custom(
-                "Failed to generate a unique storage key after retries".into(),
-            ));
-        }
+// Content-addressed: sha256/ab/cd/abcd...; the first bytes fan out the directories
+fn build_blob_key(sha256: &str) -> String {
+    format!("sha256/{}/{}/{}", &sha256[0..2], &sha256[2..4], sha256)
+}
+
+// Where a direct upload's client puts the file; it only joins the shared blob once finalize
+// has checked it
+fn build_direct_key(upload_id: &str) -> String {
+    format!("direct/{}", upload_id)
+}
+
+// Previews sit beside the content they were rendered from, so deduplicated uploads share them
+fn build_thumbnail_key(upload: &file_upload::Model) -> String {
+    match upload.sha256.as_deref() {
+        Some(sha256) => format!("thumbnails/{}.png", build_blob_key(sha256)),
+        None => format!("thumbnails/upload/{}.png", upload.id),
     }
-    Ok(key)
 }
 
-fn build_storage_key(guid: &str, ext: &str) -> String {
-    let mut parts: Vec<String> = guid.chars().map(|c| c.to_string()).collect();
-    parts.push(format!("{}.{}", guid, ext));
-    parts.join("/")
+fn sha256_hex(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+fn link_expiry() -> i64 {
+    chrono::Utc::now().timestamp() + environment::asset_url_ttl_seconds()
 }
 
-fn to_db_err(e: std::io::Error) -> DbErr {
-    DbErr::Custom(format!("file store error: {}", e))
+fn to_store_err(e: std::io::Error) -> AppError {
+    AppError::Internal(format!("file store error: {}", e))
 }
 
 fn replace_endpoint(url: &str, new_base: &str) -> Option<String> {
@@ -844,3 +1928,21 @@ fn replace_endpoint(url: &str, new_base: &str) -> Option<String> {
     let base = new_base.trim_end_matches('/');
     Some(format!("{}{}", base, path_and_query))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blob_key_is_derived_from_content() {
+        let sha256 = sha256_hex(b"test");
+        assert_eq!(
+            sha256,
+            "9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+        assert_eq!(
+            build_blob_key(&sha256),
+            "sha256/9f/86/9f86d081884c7d659a2feaa0c55ad015a3bf4f1b2b0b822cd15d6c15b0f00a08"
+        );
+    }
+}
@@ -32,7 +32,7 @@ impl HistoryCrud {
         history.insert(&self.db).await
     }
 
-    pub async fn create_with_txn<'db, C>(
+    pub async fn create_with_txn<C>(
         &self,
         user_id: i32,
         issue_id: Option<i32>,
@@ -64,18 +64,32 @@ impl HistoryCrud {
             .await
     }
 
-    pub async fn delete_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, DbErr>
+    where
+        C: sea_orm::ConnectionTrait,
+    {
         history::Entity::delete_many()
             .filter(history::Column::IssueId.eq(issue_id))
-            .exec(&self.db)
+            .exec(txn)
             .await
     }
 
-    pub async fn delete_by_comment_id(&self, comment_id: i32) -> Result<UpdateResult, DbErr> {
+    pub async fn delete_by_comment_id_with_txn<C>(
+        &self,
+        comment_id: i32,
+        txn: &C,
+    ) -> Result<UpdateResult, DbErr>
+    where
+        C: sea_orm::ConnectionTrait,
+    {
         history::Entity::update_many()
             .col_expr(history::Column::CommentId, Expr::value(Value::Int(None)))
             .filter(history::Column::CommentId.eq(comment_id))
-            .exec(&self.db)
+            .exec(txn)
             .await
     }
 }
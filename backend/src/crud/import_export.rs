@@ -2,6 +2,7 @@ use crate::entities::{
     blocker, comment, history, issue, issue_assignee, issue_tag, owner, project, project_user, tag,
     task, user,
 };
+use crate::error::AppError;
 use crate::AppState;
 use sea_orm::*;
 use serde_json::Value;
@@ -17,7 +18,7 @@ impl ImportExportCrud {
         Self { app_state }
     }
 
-    pub async fn export_all_data(&self) -> Result<HashMap<String, Vec<Value>>, DbErr> {
+    pub async fn export_all_data(&self) -> Result<HashMap<String, Vec<Value>>, AppError> {
         let mut data = HashMap::new();
 
         // Export issues
@@ -102,7 +103,7 @@ impl ImportExportCrud {
         Ok(data)
     }
 
-    pub async fn import_data(&self, data: HashMap<String, Vec<Value>>) -> Result<(), DbErr> {
+    pub async fn import_data(&self, data: HashMap<String, Vec<Value>>) -> Result<(), AppError> {
         // Clear all existing data first
         history::Entity::delete_many()
             .exec(&self.app_state.db)
@@ -137,10 +138,10 @@ impl ImportExportCrud {
         // Import in order of dependencies
 
         // 1. Users
-        if let Some(users) = data.get(&user::Entity::table_name(&user::Entity).to_string()) {
+        if let Some(users) = data.get(user::Entity::table_name(&user::Entity)) {
             for user_value in users {
                 let user: user::Model = serde_json::from_value(user_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 user::ActiveModel::from(user)
                     .insert(&self.app_state.db)
                     .await?;
@@ -148,11 +149,10 @@ impl ImportExportCrud {
         }
 
         // 2. Projects
-        if let Some(projects) = data.get(&project::Entity::table_name(&project::Entity).to_string())
-        {
+        if let Some(projects) = data.get(project::Entity::table_name(&project::Entity)) {
             for project_value in projects {
                 let project: project::Model = serde_json::from_value(project_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 project::ActiveModel::from(project)
                     .insert(&self.app_state.db)
                     .await?;
@@ -160,10 +160,10 @@ impl ImportExportCrud {
         }
 
         // 3. Owners
-        if let Some(owners) = data.get(&owner::Entity::table_name(&owner::Entity).to_string()) {
+        if let Some(owners) = data.get(owner::Entity::table_name(&owner::Entity)) {
             for owner_value in owners {
                 let owner: owner::Model = serde_json::from_value(owner_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 owner::ActiveModel::from(owner)
                     .insert(&self.app_state.db)
                     .await?;
@@ -171,10 +171,10 @@ impl ImportExportCrud {
         }
 
         // 4. Tags
-        if let Some(tags) = data.get(&tag::Entity::table_name(&tag::Entity).to_string()) {
+        if let Some(tags) = data.get(tag::Entity::table_name(&tag::Entity)) {
             for tag_value in tags {
                 let tag: tag::Model = serde_json::from_value(tag_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 tag::ActiveModel::from(tag)
                     .insert(&self.app_state.db)
                     .await?;
@@ -182,10 +182,10 @@ impl ImportExportCrud {
         }
 
         // 5. Issues
-        if let Some(issues) = data.get(&issue::Entity::table_name(&issue::Entity).to_string()) {
+        if let Some(issues) = data.get(issue::Entity::table_name(&issue::Entity)) {
             for issue_value in issues {
                 let issue: issue::Model = serde_json::from_value(issue_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 issue::ActiveModel::from(issue)
                     .insert(&self.app_state.db)
                     .await?;
@@ -194,24 +194,22 @@ impl ImportExportCrud {
 
         // 6. Issue relationships
         if let Some(issue_assignees) =
-            data.get(&issue_assignee::Entity::table_name(&issue_assignee::Entity).to_string())
+            data.get(issue_assignee::Entity::table_name(&issue_assignee::Entity))
         {
             for assignee_value in issue_assignees {
                 let assignee: issue_assignee::Model =
                     serde_json::from_value(assignee_value.clone())
-                        .map_err(|e| DbErr::Custom(e.to_string()))?;
+                        .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 issue_assignee::ActiveModel::from(assignee)
                     .insert(&self.app_state.db)
                     .await?;
             }
         }
 
-        if let Some(issue_tags) =
-            data.get(&issue_tag::Entity::table_name(&issue_tag::Entity).to_string())
-        {
+        if let Some(issue_tags) = data.get(issue_tag::Entity::table_name(&issue_tag::Entity)) {
             for tag_value in issue_tags {
                 let tag: issue_tag::Model = serde_json::from_value(tag_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 issue_tag::ActiveModel::from(tag)
                     .insert(&self.app_state.db)
                     .await?;
@@ -219,32 +217,30 @@ impl ImportExportCrud {
         }
 
         // 7. Issue-related content
-        if let Some(comments) = data.get(&comment::Entity::table_name(&comment::Entity).to_string())
-        {
+        if let Some(comments) = data.get(comment::Entity::table_name(&comment::Entity)) {
             for comment_value in comments {
                 let comment: comment::Model = serde_json::from_value(comment_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 comment::ActiveModel::from(comment)
                     .insert(&self.app_state.db)
                     .await?;
             }
         }
 
-        if let Some(tasks) = data.get(&task::Entity::table_name(&task::Entity).to_string()) {
+        if let Some(tasks) = data.get(task::Entity::table_name(&task::Entity)) {
             for task_value in tasks {
                 let task: task::Model = serde_json::from_value(task_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 task::ActiveModel::from(task)
                     .insert(&self.app_state.db)
                     .await?;
             }
         }
 
-        if let Some(blockers) = data.get(&blocker::Entity::table_name(&blocker::Entity).to_string())
-        {
+        if let Some(blockers) = data.get(blocker::Entity::table_name(&blocker::Entity)) {
             for blocker_value in blockers {
                 let blocker: blocker::Model = serde_json::from_value(blocker_value.clone())
-                    .map_err(|e| DbErr::Custom(e.to_string()))?;
+                    .map_err(|e| AppError::BadRequest(format!("Invalid import data: {}", e)))?;
                 blocker::ActiveModel::from(blocker)
                     .insert(&self.app_state.db)
                     .await?;
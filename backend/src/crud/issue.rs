@@ -1,29 +1,77 @@
 use crate::crud::blocker::BlockerCrud;
 use crate::crud::comment::CommentCrud;
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::{ISSUE_CREATED, ISSUE_DELETED, ISSUE_UPDATED};
+use crate::crud::domain_event::EventEnvelope;
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::file_upload::FileUploadCrud;
 use crate::crud::history::HistoryCrud;
 use crate::crud::issue_assignee::IssueAssigneeCrud;
+use crate::crud::issue_subscription::IssueSubscriptionCrud;
 use crate::crud::issue_tag::IssueTagCrud;
+use crate::crud::mention::MentionCrud;
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::crud::notification_settings::NotificationSettingsCrud;
+use crate::crud::point_scale;
+use crate::crud::recurrence::{self, Recurrence};
 use crate::crud::status::STATUS_MAP;
 use crate::crud::status::{STATUS_ACCEPTED, STATUS_REJECTED, STATUS_UNSTARTED};
 use crate::crud::task::TaskCrud;
-use crate::crud::work_type::{WORK_TYPE_FEATURE, WORK_TYPE_MAP, WORK_TYPE_REMINDER};
+use crate::crud::work_type::WORK_TYPE_MAP;
 use crate::entities::issue;
 use crate::entities::issue_assignee;
 use crate::entities::issue_tag;
-use crate::notifications::gotify::GotifyClient;
-use crate::notifications::push_notification::PushNotification;
+use crate::entities::project;
+use crate::error::AppError;
+use crate::notifications::gotify_provisioning::{provisioning_job_spec, ProvisionGotify};
+use crate::notifications::push_notification::{
+    reminder_due_at, reminder_job_key, reminder_job_spec, PushNotification,
+};
 use crate::AppState;
 use chrono::Datelike;
 use chrono::Utc;
-use graphile_worker::JobSpecBuilder;
 use sea_orm::entity::prelude::*;
 use sea_orm::*;
 
+// Normalises a recurrence rule and checks it can apply to an issue of `work_type`
+fn check_recurrence(
+    work_type: i32,
+    recurrence: Option<String>,
+) -> Result<Option<String>, AppError> {
+    let recurrence = recurrence
+        .map(|rule| rule.trim().to_string())
+        .filter(|rule| !rule.is_empty());
+    if let Some(rule) = &recurrence {
+        if !recurrence::can_recur(work_type) {
+            return Err(AppError::invalid(
+                "recurrence",
+                "only reminders and chores can recur",
+            ));
+        }
+        Recurrence::parse(rule).map_err(|e| AppError::invalid("recurrence", e))?;
+    }
+    Ok(recurrence)
+}
+
+fn recurrence_change_msg(recurrence: &Option<String>) -> String {
+    match recurrence {
+        Some(rule) => format!("set recurrence to '{}'", rule),
+        None => "removed recurrence".to_string(),
+    }
+}
+
+/// Follow-up work for an update whose transaction has not committed yet
+pub struct PendingIssueUpdate {
+    issue: issue::Model,
+    event: EventEnvelope,
+    reminder_due_before: Option<DateTimeWithTimeZone>,
+    changed_description: Option<String>,
+    notification_changes: Vec<String>,
+    status_changed: bool,
+    accepted: bool,
+    issue_title: String,
+    issue_created_by_id: i32,
+}
+
 #[derive(Clone)]
 pub struct IssueCrud {
     app_state: AppState,
@@ -34,6 +82,7 @@ impl IssueCrud {
         Self { app_state }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         &self,
         title: String,
@@ -46,7 +95,10 @@ impl IssueCrud {
         project_id: i32,
         target_release_at: Option<DateTimeWithTimeZone>,
         created_by_id: i32,
-    ) -> Result<issue::Model, DbErr> {
+    ) -> Result<issue::Model, AppError> {
+        let project = self.find_project(project_id).await?;
+        point_scale::check_points(&project, work_type, points.flatten())?;
+
         let txn = self.app_state.db.begin().await?;
 
         let issue = issue::ActiveModel {
@@ -59,7 +111,7 @@ impl IssueCrud {
             project_id: Set(project_id),
             is_icebox: Set(is_icebox),
             created_by_id: Set(created_by_id),
-            target_release_at: Set(target_release_at.clone()),
+            target_release_at: Set(target_release_at),
             ..Default::default()
         };
         let mut issue = issue.insert(&txn).await?;
@@ -84,97 +136,129 @@ impl IssueCrud {
             )
             .await?;
 
-        self.populate_issue_tags(&mut issue).await?;
-        self.populate_issue_assignees(&mut issue).await?;
+        self.populate_with_txn(&mut issue, &txn).await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let created = broadcaster
+            .record(&txn, project_id, DomainEvent::IssueCreated(issue.clone()))
+            .await?;
 
         txn.commit().await?;
 
-        // Track if this is a REMINDER for later scheduling
-        let is_reminder = work_type == WORK_TYPE_REMINDER && target_release_at.is_some();
+        if let Some(description) = &description {
+            MentionCrud::new(self.app_state.clone())
+                .sync(project_id, issue.id, None, description, created_by_id)
+                .await?;
+        }
 
-        // Only run notification setup code if work_type is REMINDER
-        if is_reminder {
-            // Check if notification settings exist for this project, create if not
-            let notification_settings_crud = NotificationSettingsCrud::new(self.app_state.clone());
-            match notification_settings_crud
-                .find_by_project_id(project_id)
+        // The issue is saved by now, so reminder setup problems are logged, never returned
+        if let Some(due_at) = reminder_due_at(&issue) {
+            self.ensure_reminder_channel(project_id).await;
+            if let Err(e) = self
+                .schedule_push_notification(issue.id, Some(due_at))
                 .await
             {
-                Ok(Some(_)) => {
-                    // Settings already exist, continue
-                    let _ = self
-                        .schedule_push_notification(issue.id, target_release_at)
-                        .await;
-                }
-                _ => {
-                    // Settings don't exist, create a new Gotify application
-                    let gotify_client = GotifyClient::new();
-                    match gotify_client
-                        .create_application_with_basic_auth(
-                            format!("Phoenix Tracker - Project {}", project_id),
-                            Some(
-                                "Notification application for Phoenix Tracker project".to_string(),
-                            ),
-                        )
-                        .await
-                    {
-                        Ok(app_response) => {
-                            // Create notification settings with the new token
-                            let _ = notification_settings_crud
-                                .create(app_response.token, app_response.id)
-                                .await;
-                            let _ = self
-                                .schedule_push_notification(issue.id, target_release_at)
-                                .await;
-                        }
-                        Err(e) => {
-                            tracing::error!(
-                                "Failed to create Gotify application for project {}: {}",
-                                project_id,
-                                e
-                            );
-                            return Err(DbErr::Custom(format!(
-                                "Failed to create Gotify application: {}",
-                                e
-                            )));
-                        }
-                    }
-                }
+                tracing::error!("Failed to schedule reminder {}: {}", issue.id, e);
             }
         }
 
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(project_id, ISSUE_CREATED, serde_json::json!(issue));
+        broadcaster.announce(&created).await;
 
         Ok(issue)
     }
 
+    // Replaces any reminder already queued for the issue
     async fn schedule_push_notification(
         &self,
         issue_id: i32,
         target_release_at: Option<DateTimeWithTimeZone>,
     ) -> Result<(), String> {
-        tracing::warn!("the notification setting was found");
-        tracing::info!("now {:?}", Utc::now());
-        tracing::info!("target {:?}", target_release_at);
-
-        let job_spec = JobSpecBuilder::new()
-            .run_at(target_release_at.unwrap())
-            .build();
-
+        let run_at = target_release_at.ok_or_else(|| "Reminder has no target date".to_string())?;
         let worker = self
             .app_state
-            .clone()
             .worker
-            .expect("To be present")
-            .clone();
-        let utils = worker.create_utils();
-        let _ = utils.add_job(PushNotification { issue_id }, job_spec).await;
-
+            .clone()
+            .ok_or_else(|| "Background worker is not available".to_string())?;
+        worker
+            .create_utils()
+            .add_job(
+                PushNotification { issue_id },
+                reminder_job_spec(issue_id, run_at),
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+        tracing::info!("Reminder for issue {} scheduled at {}", issue_id, run_at);
         Ok(())
     }
 
-    async fn populate_issue_tags(&self, issue: &mut issue::Model) -> Result<(), DbErr> {
+    // Projects without any channel get a Gotify application provisioned in the background,
+    // retried until Gotify is reachable; the reminder job retries until it has somewhere to go
+    async fn ensure_reminder_channel(&self, project_id: i32) {
+        let has_channels = NotificationSettingsCrud::new(self.app_state.clone())
+            .find_enabled_by_project_id(project_id)
+            .await
+            .is_ok_and(|channels| !channels.is_empty());
+        if has_channels {
+            return;
+        }
+        let Some(worker) = self.app_state.worker.clone() else {
+            tracing::warn!("Background worker is not available to provision Gotify");
+            return;
+        };
+        if let Err(e) = worker
+            .create_utils()
+            .add_job(
+                ProvisionGotify { project_id },
+                provisioning_job_spec(project_id),
+            )
+            .await
+        {
+            tracing::error!(
+                "Failed to queue Gotify provisioning for project {}: {}",
+                project_id,
+                e
+            );
+        }
+    }
+
+    async fn cancel_push_notification(&self, issue_id: i32) -> Result<(), String> {
+        // Reminders firing inside the worker have nothing else queued to cancel
+        let Some(worker) = self.app_state.worker.clone() else {
+            return Ok(());
+        };
+        worker
+            .create_utils()
+            .remove_job(&reminder_job_key(issue_id))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // Keeps the queued reminder in step with the issue after its date, type or status changed
+    async fn sync_push_notification(
+        &self,
+        project_id: i32,
+        issue_id: i32,
+        due_at: Option<DateTimeWithTimeZone>,
+    ) {
+        let result = match due_at {
+            Some(_) => {
+                self.ensure_reminder_channel(project_id).await;
+                self.schedule_push_notification(issue_id, due_at).await
+            }
+            None => self.cancel_push_notification(issue_id).await,
+        };
+        if let Err(e) = result {
+            tracing::error!("Failed to update reminder for issue {}: {}", issue_id, e);
+        }
+    }
+
+    async fn find_project(&self, project_id: i32) -> Result<project::Model, AppError> {
+        project::Entity::find_by_id(project_id)
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Project"))
+    }
+
+    async fn populate_issue_tags(&self, issue: &mut issue::Model) -> Result<(), AppError> {
         let tag_ids = IssueTagCrud::new(self.app_state.clone())
             .find_by_issue_id(issue.id)
             .await?
@@ -185,7 +269,7 @@ impl IssueCrud {
         Ok(())
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<issue::Model>, DbErr> {
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<issue::Model>, AppError> {
         let mut issue = issue::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await?;
@@ -196,7 +280,21 @@ impl IssueCrud {
         Ok(issue)
     }
 
-    pub async fn find_all_for_backlog(&self, project_id: i32) -> Result<Vec<issue::Model>, DbErr> {
+    /// Tells clients about an issue after something attached to it (a comment, tag, task,
+    /// assignee, blocker or file) changed.
+    pub async fn publish_updated(&self, issue_id: i32) -> Result<(), AppError> {
+        if let Some(issue) = self.find_by_id(issue_id).await? {
+            EventBroadcaster::new(&self.app_state)
+                .publish(issue.project_id, DomainEvent::IssueUpdated(issue))
+                .await;
+        }
+        Ok(())
+    }
+
+    pub async fn find_all_for_backlog(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<issue::Model>, AppError> {
         let now = chrono::Utc::now().date_naive();
         let days_from_monday = now.weekday().num_days_from_monday();
         let monday = now - chrono::Duration::days(days_from_monday as i64);
@@ -222,10 +320,10 @@ impl IssueCrud {
             self.populate_issue_assignees(issue).await?;
         }
 
-        Ok(self.schedule_issues(issues).await?)
+        self.schedule_issues(issues).await
     }
 
-    async fn populate_issue_assignees(&self, issue: &mut issue::Model) -> Result<(), DbErr> {
+    async fn populate_issue_assignees(&self, issue: &mut issue::Model) -> Result<(), AppError> {
         let assignee_user_ids = issue_assignee::Entity::find()
             .filter(issue_assignee::Column::IssueId.eq(issue.id))
             .all(&self.app_state.db)
@@ -239,9 +337,36 @@ impl IssueCrud {
         Ok(())
     }
 
-    async fn schedule_issues(&self, issues: Vec<issue::Model>) -> Result<Vec<issue::Model>, DbErr> {
+    // Tags and assignees as `txn` sees them, for events recorded before it commits
+    async fn populate_with_txn<C>(&self, issue: &mut issue::Model, txn: &C) -> Result<(), AppError>
+    where
+        C: ConnectionTrait,
+    {
+        issue.issue_tag_ids = issue_tag::Entity::find()
+            .filter(issue_tag::Column::IssueId.eq(issue.id))
+            .order_by_asc(issue_tag::Column::CreatedAt)
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|tag| tag.tag_id)
+            .collect();
+        issue.issue_assignee_ids = issue_assignee::Entity::find()
+            .filter(issue_assignee::Column::IssueId.eq(issue.id))
+            .all(txn)
+            .await?
+            .into_iter()
+            .map(|assignee| assignee.user_id)
+            .collect();
+        Ok(())
+    }
+
+    async fn schedule_issues(
+        &self,
+        issues: Vec<issue::Model>,
+    ) -> Result<Vec<issue::Model>, AppError> {
         let mut scheduled_issues = issues;
         let project_id = &self.app_state.project.clone().unwrap().id;
+        let project = self.find_project(*project_id).await?;
         let weekly_average = self.calculate_weekly_points_average(*project_id).await?;
 
         let now = chrono::Utc::now();
@@ -252,7 +377,8 @@ impl IssueCrud {
         let mut current_week_points = 0;
 
         for issue in &mut scheduled_issues {
-            let issue_points = issue.points.unwrap_or(0);
+            let issue_points =
+                point_scale::effective_points(&project, issue.work_type, issue.points);
 
             if issue.status != STATUS_UNSTARTED && issue.status != STATUS_REJECTED {
                 issue.scheduled_at = Some(
@@ -263,7 +389,7 @@ impl IssueCrud {
                 );
             } else {
                 if current_week_points + issue_points >= weekly_average as i32 {
-                    current_monday = current_monday + chrono::Duration::days(7);
+                    current_monday += chrono::Duration::days(7);
                     current_week_points = issue_points;
                 } else {
                     current_week_points += issue_points;
@@ -280,7 +406,7 @@ impl IssueCrud {
 
         Ok(scheduled_issues)
     }
-    pub async fn find_all_accepted(&self, project_id: i32) -> Result<Vec<issue::Model>, DbErr> {
+    pub async fn find_all_accepted(&self, project_id: i32) -> Result<Vec<issue::Model>, AppError> {
         let mut issues = issue::Entity::find()
             .filter(issue::Column::ProjectId.eq(project_id))
             .filter(issue::Column::Status.eq(STATUS_ACCEPTED))
@@ -293,7 +419,7 @@ impl IssueCrud {
         }
         Ok(issues)
     }
-    pub async fn find_all_icebox(&self, project_id: i32) -> Result<Vec<issue::Model>, DbErr> {
+    pub async fn find_all_icebox(&self, project_id: i32) -> Result<Vec<issue::Model>, AppError> {
         let mut issues = issue::Entity::find()
             .filter(issue::Column::ProjectId.eq(project_id))
             .filter(issue::Column::IsIcebox.eq(true))
@@ -312,7 +438,7 @@ impl IssueCrud {
         &self,
         project_id: i32,
         user_id: i32,
-    ) -> Result<Vec<issue::Model>, DbErr> {
+    ) -> Result<Vec<issue::Model>, AppError> {
         let now = chrono::Utc::now().date_naive();
         let days_from_monday = now.weekday().num_days_from_monday();
         let monday = now - chrono::Duration::days(days_from_monday as i64);
@@ -342,7 +468,7 @@ impl IssueCrud {
         }
         Ok(issues)
     }
-    pub async fn find_all_by_tag_id(&self, tag_id: i32) -> Result<Vec<issue::Model>, DbErr> {
+    pub async fn find_all_by_tag_id(&self, tag_id: i32) -> Result<Vec<issue::Model>, AppError> {
         let mut issues = issue_tag::Entity::find()
             .filter(issue_tag::Column::TagId.eq(tag_id))
             .find_also_related(issue::Entity)
@@ -362,7 +488,7 @@ impl IssueCrud {
     pub async fn count_issues_by_tag_ids(
         &self,
         tag_ids: Vec<i32>,
-    ) -> Result<Vec<(i32, i64)>, DbErr> {
+    ) -> Result<Vec<(i32, i64)>, AppError> {
         let counts = issue_tag::Entity::find()
             .filter(issue_tag::Column::TagId.is_in(tag_ids))
             .group_by(issue_tag::Column::TagId)
@@ -376,6 +502,7 @@ impl IssueCrud {
         Ok(counts)
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn update(
         &self,
         id: i32,
@@ -388,15 +515,65 @@ impl IssueCrud {
         work_type: Option<i32>,
         target_release_at: Option<DateTimeWithTimeZone>,
         accepted_at: Option<DateTimeWithTimeZone>,
-    ) -> Result<issue::Model, DbErr> {
+        recurrence: Option<Option<String>>,
+    ) -> Result<issue::Model, AppError> {
         let txn = self.app_state.db.begin().await?;
+        let pending = self
+            .update_with_txn(
+                id,
+                title,
+                description,
+                priority,
+                points,
+                status,
+                is_icebox,
+                work_type,
+                target_release_at,
+                accepted_at,
+                recurrence,
+                &txn,
+            )
+            .await?;
+        txn.commit().await?;
+        self.complete_update(pending).await
+    }
+
+    /// Applies an update inside the caller's transaction. Once that commits, the returned
+    /// `PendingIssueUpdate` must be passed to `complete_update` to notify and broadcast.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn update_with_txn<C>(
+        &self,
+        id: i32,
+        title: Option<String>,
+        description: Option<String>,
+        priority: Option<i32>,
+        points: Option<Option<i32>>,
+        status: Option<i32>,
+        is_icebox: Option<bool>,
+        work_type: Option<i32>,
+        target_release_at: Option<DateTimeWithTimeZone>,
+        accepted_at: Option<DateTimeWithTimeZone>,
+        recurrence: Option<Option<String>>,
+        txn: &C,
+    ) -> Result<PendingIssueUpdate, AppError>
+    where
+        C: ConnectionTrait,
+    {
         let issue = issue::Entity::find_by_id(id)
-            .one(&txn)
+            .one(txn)
             .await?
-            .ok_or(DbErr::Custom("Issue not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Issue"))?;
+
+        // Points are checked against the work type the issue will have after this update
+        let project = self.find_project(issue.project_id).await?;
+        if let Some(new_points) = points {
+            let new_work_type = work_type.unwrap_or(issue.work_type);
+            point_scale::check_points(&project, new_work_type, new_points)?;
+        }
 
-        let project_id = issue.project_id.clone();
-        let issue_created_by_id = issue.created_by_id.clone();
+        let project_id = issue.project_id;
+        let reminder_due_before = reminder_due_at(&issue);
+        let issue_created_by_id = issue.created_by_id;
         let issue_title = issue.title.clone();
         let mut history_records = Vec::new();
         let current_user_id = &self.app_state.user.clone().unwrap().id;
@@ -404,6 +581,7 @@ impl IssueCrud {
         let current_version = issue.lock_version;
         let mut issue: issue::ActiveModel = issue.into();
         let mut notification_changes = Vec::new();
+        let mut changed_description = None;
 
         // Points update
         if let Some(new_points) = points {
@@ -443,16 +621,33 @@ impl IssueCrud {
         // Work type update
         if let Some(new_work_type) = work_type {
             let old_work_type = issue.work_type.clone().unwrap();
-            let change_msg = format!(
-                "changed type from '{}' to '{}', points will are set to `Unestimated`.",
+            let mut change_msg = format!(
+                "changed type from '{}' to '{}'",
                 WORK_TYPE_MAP.get(&old_work_type).unwrap_or(&"unknown"),
                 WORK_TYPE_MAP.get(&new_work_type).unwrap_or(&"unknown")
             );
+            if !point_scale::is_estimable(&project, new_work_type) {
+                issue.points = Set(None);
+                change_msg.push_str(", points are set to `Unestimated`.");
+            }
+            if !recurrence::can_recur(new_work_type) && issue.recurrence.clone().unwrap().is_some()
+            {
+                issue.recurrence = Set(None);
+                change_msg.push_str(", recurrence removed.");
+            }
             history_records.push(change_msg.clone());
             notification_changes.push(change_msg);
             issue.work_type = Set(new_work_type);
-            if new_work_type != WORK_TYPE_FEATURE {
-                issue.points = Set(None);
+        }
+
+        // Recurrence update, applied before any status change so accepting the issue
+        // in the same request spawns the successor from the new rule
+        if let Some(new_recurrence) = recurrence {
+            let new_recurrence =
+                check_recurrence(issue.work_type.clone().unwrap(), new_recurrence)?;
+            if new_recurrence != issue.recurrence.clone().unwrap() {
+                history_records.push(recurrence_change_msg(&new_recurrence));
+                issue.recurrence = Set(new_recurrence);
             }
         }
 
@@ -477,6 +672,7 @@ impl IssueCrud {
                 );
                 history_records.push(change_msg.clone());
                 notification_changes.push(change_msg);
+                changed_description = Some(new_description.clone());
                 issue.description = Set(Some(new_description));
             }
         }
@@ -530,37 +726,103 @@ impl IssueCrud {
         }
 
         issue.lock_version = Set(current_version + 1);
-        let mut result = issue.update(&txn).await?;
+        let mut result = issue.update(txn).await?;
         if result.lock_version != current_version + 1 {
-            txn.rollback().await?;
-            return Err(DbErr::Custom("Optimistic lock error".to_owned()));
+            return Err(AppError::OptimisticLock);
         }
 
-        txn.commit().await?;
-
+        // Whoever moves an issue along takes it on
         if status_changed {
-            let issue_assignee_crud = IssueAssigneeCrud::new(self.app_state.clone());
-            issue_assignee_crud.create(id, *current_user_id).await?;
+            let already_assigned = issue_assignee::Entity::find_by_id((id, *current_user_id))
+                .one(txn)
+                .await?
+                .is_some();
+            if !already_assigned {
+                issue_assignee::ActiveModel {
+                    issue_id: Set(id),
+                    user_id: Set(*current_user_id),
+                }
+                .insert(txn)
+                .await?;
+                let name = &self.app_state.current_user()?.name;
+                history_records.insert(0, format!("assigned to user '{}'", name));
+            }
         }
 
         // Record history items
         for record in history_records {
             history_crud
-                .create(*current_user_id, Some(id), None, None, record)
+                .create_with_txn(*current_user_id, Some(id), None, None, record, txn)
+                .await?;
+        }
+
+        self.populate_with_txn(&mut result, txn).await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let updated = broadcaster
+            .record(txn, project_id, DomainEvent::IssueUpdated(result.clone()))
+            .await?;
+
+        Ok(PendingIssueUpdate {
+            issue: result,
+            event: updated,
+            reminder_due_before,
+            changed_description,
+            notification_changes,
+            status_changed,
+            accepted: status == Some(STATUS_ACCEPTED),
+            issue_title,
+            issue_created_by_id,
+        })
+    }
+
+    pub async fn complete_update(
+        &self,
+        pending: PendingIssueUpdate,
+    ) -> Result<issue::Model, AppError> {
+        let PendingIssueUpdate {
+            issue: result,
+            event: updated,
+            reminder_due_before,
+            changed_description,
+            notification_changes,
+            status_changed,
+            accepted,
+            issue_title,
+            issue_created_by_id,
+        } = pending;
+        let id = result.id;
+        let project_id = result.project_id;
+        let current_user_id = &self.app_state.current_user()?.id;
+
+        let reminder_due_after = reminder_due_at(&result);
+        if reminder_due_after != reminder_due_before {
+            self.sync_push_notification(project_id, id, reminder_due_after)
+                .await;
+        }
+
+        if let Some(description) = &changed_description {
+            MentionCrud::new(self.app_state.clone())
+                .sync(project_id, id, None, description, *current_user_id)
                 .await?;
         }
 
         // Create notifications for issue assignees and issue creator
         if !notification_changes.is_empty() {
             let notification_crud = NotificationCrud::new(self.app_state.clone());
+            let kind = if status_changed {
+                NotificationKind::StatusChange
+            } else {
+                NotificationKind::IssueUpdate
+            };
             let description = if notification_changes.is_empty() {
                 format!("Issue '{}' has been updated", issue_title)
             } else {
-                format!("{}", notification_changes.join(", "))
+                notification_changes.join(", ").to_string()
             };
 
             let _ = notification_crud
                 .notify_issue_stakeholders_with_creator_context(
+                    kind,
                     id,
                     "Issue Updated: {}",
                     description,
@@ -571,48 +833,257 @@ impl IssueCrud {
                 .await;
         }
 
-        self.populate_issue_tags(&mut result).await?;
-        self.populate_issue_assignees(&mut result).await?;
+        EventBroadcaster::new(&self.app_state)
+            .announce(&updated)
+            .await;
+
+        if accepted {
+            self.schedule_next_occurrence(id).await?;
+        }
+
+        Ok(result)
+    }
+
+    pub async fn set_recurrence(
+        &self,
+        id: i32,
+        recurrence: Option<String>,
+    ) -> Result<issue::Model, AppError> {
+        let issue = issue::Entity::find_by_id(id)
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Issue"))?;
+        let recurrence = check_recurrence(issue.work_type, recurrence)?;
+        if recurrence == issue.recurrence {
+            return self
+                .find_by_id(id)
+                .await?
+                .ok_or_else(|| AppError::not_found("Issue"));
+        }
+
+        let project_id = issue.project_id;
+        let txn = self.app_state.db.begin().await?;
+        let mut issue: issue::ActiveModel = issue.into();
+        issue.recurrence = Set(recurrence.clone());
+        let mut result = issue.update(&txn).await?;
+
+        let change_msg = recurrence_change_msg(&recurrence);
+        let current_user_id = self.app_state.current_user()?.id;
+        HistoryCrud::new(self.app_state.db.clone())
+            .create_with_txn(current_user_id, Some(id), None, None, change_msg, &txn)
+            .await?;
 
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(project_id, ISSUE_UPDATED, serde_json::json!(result));
+        self.populate_with_txn(&mut result, &txn).await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let updated = broadcaster
+            .record(&txn, project_id, DomainEvent::IssueUpdated(result.clone()))
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&updated).await;
 
         Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    // Spawns the next occurrence and, for reminders, queues its push notification
+    async fn schedule_next_occurrence(&self, id: i32) -> Result<(), AppError> {
+        if let Some(next) = self.spawn_next_occurrence(id).await? {
+            if let Some(due_at) = reminder_due_at(&next) {
+                if let Err(e) = self.schedule_push_notification(next.id, Some(due_at)).await {
+                    tracing::error!("Failed to schedule reminder {}: {}", next.id, e);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Creates the next occurrence of a recurring issue and hands the rule over to it.
+    /// The rule is cleared on the current issue in the same transaction, so firing and
+    /// accepting the same occurrence only ever creates one successor.
+    pub async fn spawn_next_occurrence(&self, id: i32) -> Result<Option<issue::Model>, AppError> {
+        let txn = self.app_state.db.begin().await?;
+        let issue = issue::Entity::find_by_id(id)
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Issue"))?;
+        let Some(rule_text) = issue.recurrence.clone() else {
+            return Ok(None);
+        };
+        let rule = Recurrence::parse(&rule_text).map_err(|e| {
+            AppError::Internal(format!("Issue {} has an invalid recurrence: {}", id, e))
+        })?;
+
+        // Occurrences missed while nothing fired (e.g. the server was down) are skipped
+        let now = Utc::now().fixed_offset();
+        let mut next = issue.target_release_at.unwrap_or(now);
+        loop {
+            next = rule.next_after(next).ok_or_else(|| {
+                AppError::Internal(format!("Recurrence '{}' has no next occurrence", rule_text))
+            })?;
+            if next > now {
+                break;
+            }
+        }
+
+        let claimed = issue::Entity::update_many()
+            .col_expr(
+                issue::Column::Recurrence,
+                Expr::value(Option::<String>::None),
+            )
+            .filter(issue::Column::Id.eq(id))
+            .filter(issue::Column::Recurrence.is_not_null())
+            .exec(&txn)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Ok(None);
+        }
+
+        let mut next_issue = issue::ActiveModel {
+            title: Set(issue.title.clone()),
+            description: Set(issue.description.clone()),
+            priority: Set(issue.priority),
+            points: Set(issue.points),
+            status: Set(STATUS_UNSTARTED),
+            work_type: Set(issue.work_type),
+            project_id: Set(issue.project_id),
+            is_icebox: Set(issue.is_icebox),
+            created_by_id: Set(issue.created_by_id),
+            target_release_at: Set(Some(next)),
+            recurrence: Set(Some(rule_text)),
+            ..Default::default()
+        }
+        .insert(&txn)
+        .await?;
+
+        let tags = IssueTagCrud::new(self.app_state.clone())
+            .find_by_issue_id(id)
+            .await?;
+        for tag in tags {
+            issue_tag::ActiveModel {
+                issue_id: Set(next_issue.id),
+                tag_id: Set(tag.tag_id),
+                ..Default::default()
+            }
+            .insert(&txn)
+            .await?;
+        }
+
+        let user_id = self
+            .app_state
+            .user
+            .as_ref()
+            .map_or(issue.created_by_id, |user| user.id);
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
-        history_crud.delete_by_issue_id(id).await?;
+        history_crud
+            .create_with_txn(
+                user_id,
+                Some(id),
+                None,
+                None,
+                format!(
+                    "scheduled next occurrence #{} for '{}'",
+                    next_issue.id, next
+                ),
+                &txn,
+            )
+            .await?;
+        history_crud
+            .create_with_txn(
+                user_id,
+                Some(next_issue.id),
+                None,
+                None,
+                format!("created as the next occurrence of #{}", id),
+                &txn,
+            )
+            .await?;
+
+        self.populate_with_txn(&mut next_issue, &txn).await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let created = broadcaster
+            .record(
+                &txn,
+                next_issue.project_id,
+                DomainEvent::IssueCreated(next_issue.clone()),
+            )
+            .await?;
+
+        txn.commit().await?;
+        broadcaster.announce(&created).await;
+
+        Ok(Some(next_issue))
+    }
+
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
+        let project_id = self.app_state.current_project()?.id;
+        let txn = self.app_state.db.begin().await?;
+
+        // Nothing is touched unless the issue belongs to the caller's project
+        let issue = issue::Entity::find_by_id(id)
+            .filter(issue::Column::ProjectId.eq(project_id))
+            .one(&txn)
+            .await?
+            .ok_or_else(|| AppError::not_found("Issue"))?;
+
+        let history_crud = HistoryCrud::new(self.app_state.db.clone());
+        history_crud.delete_by_issue_id_with_txn(id, &txn).await?;
 
         let issue_assignee_crud = IssueAssigneeCrud::new(self.app_state.clone());
-        issue_assignee_crud.delete_all_by_issue_id(id).await?;
+        issue_assignee_crud
+            .delete_all_by_issue_id_with_txn(id, &txn)
+            .await?;
 
         let issue_tag_crud = IssueTagCrud::new(self.app_state.clone());
-        issue_tag_crud.delete_all_by_issue_id(id).await?;
+        issue_tag_crud
+            .delete_all_by_issue_id_with_txn(id, &txn)
+            .await?;
 
         let comment_crud = CommentCrud::new(self.app_state.clone());
-        comment_crud.delete_all_by_issue_id(id).await?;
+        comment_crud.delete_all_by_issue_id_txn(id, &txn).await?;
 
         let task_crud = TaskCrud::new(self.app_state.clone());
-        task_crud.delete_all_by_issue_id(id).await?;
+        task_crud.delete_all_by_issue_id_with_txn(id, &txn).await?;
 
         let blocker_crud = BlockerCrud::new(self.app_state.clone());
-        blocker_crud.delete_all_by_issue_id(id).await?;
+        blocker_crud
+            .delete_all_by_issue_id_with_txn(id, &txn)
+            .await?;
 
         let notification_crud = NotificationCrud::new(self.app_state.clone());
-        notification_crud.delete_all_for_issue(id).await?;
+        let unread = notification_crud
+            .delete_all_for_issue_with_txn(id, &txn)
+            .await?;
+
+        let issue_subscription_crud = IssueSubscriptionCrud::new(self.app_state.clone());
+        issue_subscription_crud
+            .delete_all_by_issue_id_with_txn(id, &txn)
+            .await?;
+
+        let mention_crud = MentionCrud::new(self.app_state.clone());
+        mention_crud
+            .delete_all_by_issue_id_with_txn(id, &txn)
+            .await?;
 
         // Delete all file uploads and their comment-file mappings for this issue
         let file_upload_crud = FileUploadCrud::new(self.app_state.clone());
-        file_upload_crud.delete_all_by_issue_id(id).await?;
+        file_upload_crud
+            .delete_all_by_issue_id_txn(id, &txn)
+            .await?;
 
-        let result = issue::Entity::delete_by_id(id)
-            .exec(&self.app_state.db)
+        let result = issue::Entity::delete_by_id(id).exec(&txn).await?;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let deleted = broadcaster
+            .record(&txn, issue.project_id, DomainEvent::IssueDeleted { id })
             .await?;
+        txn.commit().await?;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(*project_id, ISSUE_DELETED, serde_json::json!({ "id": id }));
+        notification_crud.broadcast_unread_counts(unread).await;
+
+        // A reminder left queued would fire for an issue that no longer exists
+        if let Err(e) = self.cancel_push_notification(id).await {
+            tracing::error!("Failed to cancel reminder for issue {}: {}", id, e);
+        }
+
+        broadcaster.announce(&deleted).await;
 
         Ok(result)
     }
@@ -620,16 +1091,20 @@ impl IssueCrud {
     pub async fn bulk_update_priorities(
         &self,
         issue_priorities: Vec<(i32, i32)>,
-    ) -> Result<Vec<issue::Model>, DbErr> {
+    ) -> Result<Vec<issue::Model>, AppError> {
         let mut updated_issues = Vec::new();
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
         let current_user_id = &self.app_state.user.clone().unwrap().id;
+        let project_id = self.app_state.current_project()?.id;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let mut events = Vec::new();
+        let txn = self.app_state.db.begin().await?;
 
         for (issue_id, new_priority) in issue_priorities {
             let issue = issue::Entity::find_by_id(issue_id)
-                .one(&self.app_state.db)
+                .one(&txn)
                 .await?
-                .ok_or(DbErr::Custom("Issue not found".to_owned()))?;
+                .ok_or_else(|| AppError::not_found("Issue"))?;
 
             let current_version = issue.lock_version;
             let old_priority = issue.priority;
@@ -638,35 +1113,52 @@ impl IssueCrud {
             issue.priority = Set(new_priority);
             issue.lock_version = Set(current_version + 1);
 
-            let updated_issue = issue.update(&self.app_state.db).await?;
+            let updated_issue = issue.update(&txn).await?;
 
             // Add history record for priority update
             let history_record =
                 format!("Updated priority from {} to {}", old_priority, new_priority);
             history_crud
-                .create(*current_user_id, Some(issue_id), None, None, history_record)
+                .create_with_txn(
+                    *current_user_id,
+                    Some(issue_id),
+                    None,
+                    None,
+                    history_record,
+                    &txn,
+                )
                 .await?;
 
+            events.push(
+                broadcaster
+                    .record(
+                        &txn,
+                        project_id,
+                        DomainEvent::IssueUpdated(updated_issue.clone()),
+                    )
+                    .await?,
+            );
             updated_issues.push(updated_issue);
         }
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!(updated_issues),
-        );
+        txn.commit().await?;
+        for event in &events {
+            broadcaster.announce(event).await;
+        }
 
         Ok(updated_issues)
     }
 
-    pub async fn calculate_weekly_points_average(&self, project_id: i32) -> Result<f64, DbErr> {
+    pub async fn calculate_weekly_points_average(&self, project_id: i32) -> Result<f64, AppError> {
         let now = chrono::Utc::now().date_naive();
         let mut total_points = 0;
         let mut weeks_with_data = 0;
         let target_weeks = 3i64; // Number of weeks to consider, explicitly as i64
 
+        // Only estimable work types count, and weeks without data assume the scale's default
+        let project = self.find_project(project_id).await?;
+        let default_velocity = point_scale::scale_for(&project).default_weekly_velocity;
+
         // Find the earliest accepted issue to determine how long the project has been active
         let earliest_issue = issue::Entity::find()
             .filter(issue::Column::ProjectId.eq(project_id))
@@ -713,7 +1205,12 @@ impl IssueCrud {
                 .await?;
 
             if !issues.is_empty() {
-                let weekly_points: i32 = issues.iter().filter_map(|issue| issue.points).sum();
+                let weekly_points: i32 = issues
+                    .iter()
+                    .map(|issue| {
+                        point_scale::effective_points(&project, issue.work_type, issue.points)
+                    })
+                    .sum();
                 total_points += weekly_points;
                 weeks_with_data += 1;
             }
@@ -723,16 +1220,16 @@ impl IssueCrud {
         let actual_average = if weeks_with_data > 0 {
             total_points as f64 / weeks_with_data as f64
         } else {
-            10.0 // Default if no weeks have data
+            default_velocity // Default if no weeks have data
         };
 
-        // If the project has been active for less than 3 weeks, blend with assumed 10 points per week
+        // If the project has been active for less than 3 weeks, blend with the assumed default velocity
         let average = if weeks_active < target_weeks && weeks_with_data > 0 {
             // Calculate how many weeks to fill with the default value
             let weeks_to_fill = target_weeks - weeks_active;
 
-            // Blend actual data with assumed data (10 points per missing week)
-            (total_points as f64 + (weeks_to_fill as f64 * 10.0))
+            // Blend actual data with assumed data (default velocity per missing week)
+            (total_points as f64 + (weeks_to_fill as f64 * default_velocity))
                 / (weeks_with_data as f64 + weeks_to_fill as f64)
         } else {
             actual_average
@@ -741,3 +1238,71 @@ impl IssueCrud {
         Ok(average)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::event_broadcaster::EventSender;
+    use crate::entities::task;
+    use std::sync::Arc;
+
+    // A token for another project must not be able to strip an issue's children
+    #[tokio::test]
+    async fn test_deleting_another_projects_issue_touches_nothing() {
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        db.execute_unprepared("PRAGMA foreign_keys = OFF")
+            .await
+            .unwrap();
+        for table in [
+            schema.create_table_from_entity(issue::Entity),
+            schema.create_table_from_entity(task::Entity),
+        ] {
+            db.execute(backend.build(&table)).await.unwrap();
+        }
+        db.execute_unprepared(
+            "INSERT INTO issue (id, title, priority, status, is_icebox, work_type, project_id, \
+             created_by_id, lock_version, created_at, updated_at) \
+             VALUES (1, 'Theirs', 1, 0, 0, 1, 1, 1, 0, '2026-10-19T00:00:00Z', '2026-10-19T00:00:00Z')",
+        )
+        .await
+        .unwrap();
+        db.execute_unprepared(
+            "INSERT INTO tasks (id, title, completed, percent, issue_id, lock_version, created_at, \
+             updated_at) VALUES (1, 'Step', 0, 0, 1, 0, '2026-10-19T00:00:00Z', '2026-10-19T00:00:00Z')",
+        )
+        .await
+        .unwrap();
+
+        let now = Utc::now();
+        let (tx, _) = tokio::sync::broadcast::channel(1);
+        let tx: Arc<EventSender> = Arc::new(tx);
+        let crud = IssueCrud::new(AppState {
+            db: db.clone(),
+            tx,
+            user: None,
+            project: Some(project::Model {
+                id: 2,
+                name: "Mine".to_string(),
+                owner_id: 2,
+                point_scale: point_scale::POINT_SCALE_FIBONACCI.to_string(),
+                estimate_bugs: false,
+                estimate_chores: false,
+                created_at: now.into(),
+                updated_at: now.into(),
+                notification_count: 0,
+            }),
+            worker: None,
+        });
+
+        let err = crud.delete(1).await.unwrap_err();
+        assert!(matches!(err, AppError::NotFound(_)));
+        assert!(issue::Entity::find_by_id(1)
+            .one(&db)
+            .await
+            .unwrap()
+            .is_some());
+        assert_eq!(task::Entity::find().count(&db).await.unwrap(), 1);
+    }
+}
@@ -1,8 +1,9 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
 use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::error::AppError;
 
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::crud::user::UserCrud;
 use crate::entities::issue_assignee;
 use crate::AppState;
@@ -22,11 +23,9 @@ impl IssueAssigneeCrud {
         &self,
         issue_id: i32,
         user_id: i32,
-    ) -> Result<issue_assignee::Model, DbErr> {
+    ) -> Result<issue_assignee::Model, AppError> {
         match self.find_by_ids(issue_id, user_id).await {
-            Ok(Some(model)) => {
-                return Ok(model);
-            }
+            Ok(Some(model)) => Ok(model),
             _ => {
                 let user_crud = UserCrud::new(self.app_state.clone());
                 let user = user_crud.find_by_id(user_id).await?.unwrap();
@@ -47,7 +46,6 @@ impl IssueAssigneeCrud {
                 let issue_assignee = issue_assignee::ActiveModel {
                     issue_id: Set(issue_id),
                     user_id: Set(user_id),
-                    ..Default::default()
                 };
 
                 let result = issue_assignee.insert(&self.app_state.db).await?;
@@ -59,6 +57,7 @@ impl IssueAssigneeCrud {
 
                     let _ = notification_crud
                         .notify_single_user_with_context(
+                            NotificationKind::Assignment,
                             issue_id,
                             "Issue Assigned: {}",
                             "You have been assigned to issue '{}'",
@@ -69,13 +68,9 @@ impl IssueAssigneeCrud {
                         .await;
                 }
 
-                let project_id = &self.app_state.project.clone().unwrap().id;
-                let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-                broadcaster.broadcast_event(
-                    *project_id,
-                    ISSUE_UPDATED,
-                    serde_json::json!({"id": user_id}),
-                );
+                IssueCrud::new(self.app_state.clone())
+                    .publish_updated(issue_id)
+                    .await?;
 
                 Ok(result)
             }
@@ -86,32 +81,38 @@ impl IssueAssigneeCrud {
         &self,
         issue_id: i32,
         user_id: i32,
-    ) -> Result<Option<issue_assignee::Model>, DbErr> {
+    ) -> Result<Option<issue_assignee::Model>, AppError> {
         issue_assignee::Entity::find()
             .filter(issue_assignee::Column::IssueId.eq(issue_id))
             .filter(issue_assignee::Column::UserId.eq(user_id))
             .one(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn find_by_issue_id(
         &self,
         issue_id: i32,
-    ) -> Result<Vec<issue_assignee::Model>, DbErr> {
+    ) -> Result<Vec<issue_assignee::Model>, AppError> {
         issue_assignee::Entity::find()
             .filter(issue_assignee::Column::IssueId.eq(issue_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_user_id(&self, user_id: i32) -> Result<Vec<issue_assignee::Model>, DbErr> {
+    pub async fn find_by_user_id(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<issue_assignee::Model>, AppError> {
         issue_assignee::Entity::find()
             .filter(issue_assignee::Column::UserId.eq(user_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete(&self, issue_id: i32, user_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, issue_id: i32, user_id: i32) -> Result<DeleteResult, AppError> {
         let user_crud = UserCrud::new(self.app_state.clone());
         let user = user_crud.find_by_id(user_id).await?.unwrap();
 
@@ -134,20 +135,24 @@ impl IssueAssigneeCrud {
             .exec(&self.app_state.db)
             .await?;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"id": user_id}),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
         Ok(result)
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
         issue_assignee::Entity::delete_many()
             .filter(issue_assignee::Column::IssueId.eq(issue_id))
-            .exec(&self.app_state.db)
+            .exec(txn)
             .await
+            .map_err(AppError::from)
     }
 }
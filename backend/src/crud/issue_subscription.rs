@@ -0,0 +1,176 @@
+use crate::crud::issue::IssueCrud;
+use crate::entities::{issue, issue_subscription};
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// Who hears about an issue: the people it would normally reach, plus anyone watching
+/// it, minus anyone who unwatched it and the person who made the change.
+pub fn fan_out(
+    participants: impl IntoIterator<Item = i32>,
+    subscriptions: &[issue_subscription::Model],
+    acting_user_id: i32,
+) -> HashSet<i32> {
+    let mut recipients: HashSet<i32> = participants.into_iter().collect();
+    for subscription in subscriptions {
+        if subscription.watching {
+            recipients.insert(subscription.user_id);
+        } else {
+            recipients.remove(&subscription.user_id);
+        }
+    }
+    recipients.remove(&acting_user_id);
+    recipients
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriptionStatus {
+    pub issue_id: i32,
+    // Whether the current user is notified about this issue
+    pub watching: bool,
+    // Set once the user has explicitly watched or unwatched
+    pub explicit: bool,
+}
+
+#[derive(Clone)]
+pub struct IssueSubscriptionCrud {
+    app_state: AppState,
+}
+
+impl IssueSubscriptionCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn find_by_issue_id(
+        &self,
+        issue_id: i32,
+    ) -> Result<Vec<issue_subscription::Model>, AppError> {
+        issue_subscription::Entity::find()
+            .filter(issue_subscription::Column::IssueId.eq(issue_id))
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn status(&self, issue_id: i32) -> Result<SubscriptionStatus, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let issue = self.find_issue(issue_id).await?;
+        let subscription = self.find_for_user(issue_id, user_id).await?;
+
+        let watching = match &subscription {
+            Some(subscription) => subscription.watching,
+            None => issue.created_by_id == user_id || issue.issue_assignee_ids.contains(&user_id),
+        };
+
+        Ok(SubscriptionStatus {
+            issue_id,
+            watching,
+            explicit: subscription.is_some(),
+        })
+    }
+
+    pub async fn watch(&self, issue_id: i32) -> Result<SubscriptionStatus, AppError> {
+        self.set_watching(issue_id, true).await?;
+        self.status(issue_id).await
+    }
+
+    pub async fn unwatch(&self, issue_id: i32) -> Result<SubscriptionStatus, AppError> {
+        self.set_watching(issue_id, false).await?;
+        self.status(issue_id).await
+    }
+
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        issue_subscription::Entity::delete_many()
+            .filter(issue_subscription::Column::IssueId.eq(issue_id))
+            .exec(txn)
+            .await
+            .map_err(AppError::from)
+    }
+
+    async fn set_watching(&self, issue_id: i32, watching: bool) -> Result<(), AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        self.find_issue(issue_id).await?;
+
+        match self.find_for_user(issue_id, user_id).await? {
+            Some(existing) => {
+                let mut subscription: issue_subscription::ActiveModel = existing.into();
+                subscription.watching = Set(watching);
+                subscription.update(&self.app_state.db).await?;
+            }
+            None => {
+                issue_subscription::ActiveModel {
+                    issue_id: Set(issue_id),
+                    user_id: Set(user_id),
+                    watching: Set(watching),
+                    created_at: Set(Utc::now().into()),
+                    ..Default::default()
+                }
+                .insert(&self.app_state.db)
+                .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn find_for_user(
+        &self,
+        issue_id: i32,
+        user_id: i32,
+    ) -> Result<Option<issue_subscription::Model>, AppError> {
+        issue_subscription::Entity::find()
+            .filter(issue_subscription::Column::IssueId.eq(issue_id))
+            .filter(issue_subscription::Column::UserId.eq(user_id))
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    async fn find_issue(&self, issue_id: i32) -> Result<issue::Model, AppError> {
+        let project_id = self.app_state.current_project()?.id;
+        IssueCrud::new(self.app_state.clone())
+            .find_by_id(issue_id)
+            .await?
+            .filter(|issue| issue.project_id == project_id)
+            .ok_or_else(|| AppError::not_found("Issue"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn subscription(user_id: i32, watching: bool) -> issue_subscription::Model {
+        issue_subscription::Model {
+            id: user_id,
+            issue_id: 1,
+            user_id,
+            watching,
+            created_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_watchers_are_added_and_unwatchers_removed() {
+        let subscriptions = [subscription(3, true), subscription(2, false)];
+        let recipients = fan_out([1, 2], &subscriptions, 99);
+        assert_eq!(recipients, HashSet::from([1, 3]));
+    }
+
+    #[test]
+    fn test_acting_user_is_never_notified() {
+        let subscriptions = [subscription(1, true)];
+        let recipients = fan_out([1, 2], &subscriptions, 1);
+        assert_eq!(recipients, HashSet::from([2]));
+    }
+}
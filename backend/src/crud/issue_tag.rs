@@ -1,9 +1,9 @@
-use super::event_broadcaster::TAG_DELETED;
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
 use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::error::AppError;
 
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::crud::tag::TagCrud;
 use crate::entities::issue_tag;
 use crate::AppState;
@@ -18,7 +18,7 @@ impl IssueTagCrud {
     pub fn new(app_state: AppState) -> Self {
         Self { app_state }
     }
-    pub async fn create(&self, issue_id: i32, tag_id: i32) -> Result<issue_tag::Model, DbErr> {
+    pub async fn create(&self, issue_id: i32, tag_id: i32) -> Result<issue_tag::Model, AppError> {
         let model = self.find_by_ids(issue_id, tag_id).await?;
         if let Some(model) = model {
             return Ok(model);
@@ -55,6 +55,7 @@ impl IssueTagCrud {
 
         let _ = notification_crud
             .notify_issue_assignees_with_context(
+                NotificationKind::IssueUpdate,
                 issue_id,
                 "Label Added to Issue: {}",
                 description,
@@ -63,13 +64,9 @@ impl IssueTagCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({ "id": issue_id }),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
 
         Ok(result)
     }
@@ -78,30 +75,33 @@ impl IssueTagCrud {
         &self,
         issue_id: i32,
         tag_id: i32,
-    ) -> Result<Option<issue_tag::Model>, DbErr> {
+    ) -> Result<Option<issue_tag::Model>, AppError> {
         issue_tag::Entity::find()
             .filter(issue_tag::Column::IssueId.eq(issue_id))
             .filter(issue_tag::Column::TagId.eq(tag_id))
             .one(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<issue_tag::Model>, DbErr> {
+    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<issue_tag::Model>, AppError> {
         issue_tag::Entity::find()
             .filter(issue_tag::Column::IssueId.eq(issue_id))
             .order_by_asc(issue_tag::Column::CreatedAt)
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_tag_id(&self, tag_id: i32) -> Result<Vec<issue_tag::Model>, DbErr> {
+    pub async fn find_by_tag_id(&self, tag_id: i32) -> Result<Vec<issue_tag::Model>, AppError> {
         issue_tag::Entity::find()
             .filter(issue_tag::Column::TagId.eq(tag_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete(&self, issue_id: i32, tag_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, issue_id: i32, tag_id: i32) -> Result<DeleteResult, AppError> {
         let tag_crud = TagCrud::new(self.app_state.clone());
         let tag = tag_crud.find_by_id(tag_id).await?.unwrap();
 
@@ -131,6 +131,7 @@ impl IssueTagCrud {
 
         let _ = notification_crud
             .notify_issue_assignees_with_context(
+                NotificationKind::IssueUpdate,
                 issue_id,
                 "Label Removed from Issue: {}",
                 description,
@@ -139,25 +140,29 @@ impl IssueTagCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({ "id": issue_id }),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
 
         Ok(result)
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
         issue_tag::Entity::delete_many()
             .filter(issue_tag::Column::IssueId.eq(issue_id))
-            .exec(&self.app_state.db)
+            .exec(txn)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete_by_tag_id(&self, tag_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_by_tag_id(&self, tag_id: i32) -> Result<DeleteResult, AppError> {
         let tag_crud = TagCrud::new(self.app_state.clone());
         let tag = tag_crud.find_by_id(tag_id).await?.unwrap();
 
@@ -165,7 +170,7 @@ impl IssueTagCrud {
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
         let current_user_id = &self.app_state.user.clone().unwrap().id;
 
-        for issue in issues {
+        for issue in &issues {
             history_crud
                 .create(
                     *current_user_id,
@@ -182,14 +187,12 @@ impl IssueTagCrud {
             .exec(&self.app_state.db)
             .await?;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            TAG_DELETED,
-            serde_json::json!({ "project_id": project_id }),
-        );
-
-        return Ok(result);
+        // The tag itself is announced by TagCrud once it's gone
+        let issue_crud = IssueCrud::new(self.app_state.clone());
+        for issue in issues {
+            issue_crud.publish_updated(issue.issue_id).await?;
+        }
+
+        Ok(result)
     }
 }
@@ -0,0 +1,339 @@
+use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
+use crate::crud::project_user::ProjectUserCrud;
+use crate::entities::{comment, issue, mention, user};
+use crate::error::AppError;
+use crate::AppState;
+use chrono::{DateTime, FixedOffset, Utc};
+use regex::Regex;
+use sea_orm::*;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+lazy_static::lazy_static! {
+    static ref CODE_RE: Regex = Regex::new(r"(?s)```.*?```|`[^`\n]*`").expect("code regex is valid");
+    // `@` has to start the text or follow a non-word character, so emails in prose don't count
+    static ref MENTION_RE: Regex = Regex::new(
+        r#"(?:^|[^\w])@(?:"([^"\n]+)"|([\w.%+\-]+@[\w\-]+(?:\.[\w\-]+)+|[\w.\-]+))"#
+    )
+    .expect("mention regex is valid");
+}
+
+const EXCERPT_LENGTH: usize = 200;
+
+/// The `@email`, `@name` and `@"Full Name"` mentions in a piece of markdown, in the order
+/// they first appear. Anything inside code blocks or inline code is ignored.
+pub fn extract_mentions(text: &str) -> Vec<String> {
+    let text = CODE_RE.replace_all(text, " ");
+    let mut seen = HashSet::new();
+    let mut mentions = Vec::new();
+    for captures in MENTION_RE.captures_iter(&text) {
+        let token = match (captures.get(1), captures.get(2)) {
+            (Some(quoted), _) => quoted.as_str().trim(),
+            // Sentence punctuation after a name isn't part of it
+            (_, Some(bare)) => bare.as_str().trim_end_matches(['.', '-']),
+            _ => continue,
+        };
+        if !token.is_empty() && seen.insert(token.to_lowercase()) {
+            mentions.push(token.to_string());
+        }
+    }
+    mentions
+}
+
+fn normalize_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Matches mentions to project members by email, or by name ignoring case and spaces so
+/// `@janedoe` finds "Jane Doe". Tokens that match nobody are dropped.
+pub fn resolve_mentions(tokens: &[String], members: &[user::Model]) -> HashSet<i32> {
+    tokens
+        .iter()
+        .filter_map(|token| {
+            let normalized = normalize_name(token);
+            members
+                .iter()
+                .find(|member| {
+                    member.email.eq_ignore_ascii_case(token)
+                        || normalize_name(&member.name) == normalized
+                })
+                .map(|member| member.id)
+        })
+        .collect()
+}
+
+fn excerpt(text: &str) -> String {
+    match text.char_indices().nth(EXCERPT_LENGTH) {
+        Some((index, _)) => format!("{}…", &text[..index]),
+        None => text.to_string(),
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct MentionResponse {
+    #[serde(flatten)]
+    pub mention: mention::Model,
+    pub issue_title: String,
+    // The start of the comment or description the user was mentioned in
+    pub excerpt: String,
+}
+
+#[derive(Clone)]
+pub struct MentionCrud {
+    app_state: AppState,
+}
+
+impl MentionCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    /// Brings the stored mentions for a comment, or for the issue description when
+    /// `comment_id` is None, in line with `text`. Only newly mentioned users are notified,
+    /// so editing a comment doesn't notify everyone in it again.
+    pub async fn sync(
+        &self,
+        project_id: i32,
+        issue_id: i32,
+        comment_id: Option<i32>,
+        text: &str,
+        author_id: i32,
+    ) -> Result<Vec<mention::Model>, AppError> {
+        let tokens = extract_mentions(text);
+        let mut user_ids = if tokens.is_empty() {
+            HashSet::new()
+        } else {
+            let member_ids = ProjectUserCrud::new(self.app_state.clone())
+                .member_user_ids(project_id)
+                .await?;
+            let members = user::Entity::find()
+                .filter(user::Column::Id.is_in(member_ids))
+                .all(&self.app_state.db)
+                .await?;
+            resolve_mentions(&tokens, &members)
+        };
+        user_ids.remove(&author_id);
+
+        let existing = self.find_for_source(issue_id, comment_id).await?;
+        let stale: Vec<i32> = existing
+            .iter()
+            .filter(|m| !user_ids.contains(&m.user_id))
+            .map(|m| m.id)
+            .collect();
+        if !stale.is_empty() {
+            mention::Entity::delete_many()
+                .filter(mention::Column::Id.is_in(stale))
+                .exec(&self.app_state.db)
+                .await?;
+        }
+
+        let already: HashSet<i32> = existing.iter().map(|m| m.user_id).collect();
+        let mut created = Vec::new();
+        for user_id in user_ids.into_iter().filter(|id| !already.contains(id)) {
+            let mention = mention::ActiveModel {
+                project_id: Set(project_id),
+                issue_id: Set(issue_id),
+                comment_id: Set(comment_id),
+                user_id: Set(user_id),
+                mentioned_by_id: Set(author_id),
+                created_at: Set(Utc::now().into()),
+                ..Default::default()
+            }
+            .insert(&self.app_state.db)
+            .await?;
+            created.push(mention);
+        }
+
+        if !created.is_empty() {
+            debug!(
+                "Recorded {} new mentions on issue {}",
+                created.len(),
+                issue_id
+            );
+            self.notify(&created, text).await?;
+        }
+        Ok(created)
+    }
+
+    pub async fn find_for_current_user(
+        &self,
+        cursor: Option<(DateTime<FixedOffset>, i32)>,
+    ) -> Result<Vec<MentionResponse>, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let project_id = self.app_state.current_project()?.id;
+
+        let mut query = mention::Entity::find()
+            .filter(mention::Column::UserId.eq(user_id))
+            .filter(mention::Column::ProjectId.eq(project_id))
+            .order_by_desc(mention::Column::CreatedAt)
+            .order_by_desc(mention::Column::Id)
+            .limit(25);
+
+        if let Some((cursor_created_at, cursor_id)) = cursor {
+            query = query.filter(
+                Condition::any()
+                    .add(mention::Column::CreatedAt.lt(cursor_created_at))
+                    .add(
+                        Condition::all()
+                            .add(mention::Column::CreatedAt.eq(cursor_created_at))
+                            .add(mention::Column::Id.lt(cursor_id)),
+                    ),
+            );
+        }
+        let mentions = query.all(&self.app_state.db).await?;
+
+        let issues: HashMap<i32, issue::Model> = issue::Entity::find()
+            .filter(issue::Column::Id.is_in(mentions.iter().map(|m| m.issue_id)))
+            .all(&self.app_state.db)
+            .await?
+            .into_iter()
+            .map(|i| (i.id, i))
+            .collect();
+        let comments: HashMap<i32, String> = comment::Entity::find()
+            .filter(comment::Column::Id.is_in(mentions.iter().filter_map(|m| m.comment_id)))
+            .all(&self.app_state.db)
+            .await?
+            .into_iter()
+            .map(|c| (c.id, c.content))
+            .collect();
+
+        Ok(mentions
+            .into_iter()
+            .map(|mention| {
+                let issue = issues.get(&mention.issue_id);
+                let text = match mention.comment_id {
+                    Some(comment_id) => comments.get(&comment_id).cloned(),
+                    None => issue.and_then(|i| i.description.clone()),
+                };
+                MentionResponse {
+                    issue_title: issue.map(|i| i.title.clone()).unwrap_or_default(),
+                    excerpt: excerpt(&text.unwrap_or_default()),
+                    mention,
+                }
+            })
+            .collect())
+    }
+
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        mention::Entity::delete_many()
+            .filter(mention::Column::IssueId.eq(issue_id))
+            .exec(txn)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn delete_by_comment_id_with_txn<C>(
+        &self,
+        comment_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
+        mention::Entity::delete_many()
+            .filter(mention::Column::CommentId.eq(comment_id))
+            .exec(txn)
+            .await
+            .map_err(AppError::from)
+    }
+
+    async fn find_for_source(
+        &self,
+        issue_id: i32,
+        comment_id: Option<i32>,
+    ) -> Result<Vec<mention::Model>, AppError> {
+        let query = mention::Entity::find().filter(mention::Column::IssueId.eq(issue_id));
+        let query = match comment_id {
+            Some(comment_id) => query.filter(mention::Column::CommentId.eq(comment_id)),
+            None => query.filter(mention::Column::CommentId.is_null()),
+        };
+        query.all(&self.app_state.db).await.map_err(AppError::from)
+    }
+
+    async fn notify(&self, mentions: &[mention::Model], text: &str) -> Result<(), AppError> {
+        let first = &mentions[0];
+        let issue_title = issue::Entity::find_by_id(first.issue_id)
+            .one(&self.app_state.db)
+            .await?
+            .map(|i| i.title)
+            .unwrap_or_default();
+        let author_name = user::Entity::find_by_id(first.mentioned_by_id)
+            .one(&self.app_state.db)
+            .await?
+            .map(|u| u.name)
+            .unwrap_or_else(|| "Someone".to_string());
+        let description = match first.comment_id {
+            Some(_) => format!(
+                "{} mentioned you in a comment: '{}'",
+                author_name,
+                excerpt(text)
+            ),
+            None => format!("{} mentioned you in the issue description", author_name),
+        };
+
+        let notification_crud = NotificationCrud::new(self.app_state.clone());
+        for mention in mentions {
+            let _ = notification_crud
+                .create(
+                    NotificationKind::Mention,
+                    format!("Mentioned in Issue: {}", issue_title),
+                    description.clone(),
+                    mention.project_id,
+                    mention.issue_id,
+                    mention.mentioned_by_id,
+                    mention.user_id,
+                )
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(id: i32, name: &str, email: &str) -> user::Model {
+        user::Model {
+            id,
+            name: name.to_string(),
+            email: email.to_string(),
+            created_at: Utc::now().into(),
+            updated_at: Utc::now().into(),
+            is_project_owner: false,
+        }
+    }
+
+    #[test]
+    fn test_mentions_are_extracted_outside_code() {
+        let text = "Thanks @alice, and @bob@example.com. Ask @\"Jane Doe\" too\n\
+                    ```\n@carol in a block\n```\nnot `@dave` or mail@example.com, but (@erin).";
+        assert_eq!(
+            extract_mentions(text),
+            vec!["alice", "bob@example.com", "Jane Doe", "erin"]
+        );
+        assert_eq!(extract_mentions("@alice @Alice"), vec!["alice"]);
+    }
+
+    #[test]
+    fn test_mentions_resolve_by_email_or_name() {
+        let members = [
+            member(1, "Alice", "alice@example.com"),
+            member(2, "Jane Doe", "jane@example.com"),
+        ];
+        let tokens = extract_mentions("@ALICE@example.com @janedoe @\"jane doe\" @nobody");
+        assert_eq!(resolve_mentions(&tokens, &members), HashSet::from([1, 2]));
+    }
+}
@@ -1,24 +1,41 @@
 pub mod blocker;
 pub mod comment;
 pub mod comment_file_upload;
+pub mod direct_upload;
+pub mod domain_event;
+pub mod estimation_session;
 pub mod event_broadcaster;
 pub mod file_upload;
 pub mod history;
 pub mod import_export;
 pub mod issue;
 pub mod issue_assignee;
+pub mod issue_subscription;
 pub mod issue_tag;
+pub mod mention;
 pub mod notification;
+pub mod notification_delivery;
+pub mod notification_digest;
+pub mod notification_preference;
 pub mod notification_settings;
 pub mod owner;
+pub mod point_scale;
 pub mod project;
 pub mod project_note;
 pub mod project_note_history;
 pub mod project_note_parts;
 pub mod project_note_tag;
 pub mod project_user;
+pub mod recurrence;
+pub mod repository_event;
+pub mod repository_integration;
 pub mod status;
 pub mod tag;
 pub mod task;
+pub mod thumbnail;
+pub mod upload_session;
 pub mod user;
+pub mod user_notification_channel;
+pub mod webhook;
+pub mod webhook_delivery;
 pub mod work_type;
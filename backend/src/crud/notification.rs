@@ -1,11 +1,28 @@
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::issue::IssueCrud;
 use crate::crud::issue_assignee::IssueAssigneeCrud;
+use crate::crud::issue_subscription::{self, IssueSubscriptionCrud};
+use crate::crud::notification_preference::{NotificationKind, NotificationPreferenceCrud};
 use crate::entities::notification;
+use crate::error::AppError;
+use crate::notifications::channel::OutgoingNotification;
+use crate::notifications::delivery::NotificationDelivery;
 use crate::AppState;
-use chrono::{DateTime, FixedOffset};
+use chrono::{DateTime, FixedOffset, Utc};
+use sea_orm::sea_query::Expr;
 use sea_orm::*;
 use std::collections::HashSet;
-use tracing::debug;
+use tracing::{debug, warn};
+
+/// Narrows the inbox; fields left unset don't filter anything.
+#[derive(Debug, Default)]
+pub struct NotificationFilter {
+    pub read: Option<bool>,
+    pub kind: Option<String>,
+    pub issue_id: Option<i32>,
+    // Archived notifications are only listed when asked for
+    pub archived: bool,
+}
 
 #[derive(Clone)]
 pub struct NotificationCrud {
@@ -17,15 +34,17 @@ impl NotificationCrud {
         Self { state }
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn create(
         &self,
+        kind: NotificationKind,
         title: String,
         description: String,
         project_id: i32,
         issue_id: i32,
         initiated_by_user_id: i32,
         targeted_user_id: i32,
-    ) -> Result<notification::Model, DbErr> {
+    ) -> Result<notification::Model, AppError> {
         debug!(
             "Creating notification for project {} and user {} from user {}",
             project_id, targeted_user_id, initiated_by_user_id
@@ -39,11 +58,40 @@ impl NotificationCrud {
             initiated_by_user_id: Set(initiated_by_user_id),
             targeted_user_id: Set(targeted_user_id),
             read: Set(false),
+            kind: Set(kind.as_str().to_string()),
             ..Default::default()
         };
 
         let created_notification = notification.insert(&self.state.db).await?;
 
+        // Push to the user's own channels without holding up the request
+        let delivery = NotificationDelivery::new(self.state.clone());
+        let preference_crud = NotificationPreferenceCrud::new(self.state.clone());
+        let outgoing = OutgoingNotification::new(
+            created_notification.title.clone(),
+            created_notification.description.clone(),
+        )
+        .for_issue(project_id, issue_id);
+        tokio::spawn(async move {
+            let result = match preference_crud.find(targeted_user_id, project_id).await {
+                Ok(preferences) => {
+                    delivery
+                        .send_to_user(targeted_user_id, &preferences, &outgoing)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                warn!(
+                    "Failed to push notification to user {}: {}",
+                    targeted_user_id, e
+                );
+            }
+        });
+
+        self.broadcast_unread_count(project_id, targeted_user_id)
+            .await;
+
         debug!(
             "Successfully created notification with id {}",
             created_notification.id
@@ -56,21 +104,33 @@ impl NotificationCrud {
         &self,
         project_id: i32,
         target_user_id: i32,
+        filter: &NotificationFilter,
         cursor: Option<(DateTime<FixedOffset>, i32)>,
-    ) -> Result<Vec<notification::Model>, DbErr> {
+    ) -> Result<Vec<notification::Model>, AppError> {
         debug!(
-            "Finding notifications for project {} and user {}",
-            project_id, target_user_id
+            "Finding notifications for project {} and user {} matching {:?}",
+            project_id, target_user_id, filter
         );
 
         let limit = 25;
         let mut query = notification::Entity::find()
             .filter(notification::Column::ProjectId.eq(project_id))
             .filter(notification::Column::TargetedUserId.eq(target_user_id))
+            .filter(notification::Column::Archived.eq(filter.archived))
             .order_by_desc(notification::Column::CreatedAt)
             .order_by_desc(notification::Column::Id)
             .limit(limit);
 
+        if let Some(read) = filter.read {
+            query = query.filter(notification::Column::Read.eq(read));
+        }
+        if let Some(kind) = &filter.kind {
+            query = query.filter(notification::Column::Kind.eq(kind.as_str()));
+        }
+        if let Some(issue_id) = filter.issue_id {
+            query = query.filter(notification::Column::IssueId.eq(issue_id));
+        }
+
         if let Some((cursor_created_at, cursor_id)) = cursor {
             query = query.filter(
                 Condition::any()
@@ -95,7 +155,7 @@ impl NotificationCrud {
         Ok(notifications)
     }
 
-    pub async fn delete_all_for_project(&self, project_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_for_project(&self, project_id: i32) -> Result<DeleteResult, AppError> {
         debug!("Deleting all notifications for project {}", project_id);
 
         let result = notification::Entity::delete_many()
@@ -111,12 +171,33 @@ impl NotificationCrud {
         Ok(result)
     }
 
-    pub async fn delete_all_for_issue(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    // Returns the (project, user) pairs whose unread count dropped; pass them to
+    // broadcast_unread_counts once the transaction has committed.
+    pub async fn delete_all_for_issue_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<Vec<(i32, i32)>, AppError>
+    where
+        C: ConnectionTrait,
+    {
         debug!("Deleting all notifications for issue {}", issue_id);
 
+        // Whoever had unread notifications here sees their count drop
+        let unread: Vec<(i32, i32)> = notification::Entity::find()
+            .select_only()
+            .column(notification::Column::ProjectId)
+            .column(notification::Column::TargetedUserId)
+            .filter(notification::Column::IssueId.eq(issue_id))
+            .filter(notification::Column::Read.eq(false))
+            .distinct()
+            .into_tuple()
+            .all(txn)
+            .await?;
+
         let result = notification::Entity::delete_many()
             .filter(notification::Column::IssueId.eq(issue_id))
-            .exec(&self.state.db)
+            .exec(txn)
             .await?;
 
         debug!(
@@ -124,16 +205,24 @@ impl NotificationCrud {
             result.rows_affected, issue_id
         );
 
-        Ok(result)
+        Ok(unread)
+    }
+
+    pub async fn broadcast_unread_counts(&self, unread: Vec<(i32, i32)>) {
+        for (project_id, user_id) in unread {
+            self.broadcast_unread_count(project_id, user_id).await;
+        }
     }
 
-    pub async fn mark_as_read(&self, notification_id: i32) -> Result<notification::Model, DbErr> {
+    pub async fn mark_as_read(
+        &self,
+        notification_id: i32,
+    ) -> Result<notification::Model, AppError> {
         debug!("Marking notification {} as read", notification_id);
 
-        let notification = notification::Entity::find_by_id(notification_id)
-            .one(&self.state.db)
-            .await?
-            .ok_or(DbErr::Custom("Notification not found".to_owned()))?;
+        let notification = self.find_for_current_user(notification_id).await?;
+        let project_id = notification.project_id;
+        let user_id = notification.targeted_user_id;
 
         let mut notification: notification::ActiveModel = notification.into();
         notification.read = Set(true);
@@ -145,14 +234,140 @@ impl NotificationCrud {
             notification_id
         );
 
+        self.broadcast_unread_count(project_id, user_id).await;
+
         Ok(updated_notification)
     }
 
+    /// Marks the current user's notifications in the current project read, or only
+    /// those about `issue_id`. Returns how many changed.
+    pub async fn mark_all_as_read(&self, issue_id: Option<i32>) -> Result<u64, AppError> {
+        let user_id = self.state.current_user()?.id;
+        let project_id = self.state.current_project()?.id;
+
+        let mut update = notification::Entity::update_many()
+            .col_expr(notification::Column::Read, Expr::value(true))
+            .col_expr(notification::Column::UpdatedAt, Expr::value(Utc::now()))
+            .filter(notification::Column::ProjectId.eq(project_id))
+            .filter(notification::Column::TargetedUserId.eq(user_id))
+            .filter(notification::Column::Read.eq(false));
+        if let Some(issue_id) = issue_id {
+            update = update.filter(notification::Column::IssueId.eq(issue_id));
+        }
+        let result = update.exec(&self.state.db).await?;
+
+        debug!(
+            "Marked {} notifications read for user {} in project {}",
+            result.rows_affected, user_id, project_id
+        );
+        if result.rows_affected > 0 {
+            self.broadcast_unread_count(project_id, user_id).await;
+        }
+        Ok(result.rows_affected)
+    }
+
+    pub async fn archive(&self, notification_id: i32) -> Result<notification::Model, AppError> {
+        self.find_for_current_user(notification_id).await?;
+        self.archive_many(&[notification_id]).await?;
+        self.find_for_current_user(notification_id).await
+    }
+
+    pub async fn delete(&self, notification_id: i32) -> Result<(), AppError> {
+        self.find_for_current_user(notification_id).await?;
+        self.delete_many(&[notification_id]).await?;
+        Ok(())
+    }
+
+    // Ids that aren't the current user's, or belong to another project, are ignored
+    pub async fn mark_many_as_read(&self, ids: &[i32]) -> Result<u64, AppError> {
+        self.update_many(ids, false).await
+    }
+
+    // Archiving also marks read, so archived notifications never count as unread
+    pub async fn archive_many(&self, ids: &[i32]) -> Result<u64, AppError> {
+        self.update_many(ids, true).await
+    }
+
+    pub async fn delete_many(&self, ids: &[i32]) -> Result<u64, AppError> {
+        let user_id = self.state.current_user()?.id;
+        let project_id = self.state.current_project()?.id;
+
+        let result = notification::Entity::delete_many()
+            .filter(notification::Column::Id.is_in(ids.iter().copied()))
+            .filter(notification::Column::ProjectId.eq(project_id))
+            .filter(notification::Column::TargetedUserId.eq(user_id))
+            .exec(&self.state.db)
+            .await?;
+
+        if result.rows_affected > 0 {
+            self.broadcast_unread_count(project_id, user_id).await;
+        }
+        Ok(result.rows_affected)
+    }
+
+    async fn update_many(&self, ids: &[i32], archive: bool) -> Result<u64, AppError> {
+        let user_id = self.state.current_user()?.id;
+        let project_id = self.state.current_project()?.id;
+
+        let mut update = notification::Entity::update_many()
+            .col_expr(notification::Column::Read, Expr::value(true))
+            .col_expr(notification::Column::UpdatedAt, Expr::value(Utc::now()));
+        if archive {
+            update = update.col_expr(notification::Column::Archived, Expr::value(true));
+        }
+        let result = update
+            .filter(notification::Column::Id.is_in(ids.iter().copied()))
+            .filter(notification::Column::ProjectId.eq(project_id))
+            .filter(notification::Column::TargetedUserId.eq(user_id))
+            .exec(&self.state.db)
+            .await?;
+
+        if result.rows_affected > 0 {
+            self.broadcast_unread_count(project_id, user_id).await;
+        }
+        Ok(result.rows_affected)
+    }
+
+    // Other users' notifications are reported as missing rather than forbidden
+    async fn find_for_current_user(
+        &self,
+        notification_id: i32,
+    ) -> Result<notification::Model, AppError> {
+        let user_id = self.state.current_user()?.id;
+        notification::Entity::find_by_id(notification_id)
+            .filter(notification::Column::TargetedUserId.eq(user_id))
+            .one(&self.state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Notification"))
+    }
+
+    // Keeps the badge in every open tab in step; a failed count only costs a stale badge
+    async fn broadcast_unread_count(&self, project_id: i32, user_id: i32) {
+        match self
+            .get_unread_count_for_user_and_project(project_id, user_id)
+            .await
+        {
+            Ok(count) => {
+                EventBroadcaster::new(&self.state)
+                    .publish_to_user(
+                        project_id,
+                        user_id,
+                        DomainEvent::NotificationCountUpdated { count },
+                    )
+                    .await
+            }
+            Err(e) => warn!(
+                "Failed to count unread notifications for user {}: {}",
+                user_id, e
+            ),
+        }
+    }
+
     pub async fn get_unread_count_for_user_and_project(
         &self,
         project_id: i32,
         target_user_id: i32,
-    ) -> Result<i32, DbErr> {
+    ) -> Result<i32, AppError> {
         debug!(
             "Getting notification count for project {} and user {}",
             project_id, target_user_id
@@ -172,46 +387,75 @@ impl NotificationCrud {
         Ok(count as i32)
     }
 
+    // Recipients the email digest has to consider
+    pub async fn find_user_ids_with_unread(&self) -> Result<Vec<i32>, AppError> {
+        notification::Entity::find()
+            .select_only()
+            .column(notification::Column::TargetedUserId)
+            .filter(notification::Column::Read.eq(false))
+            .distinct()
+            .into_tuple::<i32>()
+            .all(&self.state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_unread_for_user_since(
+        &self,
+        target_user_id: i32,
+        since: DateTime<FixedOffset>,
+        limit: u64,
+    ) -> Result<Vec<notification::Model>, AppError> {
+        notification::Entity::find()
+            .filter(notification::Column::TargetedUserId.eq(target_user_id))
+            .filter(notification::Column::Read.eq(false))
+            .filter(notification::Column::CreatedAt.gt(since))
+            .order_by_desc(notification::Column::CreatedAt)
+            .limit(limit)
+            .all(&self.state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
     /// Notify only the assignees of an issue (excluding the current user)
     pub async fn notify_issue_assignees(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title: String,
         description: String,
         current_user_id: i32,
         project_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_assignee_crud = IssueAssigneeCrud::new(self.state.clone());
 
-        if let Ok(assignees) = issue_assignee_crud.find_by_issue_id(issue_id).await {
-            for assignee in assignees {
-                if assignee.user_id != current_user_id {
-                    let _ = self
-                        .create(
-                            title.clone(),
-                            description.clone(),
-                            project_id,
-                            issue_id,
-                            current_user_id,
-                            assignee.user_id,
-                        )
-                        .await;
-                }
-            }
-        }
-
-        Ok(())
+        let assignees = issue_assignee_crud
+            .find_by_issue_id(issue_id)
+            .await
+            .unwrap_or_default();
+
+        self.notify_participants(
+            kind,
+            issue_id,
+            assignees.into_iter().map(|a| a.user_id),
+            title,
+            description,
+            current_user_id,
+            project_id,
+        )
+        .await
     }
 
     /// Notify both assignees and issue creator (excluding the current user)
     pub async fn notify_issue_stakeholders(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title: String,
         description: String,
         current_user_id: i32,
         project_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_assignee_crud = IssueAssigneeCrud::new(self.state.clone());
         let issue_crud = IssueCrud::new(self.state.clone());
 
@@ -230,13 +474,50 @@ impl NotificationCrud {
             target_user_ids.insert(issue.created_by_id);
         }
 
-        // Remove current user to avoid self-notification
-        target_user_ids.remove(&current_user_id);
+        self.notify_participants(
+            kind,
+            issue_id,
+            target_user_ids,
+            title,
+            description,
+            current_user_id,
+            project_id,
+        )
+        .await
+    }
 
-        // Create notifications for each target user
+    // Adds watchers, drops anyone who unwatched the issue or switched this kind off,
+    // and never notifies the user who made the change.
+    #[allow(clippy::too_many_arguments)]
+    async fn notify_participants(
+        &self,
+        kind: NotificationKind,
+        issue_id: i32,
+        participants: impl IntoIterator<Item = i32>,
+        title: String,
+        description: String,
+        current_user_id: i32,
+        project_id: i32,
+    ) -> Result<(), AppError> {
+        let subscriptions = IssueSubscriptionCrud::new(self.state.clone())
+            .find_by_issue_id(issue_id)
+            .await?;
+        let target_user_ids =
+            issue_subscription::fan_out(participants, &subscriptions, current_user_id);
+
+        let preference_crud = NotificationPreferenceCrud::new(self.state.clone());
         for target_user_id in target_user_ids {
+            let preferences = preference_crud.find(target_user_id, project_id).await?;
+            if !preferences.wants(kind) {
+                debug!(
+                    "User {} has {:?} notifications switched off",
+                    target_user_id, kind
+                );
+                continue;
+            }
             let _ = self
                 .create(
+                    kind,
                     title.clone(),
                     description.clone(),
                     project_id,
@@ -253,18 +534,26 @@ impl NotificationCrud {
     /// Notify assignees with issue context (gets issue title automatically)
     pub async fn notify_issue_assignees_with_context(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title_template: &str,
         description: String,
         current_user_id: i32,
         project_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_crud = IssueCrud::new(self.state.clone());
 
         if let Ok(Some(issue)) = issue_crud.find_by_id(issue_id).await {
             let title = title_template.replace("{}", &issue.title);
-            self.notify_issue_assignees(issue_id, title, description, current_user_id, project_id)
-                .await?
+            self.notify_issue_assignees(
+                kind,
+                issue_id,
+                title,
+                description,
+                current_user_id,
+                project_id,
+            )
+            .await?
         }
 
         Ok(())
@@ -273,17 +562,19 @@ impl NotificationCrud {
     /// Notify stakeholders with issue context (gets issue title automatically)
     pub async fn notify_issue_stakeholders_with_context(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title_template: &str,
         description: String,
         current_user_id: i32,
         project_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_crud = IssueCrud::new(self.state.clone());
 
         if let Ok(Some(issue)) = issue_crud.find_by_id(issue_id).await {
             let title = title_template.replace("{}", &issue.title);
             self.notify_issue_stakeholders(
+                kind,
                 issue_id,
                 title,
                 description,
@@ -297,15 +588,17 @@ impl NotificationCrud {
     }
 
     /// Notify a single user with issue context (gets issue title automatically)
+    #[allow(clippy::too_many_arguments)]
     pub async fn notify_single_user_with_context(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title_template: &str,
         description_template: &str,
         current_user_id: i32,
         target_user_id: i32,
         project_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_crud = IssueCrud::new(self.state.clone());
 
         if let Ok(Some(issue)) = issue_crud.find_by_id(issue_id).await {
@@ -314,6 +607,7 @@ impl NotificationCrud {
 
             let _ = self
                 .create(
+                    kind,
                     title,
                     description,
                     project_id,
@@ -328,15 +622,17 @@ impl NotificationCrud {
     }
 
     /// Notify stakeholders with explicit creator context (gets issue title automatically)
+    #[allow(clippy::too_many_arguments)]
     pub async fn notify_issue_stakeholders_with_creator_context(
         &self,
+        kind: NotificationKind,
         issue_id: i32,
         title_template: &str,
         description: String,
         current_user_id: i32,
         project_id: i32,
         issue_creator_id: i32,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         let issue_assignee_crud = IssueAssigneeCrud::new(self.state.clone());
         let issue_crud = IssueCrud::new(self.state.clone());
 
@@ -353,23 +649,17 @@ impl NotificationCrud {
             // Add explicit creator
             target_user_ids.insert(issue_creator_id);
 
-            // Remove current user to avoid self-notification
-            target_user_ids.remove(&current_user_id);
-
-            // Create notifications for each target user
             let title = title_template.replace("{}", &issue.title);
-            for target_user_id in target_user_ids {
-                let _ = self
-                    .create(
-                        title.clone(),
-                        description.clone(),
-                        project_id,
-                        issue_id,
-                        current_user_id,
-                        target_user_id,
-                    )
-                    .await;
-            }
+            self.notify_participants(
+                kind,
+                issue_id,
+                target_user_ids,
+                title,
+                description,
+                current_user_id,
+                project_id,
+            )
+            .await?
         }
 
         Ok(())
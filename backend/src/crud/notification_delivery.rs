@@ -0,0 +1,117 @@
+use crate::entities::notification_delivery;
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+
+pub const DELIVERY_SENT: &str = "sent";
+pub const DELIVERY_FAILED: &str = "failed";
+pub const DELIVERY_STATUSES: [&str; 2] = [DELIVERY_SENT, DELIVERY_FAILED];
+
+// Which table a delivery's channel_id points into
+pub const SOURCE_PROJECT: &str = "project";
+pub const SOURCE_USER: &str = "user";
+
+const MAX_LISTED_DELIVERIES: u64 = 100;
+
+/// Where an attempt went, for recording it.
+#[derive(Debug, Clone, Copy)]
+pub struct DeliveryChannel<'a> {
+    pub source: &'static str,
+    pub channel_id: i32,
+    pub channel: &'a str,
+    pub user_id: Option<i32>,
+}
+
+fn status_of(result: &Result<(), String>) -> (&'static str, Option<String>) {
+    match result {
+        Ok(()) => (DELIVERY_SENT, None),
+        Err(e) => (DELIVERY_FAILED, Some(e.clone())),
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationDeliveryCrud {
+    app_state: AppState,
+}
+
+impl NotificationDeliveryCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn record(
+        &self,
+        project_id: i32,
+        target: DeliveryChannel<'_>,
+        payload: String,
+        result: &Result<(), String>,
+    ) -> Result<notification_delivery::Model, AppError> {
+        let (status, last_error) = status_of(result);
+        let now = Utc::now();
+        notification_delivery::ActiveModel {
+            project_id: Set(project_id),
+            user_id: Set(target.user_id),
+            source: Set(target.source.to_string()),
+            channel_id: Set(target.channel_id),
+            channel: Set(target.channel.to_string()),
+            payload: Set(payload),
+            status: Set(status.to_string()),
+            attempts: Set(1),
+            last_error: Set(last_error),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    // A retried attempt updates its original record rather than adding another
+    pub async fn record_retry(
+        &self,
+        delivery: notification_delivery::Model,
+        result: &Result<(), String>,
+    ) -> Result<notification_delivery::Model, AppError> {
+        let (status, last_error) = status_of(result);
+        let attempts = delivery.attempts + 1;
+        let mut delivery: notification_delivery::ActiveModel = delivery.into();
+        delivery.status = Set(status.to_string());
+        delivery.last_error = Set(last_error);
+        delivery.attempts = Set(attempts);
+        delivery.updated_at = Set(Utc::now().into());
+        delivery
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_all_by_project_id(
+        &self,
+        project_id: i32,
+        status: Option<&str>,
+    ) -> Result<Vec<notification_delivery::Model>, AppError> {
+        let mut query = notification_delivery::Entity::find()
+            .filter(notification_delivery::Column::ProjectId.eq(project_id))
+            .order_by_desc(notification_delivery::Column::CreatedAt)
+            .order_by_desc(notification_delivery::Column::Id)
+            .limit(MAX_LISTED_DELIVERIES);
+        if let Some(status) = status {
+            query = query.filter(notification_delivery::Column::Status.eq(status));
+        }
+        query.all(&self.app_state.db).await.map_err(AppError::from)
+    }
+
+    pub async fn find_by_id(
+        &self,
+        project_id: i32,
+        id: i32,
+    ) -> Result<notification_delivery::Model, AppError> {
+        notification_delivery::Entity::find_by_id(id)
+            .filter(notification_delivery::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Notification delivery"))
+    }
+}
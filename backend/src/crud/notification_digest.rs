@@ -0,0 +1,128 @@
+use crate::entities::notification_digest;
+use crate::error::AppError;
+use crate::notifications::digest::{DEFAULT_TIMEZONE, DIGEST_OFF};
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use sea_orm::entity::prelude::DateTimeWithTimeZone;
+use sea_orm::*;
+use serde::Serialize;
+
+/// How often a user gets the email digest and in which timezone. The digest is opt-in:
+/// users who never chose get none.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DigestSettings {
+    pub frequency: String,
+    pub timezone: String,
+    pub last_sent_at: Option<DateTimeWithTimeZone>,
+}
+
+impl Default for DigestSettings {
+    fn default() -> Self {
+        Self {
+            frequency: DIGEST_OFF.to_string(),
+            timezone: DEFAULT_TIMEZONE.to_string(),
+            last_sent_at: None,
+        }
+    }
+}
+
+impl From<notification_digest::Model> for DigestSettings {
+    fn from(model: notification_digest::Model) -> Self {
+        Self {
+            frequency: model.frequency,
+            timezone: model.timezone,
+            last_sent_at: model.last_sent_at,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationDigestCrud {
+    app_state: AppState,
+}
+
+impl NotificationDigestCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn find_by_user_id(&self, user_id: i32) -> Result<DigestSettings, AppError> {
+        Ok(self
+            .find_model(user_id)
+            .await?
+            .map(DigestSettings::from)
+            .unwrap_or_default())
+    }
+
+    pub async fn find_for_current_user(&self) -> Result<DigestSettings, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        self.find_by_user_id(user_id).await
+    }
+
+    pub async fn update_for_current_user(
+        &self,
+        frequency: Option<String>,
+        timezone: Option<String>,
+    ) -> Result<DigestSettings, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        self.upsert(user_id, |model| {
+            if let Some(frequency) = frequency {
+                model.frequency = Set(frequency);
+            }
+            if let Some(timezone) = timezone {
+                model.timezone = Set(timezone);
+            }
+        })
+        .await
+    }
+
+    pub async fn mark_sent(
+        &self,
+        user_id: i32,
+        sent_at: DateTime<Utc>,
+    ) -> Result<DigestSettings, AppError> {
+        self.upsert(user_id, |model| {
+            model.last_sent_at = Set(Some(sent_at.into()))
+        })
+        .await
+    }
+
+    async fn find_model(
+        &self,
+        user_id: i32,
+    ) -> Result<Option<notification_digest::Model>, AppError> {
+        notification_digest::Entity::find()
+            .filter(notification_digest::Column::UserId.eq(user_id))
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    async fn upsert(
+        &self,
+        user_id: i32,
+        apply: impl FnOnce(&mut notification_digest::ActiveModel),
+    ) -> Result<DigestSettings, AppError> {
+        let now = Utc::now();
+        let mut model = match self.find_model(user_id).await? {
+            Some(model) => model.into(),
+            None => {
+                let defaults = DigestSettings::default();
+                notification_digest::ActiveModel {
+                    user_id: Set(user_id),
+                    frequency: Set(defaults.frequency),
+                    timezone: Set(defaults.timezone),
+                    last_sent_at: Set(None),
+                    created_at: Set(now.into()),
+                    ..Default::default()
+                }
+            }
+        };
+        apply(&mut model);
+        model.updated_at = Set(now.into());
+
+        let saved = model.save(&self.app_state.db).await?;
+        let saved: notification_digest::Model = saved.try_into_model()?;
+        Ok(saved.into())
+    }
+}
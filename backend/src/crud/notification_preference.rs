@@ -0,0 +1,263 @@
+use crate::entities::notification_preference;
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+use serde::Serialize;
+
+/// What a notification is about, so recipients can choose which ones they get.
+/// Assignments and mentions are addressed to one user directly and are always sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationKind {
+    Comment,
+    StatusChange,
+    // Edits to other issue fields and labels
+    IssueUpdate,
+    Blocker,
+    TaskUpdate,
+    Assignment,
+    Mention,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 7] = [
+        NotificationKind::Comment,
+        NotificationKind::StatusChange,
+        NotificationKind::IssueUpdate,
+        NotificationKind::Blocker,
+        NotificationKind::TaskUpdate,
+        NotificationKind::Assignment,
+        NotificationKind::Mention,
+    ];
+
+    pub fn parse(kind: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|k| k.as_str() == kind)
+    }
+
+    // Stored on each notification so clients can tell them apart
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Comment => "comment",
+            NotificationKind::StatusChange => "status_change",
+            NotificationKind::IssueUpdate => "issue_update",
+            NotificationKind::Blocker => "blocker",
+            NotificationKind::TaskUpdate => "task_update",
+            NotificationKind::Assignment => "assignment",
+            NotificationKind::Mention => "mention",
+        }
+    }
+}
+
+/// A user's choices for one project. Users who never saved any get everything on
+/// every channel.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Preferences {
+    pub project_id: i32,
+    pub comments: bool,
+    pub status_changes: bool,
+    pub issue_updates: bool,
+    pub blockers: bool,
+    pub task_updates: bool,
+    // Personal channel kinds to push to; None means all of them, empty means in-app only
+    pub channels: Option<Vec<String>>,
+    // Whether unread notifications from this project go into the email digest
+    pub digest: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct PreferencesUpdate {
+    pub comments: Option<bool>,
+    pub status_changes: Option<bool>,
+    pub issue_updates: Option<bool>,
+    pub blockers: Option<bool>,
+    pub task_updates: Option<bool>,
+    pub channels: Option<Option<Vec<String>>>,
+    pub digest: Option<bool>,
+}
+
+impl Preferences {
+    pub fn defaults(project_id: i32) -> Self {
+        Self {
+            project_id,
+            comments: true,
+            status_changes: true,
+            issue_updates: true,
+            blockers: true,
+            task_updates: true,
+            channels: None,
+            digest: true,
+        }
+    }
+
+    pub fn wants(&self, kind: NotificationKind) -> bool {
+        match kind {
+            NotificationKind::Comment => self.comments,
+            NotificationKind::StatusChange => self.status_changes,
+            NotificationKind::IssueUpdate => self.issue_updates,
+            NotificationKind::Blocker => self.blockers,
+            NotificationKind::TaskUpdate => self.task_updates,
+            NotificationKind::Assignment | NotificationKind::Mention => true,
+        }
+    }
+
+    pub fn allows_channel(&self, channel: &str) -> bool {
+        self.channels
+            .as_ref()
+            .is_none_or(|channels| channels.iter().any(|c| c == channel))
+    }
+}
+
+impl From<notification_preference::Model> for Preferences {
+    fn from(model: notification_preference::Model) -> Self {
+        Self {
+            project_id: model.project_id,
+            comments: model.comments,
+            status_changes: model.status_changes,
+            issue_updates: model.issue_updates,
+            blockers: model.blockers,
+            task_updates: model.task_updates,
+            // An unreadable list falls back to every channel rather than silencing the user
+            channels: model
+                .channels
+                .as_deref()
+                .and_then(|c| serde_json::from_str(c).ok()),
+            digest: model.digest,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct NotificationPreferenceCrud {
+    app_state: AppState,
+}
+
+impl NotificationPreferenceCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn find(&self, user_id: i32, project_id: i32) -> Result<Preferences, AppError> {
+        let model = notification_preference::Entity::find()
+            .filter(notification_preference::Column::UserId.eq(user_id))
+            .filter(notification_preference::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?;
+        Ok(model
+            .map(Preferences::from)
+            .unwrap_or_else(|| Preferences::defaults(project_id)))
+    }
+
+    pub async fn find_for_current_user(&self) -> Result<Preferences, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let project_id = self.app_state.current_project()?.id;
+        self.find(user_id, project_id).await
+    }
+
+    pub async fn update_for_current_user(
+        &self,
+        update: PreferencesUpdate,
+    ) -> Result<Preferences, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        let project_id = self.app_state.current_project()?.id;
+
+        let existing = notification_preference::Entity::find()
+            .filter(notification_preference::Column::UserId.eq(user_id))
+            .filter(notification_preference::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?;
+
+        let now = Utc::now();
+        let mut model = match existing {
+            Some(model) => model.into(),
+            None => {
+                let defaults = Preferences::defaults(project_id);
+                notification_preference::ActiveModel {
+                    user_id: Set(user_id),
+                    project_id: Set(project_id),
+                    comments: Set(defaults.comments),
+                    status_changes: Set(defaults.status_changes),
+                    issue_updates: Set(defaults.issue_updates),
+                    blockers: Set(defaults.blockers),
+                    task_updates: Set(defaults.task_updates),
+                    channels: Set(None),
+                    digest: Set(defaults.digest),
+                    created_at: Set(now.into()),
+                    ..Default::default()
+                }
+            }
+        };
+
+        if let Some(comments) = update.comments {
+            model.comments = Set(comments);
+        }
+        if let Some(status_changes) = update.status_changes {
+            model.status_changes = Set(status_changes);
+        }
+        if let Some(issue_updates) = update.issue_updates {
+            model.issue_updates = Set(issue_updates);
+        }
+        if let Some(blockers) = update.blockers {
+            model.blockers = Set(blockers);
+        }
+        if let Some(task_updates) = update.task_updates {
+            model.task_updates = Set(task_updates);
+        }
+        if let Some(channels) = update.channels {
+            model.channels = Set(channels.map(|c| serde_json::json!(c).to_string()));
+        }
+        if let Some(digest) = update.digest {
+            model.digest = Set(digest);
+        }
+        model.updated_at = Set(now.into());
+
+        let saved = model.save(&self.app_state.db).await?;
+        let saved: notification_preference::Model = saved.try_into_model()?;
+        Ok(saved.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_each_kind_follows_its_own_switch() {
+        let mut prefs = Preferences::defaults(1);
+        assert!(prefs.wants(NotificationKind::Comment));
+
+        prefs.status_changes = false;
+        assert!(!prefs.wants(NotificationKind::StatusChange));
+        assert!(prefs.wants(NotificationKind::IssueUpdate));
+        assert!(prefs.wants(NotificationKind::Comment));
+    }
+
+    #[test]
+    fn test_direct_kinds_cannot_be_switched_off() {
+        let mut prefs = Preferences::defaults(1);
+        prefs.comments = false;
+        prefs.issue_updates = false;
+        assert!(prefs.wants(NotificationKind::Mention));
+        assert!(prefs.wants(NotificationKind::Assignment));
+    }
+
+    #[test]
+    fn test_kinds_round_trip_through_their_names() {
+        for kind in NotificationKind::ALL {
+            assert_eq!(NotificationKind::parse(kind.as_str()), Some(kind));
+        }
+        assert_eq!(NotificationKind::parse("gossip"), None);
+    }
+
+    #[test]
+    fn test_channel_list_filters_personal_channels() {
+        let mut prefs = Preferences::defaults(1);
+        assert!(prefs.allows_channel("email"));
+
+        prefs.channels = Some(vec!["ntfy".to_string()]);
+        assert!(prefs.allows_channel("ntfy"));
+        assert!(!prefs.allows_channel("email"));
+
+        prefs.channels = Some(vec![]);
+        assert!(!prefs.allows_channel("ntfy"));
+    }
+}
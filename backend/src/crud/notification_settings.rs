@@ -1,7 +1,10 @@
 use crate::entities::notification_settings;
+use crate::error::AppError;
+use crate::notifications::channel::{self, CHANNEL_GOTIFY};
 use crate::AppState;
 use chrono::Utc;
 use sea_orm::*;
+use serde_json::Value;
 
 #[derive(Clone)]
 pub struct NotificationSettingsCrud {
@@ -15,52 +18,159 @@ impl NotificationSettingsCrud {
 
     pub async fn create(
         &self,
+        project_id: i32,
         token: String,
         app_id: i32,
-    ) -> Result<notification_settings::Model, DbErr> {
+    ) -> Result<notification_settings::Model, AppError> {
         let now = Utc::now();
-        let project_id = &self.app_state.project.clone().unwrap().id;
 
         let notification_settings = notification_settings::ActiveModel {
-            project_id: Set(*project_id),
+            project_id: Set(project_id),
             token: Set(token),
             application_id: Set(app_id),
+            channel: Set(CHANNEL_GOTIFY.to_string()),
+            config: Set(None),
+            enabled: Set(true),
             created_at: Set(now.into()),
             updated_at: Set(now.into()),
             ..Default::default()
         };
 
-        notification_settings.insert(&self.app_state.db).await
+        notification_settings
+            .insert(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_project_id(
+    // Channels other than the provisioned Gotify application keep everything in `config`
+    pub async fn create_channel(
         &self,
         project_id: i32,
-    ) -> Result<Option<notification_settings::Model>, DbErr> {
-        notification_settings::Entity::find()
+        channel: String,
+        config: Value,
+        enabled: bool,
+    ) -> Result<notification_settings::Model, AppError> {
+        channel::check_channel(&channel, &config)
+            .await
+            .map_err(|e| AppError::invalid("config", e))?;
+
+        let now = Utc::now();
+        notification_settings::ActiveModel {
+            project_id: Set(project_id),
+            token: Set(String::new()),
+            application_id: Set(0),
+            channel: Set(channel),
+            config: Set(Some(config.to_string())),
+            enabled: Set(enabled),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn update_channel(
+        &self,
+        project_id: i32,
+        id: i32,
+        config: Option<Value>,
+        enabled: Option<bool>,
+    ) -> Result<notification_settings::Model, AppError> {
+        let settings = self.find_channel(project_id, id).await?;
+        if let Some(config) = &config {
+            channel::check_channel(&settings.channel, config)
+                .await
+                .map_err(|e| AppError::invalid("config", e))?;
+        }
+
+        let mut settings: notification_settings::ActiveModel = settings.into();
+        if let Some(config) = config {
+            settings.config = Set(Some(config.to_string()));
+        }
+        if let Some(enabled) = enabled {
+            settings.enabled = Set(enabled);
+        }
+        settings.updated_at = Set(Utc::now().into());
+        settings
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_channel(
+        &self,
+        project_id: i32,
+        id: i32,
+    ) -> Result<notification_settings::Model, AppError> {
+        notification_settings::Entity::find_by_id(id)
             .filter(notification_settings::Column::ProjectId.eq(project_id))
             .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Notification channel"))
+    }
+
+    pub async fn delete_channel(&self, project_id: i32, id: i32) -> Result<DeleteResult, AppError> {
+        let settings = self.find_channel(project_id, id).await?;
+        if settings.channel == CHANNEL_GOTIFY && settings.application_id > 0 {
+            crate::notifications::gotify::GotifyClient::new()
+                .delete_application(settings.application_id as i64)
+                .await
+                .map_err(|e| {
+                    AppError::Upstream(format!("Failed to delete Gotify application: {}", e))
+                })?;
+        }
+        notification_settings::Entity::delete_by_id(id)
+            .exec(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_all_by_project_id(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<notification_settings::Model>, AppError> {
+        notification_settings::Entity::find()
+            .filter(notification_settings::Column::ProjectId.eq(project_id))
+            .order_by_asc(notification_settings::Column::Id)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_enabled_by_project_id(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<notification_settings::Model>, AppError> {
+        notification_settings::Entity::find()
+            .filter(notification_settings::Column::ProjectId.eq(project_id))
+            .filter(notification_settings::Column::Enabled.eq(true))
+            .order_by_asc(notification_settings::Column::Id)
+            .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     #[allow(dead_code)]
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
         let project_id = &self.app_state.project.clone().unwrap().id;
         let _notification_settings = notification_settings::Entity::find_by_id(id)
             .filter(notification_settings::Column::ProjectId.eq(*project_id))
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Notification settings not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Notification settings"))?;
 
         notification_settings::Entity::delete_by_id(id)
             .exec(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete_by_project_id(&self, project_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_by_project_id(&self, project_id: i32) -> Result<DeleteResult, AppError> {
         let current_project_id = self.app_state.project.clone().unwrap().id;
         if current_project_id != project_id {
-            return Err(DbErr::Custom(
+            return Err(AppError::Forbidden(
                 "Project id mismatch with current context".to_owned(),
             ));
         }
@@ -73,12 +183,15 @@ impl NotificationSettingsCrud {
 
         // Delete applications from Gotify before deleting records
         let gotify_client = crate::notifications::gotify::GotifyClient::new();
-        for setting in settings {
+        for setting in settings
+            .into_iter()
+            .filter(|s| s.channel == CHANNEL_GOTIFY && s.application_id > 0)
+        {
             if let Err(e) = gotify_client
                 .delete_application(setting.application_id as i64)
                 .await
             {
-                return Err(DbErr::Custom(format!(
+                return Err(AppError::Upstream(format!(
                     "Failed to delete Gotify application: {}",
                     e
                 )));
@@ -90,5 +203,6 @@ impl NotificationSettingsCrud {
             .filter(notification_settings::Column::ProjectId.eq(project_id))
             .exec(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 }
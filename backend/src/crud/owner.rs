@@ -1,4 +1,5 @@
 use crate::entities::owner;
+use crate::error::AppError;
 use sea_orm::*;
 
 pub struct OwnerCrud {
@@ -10,28 +11,34 @@ impl OwnerCrud {
         Self { db }
     }
 
-    pub async fn create(&self, user_id: Option<i32>) -> Result<owner::Model, DbErr> {
+    pub async fn create(&self, user_id: Option<i32>) -> Result<owner::Model, AppError> {
         let owner = owner::ActiveModel {
             user_id: Set(user_id),
             ..Default::default()
         };
 
-        owner.insert(&self.db).await
+        owner.insert(&self.db).await.map_err(AppError::from)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<owner::Model>, DbErr> {
-        owner::Entity::find_by_id(id).one(&self.db).await
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<owner::Model>, AppError> {
+        owner::Entity::find_by_id(id)
+            .one(&self.db)
+            .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_all(&self) -> Result<Vec<owner::Model>, DbErr> {
-        owner::Entity::find().all(&self.db).await
+    pub async fn find_all(&self) -> Result<Vec<owner::Model>, AppError> {
+        owner::Entity::find()
+            .all(&self.db)
+            .await
+            .map_err(AppError::from)
     }
 
-    pub async fn update(&self, id: i32, user_id: Option<i32>) -> Result<owner::Model, DbErr> {
+    pub async fn update(&self, id: i32, user_id: Option<i32>) -> Result<owner::Model, AppError> {
         let owner = owner::Entity::find_by_id(id)
             .one(&self.db)
             .await?
-            .ok_or(DbErr::Custom("Owner not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Owner"))?;
 
         let mut owner: owner::ActiveModel = owner.into();
 
@@ -39,10 +46,13 @@ impl OwnerCrud {
             owner.user_id = Set(Some(user_id));
         }
 
-        owner.update(&self.db).await
+        owner.update(&self.db).await.map_err(AppError::from)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
-        owner::Entity::delete_by_id(id).exec(&self.db).await
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
+        owner::Entity::delete_by_id(id)
+            .exec(&self.db)
+            .await
+            .map_err(AppError::from)
     }
 }
@@ -0,0 +1,206 @@
+use crate::crud::work_type::{WORK_TYPE_BUG, WORK_TYPE_CHORE, WORK_TYPE_FEATURE, WORK_TYPE_MAP};
+use crate::entities::project;
+use crate::error::{AppError, FieldError};
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const POINT_SCALE_LINEAR: &str = "linear";
+pub const POINT_SCALE_FIBONACCI: &str = "fibonacci";
+pub const POINT_SCALE_POWERS_OF_TWO: &str = "powers_of_two";
+pub const POINT_SCALE_TSHIRT: &str = "tshirt";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PointValue {
+    pub value: i32,
+    pub label: &'static str,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PointScale {
+    pub name: &'static str,
+    pub values: Vec<PointValue>,
+    // Velocity assumed for weeks without accepted work, sized to the scale
+    pub default_weekly_velocity: f64,
+}
+
+impl PointScale {
+    pub fn contains(&self, points: i32) -> bool {
+        self.values.iter().any(|v| v.value == points)
+    }
+
+    pub fn describe(&self) -> String {
+        self.values
+            .iter()
+            .map(|v| {
+                if v.label == v.value.to_string() {
+                    v.label.to_string()
+                } else {
+                    format!("{} ({})", v.value, v.label)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+fn scale(name: &'static str, values: &[(i32, &'static str)], velocity: f64) -> PointScale {
+    PointScale {
+        name,
+        values: values
+            .iter()
+            .map(|&(value, label)| PointValue { value, label })
+            .collect(),
+        default_weekly_velocity: velocity,
+    }
+}
+
+lazy_static! {
+    pub static ref POINT_SCALE_MAP: HashMap<&'static str, PointScale> = {
+        let mut m = HashMap::new();
+        m.insert(
+            POINT_SCALE_LINEAR,
+            scale(
+                POINT_SCALE_LINEAR,
+                &[(0, "0"), (1, "1"), (2, "2"), (3, "3")],
+                5.0,
+            ),
+        );
+        m.insert(
+            POINT_SCALE_FIBONACCI,
+            scale(
+                POINT_SCALE_FIBONACCI,
+                &[(0, "0"), (1, "1"), (2, "2"), (3, "3"), (5, "5"), (8, "8")],
+                10.0,
+            ),
+        );
+        m.insert(
+            POINT_SCALE_POWERS_OF_TWO,
+            scale(
+                POINT_SCALE_POWERS_OF_TWO,
+                &[(0, "0"), (1, "1"), (2, "2"), (4, "4"), (8, "8")],
+                10.0,
+            ),
+        );
+        m.insert(
+            POINT_SCALE_TSHIRT,
+            scale(
+                POINT_SCALE_TSHIRT,
+                &[(1, "XS"), (2, "S"), (3, "M"), (5, "L"), (8, "XL")],
+                10.0,
+            ),
+        );
+        m
+    };
+}
+
+// Unknown names (e.g. rows written by a newer build) fall back to the default scale
+pub fn scale_for(project: &project::Model) -> &'static PointScale {
+    POINT_SCALE_MAP
+        .get(project.point_scale.as_str())
+        .unwrap_or_else(|| &POINT_SCALE_MAP[POINT_SCALE_FIBONACCI])
+}
+
+pub fn is_estimable(project: &project::Model, work_type: i32) -> bool {
+    match work_type {
+        WORK_TYPE_FEATURE => true,
+        WORK_TYPE_BUG => project.estimate_bugs,
+        WORK_TYPE_CHORE => project.estimate_chores,
+        _ => false,
+    }
+}
+
+// Points that count toward velocity and scheduling for an issue of this work type
+pub fn effective_points(project: &project::Model, work_type: i32, points: Option<i32>) -> i32 {
+    if is_estimable(project, work_type) {
+        points.unwrap_or(0)
+    } else {
+        0
+    }
+}
+
+pub fn check_points(
+    project: &project::Model,
+    work_type: i32,
+    points: Option<i32>,
+) -> Result<(), AppError> {
+    let Some(points) = points else {
+        return Ok(());
+    };
+    if !is_estimable(project, work_type) {
+        let work_type_name = WORK_TYPE_MAP.get(&work_type).unwrap_or(&"issue");
+        return Err(AppError::Validation(vec![FieldError::new(
+            "points",
+            format!(
+                "{} issues are not estimated in this project",
+                work_type_name
+            ),
+        )]));
+    }
+    let scale = scale_for(project);
+    if !scale.contains(points) {
+        return Err(AppError::Validation(vec![FieldError::new(
+            "points",
+            format!("must be one of {}", scale.describe()),
+        )]));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::work_type::WORK_TYPE_REMINDER;
+
+    fn project(point_scale: &str, estimate_bugs: bool) -> project::Model {
+        let now = chrono::Utc::now().fixed_offset();
+        project::Model {
+            id: 1,
+            name: "Test".to_string(),
+            owner_id: 1,
+            point_scale: point_scale.to_string(),
+            estimate_bugs,
+            estimate_chores: false,
+            created_at: now,
+            updated_at: now,
+            notification_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_off_scale_points_are_rejected() {
+        let linear = project(POINT_SCALE_LINEAR, false);
+        assert!(check_points(&linear, WORK_TYPE_FEATURE, Some(3)).is_ok());
+        assert!(check_points(&linear, WORK_TYPE_FEATURE, Some(5)).is_err());
+
+        let powers = project(POINT_SCALE_POWERS_OF_TWO, false);
+        assert!(check_points(&powers, WORK_TYPE_FEATURE, Some(4)).is_ok());
+        assert!(check_points(&powers, WORK_TYPE_FEATURE, Some(3)).is_err());
+    }
+
+    #[test]
+    fn test_tshirt_sizes_map_to_numbers() {
+        let tshirt = project(POINT_SCALE_TSHIRT, false);
+        assert!(check_points(&tshirt, WORK_TYPE_FEATURE, Some(5)).is_ok());
+        assert!(check_points(&tshirt, WORK_TYPE_FEATURE, Some(0)).is_err());
+        assert_eq!(
+            scale_for(&tshirt).describe(),
+            "1 (XS), 2 (S), 3 (M), 5 (L), 8 (XL)"
+        );
+    }
+
+    #[test]
+    fn test_estimable_work_types_follow_project_settings() {
+        let no_bugs = project(POINT_SCALE_FIBONACCI, false);
+        assert!(check_points(&no_bugs, WORK_TYPE_BUG, Some(2)).is_err());
+        assert!(check_points(&no_bugs, WORK_TYPE_BUG, None).is_ok());
+        assert_eq!(effective_points(&no_bugs, WORK_TYPE_BUG, Some(2)), 0);
+
+        let bugs = project(POINT_SCALE_FIBONACCI, true);
+        assert!(check_points(&bugs, WORK_TYPE_BUG, Some(2)).is_ok());
+        assert_eq!(effective_points(&bugs, WORK_TYPE_BUG, Some(2)), 2);
+
+        assert!(!is_estimable(&bugs, WORK_TYPE_REMINDER));
+    }
+}
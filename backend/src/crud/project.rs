@@ -7,6 +7,7 @@ use crate::crud::tag::TagCrud;
 use crate::entities::issue;
 use crate::entities::project;
 use crate::entities::project_user;
+use crate::error::AppError;
 use crate::AppState;
 use chrono::{DateTime, FixedOffset};
 use sea_orm::*;
@@ -22,24 +23,27 @@ impl ProjectCrud {
         Self { state }
     }
 
-    pub async fn create(&self, name: String, owner_id: i32) -> Result<project::Model, DbErr> {
+    pub async fn create(&self, name: String, owner_id: i32) -> Result<project::Model, AppError> {
         let project = project::ActiveModel {
             name: Set(name),
             owner_id: Set(owner_id),
             ..Default::default()
         };
 
-        project.insert(&self.state.db).await
+        project.insert(&self.state.db).await.map_err(AppError::from)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<project::Model>, DbErr> {
-        project::Entity::find_by_id(id).one(&self.state.db).await
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<project::Model>, AppError> {
+        project::Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .map_err(AppError::from)
     }
 
     pub async fn find_all_projects_by_user_id(
         &self,
         user_id: i32,
-    ) -> Result<Vec<project::Model>, DbErr> {
+    ) -> Result<Vec<project::Model>, AppError> {
         debug!("Finding projects for user with ID: {}", user_id);
 
         // First get all the projects for this user
@@ -78,8 +82,10 @@ impl ProjectCrud {
         for issue_model in latest_issues {
             // Note: project_id is not an Option, so we access it directly
             let project_id = issue_model.project_id;
-            if !project_latest_update.contains_key(&project_id) {
-                project_latest_update.insert(project_id, issue_model.updated_at);
+            if let std::collections::hash_map::Entry::Vacant(e) =
+                project_latest_update.entry(project_id)
+            {
+                e.insert(issue_model.updated_at);
             }
         }
 
@@ -102,11 +108,11 @@ impl ProjectCrud {
             let a_time = project_latest_update
                 .get(&a.id)
                 .cloned()
-                .unwrap_or_else(|| a.created_at);
+                .unwrap_or(a.created_at);
             let b_time = project_latest_update
                 .get(&b.id)
                 .cloned()
-                .unwrap_or_else(|| b.created_at);
+                .unwrap_or(b.created_at);
             b_time.cmp(&a_time) // Descending order
         });
 
@@ -126,11 +132,14 @@ impl ProjectCrud {
         id: i32,
         name: Option<String>,
         owner_id: Option<i32>,
-    ) -> Result<project::Model, DbErr> {
+        point_scale: Option<String>,
+        estimate_bugs: Option<bool>,
+        estimate_chores: Option<bool>,
+    ) -> Result<project::Model, AppError> {
         let project = project::Entity::find_by_id(id)
             .one(&self.state.db)
             .await?
-            .ok_or(DbErr::Custom("Project not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Project"))?;
 
         let mut project: project::ActiveModel = project.into();
 
@@ -142,13 +151,25 @@ impl ProjectCrud {
             project.owner_id = Set(owner_id);
         }
 
-        project.update(&self.state.db).await
+        if let Some(point_scale) = point_scale {
+            project.point_scale = Set(point_scale);
+        }
+
+        if let Some(estimate_bugs) = estimate_bugs {
+            project.estimate_bugs = Set(estimate_bugs);
+        }
+
+        if let Some(estimate_chores) = estimate_chores {
+            project.estimate_chores = Set(estimate_chores);
+        }
+
+        project.update(&self.state.db).await.map_err(AppError::from)
     }
 
     pub async fn find_users_by_project_id(
         &self,
         project_id: i32,
-    ) -> Result<Vec<project_user::Model>, DbErr> {
+    ) -> Result<Vec<project_user::Model>, AppError> {
         debug!("Finding users for project with ID: {}", project_id);
         let query =
             project_user::Entity::find().filter(project_user::Column::ProjectId.eq(project_id));
@@ -160,15 +181,15 @@ impl ProjectCrud {
             Ok(data) => Ok(data),
             Err(e) => {
                 debug!("Query error: {:?}", e);
-                Err(e)
+                Err(e.into())
             }
         }
     }
 
-    pub async fn delete_cascade(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_cascade(&self, id: i32) -> Result<DeleteResult, AppError> {
         let project = self.find_by_id(id).await?;
         if project.is_none() {
-            return Err(DbErr::Custom("Project not found".to_owned()));
+            return Err(AppError::not_found("Project"));
         }
 
         let issues = issue::Entity::find()
@@ -203,7 +224,7 @@ impl ProjectCrud {
             .exec(&self.state.db)
             .await?;
 
-        let owner_id = project.unwrap().owner_id.clone();
+        let owner_id = project.unwrap().owner_id;
         let owner_crud = OwnerCrud::new(self.state.db.clone());
         owner_crud.delete(owner_id).await
     }
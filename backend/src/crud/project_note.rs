@@ -1,11 +1,9 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::{
-    PROJECT_NOTE_CREATED, PROJECT_NOTE_DELETED, PROJECT_NOTE_UPDATED,
-};
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::file_upload::FileUploadCrud;
 use crate::crud::project_note_history::ProjectNoteHistoryCrud;
 use crate::crud::project_note_parts::ProjectNotePartsCrud;
 use crate::entities::project_note;
+use crate::error::AppError;
 use crate::AppState;
 use chrono::Utc;
 use sea_orm::*;
@@ -24,7 +22,7 @@ impl ProjectNoteCrud {
         &self,
         title: String,
         detail: String,
-    ) -> Result<project_note::Model, DbErr> {
+    ) -> Result<project_note::Model, AppError> {
         let now = Utc::now();
         let project_id = &self.app_state.project.clone().unwrap().id;
 
@@ -38,8 +36,20 @@ impl ProjectNoteCrud {
             ..Default::default()
         };
 
-        let result = project_note.insert(&self.app_state.db).await?;
+        let txn = self.app_state.db.begin().await?;
+        let result = project_note.insert(&txn).await?;
         let note_id = result.id;
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let created = broadcaster
+            .record(
+                &txn,
+                *project_id,
+                DomainEvent::ProjectNoteCreated {
+                    project_note_id: note_id,
+                },
+            )
+            .await?;
+        txn.commit().await?;
 
         // Add history entry
         let history_crud = ProjectNoteHistoryCrud::new(self.app_state.db.clone());
@@ -48,23 +58,16 @@ impl ProjectNoteCrud {
             .create(note_id, format!("created note: {}", title), current_user_id)
             .await?;
 
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-
         // Spawn a new task for delayed broadcast
-        let project_id_clone = *project_id;
         tokio::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            broadcaster.broadcast_event(
-                project_id_clone,
-                PROJECT_NOTE_CREATED,
-                serde_json::json!({ "project_id": project_id_clone }),
-            );
+            broadcaster.announce(&created).await;
         });
 
         Ok(result)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<project_note::Model>, DbErr> {
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<project_note::Model>, AppError> {
         let project_id = &self.app_state.project.clone().unwrap().id;
         let res = project_note::Entity::find()
             .filter(project_note::Column::ProjectId.eq(*project_id))
@@ -81,7 +84,7 @@ impl ProjectNoteCrud {
         }
     }
 
-    pub async fn find_all(&self) -> Result<Vec<project_note::Model>, DbErr> {
+    pub async fn find_all(&self) -> Result<Vec<project_note::Model>, AppError> {
         let project_id = &self.app_state.project.clone().unwrap().id;
         let res = project_note::Entity::find()
             .filter(project_note::Column::ProjectId.eq(*project_id))
@@ -93,7 +96,7 @@ impl ProjectNoteCrud {
             .into_iter()
             .map(|(mut note, parts)| {
                 let md = ProjectNotePartsCrud::ast_to_markdown_string(&parts);
-                if md != "" {
+                if !md.is_empty() {
                     note.detail = md;
                 }
                 note
@@ -107,13 +110,13 @@ impl ProjectNoteCrud {
         id: i32,
         title: Option<String>,
         detail: Option<String>,
-    ) -> Result<project_note::Model, DbErr> {
+    ) -> Result<project_note::Model, AppError> {
         let project_id = &self.app_state.project.clone().unwrap().id;
         let project_note_model = project_note::Entity::find_by_id(id)
             .filter(project_note::Column::ProjectId.eq(*project_id))
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Project note not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Project note"))?;
 
         let current_version = project_note_model.lock_version;
         let old_title = project_note_model.title.clone();
@@ -141,7 +144,7 @@ impl ProjectNoteCrud {
         let result = project_note.clone().update(&txn).await?;
         if result.lock_version != current_version + 1 {
             txn.rollback().await?;
-            return Err(DbErr::Custom("Optimistic lock error".to_owned()));
+            return Err(AppError::OptimisticLock);
         }
 
         // Only store AST for the detail content
@@ -152,6 +155,16 @@ impl ProjectNoteCrud {
                 .await?;
         }
 
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let updated = broadcaster
+            .record(
+                &txn,
+                *project_id,
+                DomainEvent::ProjectNoteUpdated {
+                    project_note_id: id,
+                },
+            )
+            .await?;
         txn.commit().await?;
 
         // Add history entry if content changed
@@ -181,24 +194,18 @@ impl ProjectNoteCrud {
                 .await?;
         }
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            PROJECT_NOTE_UPDATED,
-            serde_json::json!({ "project_id": project_id }),
-        );
-
-        return Ok(result);
+        broadcaster.announce(&updated).await;
+
+        Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
         let project_id = &self.app_state.project.clone().unwrap().id;
         let _project_note = project_note::Entity::find_by_id(id)
             .filter(project_note::Column::ProjectId.eq(*project_id))
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Project note not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Project note"))?;
 
         // Delete all uploads associated with this project note
         let file_crud = FileUploadCrud::new(self.app_state.clone());
@@ -215,25 +222,29 @@ impl ProjectNoteCrud {
             .await?;
 
         // Delete the project note itself
-        let result = project_note::Entity::delete_by_id(id)
-            .exec(&self.app_state.db)
+        let txn = self.app_state.db.begin().await?;
+        let result = project_note::Entity::delete_by_id(id).exec(&txn).await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let deleted = broadcaster
+            .record(
+                &txn,
+                *project_id,
+                DomainEvent::ProjectNoteDeleted {
+                    project_note_id: id,
+                },
+            )
             .await?;
+        txn.commit().await?;
+        broadcaster.announce(&deleted).await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            PROJECT_NOTE_DELETED,
-            serde_json::json!({ "project_id": project_id }),
-        );
-
-        return Ok(result);
+        Ok(result)
     }
 
-    pub async fn delete_all_by_project_id(&self, project_id: i32) -> Result<(), DbErr> {
+    pub async fn delete_all_by_project_id(&self, project_id: i32) -> Result<(), AppError> {
         let current_project_id = self.app_state.project.clone().unwrap().id;
         if current_project_id != project_id {
-            return Err(DbErr::Custom(
+            return Err(AppError::Forbidden(
                 "Project id mismatch with current context".to_owned(),
             ));
         }
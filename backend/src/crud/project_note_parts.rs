@@ -1,7 +1,7 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::{PROJECT_NOTE_PART_UPDATED, PROJECT_NOTE_UPDATED};
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::project_note_tag::ProjectNoteTagCrud;
 use crate::entities::project_note_parts;
+use crate::error::AppError;
 use crate::AppState;
 use comrak::nodes::{
     Ast, AstNode, ListDelimType, ListType, NodeCode, NodeCodeBlock, NodeHeading, NodeHtmlBlock,
@@ -119,7 +119,10 @@ impl ProjectNotePartsCrud {
         markdown_output
     }
 
-    pub async fn delete_all_by_project_note_id(&self, project_note_id: i32) -> Result<(), DbErr> {
+    pub async fn delete_all_by_project_note_id(
+        &self,
+        project_note_id: i32,
+    ) -> Result<(), AppError> {
         // Delete tags referencing parts of this note, then parts
         let ids: Vec<i32> = project_note_parts::Entity::find()
             .filter(project_note_parts::Column::ProjectNoteId.eq(project_note_id))
@@ -152,7 +155,7 @@ impl ProjectNotePartsCrud {
         project_note_id: i32,
         project_id: i32,
         markdown: &str,
-    ) -> Result<(), DbErr> {
+    ) -> Result<(), AppError> {
         // Delete existing tags and parts for this note
         self.delete_all_by_project_note_id(project_note_id).await?;
 
@@ -183,8 +186,7 @@ impl ProjectNotePartsCrud {
             // Stack of (node ptr, parent_local_id, idx, ctx)
             let mut stack: Vec<(*const CNode<'_, RefCell<Ast>>, i32, i32, Ctx)> = Vec::new();
 
-            let mut child_idx = 0;
-            for child in root.children() {
+            for (child_idx, child) in (0_i32..).zip(root.children()) {
                 stack.push((
                     child as *const _,
                     root_local_id,
@@ -195,7 +197,6 @@ impl ProjectNotePartsCrud {
                         in_html: false,
                     },
                 ));
-                child_idx += 1;
             }
 
             while let Some((node_ptr, parent_local, idx, ctx)) = stack.pop() {
@@ -382,41 +383,47 @@ impl ProjectNotePartsCrud {
         &self,
         project_note_part_id: i32,
         content: String,
-    ) -> Result<project_note_parts::Model, DbErr> {
+    ) -> Result<project_note_parts::Model, AppError> {
         // Find the part to get project_note_id and project_id
         let part = project_note_parts::Entity::find_by_id(project_note_part_id)
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Project note part not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Project note part"))?;
 
         let mut active_part: project_note_parts::ActiveModel = part.into();
         active_part.content = Set(Some(content));
         active_part.updated_at = Set(chrono::Utc::now().into());
 
-        let updated_part = active_part.update(&self.app_state.db).await?;
+        let txn = self.app_state.db.begin().await?;
+        let updated_part = active_part.update(&txn).await?;
 
         // Broadcast events
         let project_id = self.app_state.project.as_ref().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-
-        broadcaster.broadcast_event(
-            project_id,
-            PROJECT_NOTE_PART_UPDATED,
-            serde_json::json!({
-                "project_id": project_id,
-                "project_note_id": updated_part.project_note_id,
-                "project_note_part_id": updated_part.id
-            }),
-        );
-
-        broadcaster.broadcast_event(
-            project_id,
-            PROJECT_NOTE_UPDATED,
-            serde_json::json!({
-                "project_id": project_id,
-                "project_note_id": updated_part.project_note_id
-            }),
-        );
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+
+        let part_updated = broadcaster
+            .record(
+                &txn,
+                project_id,
+                DomainEvent::ProjectNotePartUpdated {
+                    project_note_id: updated_part.project_note_id,
+                    project_note_part_id: updated_part.id,
+                },
+            )
+            .await?;
+        let note_updated = broadcaster
+            .record(
+                &txn,
+                project_id,
+                DomainEvent::ProjectNoteUpdated {
+                    project_note_id: updated_part.project_note_id,
+                },
+            )
+            .await?;
+        txn.commit().await?;
+
+        broadcaster.announce(&part_updated).await;
+        broadcaster.announce(&note_updated).await;
 
         Ok(updated_part)
     }
@@ -83,7 +83,7 @@ impl ProjectNoteTagCrud {
         for part in combined_parts {
             grouped_by_note
                 .entry(part.project_note_id)
-                .or_insert_with(Vec::new)
+                .or_default()
                 .push(part);
         }
 
@@ -107,7 +107,7 @@ impl ProjectNoteTagCrud {
             .into_iter()
             .filter_map(|(note_id, mut parts)| {
                 note_titles.get(&note_id).map(|title| {
-                    parts.sort_by(|a, b| a.created_at.cmp(&b.created_at));
+                    parts.sort_by_key(|a| a.created_at);
                     ProjectNoteWithParts {
                         id: note_id,
                         title: title.clone(),
@@ -118,7 +118,7 @@ impl ProjectNoteTagCrud {
             .collect();
 
         // Sort by note id for consistent ordering
-        project_note_parts_grouped.sort_by(|a, b| a.id.cmp(&b.id));
+        project_note_parts_grouped.sort_by_key(|a| a.id);
 
         Ok(first_tag.map(|mut tag_model| {
             tag_model.project_note_parts = project_note_parts_grouped;
@@ -1,6 +1,8 @@
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::owner::OwnerCrud;
 use crate::entities::project;
 use crate::entities::project_user;
+use crate::error::AppError;
 use crate::AppState;
 use sea_orm::*;
 
@@ -17,42 +19,104 @@ impl ProjectUserCrud {
         &self,
         project_id: i32,
         user_id: i32,
-    ) -> Result<project_user::Model, DbErr> {
+    ) -> Result<project_user::Model, AppError> {
         let project_user = project_user::ActiveModel {
             project_id: Set(project_id),
             user_id: Set(user_id),
         };
 
-        project_user.insert(&self.state.db).await
+        project_user
+            .insert(&self.state.db)
+            .await
+            .map_err(AppError::from)
     }
 
     pub async fn get_users_for_project(
         &self,
         project_id: i32,
-    ) -> Result<Vec<project_user::Model>, DbErr> {
+    ) -> Result<Vec<project_user::Model>, AppError> {
         project_user::Entity::find()
             .filter(project_user::Column::ProjectId.eq(project_id))
             .all(&self.state.db)
             .await
+            .map_err(AppError::from)
     }
 
-    pub async fn delete(&self, project_id: i32, user_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, project_id: i32, user_id: i32) -> Result<DeleteResult, AppError> {
         if self.is_project_owner(user_id, project_id).await? {
             return Ok(DeleteResult { rows_affected: 0 });
         }
 
-        project_user::Entity::delete_many()
+        let txn = self.state.db.begin().await?;
+        let result = project_user::Entity::delete_many()
             .filter(project_user::Column::ProjectId.eq(project_id))
             .filter(project_user::Column::UserId.eq(user_id))
-            .exec(&self.state.db)
-            .await
+            .exec(&txn)
+            .await?;
+
+        let broadcaster = EventBroadcaster::new(&self.state);
+        let revoked = if result.rows_affected > 0 {
+            let event = DomainEvent::MembershipRevoked { user_id };
+            Some(
+                broadcaster
+                    .record_for_user(&txn, project_id, user_id, event)
+                    .await?,
+            )
+        } else {
+            None
+        };
+        txn.commit().await?;
+        if let Some(revoked) = &revoked {
+            broadcaster.announce(revoked).await;
+        }
+        Ok(result)
+    }
+
+    // Everyone who belongs to the project, including the owner
+    pub async fn member_user_ids(&self, project_id: i32) -> Result<Vec<i32>, AppError> {
+        let mut user_ids: Vec<i32> = self
+            .get_users_for_project(project_id)
+            .await?
+            .into_iter()
+            .map(|pu| pu.user_id)
+            .collect();
+        if let Some(owner_user_id) = self.owner_user_id(project_id).await? {
+            if !user_ids.contains(&owner_user_id) {
+                user_ids.push(owner_user_id);
+            }
+        }
+        Ok(user_ids)
+    }
+
+    pub async fn owner_user_id(&self, project_id: i32) -> Result<Option<i32>, AppError> {
+        let project = project::Entity::find_by_id(project_id)
+            .one(&self.state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Project"))?;
+        let owner = OwnerCrud::new(self.state.db.clone())
+            .find_by_id(project.owner_id)
+            .await?;
+        Ok(owner.and_then(|o| o.user_id))
+    }
+
+    // Owners are members of their projects even without a project_user row
+    pub async fn is_member(&self, user_id: i32, project_id: i32) -> Result<bool, AppError> {
+        let membership = project_user::Entity::find()
+            .filter(project_user::Column::ProjectId.eq(project_id))
+            .filter(project_user::Column::UserId.eq(user_id))
+            .one(&self.state.db)
+            .await?;
+        if membership.is_some() {
+            return Ok(true);
+        }
+        self.is_project_owner(user_id, project_id).await
     }
 
-    pub async fn is_project_owner(&self, user_id: i32, project_id: i32) -> Result<bool, DbErr> {
+    pub async fn is_project_owner(&self, user_id: i32, project_id: i32) -> Result<bool, AppError> {
         let project = project::Entity::find_by_id(project_id)
             .one(&self.state.db)
             .await?
-            .ok_or(DbErr::Custom("Project not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Project"))?;
         let owner_crud = OwnerCrud::new(self.state.db.clone());
         let owner = owner_crud.find_by_id(project.owner_id).await?;
         if let Some(owner) = owner {
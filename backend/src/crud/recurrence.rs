@@ -0,0 +1,364 @@
+use crate::crud::work_type::{WORK_TYPE_CHORE, WORK_TYPE_REMINDER};
+use chrono::{DateTime, Datelike, Duration, FixedOffset, NaiveDate, TimeZone, Weekday};
+
+// Upper bound on how many periods ahead a rule is searched so a rule that can never
+// match (e.g. BYMONTHDAY=31 every 12 months starting in February) cannot loop forever.
+const MAX_PERIODS_AHEAD: i64 = 12 * 10;
+
+// Larger intervals are almost certainly typos and would push occurrences out of range
+pub const MAX_INTERVAL: u32 = 1000;
+
+// Only work that genuinely repeats may recur
+pub fn can_recur(work_type: i32) -> bool {
+    work_type == WORK_TYPE_REMINDER || work_type == WORK_TYPE_CHORE
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// A BYDAY entry; `ordinal` is only meaningful for monthly rules (1 = first, -1 = last)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByDay {
+    pub ordinal: Option<i32>,
+    pub weekday: Weekday,
+}
+
+/// The subset of RFC 5545 RRULE that issues can recur on: FREQ (DAILY, WEEKLY,
+/// MONTHLY), INTERVAL, BYDAY and BYMONTHDAY. Examples: `FREQ=DAILY`,
+/// `FREQ=WEEKLY;BYDAY=MO,TH`, `FREQ=MONTHLY;BYDAY=-1FR`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub by_day: Vec<ByDay>,
+    pub by_month_day: Vec<i32>,
+}
+
+fn parse_weekday(value: &str) -> Result<Weekday, String> {
+    match value {
+        "MO" => Ok(Weekday::Mon),
+        "TU" => Ok(Weekday::Tue),
+        "WE" => Ok(Weekday::Wed),
+        "TH" => Ok(Weekday::Thu),
+        "FR" => Ok(Weekday::Fri),
+        "SA" => Ok(Weekday::Sat),
+        "SU" => Ok(Weekday::Sun),
+        _ => Err(format!("unknown weekday '{}'", value)),
+    }
+}
+
+fn parse_by_day(value: &str) -> Result<ByDay, String> {
+    if value.len() < 2 {
+        return Err(format!("invalid BYDAY value '{}'", value));
+    }
+    let (ordinal, weekday) = value.split_at(value.len() - 2);
+    let ordinal = if ordinal.is_empty() {
+        None
+    } else {
+        let n: i32 = ordinal
+            .parse()
+            .map_err(|_| format!("invalid BYDAY value '{}'", value))?;
+        if n == 0 || !(-5..=5).contains(&n) {
+            return Err(format!("BYDAY ordinal must be 1-5 or -1 to -5, got {}", n));
+        }
+        Some(n)
+    };
+    Ok(ByDay {
+        ordinal,
+        weekday: parse_weekday(weekday)?,
+    })
+}
+
+impl Recurrence {
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut frequency = None;
+        let mut interval = 1;
+        let mut by_day = Vec::new();
+        let mut by_month_day = Vec::new();
+
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or_else(|| format!("expected KEY=VALUE, got '{}'", part))?;
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    frequency = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        other => return Err(format!("unsupported FREQ '{}'", other)),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse()
+                        .ok()
+                        .filter(|n| (1..=MAX_INTERVAL).contains(n))
+                        .ok_or_else(|| {
+                            format!(
+                                "INTERVAL must be a number from 1 to {}, got '{}'",
+                                MAX_INTERVAL, value
+                            )
+                        })?;
+                }
+                "BYDAY" => {
+                    for day in value.split(',') {
+                        by_day.push(parse_by_day(&day.to_ascii_uppercase())?);
+                    }
+                }
+                "BYMONTHDAY" => {
+                    for day in value.split(',') {
+                        let n: i32 = day
+                            .parse()
+                            .map_err(|_| format!("invalid BYMONTHDAY value '{}'", day))?;
+                        if n == 0 || !(-31..=31).contains(&n) {
+                            return Err(format!("BYMONTHDAY must be 1-31 or -1 to -31, got {}", n));
+                        }
+                        by_month_day.push(n);
+                    }
+                }
+                other => return Err(format!("unsupported rule part '{}'", other)),
+            }
+        }
+
+        let frequency = frequency.ok_or_else(|| "FREQ is required".to_string())?;
+        if frequency != Frequency::Monthly {
+            if !by_month_day.is_empty() {
+                return Err("BYMONTHDAY is only supported for monthly rules".to_string());
+            }
+            if by_day.iter().any(|d| d.ordinal.is_some()) {
+                return Err("BYDAY ordinals are only supported for monthly rules".to_string());
+            }
+        }
+        if frequency == Frequency::Daily && !by_day.is_empty() {
+            return Err("BYDAY is not supported for daily rules".to_string());
+        }
+
+        Ok(Self {
+            frequency,
+            interval,
+            by_day,
+            by_month_day,
+        })
+    }
+
+    /// The first occurrence strictly after `after`, at the same time of day and offset.
+    /// `after` is normally the previous occurrence, which anchors intervals and defaults
+    /// (the weekday for weekly rules, the day of month for monthly rules).
+    pub fn next_after(&self, after: DateTime<FixedOffset>) -> Option<DateTime<FixedOffset>> {
+        let start = after.date_naive();
+        let date = match self.frequency {
+            Frequency::Daily => start.checked_add_signed(Duration::days(self.interval as i64)),
+            Frequency::Weekly => self.next_weekly(start),
+            Frequency::Monthly => self.next_monthly(start),
+        }?;
+        after
+            .timezone()
+            .from_local_datetime(&date.and_time(after.time()))
+            .single()
+    }
+
+    // Weeks start on Monday; the anchor's week is always an occurrence week, so the
+    // next date is either later in that week or in the week `interval` weeks on.
+    fn next_weekly(&self, start: NaiveDate) -> Option<NaiveDate> {
+        let mut weekdays: Vec<i64> = if self.by_day.is_empty() {
+            vec![start.weekday()]
+        } else {
+            self.by_day.iter().map(|d| d.weekday).collect()
+        }
+        .into_iter()
+        .map(|d| d.num_days_from_monday() as i64)
+        .collect();
+        weekdays.sort_unstable();
+
+        let start_week = start.week(Weekday::Mon).first_day();
+        let today = start.weekday().num_days_from_monday() as i64;
+        let offset = match weekdays.iter().find(|&&d| d > today) {
+            Some(&day) => day,
+            None => 7 * self.interval as i64 + weekdays[0],
+        };
+        start_week.checked_add_signed(Duration::days(offset))
+    }
+
+    fn next_monthly(&self, start: NaiveDate) -> Option<NaiveDate> {
+        let base = start.year() as i64 * 12 + start.month0() as i64;
+        for period in 0..=MAX_PERIODS_AHEAD {
+            let total = base.checked_add(period.checked_mul(self.interval as i64)?)?;
+            let year = i32::try_from(total.div_euclid(12)).ok()?;
+            let month = total.rem_euclid(12) as u32 + 1;
+            let candidate = self
+                .days_in_month(year, month, start.day())
+                .into_iter()
+                .filter(|date| *date > start)
+                .min();
+            if candidate.is_some() {
+                return candidate;
+            }
+        }
+        None
+    }
+
+    fn days_in_month(&self, year: i32, month: u32, default_day: u32) -> Vec<NaiveDate> {
+        let Some(first) = NaiveDate::from_ymd_opt(year, month, 1) else {
+            return vec![];
+        };
+        let last = last_day_of_month(first);
+        let mut days = Vec::new();
+
+        for by_day in &self.by_day {
+            let matching: Vec<NaiveDate> = first
+                .iter_days()
+                .take_while(|d| *d <= last)
+                .filter(|d| d.weekday() == by_day.weekday)
+                .collect();
+            match by_day.ordinal {
+                None => days.extend(matching),
+                Some(n) if n > 0 => days.extend(matching.get(n as usize - 1)),
+                Some(n) => days.extend(
+                    matching
+                        .len()
+                        .checked_sub(n.unsigned_abs() as usize)
+                        .map(|i| matching[i]),
+                ),
+            }
+        }
+
+        for &day in &self.by_month_day {
+            let day = if day > 0 {
+                day
+            } else {
+                last.day() as i32 + 1 + day
+            };
+            if day > 0 {
+                days.extend(NaiveDate::from_ymd_opt(year, month, day as u32));
+            }
+        }
+
+        // Without BYDAY/BYMONTHDAY the rule repeats on the anchor's day of month;
+        // months too short for it are skipped, as RFC 5545 does.
+        if self.by_day.is_empty() && self.by_month_day.is_empty() {
+            days.extend(NaiveDate::from_ymd_opt(year, month, default_day));
+        }
+        days
+    }
+}
+
+fn last_day_of_month(first: NaiveDate) -> NaiveDate {
+    let next_month = if first.month() == 12 {
+        NaiveDate::from_ymd_opt(first.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(first.year(), first.month() + 1, 1)
+    };
+    next_month.and_then(|d| d.pred_opt()).unwrap_or(first)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(date: &str) -> DateTime<FixedOffset> {
+        DateTime::parse_from_rfc3339(date).unwrap()
+    }
+
+    fn next(rule: &str, after: &str) -> String {
+        Recurrence::parse(rule)
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+            .to_rfc3339()
+    }
+
+    #[test]
+    fn test_daily_keeps_time_of_day() {
+        assert_eq!(
+            next("FREQ=DAILY", "2026-10-19T09:30:00+02:00"),
+            "2026-10-20T09:30:00+02:00"
+        );
+        assert_eq!(
+            next("RRULE:FREQ=DAILY;INTERVAL=3", "2026-12-30T09:30:00+00:00"),
+            "2027-01-02T09:30:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_weekly_on_monday_and_thursday() {
+        let rule = "FREQ=WEEKLY;BYDAY=MO,TH";
+        // Monday 19 Oct -> Thursday 22 Oct -> Monday 26 Oct
+        assert_eq!(
+            next(rule, "2026-10-19T08:00:00+00:00"),
+            "2026-10-22T08:00:00+00:00"
+        );
+        assert_eq!(
+            next(rule, "2026-10-22T08:00:00+00:00"),
+            "2026-10-26T08:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_weekly_interval_skips_weeks() {
+        assert_eq!(
+            next("FREQ=WEEKLY;INTERVAL=2", "2026-10-19T08:00:00+00:00"),
+            "2026-11-02T08:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_weekly_large_interval_wraps_to_first_weekday() {
+        // Thursday 22 Oct has no later weekday this week, so jump 1000 weeks to a Monday
+        assert_eq!(
+            next(
+                "FREQ=WEEKLY;INTERVAL=1000;BYDAY=TH,MO",
+                "2026-10-22T08:00:00+00:00"
+            ),
+            "2045-12-18T08:00:00+00:00"
+        );
+        assert_eq!(
+            next("FREQ=MONTHLY;INTERVAL=1000", "2026-10-19T08:00:00+00:00"),
+            "2110-02-19T08:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_monthly_on_last_friday() {
+        let rule = "FREQ=MONTHLY;BYDAY=-1FR";
+        assert_eq!(
+            next(rule, "2026-10-19T17:00:00+00:00"),
+            "2026-10-30T17:00:00+00:00"
+        );
+        assert_eq!(
+            next(rule, "2026-10-30T17:00:00+00:00"),
+            "2026-11-27T17:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_monthly_skips_months_without_the_day() {
+        assert_eq!(
+            next("FREQ=MONTHLY", "2027-01-31T10:00:00+00:00"),
+            "2027-03-31T10:00:00+00:00"
+        );
+        assert_eq!(
+            next("FREQ=MONTHLY;BYMONTHDAY=-1", "2027-01-31T10:00:00+00:00"),
+            "2027-02-28T10:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_rejected() {
+        assert!(Recurrence::parse("").is_err());
+        assert!(Recurrence::parse("FREQ=YEARLY").is_err());
+        assert!(Recurrence::parse("FREQ=WEEKLY;BYDAY=XX").is_err());
+        assert!(Recurrence::parse("FREQ=WEEKLY;BYDAY=-1FR").is_err());
+        assert!(Recurrence::parse("FREQ=DAILY;INTERVAL=0").is_err());
+        assert!(Recurrence::parse("FREQ=WEEKLY;INTERVAL=1001").is_err());
+        assert!(Recurrence::parse("FREQ=MONTHLY;INTERVAL=4000000000").is_err());
+        assert!(Recurrence::parse("FREQ=DAILY;COUNT=3").is_err());
+    }
+}
@@ -0,0 +1,324 @@
+use axum::http::HeaderMap;
+use hmac::{Hmac, Mac};
+use regex::Regex;
+use serde_json::Value;
+use sha2::Sha256;
+
+pub const PROVIDER_GITHUB: &str = "github";
+pub const PROVIDER_GITLAB: &str = "gitlab";
+pub const PROVIDER_GITEA: &str = "gitea";
+pub const PROVIDERS: [&str; 3] = [PROVIDER_GITHUB, PROVIDER_GITLAB, PROVIDER_GITEA];
+
+pub const LINK_COMMIT: &str = "commit";
+pub const LINK_PULL_REQUEST: &str = "pull_request";
+
+pub const STATE_PUSHED: &str = "pushed";
+pub const STATE_OPEN: &str = "open";
+pub const STATE_MERGED: &str = "merged";
+pub const STATE_CLOSED: &str = "closed";
+
+lazy_static::lazy_static! {
+    static ref REFERENCE_RE: Regex = Regex::new(r"#(\d+)\b").expect("reference regex is valid");
+    static ref CLOSING_RE: Regex = Regex::new(r"(?i)\b(?:close[sd]?|fix(?:e[sd])?|resolve[sd]?)\s*:?\s*$")
+        .expect("closing keyword regex is valid");
+}
+
+/// Checks a delivery came from the provider: an HMAC-SHA256 of the body for GitHub
+/// and Gitea, the shared token for GitLab.
+pub fn verify_signature(provider: &str, secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+    let signature = match provider {
+        PROVIDER_GITHUB => header("X-Hub-Signature-256").and_then(|s| s.strip_prefix("sha256=")),
+        PROVIDER_GITEA => header("X-Gitea-Signature"),
+        PROVIDER_GITLAB => {
+            return header("X-Gitlab-Token")
+                .is_some_and(|token| constant_time_eq(token.as_bytes(), secret.as_bytes()))
+        }
+        _ => None,
+    };
+    let Some(signature) = signature.and_then(|s| hex::decode(s).ok()) else {
+        return false;
+    };
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// The provider's name for the event being delivered.
+pub fn event_name<'a>(provider: &str, headers: &'a HeaderMap) -> Option<&'a str> {
+    let name = match provider {
+        PROVIDER_GITHUB => "X-GitHub-Event",
+        PROVIDER_GITLAB => "X-Gitlab-Event",
+        PROVIDER_GITEA => "X-Gitea-Event",
+        _ => return None,
+    };
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssueReference {
+    pub issue_id: i32,
+    // Preceded by fixes/closes/resolves
+    pub closes: bool,
+}
+
+/// The `#123` issue references in a commit message or pull request, in order.
+/// A reference repeated with and without a keyword counts as closing.
+pub fn extract_references(text: &str) -> Vec<IssueReference> {
+    let mut references: Vec<IssueReference> = Vec::new();
+    for captures in REFERENCE_RE.captures_iter(text) {
+        let whole = captures.get(0).expect("match has a whole group");
+        // Skip URL fragments and HTML entities like `page#12` or `&#39;`
+        if text[..whole.start()]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric() || c == '/' || c == '&')
+        {
+            continue;
+        }
+        let Ok(issue_id) = captures[1].parse::<i32>() else {
+            continue;
+        };
+        let closes = CLOSING_RE.is_match(&text[..whole.start()]);
+        match references.iter_mut().find(|r| r.issue_id == issue_id) {
+            Some(existing) => existing.closes |= closes,
+            None => references.push(IssueReference { issue_id, closes }),
+        }
+    }
+    references
+}
+
+/// A commit or pull request from a delivery, in the same shape for every provider.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryActivity {
+    pub kind: &'static str,
+    // Commit sha or pull request number
+    pub external_id: String,
+    pub title: String,
+    // Everything that may reference issues
+    pub text: String,
+    pub url: Option<String>,
+    pub state: &'static str,
+    pub author_email: Option<String>,
+    // Only set for commits
+    pub on_default_branch: bool,
+}
+
+impl RepositoryActivity {
+    /// Whether the work has landed: a commit on the default branch or a merged pull request.
+    pub fn completes(&self) -> bool {
+        match self.kind {
+            LINK_COMMIT => self.on_default_branch,
+            _ => self.state == STATE_MERGED,
+        }
+    }
+
+    /// Short label for history entries, e.g. "commit 1a2b3c4" or "pull request #12".
+    pub fn label(&self) -> String {
+        match self.kind {
+            LINK_COMMIT => format!("commit {}", short_sha(&self.external_id)),
+            _ => format!("pull request #{}", self.external_id),
+        }
+    }
+}
+
+fn short_sha(sha: &str) -> &str {
+    sha.get(..7).unwrap_or(sha)
+}
+
+fn string(value: &Value) -> Option<String> {
+    value.as_str().map(str::to_string)
+}
+
+/// Commits and pull requests in a push or pull/merge request delivery. Other events
+/// (pings, tags, issues) have none.
+pub fn parse_activities(provider: &str, event: &str, payload: &Value) -> Vec<RepositoryActivity> {
+    match (provider, event) {
+        (PROVIDER_GITHUB | PROVIDER_GITEA, "push") => {
+            parse_push(payload, &payload["repository"]["default_branch"])
+        }
+        (PROVIDER_GITLAB, "Push Hook") => {
+            parse_push(payload, &payload["project"]["default_branch"])
+        }
+        (PROVIDER_GITHUB | PROVIDER_GITEA, "pull_request") => {
+            let pr = &payload["pull_request"];
+            let state = if pr["merged"].as_bool() == Some(true) {
+                STATE_MERGED
+            } else if pr["state"].as_str() == Some("closed") {
+                STATE_CLOSED
+            } else {
+                STATE_OPEN
+            };
+            pull_request(
+                &pr["number"],
+                &pr["title"],
+                &pr["body"],
+                &pr["html_url"],
+                state,
+                &pr["user"]["email"],
+            )
+        }
+        (PROVIDER_GITLAB, "Merge Request Hook") => {
+            let mr = &payload["object_attributes"];
+            let state = match mr["state"].as_str() {
+                Some("merged") => STATE_MERGED,
+                Some("closed") => STATE_CLOSED,
+                _ => STATE_OPEN,
+            };
+            pull_request(
+                &mr["iid"],
+                &mr["title"],
+                &mr["description"],
+                &mr["url"],
+                state,
+                &payload["user"]["email"],
+            )
+        }
+        _ => Vec::new(),
+    }
+}
+
+fn parse_push(payload: &Value, default_branch: &Value) -> Vec<RepositoryActivity> {
+    let on_default_branch = default_branch
+        .as_str()
+        .is_some_and(|branch| payload["ref"].as_str() == Some(&format!("refs/heads/{}", branch)));
+    payload["commits"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|commit| {
+            let sha = commit["id"].as_str()?;
+            let message = commit["message"].as_str().unwrap_or_default();
+            Some(RepositoryActivity {
+                kind: LINK_COMMIT,
+                external_id: sha.to_string(),
+                title: message.lines().next().unwrap_or_default().to_string(),
+                text: message.to_string(),
+                url: string(&commit["url"]),
+                state: STATE_PUSHED,
+                author_email: string(&commit["author"]["email"]),
+                on_default_branch,
+            })
+        })
+        .collect()
+}
+
+fn pull_request(
+    number: &Value,
+    title: &Value,
+    body: &Value,
+    url: &Value,
+    state: &'static str,
+    author_email: &Value,
+) -> Vec<RepositoryActivity> {
+    let Some(number) = number.as_i64() else {
+        return Vec::new();
+    };
+    let title = title.as_str().unwrap_or_default();
+    vec![RepositoryActivity {
+        kind: LINK_PULL_REQUEST,
+        external_id: number.to_string(),
+        title: title.to_string(),
+        text: format!("{}\n{}", title, body.as_str().unwrap_or_default()),
+        url: string(url),
+        state,
+        author_email: string(author_email),
+        on_default_branch: false,
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_references_and_closing_keywords() {
+        let text = "Fixes #12: login loop\n\nSee #7 and https://example.com/page#9, \
+                    resolves: #30, #7 again. Closed #4";
+        assert_eq!(
+            extract_references(text),
+            vec![
+                IssueReference {
+                    issue_id: 12,
+                    closes: true
+                },
+                IssueReference {
+                    issue_id: 7,
+                    closes: false
+                },
+                IssueReference {
+                    issue_id: 30,
+                    closes: true
+                },
+                IssueReference {
+                    issue_id: 4,
+                    closes: true
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_signatures_are_verified() {
+        let body = br#"{"zen":"Keep it logically awesome."}"#;
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"s3cret").unwrap();
+        mac.update(body);
+        let signature = hex::encode(mac.finalize().into_bytes());
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "X-Hub-Signature-256",
+            HeaderValue::from_str(&format!("sha256={}", signature)).unwrap(),
+        );
+        headers.insert(
+            "X-Gitea-Signature",
+            HeaderValue::from_str(&signature).unwrap(),
+        );
+        headers.insert("X-Gitlab-Token", HeaderValue::from_static("s3cret"));
+        for provider in PROVIDERS {
+            assert!(verify_signature(provider, "s3cret", &headers, body));
+            assert!(!verify_signature(provider, "other", &headers, body));
+        }
+        assert!(!verify_signature(
+            PROVIDER_GITHUB,
+            "s3cret",
+            &HeaderMap::new(),
+            body
+        ));
+    }
+
+    #[test]
+    fn test_push_and_merge_request_payloads() {
+        let push = serde_json::json!({
+            "ref": "refs/heads/main",
+            "project": { "default_branch": "main" },
+            "commits": [{
+                "id": "1a2b3c4d5e6f",
+                "message": "Fix redirect\n\nfixes #3",
+                "url": "https://gitlab.example.com/c/1a2b3c4d5e6f",
+                "author": { "email": "dev@example.com" }
+            }]
+        });
+        let commits = parse_activities(PROVIDER_GITLAB, "Push Hook", &push);
+        assert_eq!(commits.len(), 1);
+        assert_eq!(commits[0].title, "Fix redirect");
+        assert_eq!(commits[0].label(), "commit 1a2b3c4");
+        assert!(commits[0].completes());
+
+        let pr = serde_json::json!({
+            "action": "closed",
+            "pull_request": { "number": 8, "title": "Login", "body": "closes #3",
+                              "state": "closed", "merged": true }
+        });
+        let prs = parse_activities(PROVIDER_GITHUB, "pull_request", &pr);
+        assert_eq!(prs[0].state, STATE_MERGED);
+        assert!(prs[0].completes());
+        assert!(parse_activities(PROVIDER_GITHUB, "ping", &pr).is_empty());
+    }
+}
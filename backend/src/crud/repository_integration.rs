@@ -0,0 +1,317 @@
+use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::crud::project_user::ProjectUserCrud;
+use crate::crud::repository_event::{extract_references, RepositoryActivity};
+use crate::crud::status::STATUS_ACCEPTED;
+use crate::crud::webhook::generate_secret;
+use crate::entities::{issue, issue_repository_link, repository_integration, user};
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+use serde::Serialize;
+use tracing::{info, warn};
+
+#[derive(Debug, Default, Serialize)]
+pub struct ActivitySummary {
+    pub linked: usize,
+    pub transitioned: usize,
+}
+
+#[derive(Clone)]
+pub struct RepositoryIntegrationCrud {
+    app_state: AppState,
+}
+
+impl RepositoryIntegrationCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn create(
+        &self,
+        project_id: i32,
+        provider: String,
+        secret: Option<String>,
+        merge_status: Option<i32>,
+        enabled: bool,
+    ) -> Result<repository_integration::Model, AppError> {
+        let now = Utc::now();
+        repository_integration::ActiveModel {
+            project_id: Set(project_id),
+            provider: Set(provider),
+            secret: Set(secret.unwrap_or_else(generate_secret)),
+            merge_status: Set(merge_status),
+            enabled: Set(enabled),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn update(
+        &self,
+        project_id: i32,
+        id: i32,
+        secret: Option<String>,
+        merge_status: Option<Option<i32>>,
+        enabled: Option<bool>,
+    ) -> Result<repository_integration::Model, AppError> {
+        let integration = self.find_by_id(project_id, id).await?;
+        let mut integration: repository_integration::ActiveModel = integration.into();
+        if let Some(secret) = secret {
+            integration.secret = Set(secret);
+        }
+        if let Some(merge_status) = merge_status {
+            integration.merge_status = Set(merge_status);
+        }
+        if let Some(enabled) = enabled {
+            integration.enabled = Set(enabled);
+        }
+        integration.updated_at = Set(Utc::now().into());
+        integration
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_all_by_project_id(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<repository_integration::Model>, AppError> {
+        repository_integration::Entity::find()
+            .filter(repository_integration::Column::ProjectId.eq(project_id))
+            .order_by_asc(repository_integration::Column::Id)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_by_id(
+        &self,
+        project_id: i32,
+        id: i32,
+    ) -> Result<repository_integration::Model, AppError> {
+        repository_integration::Entity::find_by_id(id)
+            .filter(repository_integration::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Repository integration"))
+    }
+
+    // For inbound deliveries, which carry no project; the signature is checked by the caller
+    pub async fn find_enabled(
+        &self,
+        id: i32,
+    ) -> Result<Option<repository_integration::Model>, AppError> {
+        repository_integration::Entity::find_by_id(id)
+            .filter(repository_integration::Column::Enabled.eq(true))
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    // Links go with it through the cascade
+    pub async fn delete(&self, project_id: i32, id: i32) -> Result<DeleteResult, AppError> {
+        let integration = self.find_by_id(project_id, id).await?;
+        repository_integration::Entity::delete_by_id(integration.id)
+            .exec(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_links_by_issue_id(
+        &self,
+        issue_id: i32,
+    ) -> Result<Vec<issue_repository_link::Model>, AppError> {
+        issue_repository_link::Entity::find()
+            .filter(issue_repository_link::Column::IssueId.eq(issue_id))
+            .order_by_desc(issue_repository_link::Column::CreatedAt)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    /// Links each commit or pull request to the project issues it references and records
+    /// it in their history. A closing reference moves the issue to the integration's
+    /// `merge_status` once the work lands. Replayed deliveries change nothing.
+    pub async fn apply(
+        &self,
+        integration: &repository_integration::Model,
+        activities: &[RepositoryActivity],
+    ) -> Result<ActivitySummary, AppError> {
+        let mut summary = ActivitySummary::default();
+        for activity in activities {
+            let references = extract_references(&activity.text);
+            if references.is_empty() {
+                continue;
+            }
+            let issues = issue::Entity::find()
+                .filter(issue::Column::ProjectId.eq(integration.project_id))
+                .filter(issue::Column::Id.is_in(references.iter().map(|r| r.issue_id)))
+                .all(&self.app_state.db)
+                .await?;
+            if issues.is_empty() {
+                continue;
+            }
+            let Some(actor) = self
+                .acting_user(integration.project_id, activity.author_email.as_deref())
+                .await?
+            else {
+                warn!(
+                    "Project {} has no user to record repository activity as",
+                    integration.project_id
+                );
+                return Ok(summary);
+            };
+
+            for issue in issues {
+                let Some(history) = self.link(integration, &issue, activity).await? else {
+                    continue;
+                };
+                summary.linked += 1;
+                HistoryCrud::new(self.app_state.db.clone())
+                    .create(actor.id, Some(issue.id), None, None, history)
+                    .await?;
+
+                let closes = references
+                    .iter()
+                    .any(|r| r.issue_id == issue.id && r.closes);
+                let Some(merge_status) = integration.merge_status else {
+                    continue;
+                };
+                if closes
+                    && activity.completes()
+                    && issue.status != merge_status
+                    && issue.status != STATUS_ACCEPTED
+                {
+                    self.transition(&actor, integration.project_id, issue.id, merge_status)
+                        .await?;
+                    summary.transitioned += 1;
+                }
+            }
+        }
+        Ok(summary)
+    }
+
+    // Returns the history entry to record, or None when nothing about the link changed
+    async fn link(
+        &self,
+        integration: &repository_integration::Model,
+        issue: &issue::Model,
+        activity: &RepositoryActivity,
+    ) -> Result<Option<String>, AppError> {
+        let existing = issue_repository_link::Entity::find()
+            .filter(issue_repository_link::Column::IssueId.eq(issue.id))
+            .filter(issue_repository_link::Column::IntegrationId.eq(integration.id))
+            .filter(issue_repository_link::Column::Kind.eq(activity.kind))
+            .filter(issue_repository_link::Column::ExternalId.eq(&activity.external_id))
+            .one(&self.app_state.db)
+            .await?;
+        let now = Utc::now();
+
+        match existing {
+            Some(link) if link.state == activity.state => Ok(None),
+            Some(link) => {
+                let mut link: issue_repository_link::ActiveModel = link.into();
+                link.state = Set(activity.state.to_string());
+                link.title = Set(activity.title.clone());
+                link.updated_at = Set(now.into());
+                link.update(&self.app_state.db).await?;
+                Ok(Some(format!(
+                    "{} '{}' was {}",
+                    activity.label(),
+                    activity.title,
+                    activity.state
+                )))
+            }
+            None => {
+                issue_repository_link::ActiveModel {
+                    issue_id: Set(issue.id),
+                    integration_id: Set(integration.id),
+                    kind: Set(activity.kind.to_string()),
+                    external_id: Set(activity.external_id.clone()),
+                    title: Set(activity.title.clone()),
+                    url: Set(activity.url.clone()),
+                    state: Set(activity.state.to_string()),
+                    created_at: Set(now.into()),
+                    updated_at: Set(now.into()),
+                    ..Default::default()
+                }
+                .insert(&self.app_state.db)
+                .await?;
+                Ok(Some(format!(
+                    "linked {} '{}' from {}",
+                    activity.label(),
+                    activity.title,
+                    integration.provider
+                )))
+            }
+        }
+    }
+
+    // The member who wrote the commit when their email matches, otherwise the project owner
+    async fn acting_user(
+        &self,
+        project_id: i32,
+        author_email: Option<&str>,
+    ) -> Result<Option<user::Model>, AppError> {
+        let project_user_crud = ProjectUserCrud::new(self.app_state.clone());
+        if let Some(email) = author_email {
+            let member_ids = project_user_crud.member_user_ids(project_id).await?;
+            let author = user::Entity::find()
+                .filter(user::Column::Id.is_in(member_ids))
+                .all(&self.app_state.db)
+                .await?
+                .into_iter()
+                .find(|u| u.email.eq_ignore_ascii_case(email));
+            if author.is_some() {
+                return Ok(author);
+            }
+        }
+        let Some(owner_id) = project_user_crud.owner_user_id(project_id).await? else {
+            return Ok(None);
+        };
+        user::Entity::find_by_id(owner_id)
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    // Goes through the normal update so the change is broadcast, notified and in history
+    async fn transition(
+        &self,
+        actor: &user::Model,
+        project_id: i32,
+        issue_id: i32,
+        status: i32,
+    ) -> Result<(), AppError> {
+        let app_state = AppState {
+            user: Some(actor.clone()),
+            ..self.app_state.clone()
+        };
+        IssueCrud::new(app_state)
+            .update(
+                issue_id,
+                None,
+                None,
+                None,
+                None,
+                Some(status),
+                None,
+                None,
+                None,
+                None,
+                None,
+            )
+            .await?;
+        info!(
+            "Moved issue {} in project {} to status {} from repository activity",
+            issue_id, project_id, status
+        );
+        Ok(())
+    }
+}
@@ -1,7 +1,7 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::{TAG_CREATED, TAG_DELETED, TAG_UPDATED};
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::issue_tag::IssueTagCrud;
 use crate::entities::tag;
+use crate::error::AppError;
 use crate::AppState;
 use sea_orm::*;
 
@@ -20,7 +20,7 @@ impl TagCrud {
         project_id: i32,
         name: String,
         is_epic: bool,
-    ) -> Result<tag::Model, DbErr> {
+    ) -> Result<tag::Model, AppError> {
         let tag = tag::ActiveModel {
             project_id: Set(project_id),
             name: Set(name),
@@ -28,34 +28,38 @@ impl TagCrud {
             ..Default::default()
         };
 
-        let result = tag.insert(&self.app_state.db).await?;
+        let txn = self.app_state.db.begin().await?;
+        let result = tag.insert(&txn).await?;
 
         let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let created = broadcaster
+            .record(&txn, *project_id, DomainEvent::TagCreated(result.clone()))
+            .await?;
+        txn.commit().await?;
 
         // Spawn a new task for delayed broadcast
-        let project_id_clone = *project_id;
         tokio::spawn(async move {
             tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
-            broadcaster.broadcast_event(
-                project_id_clone,
-                TAG_CREATED,
-                serde_json::json!({ "project_id": project_id_clone }),
-            );
+            broadcaster.announce(&created).await;
         });
 
         Ok(result)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<tag::Model>, DbErr> {
-        tag::Entity::find_by_id(id).one(&self.app_state.db).await
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<tag::Model>, AppError> {
+        tag::Entity::find_by_id(id)
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_all(&self, project_id: i32) -> Result<Vec<tag::Model>, DbErr> {
+    pub async fn find_all(&self, project_id: i32) -> Result<Vec<tag::Model>, AppError> {
         tag::Entity::find()
             .filter(tag::Column::ProjectId.eq(project_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn update(
@@ -63,11 +67,11 @@ impl TagCrud {
         id: i32,
         name: Option<String>,
         is_epic: Option<bool>,
-    ) -> Result<tag::Model, DbErr> {
+    ) -> Result<tag::Model, AppError> {
         let tag = tag::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Tag not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Tag"))?;
 
         let mut tag: tag::ActiveModel = tag.into();
 
@@ -79,41 +83,41 @@ impl TagCrud {
             tag.is_epic = Set(is_epic);
         }
 
-        let result = tag.update(&self.app_state.db).await?;
+        let txn = self.app_state.db.begin().await?;
+        let result = tag.update(&txn).await?;
 
         let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            TAG_UPDATED,
-            serde_json::json!({ "project_id": project_id }),
-        );
-
-        return Ok(result);
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let updated = broadcaster
+            .record(&txn, *project_id, DomainEvent::TagUpdated(result.clone()))
+            .await?;
+        txn.commit().await?;
+        broadcaster.announce(&updated).await;
+
+        Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
         // First delete all associated issue tags
         let issue_tag_crud = IssueTagCrud::new(self.app_state.clone());
         issue_tag_crud.delete_by_tag_id(id).await?;
 
         // Then delete the tag itself
-        let result = tag::Entity::delete_by_id(id)
-            .exec(&self.app_state.db)
+        let project_id = &self.app_state.project.clone().unwrap().id;
+        let txn = self.app_state.db.begin().await?;
+        let result = tag::Entity::delete_by_id(id).exec(&txn).await?;
+
+        let broadcaster = EventBroadcaster::new(&self.app_state);
+        let deleted = broadcaster
+            .record(&txn, *project_id, DomainEvent::TagDeleted { id })
             .await?;
+        txn.commit().await?;
+        broadcaster.announce(&deleted).await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            TAG_DELETED,
-            serde_json::json!({ "project_id": project_id }),
-        );
-
-        return Ok(result);
+        Ok(result)
     }
 
-    pub async fn delete_all_for_project(&self, project_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_for_project(&self, project_id: i32) -> Result<DeleteResult, AppError> {
         let tags = self.find_all(project_id).await?;
 
         for tag in tags {
@@ -1,8 +1,9 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::ISSUE_UPDATED;
 use crate::crud::history::HistoryCrud;
+use crate::crud::issue::IssueCrud;
+use crate::error::AppError;
 
 use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_preference::NotificationKind;
 use crate::entities::task;
 use crate::AppState;
 use sea_orm::*;
@@ -25,7 +26,7 @@ impl TaskCrud {
         issue_id: i32,
         completed: bool,
         percent: f32,
-    ) -> Result<task::Model, DbErr> {
+    ) -> Result<task::Model, AppError> {
         // Add history record
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
         let current_user_id = &self.app_state.user.clone().unwrap().id;
@@ -60,6 +61,7 @@ impl TaskCrud {
 
         let _ = notification_crud
             .notify_issue_stakeholders_with_context(
+                NotificationKind::TaskUpdate,
                 issue_id,
                 "New Task Created: {}",
                 description,
@@ -68,27 +70,27 @@ impl TaskCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"issue_id": issue_id}),
-        );
-
         txn.commit().await?;
-        return Ok(result);
+
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(issue_id)
+            .await?;
+        Ok(result)
     }
 
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<task::Model>, DbErr> {
-        task::Entity::find_by_id(id).one(&self.app_state.db).await
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<task::Model>, AppError> {
+        task::Entity::find_by_id(id)
+            .one(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
     }
 
-    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<task::Model>, DbErr> {
+    pub async fn find_by_issue_id(&self, issue_id: i32) -> Result<Vec<task::Model>, AppError> {
         task::Entity::find()
             .filter(task::Column::IssueId.eq(issue_id))
             .all(&self.app_state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn update(
@@ -97,11 +99,11 @@ impl TaskCrud {
         title: Option<String>,
         completed: Option<bool>,
         percent: Option<f32>,
-    ) -> Result<task::Model, DbErr> {
+    ) -> Result<task::Model, AppError> {
         let task = task::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Cannot find task.".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Task"))?;
 
         // Add history record
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
@@ -166,7 +168,7 @@ impl TaskCrud {
         let result = task.clone().update(&txn).await?;
         if result.lock_version != current_version + 1 {
             txn.rollback().await?;
-            return Err(DbErr::Custom("Optimistic lock error".to_owned()));
+            return Err(AppError::OptimisticLock);
         }
 
         txn.commit().await?;
@@ -187,6 +189,7 @@ impl TaskCrud {
 
             let _ = notification_crud
                 .notify_issue_stakeholders_with_context(
+                    NotificationKind::TaskUpdate,
                     result.issue_id,
                     "Task Updated: {}",
                     description,
@@ -196,21 +199,17 @@ impl TaskCrud {
                 .await;
         }
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"issue_id": task.issue_id.unwrap()}),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(task.issue_id.unwrap())
+            .await?;
         Ok(result)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
         let task = task::Entity::find_by_id(id)
             .one(&self.app_state.db)
             .await?
-            .ok_or(DbErr::Custom("Cannot find task.".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("Task"))?;
 
         // Add history record
         let history_crud = HistoryCrud::new(self.app_state.db.clone());
@@ -237,6 +236,7 @@ impl TaskCrud {
 
         let _ = notification_crud
             .notify_issue_stakeholders_with_context(
+                NotificationKind::TaskUpdate,
                 task.issue_id,
                 "Task Deleted: {}",
                 description,
@@ -245,21 +245,24 @@ impl TaskCrud {
             )
             .await;
 
-        let project_id = &self.app_state.project.clone().unwrap().id;
-        let broadcaster = EventBroadcaster::new(self.app_state.tx.clone());
-        broadcaster.broadcast_event(
-            *project_id,
-            ISSUE_UPDATED,
-            serde_json::json!({"issue_id": task.issue_id}),
-        );
+        IssueCrud::new(self.app_state.clone())
+            .publish_updated(task.issue_id)
+            .await?;
 
         Ok(result)
     }
 
-    pub async fn delete_all_by_issue_id(&self, issue_id: i32) -> Result<DeleteResult, DbErr> {
+    pub async fn delete_all_by_issue_id_with_txn<C>(
+        &self,
+        issue_id: i32,
+        txn: &C,
+    ) -> Result<DeleteResult, AppError>
+    where
+        C: ConnectionTrait,
+    {
         let result = task::Entity::delete_many()
             .filter(task::Column::IssueId.eq(issue_id))
-            .exec(&self.app_state.db)
+            .exec(txn)
             .await?;
 
         Ok(result)
@@ -0,0 +1,236 @@
+use crate::crud::file_upload::{generate_guid, FileUploadCrud};
+use crate::environment;
+use crate::{AppState, WorkerAppState};
+use graphile_worker::{IntoTaskHandlerResult, JobSpec, JobSpecBuilder, TaskHandler, WorkerContext};
+use image::{ImageFormat, ImageReader, Limits};
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+use tracing::{info, warn};
+
+// Longest side of a preview, in pixels
+pub const THUMBNAIL_MAX_SIZE: u32 = 320;
+const MAX_THUMBNAIL_ATTEMPTS: i16 = 5;
+const PDF_RENDER_TIMEOUT: Duration = Duration::from_secs(30);
+// A small file can declare a huge image; anything past these is refused before decoding
+const MAX_SOURCE_DIMENSION: u32 = 10_000;
+const MAX_DECODE_ALLOC: u64 = 256 * 1024 * 1024;
+
+/// Renders the preview of a new upload and stores it next to the original.
+#[derive(Deserialize, Serialize)]
+pub struct GenerateThumbnail {
+    pub file_upload_id: i32,
+}
+
+fn thumbnail_job_spec(file_upload_id: i32) -> JobSpec {
+    JobSpecBuilder::new()
+        .job_key(format!("generate_thumbnail:{}", file_upload_id))
+        .max_attempts(MAX_THUMBNAIL_ATTEMPTS)
+        .build()
+}
+
+// Queues a preview for uploads that can have one; the upload itself never waits on this
+pub async fn enqueue(app_state: &AppState, file_upload_id: i32, mime_type: &str) {
+    if !can_preview(mime_type) {
+        return;
+    }
+    let Some(worker) = app_state.worker.clone() else {
+        warn!(
+            "Background worker is not available to render a thumbnail for upload {}",
+            file_upload_id
+        );
+        return;
+    };
+    if let Err(e) = worker
+        .create_utils()
+        .add_job(
+            GenerateThumbnail { file_upload_id },
+            thumbnail_job_spec(file_upload_id),
+        )
+        .await
+    {
+        tracing::error!(
+            "Failed to queue thumbnail for upload {}: {}",
+            file_upload_id,
+            e
+        );
+    }
+}
+
+pub fn can_preview(mime_type: &str) -> bool {
+    image_format(mime_type).is_some() || mime_type == "application/pdf"
+}
+
+fn image_format(mime_type: &str) -> Option<ImageFormat> {
+    match mime_type {
+        "image/png" => Some(ImageFormat::Png),
+        "image/jpeg" | "image/jpg" => Some(ImageFormat::Jpeg),
+        "image/gif" => Some(ImageFormat::Gif),
+        "image/webp" => Some(ImageFormat::WebP),
+        "image/bmp" => Some(ImageFormat::Bmp),
+        _ => None,
+    }
+}
+
+/// A PNG preview of the content, or None when there is nothing to show for it: an
+/// unsupported type, a file that doesn't decode, or no PDF renderer installed.
+pub async fn render(mime_type: &str, content: Vec<u8>) -> Option<Vec<u8>> {
+    let result = if let Some(format) = image_format(mime_type) {
+        tokio::task::spawn_blocking(move || scale_to_png(&content, Some(format)))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|r| r)
+    } else if mime_type == "application/pdf" {
+        render_pdf_first_page(content).await
+    } else {
+        return None;
+    };
+    result
+        .inspect_err(|e| warn!("Could not render a {} preview: {}", mime_type, e))
+        .ok()
+}
+
+fn decode_limits() -> Limits {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+    limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_ALLOC);
+    limits
+}
+
+fn scale_to_png(content: &[u8], format: Option<ImageFormat>) -> Result<Vec<u8>, String> {
+    let mut reader = match format {
+        Some(format) => ImageReader::with_format(Cursor::new(content), format),
+        None => ImageReader::new(Cursor::new(content))
+            .with_guessed_format()
+            .map_err(|e| e.to_string())?,
+    };
+    reader.limits(decode_limits());
+    let decoded = reader.decode().map_err(|e| e.to_string())?;
+    let mut png = Vec::new();
+    decoded
+        .thumbnail(THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .map_err(|e| e.to_string())?;
+    Ok(png)
+}
+
+// Shells out to PDF_PREVIEW_COMMAND (pdftoppm) in a scratch directory under UPLOAD_TMP_DIR
+async fn render_pdf_first_page(content: Vec<u8>) -> Result<Vec<u8>, String> {
+    let dir = environment::upload_tmp_dir().join(format!(".preview-{}", generate_guid(16)));
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| e.to_string())?;
+    let result = run_pdf_renderer(&dir, content).await;
+    let _ = tokio::fs::remove_dir_all(&dir).await;
+    result
+}
+
+async fn run_pdf_renderer(dir: &Path, content: Vec<u8>) -> Result<Vec<u8>, String> {
+    let source = dir.join("source.pdf");
+    let output = dir.join("page");
+    tokio::fs::write(&source, content)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let command = environment::pdf_preview_command();
+    let child = Command::new(command)
+        .args(["-png", "-f", "1", "-l", "1", "-singlefile", "-scale-to"])
+        .arg(THUMBNAIL_MAX_SIZE.to_string())
+        .arg(&source)
+        .arg(&output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("could not run {}: {}", command, e))?;
+    let finished = tokio::time::timeout(PDF_RENDER_TIMEOUT, child.wait_with_output())
+        .await
+        .map_err(|_| format!("{} timed out", command))?
+        .map_err(|e| e.to_string())?;
+    if !finished.status.success() {
+        return Err(format!(
+            "{} failed: {}",
+            command,
+            String::from_utf8_lossy(&finished.stderr).trim()
+        ));
+    }
+
+    let page = tokio::fs::read(output.with_extension("png"))
+        .await
+        .map_err(|e| e.to_string())?;
+    // Normalizes whatever the renderer produced to the same size and format as image previews
+    tokio::task::spawn_blocking(move || scale_to_png(&page, None))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+impl TaskHandler for GenerateThumbnail {
+    const IDENTIFIER: &'static str = "generate_thumbnail";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+        let app_state = AppState {
+            db: worker_state.db.0.clone(),
+            tx: worker_state.tx.0.clone(),
+            user: None,
+            project: None,
+            worker: None,
+        };
+
+        let generated = FileUploadCrud::new(app_state)
+            .generate_thumbnail(self.file_upload_id)
+            .await
+            .map_err(|e| format!("Failed to generate thumbnail: {}", e))?;
+        if generated {
+            info!("Generated thumbnail for upload {}", self.file_upload_id);
+        }
+        Ok::<(), String>(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, RgbImage};
+
+    #[test]
+    fn test_images_are_scaled_down_to_png() {
+        let mut source = Vec::new();
+        image::DynamicImage::ImageRgb8(RgbImage::new(1280, 640))
+            .write_to(&mut Cursor::new(&mut source), ImageFormat::Jpeg)
+            .unwrap();
+
+        let png = scale_to_png(&source, image_format("image/jpeg")).unwrap();
+        let preview = image::load_from_memory_with_format(&png, ImageFormat::Png).unwrap();
+        assert_eq!(
+            preview.dimensions(),
+            (THUMBNAIL_MAX_SIZE, THUMBNAIL_MAX_SIZE / 2)
+        );
+    }
+
+    #[test]
+    fn test_oversized_images_are_not_decoded() {
+        let mut source = Vec::new();
+        image::DynamicImage::ImageLuma8(image::GrayImage::new(MAX_SOURCE_DIMENSION + 1, 1))
+            .write_to(&mut Cursor::new(&mut source), ImageFormat::Png)
+            .unwrap();
+        assert!(scale_to_png(&source, image_format("image/png")).is_err());
+        assert!(scale_to_png(&source, None).is_err());
+    }
+
+    #[test]
+    fn test_only_images_and_pdfs_get_previews() {
+        assert!(can_preview("image/webp"));
+        assert!(can_preview("application/pdf"));
+        assert!(!can_preview("image/svg+xml"));
+        assert!(!can_preview("text/plain"));
+    }
+}
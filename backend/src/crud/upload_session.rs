@@ -0,0 +1,475 @@
+use crate::crud::file_upload::{check_upload_size, generate_guid, SpooledFile};
+use crate::entities::upload_session;
+use crate::environment;
+use crate::error::AppError;
+use chrono::{Duration, Utc};
+use futures::{Stream, StreamExt};
+use sea_orm::prelude::DateTimeWithTimeZone;
+use sea_orm::sea_query::Expr;
+use sea_orm::*;
+use std::fmt::Display;
+use std::io::SeekFrom;
+use std::path::PathBuf;
+use tokio::fs;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tracing::warn;
+
+// How long a request may go without writing before another may take the upload over;
+// the lease is renewed as data arrives
+const WRITE_LEASE: Duration = Duration::seconds(30);
+const WRITE_LEASE_RENEWAL: Duration = Duration::seconds(10);
+// How long storing a finished upload may take before a retry may claim it again
+const COMPLETION_LEASE: Duration = Duration::minutes(10);
+
+// What a resumable upload will be attached to once it is complete
+pub enum UploadTarget {
+    Issue(i32),
+    ProjectNote(i32),
+}
+
+pub struct NewUploadSession {
+    pub project_id: i32,
+    pub target: UploadTarget,
+    pub uploader_user_id: i32,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+}
+
+#[derive(Clone)]
+pub struct UploadSessionCrud {
+    db: DatabaseConnection,
+}
+
+impl UploadSessionCrud {
+    pub fn new(db: DatabaseConnection) -> Self {
+        Self { db }
+    }
+
+    pub async fn create(&self, new: NewUploadSession) -> Result<upload_session::Model, AppError> {
+        if new.size_bytes <= 0 {
+            return Err(AppError::invalid(
+                "size",
+                "Empty file uploads are not allowed",
+            ));
+        }
+        check_upload_size(new.size_bytes)?;
+
+        let (issue_id, project_note_id) = match new.target {
+            UploadTarget::Issue(id) => (Some(id), None),
+            UploadTarget::ProjectNote(id) => (None, Some(id)),
+        };
+        let now = Utc::now();
+        let session = upload_session::ActiveModel {
+            id: Set(generate_guid(32)),
+            project_id: Set(new.project_id),
+            issue_id: Set(issue_id),
+            project_note_id: Set(project_note_id),
+            uploader_user_id: Set(new.uploader_user_id),
+            original_filename: Set(new.original_filename),
+            mime_type: Set(new.mime_type),
+            size_bytes: Set(new.size_bytes),
+            offset_bytes: Set(0),
+            created_at: Set(now.into()),
+            expires_at: Set(expires_from(now).into()),
+            write_lease: Set(None),
+            write_lease_expires_at: Set(None),
+        }
+        .insert(&self.db)
+        .await?;
+
+        fs::create_dir_all(parts_dir()).await.map_err(to_io_err)?;
+        fs::File::create(part_path(&session.id))
+            .await
+            .map_err(to_io_err)?;
+        Ok(session)
+    }
+
+    // Sessions are private to the user who started them, in the project they started them in
+    pub async fn find_for_user(
+        &self,
+        id: &str,
+        user_id: i32,
+        project_id: i32,
+    ) -> Result<Option<upload_session::Model>, AppError> {
+        let session = upload_session::Entity::find_by_id(id.to_string())
+            .filter(upload_session::Column::UploaderUserId.eq(user_id))
+            .filter(upload_session::Column::ProjectId.eq(project_id))
+            .filter(upload_session::Column::ExpiresAt.gt(Utc::now()))
+            .one(&self.db)
+            .await?;
+        Ok(session)
+    }
+
+    /// Appends a chunk that must start at `offset`. Whatever arrived before the connection
+    /// dropped is kept and counted, so the client can resume from the returned offset.
+    /// The request claims the session's write lease before touching the part file, so
+    /// concurrent requests for the same offset cannot interleave their bytes.
+    pub async fn append<S, B, E>(
+        &self,
+        session: &upload_session::Model,
+        offset: i64,
+        stream: S,
+    ) -> Result<upload_session::Model, AppError>
+    where
+        S: Stream<Item = Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: Display,
+    {
+        if offset != session.offset_bytes {
+            return Err(AppError::Conflict(format!(
+                "Upload-Offset {} does not match the upload's offset {}",
+                offset, session.offset_bytes
+            )));
+        }
+
+        let lease = generate_guid(32);
+        let now = Utc::now();
+        let claimed = upload_session::Entity::update_many()
+            .col_expr(
+                upload_session::Column::WriteLease,
+                Expr::value(lease.clone()),
+            )
+            .col_expr(
+                upload_session::Column::WriteLeaseExpiresAt,
+                Expr::value(now + WRITE_LEASE),
+            )
+            .filter(upload_session::Column::Id.eq(session.id.clone()))
+            .filter(upload_session::Column::OffsetBytes.eq(offset))
+            .filter(
+                Condition::any()
+                    .add(upload_session::Column::WriteLeaseExpiresAt.is_null())
+                    .add(upload_session::Column::WriteLeaseExpiresAt.lte(now)),
+            )
+            .exec(&self.db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Err(AppError::Conflict(
+                "Another request is writing to this upload".to_string(),
+            ));
+        }
+
+        let (written, failure) = match self.write_part(session, offset, &lease, stream).await {
+            Ok(result) => result,
+            Err(e) => {
+                self.release(&session.id, &lease, None).await?;
+                return Err(e);
+            }
+        };
+
+        // Moving the offset forward also gives up the lease
+        if !self.release(&session.id, &lease, Some(written)).await? {
+            return Err(AppError::Conflict(
+                "Another request is writing to this upload".to_string(),
+            ));
+        }
+        if let Some(failure) = failure {
+            return Err(failure);
+        }
+
+        upload_session::Entity::find_by_id(session.id.clone())
+            .one(&self.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Upload"))
+    }
+
+    // Streams into the part file while holding `lease`. Returns how far the file got and
+    // why the stream stopped early, if it did.
+    async fn write_part<S, B, E>(
+        &self,
+        session: &upload_session::Model,
+        offset: i64,
+        lease: &str,
+        stream: S,
+    ) -> Result<(i64, Option<AppError>), AppError>
+    where
+        S: Stream<Item = Result<B, E>>,
+        B: AsRef<[u8]>,
+        E: Display,
+    {
+        let path = part_path(&session.id);
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(&path)
+            .await
+            .map_err(to_io_err)?;
+        // Drop anything past the recorded offset left by an earlier interrupted write
+        file.set_len(offset as u64).await.map_err(to_io_err)?;
+        file.seek(SeekFrom::End(0)).await.map_err(to_io_err)?;
+
+        let mut written = offset;
+        let mut failure = None;
+        let mut renewed_at = Utc::now();
+        let mut stream = std::pin::pin!(stream);
+        while let Some(chunk) = stream.next().await {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    failure = Some(AppError::BadRequest(format!(
+                        "Upload interrupted at offset {}: {}",
+                        written, e
+                    )));
+                    break;
+                }
+            };
+            let chunk = chunk.as_ref();
+            if written + chunk.len() as i64 > session.size_bytes {
+                failure = Some(AppError::invalid(
+                    "file",
+                    format!(
+                        "More data than the declared length of {} bytes",
+                        session.size_bytes
+                    ),
+                ));
+                break;
+            }
+            // A writer that stalled long enough to lose its lease must not write again
+            if Utc::now() - renewed_at >= WRITE_LEASE_RENEWAL {
+                renewed_at = Utc::now();
+                if !self.renew(&session.id, lease, renewed_at).await? {
+                    return Err(AppError::Conflict(
+                        "Another request is writing to this upload".to_string(),
+                    ));
+                }
+            }
+            file.write_all(chunk).await.map_err(to_io_err)?;
+            written += chunk.len() as i64;
+        }
+        file.flush().await.map_err(to_io_err)?;
+        file.sync_data().await.map_err(to_io_err)?;
+        Ok((written, failure))
+    }
+
+    async fn renew(
+        &self,
+        id: &str,
+        lease: &str,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<bool, AppError> {
+        let renewed = upload_session::Entity::update_many()
+            .col_expr(
+                upload_session::Column::WriteLeaseExpiresAt,
+                Expr::value(now + WRITE_LEASE),
+            )
+            .filter(upload_session::Column::Id.eq(id))
+            .filter(upload_session::Column::WriteLease.eq(lease))
+            .exec(&self.db)
+            .await?;
+        Ok(renewed.rows_affected > 0)
+    }
+
+    // Gives up `lease`, recording the new offset if the write got that far. False if the
+    // lease had already been taken over.
+    async fn release(&self, id: &str, lease: &str, offset: Option<i64>) -> Result<bool, AppError> {
+        let mut update = upload_session::Entity::update_many()
+            .col_expr(
+                upload_session::Column::WriteLease,
+                Expr::value(Option::<String>::None),
+            )
+            .col_expr(
+                upload_session::Column::WriteLeaseExpiresAt,
+                Expr::value(Option::<DateTimeWithTimeZone>::None),
+            );
+        if let Some(offset) = offset {
+            update = update
+                .col_expr(upload_session::Column::OffsetBytes, offset.into())
+                .col_expr(
+                    upload_session::Column::ExpiresAt,
+                    Expr::value(DateTimeWithTimeZone::from(expires_from(Utc::now()))),
+                );
+        }
+        let released = update
+            .filter(upload_session::Column::Id.eq(id))
+            .filter(upload_session::Column::WriteLease.eq(lease))
+            .exec(&self.db)
+            .await?;
+        Ok(released.rows_affected > 0)
+    }
+
+    /// Hands the received file over once every byte is in, along with the lease that claims
+    /// it. The session and its part file stay until `finish_completed`, so if storing the
+    /// file fails the client can retry the final request after `release_completed`.
+    pub async fn claim_completed(
+        &self,
+        session: &upload_session::Model,
+    ) -> Result<(String, SpooledFile), AppError> {
+        // Claiming the lease keeps a repeated final request from storing the upload twice
+        let lease = generate_guid(32);
+        let now = Utc::now();
+        let claimed = upload_session::Entity::update_many()
+            .col_expr(
+                upload_session::Column::WriteLease,
+                Expr::value(lease.clone()),
+            )
+            .col_expr(
+                upload_session::Column::WriteLeaseExpiresAt,
+                Expr::value(now + COMPLETION_LEASE),
+            )
+            .filter(upload_session::Column::Id.eq(session.id.clone()))
+            .filter(upload_session::Column::OffsetBytes.eq(session.size_bytes))
+            .filter(
+                Condition::any()
+                    .add(upload_session::Column::WriteLeaseExpiresAt.is_null())
+                    .add(upload_session::Column::WriteLeaseExpiresAt.lte(now)),
+            )
+            .exec(&self.db)
+            .await?;
+        if claimed.rows_affected == 0 {
+            return Err(AppError::Conflict(
+                "This upload is already being completed".to_string(),
+            ));
+        }
+
+        match completed_file(session).await {
+            Ok(file) => Ok((lease, file)),
+            Err(e) => {
+                self.release(&session.id, &lease, None).await?;
+                Err(e)
+            }
+        }
+    }
+
+    // The upload has been stored, so the session and its parts can go
+    pub async fn finish_completed(&self, id: &str, lease: &str) -> Result<(), AppError> {
+        upload_session::Entity::delete_many()
+            .filter(upload_session::Column::Id.eq(id))
+            .filter(upload_session::Column::WriteLease.eq(lease))
+            .exec(&self.db)
+            .await?;
+        remove_part(id).await;
+        Ok(())
+    }
+
+    pub async fn release_completed(&self, id: &str, lease: &str) -> Result<(), AppError> {
+        self.release(id, lease, None).await?;
+        Ok(())
+    }
+
+    pub async fn delete(&self, id: &str) -> Result<(), AppError> {
+        upload_session::Entity::delete_by_id(id.to_string())
+            .exec(&self.db)
+            .await?;
+        remove_part(id).await;
+        Ok(())
+    }
+
+    pub async fn delete_expired(&self) -> Result<u64, AppError> {
+        let expired = upload_session::Entity::find()
+            .filter(upload_session::Column::ExpiresAt.lte(Utc::now()))
+            .all(&self.db)
+            .await?;
+        for session in &expired {
+            self.delete(&session.id).await?;
+        }
+        Ok(expired.len() as u64)
+    }
+}
+
+// The stored file is taken from a link to the part file, so the part survives a failed
+// attempt to store it
+async fn completed_file(session: &upload_session::Model) -> Result<SpooledFile, AppError> {
+    let link = parts_dir().join(format!("{}.completing", session.id));
+    if let Err(e) = fs::remove_file(&link).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            return Err(to_io_err(e));
+        }
+    }
+    fs::hard_link(part_path(&session.id), &link)
+        .await
+        .map_err(to_io_err)?;
+    let file = SpooledFile::from_file(link).await?;
+    if file.size_bytes != session.size_bytes {
+        return Err(AppError::Internal(format!(
+            "upload {} has {} bytes, expected {}",
+            session.id, file.size_bytes, session.size_bytes
+        )));
+    }
+    Ok(file)
+}
+
+fn expires_from(now: chrono::DateTime<Utc>) -> chrono::DateTime<Utc> {
+    now + Duration::hours(environment::upload_session_ttl_hours())
+}
+
+fn parts_dir() -> PathBuf {
+    environment::upload_tmp_dir().join("resumable")
+}
+
+// Session ids are alphanumeric, so they are safe to use as file names
+fn part_path(id: &str) -> PathBuf {
+    parts_dir().join(format!("{}.part", id))
+}
+
+async fn remove_part(id: &str) {
+    let path = part_path(id);
+    if let Err(e) = fs::remove_file(&path).await {
+        if e.kind() != std::io::ErrorKind::NotFound {
+            warn!("Failed to remove {}: {}", path.display(), e);
+        }
+    }
+}
+
+fn to_io_err(e: std::io::Error) -> AppError {
+    AppError::Internal(format!("upload session storage error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A final request whose upload could not be stored leaves the session to retry
+    #[tokio::test]
+    async fn test_failed_completion_can_be_retried() {
+        // Set explicitly so the default doesn't fix the store scheme for other tests
+        std::env::set_var(
+            "UPLOAD_TMP_DIR",
+            std::env::temp_dir().join("phoenix-uploads"),
+        );
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let table = Schema::new(backend).create_table_from_entity(upload_session::Entity);
+        db.execute(backend.build(&table)).await.unwrap();
+
+        let sessions = UploadSessionCrud::new(db.clone());
+        let session = sessions
+            .create(NewUploadSession {
+                project_id: 1,
+                target: UploadTarget::Issue(1),
+                uploader_user_id: 1,
+                original_filename: "notes.txt".to_string(),
+                mime_type: "text/plain".to_string(),
+                size_bytes: 5,
+            })
+            .await
+            .unwrap();
+        let chunks = futures::stream::iter([Ok::<_, std::io::Error>(b"hello".to_vec())]);
+        let session = sessions.append(&session, 0, chunks).await.unwrap();
+
+        let (lease, file) = sessions.claim_completed(&session).await.unwrap();
+        assert!(matches!(
+            sessions.claim_completed(&session).await,
+            Err(AppError::Conflict(_))
+        ));
+
+        // Storing failed: the file is dropped and the claim given back
+        drop(file);
+        sessions
+            .release_completed(&session.id, &lease)
+            .await
+            .unwrap();
+
+        let (lease, file) = sessions.claim_completed(&session).await.unwrap();
+        assert_eq!(file.size_bytes, 5);
+        drop(file);
+        sessions
+            .finish_completed(&session.id, &lease)
+            .await
+            .unwrap();
+        assert!(upload_session::Entity::find_by_id(session.id.clone())
+            .one(&db)
+            .await
+            .unwrap()
+            .is_none());
+        assert!(!part_path(&session.id).exists());
+    }
+}
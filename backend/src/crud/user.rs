@@ -1,5 +1,6 @@
 use crate::crud::project_user::ProjectUserCrud;
 use crate::entities::user;
+use crate::error::AppError;
 use crate::AppState;
 use sea_orm::*;
 
@@ -13,24 +14,27 @@ impl UserCrud {
         Self { state }
     }
 
-    pub async fn create(&self, name: String, email: String) -> Result<user::Model, DbErr> {
+    pub async fn create(&self, name: String, email: String) -> Result<user::Model, AppError> {
         let user = user::ActiveModel {
             name: Set(name),
             email: Set(email),
             ..Default::default()
         };
 
-        user.insert(&self.state.db).await
+        user.insert(&self.state.db).await.map_err(AppError::from)
     }
-    pub async fn find_by_id(&self, id: i32) -> Result<Option<user::Model>, DbErr> {
-        user::Entity::find_by_id(id).one(&self.state.db).await
+    pub async fn find_by_id(&self, id: i32) -> Result<Option<user::Model>, AppError> {
+        user::Entity::find_by_id(id)
+            .one(&self.state.db)
+            .await
+            .map_err(AppError::from)
     }
 
     pub async fn find_all(
         &self,
         project_id: i32,
         user_ids: Vec<i32>,
-    ) -> Result<Vec<user::Model>, DbErr> {
+    ) -> Result<Vec<user::Model>, AppError> {
         let mut users = user::Entity::find()
             .filter(user::Column::Id.is_in(user_ids))
             .all(&self.state.db)
@@ -47,11 +51,12 @@ impl UserCrud {
         Ok(users)
     }
 
-    pub async fn find_by_email(&self, email: String) -> Result<Option<user::Model>, DbErr> {
+    pub async fn find_by_email(&self, email: String) -> Result<Option<user::Model>, AppError> {
         user::Entity::find()
             .filter(user::Column::Email.eq(email))
             .one(&self.state.db)
             .await
+            .map_err(AppError::from)
     }
 
     pub async fn update(
@@ -59,11 +64,11 @@ impl UserCrud {
         id: i32,
         name: Option<String>,
         email: Option<String>,
-    ) -> Result<user::Model, DbErr> {
+    ) -> Result<user::Model, AppError> {
         let user = user::Entity::find_by_id(id)
             .one(&self.state.db)
             .await?
-            .ok_or(DbErr::Custom("User not found".to_owned()))?;
+            .ok_or_else(|| AppError::not_found("User"))?;
 
         let mut user: user::ActiveModel = user.into();
 
@@ -79,7 +84,10 @@ impl UserCrud {
         Ok(updated_user)
     }
 
-    pub async fn delete(&self, id: i32) -> Result<DeleteResult, DbErr> {
-        user::Entity::delete_by_id(id).exec(&self.state.db).await
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
+        user::Entity::delete_by_id(id)
+            .exec(&self.state.db)
+            .await
+            .map_err(AppError::from)
     }
 }
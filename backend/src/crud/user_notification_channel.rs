@@ -0,0 +1,115 @@
+use crate::entities::user_notification_channel;
+use crate::error::AppError;
+use crate::notifications::channel;
+use crate::notifications::delivery;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+use serde_json::Value;
+
+#[derive(Clone)]
+pub struct UserNotificationChannelCrud {
+    app_state: AppState,
+}
+
+impl UserNotificationChannelCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn create(
+        &self,
+        channel: String,
+        config: Value,
+        enabled: bool,
+    ) -> Result<user_notification_channel::Model, AppError> {
+        let user = self.app_state.current_user()?;
+        delivery::check_user_config(&channel, &config, &user.email)
+            .map_err(|e| AppError::invalid("config", e))?;
+        let resolved = delivery::resolve_user_config(&channel, Some(&config), &user.email);
+        channel::check_channel(&channel, &resolved)
+            .await
+            .map_err(|e| AppError::invalid("config", e))?;
+
+        let now = Utc::now();
+        user_notification_channel::ActiveModel {
+            user_id: Set(user.id),
+            channel: Set(channel),
+            config: Set(Some(config.to_string())),
+            enabled: Set(enabled),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn find_all_for_current_user(
+        &self,
+    ) -> Result<Vec<user_notification_channel::Model>, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        self.find_all_by_user_id(user_id).await
+    }
+
+    pub async fn find_all_by_user_id(
+        &self,
+        user_id: i32,
+    ) -> Result<Vec<user_notification_channel::Model>, AppError> {
+        user_notification_channel::Entity::find()
+            .filter(user_notification_channel::Column::UserId.eq(user_id))
+            .order_by_asc(user_notification_channel::Column::Id)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_by_id(&self, id: i32) -> Result<user_notification_channel::Model, AppError> {
+        let user_id = self.app_state.current_user()?.id;
+        user_notification_channel::Entity::find_by_id(id)
+            .filter(user_notification_channel::Column::UserId.eq(user_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Notification channel"))
+    }
+
+    pub async fn update(
+        &self,
+        id: i32,
+        config: Option<Value>,
+        enabled: Option<bool>,
+    ) -> Result<user_notification_channel::Model, AppError> {
+        let existing = self.find_by_id(id).await?;
+        if let Some(config) = &config {
+            let email = &self.app_state.current_user()?.email;
+            delivery::check_user_config(&existing.channel, config, email)
+                .map_err(|e| AppError::invalid("config", e))?;
+            let resolved = delivery::resolve_user_config(&existing.channel, Some(config), email);
+            channel::check_channel(&existing.channel, &resolved)
+                .await
+                .map_err(|e| AppError::invalid("config", e))?;
+        }
+
+        let mut model: user_notification_channel::ActiveModel = existing.into();
+        if let Some(config) = config {
+            model.config = Set(Some(config.to_string()));
+        }
+        if let Some(enabled) = enabled {
+            model.enabled = Set(enabled);
+        }
+        model.updated_at = Set(Utc::now().into());
+        model
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn delete(&self, id: i32) -> Result<DeleteResult, AppError> {
+        let existing = self.find_by_id(id).await?;
+        user_notification_channel::Entity::delete_by_id(existing.id)
+            .exec(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+}
@@ -0,0 +1,192 @@
+use crate::entities::webhook;
+use crate::error::AppError;
+use crate::notifications::destination::{check_destination, Destination};
+use crate::AppState;
+use chrono::Utc;
+use rand::Rng;
+use sea_orm::*;
+
+/// The URL is resolved when it is saved, and again before every delivery
+async fn check_webhook_url(url: &str) -> Result<(), AppError> {
+    check_destination(url, Destination::Public)
+        .await
+        .map_err(|e| AppError::invalid("url", e))
+}
+
+/// A random 32-byte signing secret, hex encoded.
+pub fn generate_secret() -> String {
+    let bytes: [u8; 32] = rand::rng().random();
+    hex::encode(bytes)
+}
+
+/// Whether the webhook wants `event_type`. No filter, or an empty one, means every event.
+pub fn subscribes_to(webhook: &webhook::Model, event_type: &str) -> bool {
+    match webhook
+        .events
+        .as_deref()
+        .and_then(|events| serde_json::from_str::<Vec<String>>(events).ok())
+    {
+        Some(events) if !events.is_empty() => events.iter().any(|e| e == event_type),
+        _ => true,
+    }
+}
+
+fn encode_events(events: Option<Vec<String>>) -> Option<String> {
+    events
+        .filter(|events| !events.is_empty())
+        .map(|events| serde_json::Value::from(events).to_string())
+}
+
+#[derive(Clone)]
+pub struct WebhookCrud {
+    app_state: AppState,
+}
+
+impl WebhookCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn create(
+        &self,
+        project_id: i32,
+        url: String,
+        secret: Option<String>,
+        events: Option<Vec<String>>,
+        enabled: bool,
+    ) -> Result<webhook::Model, AppError> {
+        check_webhook_url(&url).await?;
+        let now = Utc::now();
+        webhook::ActiveModel {
+            project_id: Set(project_id),
+            url: Set(url),
+            secret: Set(secret.unwrap_or_else(generate_secret)),
+            events: Set(encode_events(events)),
+            enabled: Set(enabled),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    pub async fn update(
+        &self,
+        project_id: i32,
+        id: i32,
+        url: Option<String>,
+        secret: Option<String>,
+        events: Option<Option<Vec<String>>>,
+        enabled: Option<bool>,
+    ) -> Result<webhook::Model, AppError> {
+        if let Some(url) = &url {
+            check_webhook_url(url).await?;
+        }
+        let webhook = self.find_by_id(project_id, id).await?;
+        let mut webhook: webhook::ActiveModel = webhook.into();
+        if let Some(url) = url {
+            webhook.url = Set(url);
+        }
+        if let Some(secret) = secret {
+            webhook.secret = Set(secret);
+        }
+        if let Some(events) = events {
+            webhook.events = Set(encode_events(events));
+        }
+        if let Some(enabled) = enabled {
+            webhook.enabled = Set(enabled);
+        }
+        webhook.updated_at = Set(Utc::now().into());
+        webhook
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_all_by_project_id(
+        &self,
+        project_id: i32,
+    ) -> Result<Vec<webhook::Model>, AppError> {
+        webhook::Entity::find()
+            .filter(webhook::Column::ProjectId.eq(project_id))
+            .order_by_asc(webhook::Column::Id)
+            .all(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_by_id(&self, project_id: i32, id: i32) -> Result<webhook::Model, AppError> {
+        webhook::Entity::find_by_id(id)
+            .filter(webhook::Column::ProjectId.eq(project_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Webhook"))
+    }
+
+    /// The enabled webhooks in a project that want `event_type`.
+    pub async fn find_subscribed(
+        &self,
+        project_id: i32,
+        event_type: &str,
+    ) -> Result<Vec<webhook::Model>, AppError> {
+        let webhooks = webhook::Entity::find()
+            .filter(webhook::Column::ProjectId.eq(project_id))
+            .filter(webhook::Column::Enabled.eq(true))
+            .all(&self.app_state.db)
+            .await?;
+        Ok(webhooks
+            .into_iter()
+            .filter(|webhook| subscribes_to(webhook, event_type))
+            .collect())
+    }
+
+    // Deliveries go with it through the cascade
+    pub async fn delete(&self, project_id: i32, id: i32) -> Result<DeleteResult, AppError> {
+        let webhook = self.find_by_id(project_id, id).await?;
+        webhook::Entity::delete_by_id(webhook.id)
+            .exec(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn webhook_with(events: Option<&str>) -> webhook::Model {
+        webhook::Model {
+            id: 1,
+            project_id: 1,
+            url: "https://ci.example.com/hook".to_string(),
+            secret: generate_secret(),
+            events: events.map(str::to_string),
+            enabled: true,
+            created_at: Utc::now().into(),
+            updated_at: Utc::now().into(),
+        }
+    }
+
+    #[test]
+    fn test_event_filters() {
+        assert!(subscribes_to(&webhook_with(None), "tag_created"));
+        assert!(subscribes_to(&webhook_with(Some("[]")), "tag_created"));
+        let filtered =
+            webhook_with(encode_events(Some(vec!["issue_created".to_string()])).as_deref());
+        assert!(subscribes_to(&filtered, "issue_created"));
+        assert!(!subscribes_to(&filtered, "tag_created"));
+        assert_eq!(generate_secret().len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_internal_urls_are_rejected() {
+        assert!(check_webhook_url("http://127.0.0.1/hook").await.is_err());
+        assert!(check_webhook_url("http://[::1]:8080/hook").await.is_err());
+        assert!(check_webhook_url("http://localhost/hook").await.is_err());
+        assert!(check_webhook_url("https://93.184.215.14/hook")
+            .await
+            .is_ok());
+    }
+}
@@ -0,0 +1,111 @@
+use crate::entities::webhook_delivery;
+use crate::error::AppError;
+use crate::AppState;
+use chrono::Utc;
+use sea_orm::*;
+
+// Queued, or failed with retries still to come
+pub const WEBHOOK_PENDING: &str = "pending";
+pub const WEBHOOK_SENT: &str = "sent";
+// Every attempt failed
+pub const WEBHOOK_FAILED: &str = "failed";
+pub const WEBHOOK_STATUSES: [&str; 3] = [WEBHOOK_PENDING, WEBHOOK_SENT, WEBHOOK_FAILED];
+
+const MAX_LISTED_DELIVERIES: u64 = 100;
+
+/// The outcome of one POST to a webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookAttempt {
+    pub response_status: Option<i32>,
+    pub error: Option<String>,
+}
+
+#[derive(Clone)]
+pub struct WebhookDeliveryCrud {
+    app_state: AppState,
+}
+
+impl WebhookDeliveryCrud {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    pub async fn create(
+        &self,
+        webhook_id: i32,
+        event_type: &str,
+        payload: String,
+    ) -> Result<webhook_delivery::Model, AppError> {
+        let now = Utc::now();
+        webhook_delivery::ActiveModel {
+            webhook_id: Set(webhook_id),
+            event_type: Set(event_type.to_string()),
+            payload: Set(payload),
+            status: Set(WEBHOOK_PENDING.to_string()),
+            attempts: Set(0),
+            created_at: Set(now.into()),
+            updated_at: Set(now.into()),
+            ..Default::default()
+        }
+        .insert(&self.app_state.db)
+        .await
+        .map_err(AppError::from)
+    }
+
+    /// Records an attempt. A failure stays pending unless it was the last attempt.
+    pub async fn record_attempt(
+        &self,
+        delivery: webhook_delivery::Model,
+        attempt: WebhookAttempt,
+        final_attempt: bool,
+    ) -> Result<webhook_delivery::Model, AppError> {
+        let attempts = delivery.attempts + 1;
+        let now = Utc::now();
+        let mut delivery: webhook_delivery::ActiveModel = delivery.into();
+        let status = match (&attempt.error, final_attempt) {
+            (None, _) => {
+                delivery.delivered_at = Set(Some(now.into()));
+                WEBHOOK_SENT
+            }
+            (Some(_), false) => WEBHOOK_PENDING,
+            (Some(_), true) => WEBHOOK_FAILED,
+        };
+        delivery.status = Set(status.to_string());
+        delivery.response_status = Set(attempt.response_status);
+        delivery.last_error = Set(attempt.error);
+        delivery.attempts = Set(attempts);
+        delivery.updated_at = Set(now.into());
+        delivery
+            .update(&self.app_state.db)
+            .await
+            .map_err(AppError::from)
+    }
+
+    pub async fn find_all_by_webhook_id(
+        &self,
+        webhook_id: i32,
+        status: Option<&str>,
+    ) -> Result<Vec<webhook_delivery::Model>, AppError> {
+        let mut query = webhook_delivery::Entity::find()
+            .filter(webhook_delivery::Column::WebhookId.eq(webhook_id))
+            .order_by_desc(webhook_delivery::Column::CreatedAt)
+            .order_by_desc(webhook_delivery::Column::Id)
+            .limit(MAX_LISTED_DELIVERIES);
+        if let Some(status) = status {
+            query = query.filter(webhook_delivery::Column::Status.eq(status));
+        }
+        query.all(&self.app_state.db).await.map_err(AppError::from)
+    }
+
+    pub async fn find_by_id(
+        &self,
+        webhook_id: i32,
+        id: i32,
+    ) -> Result<webhook_delivery::Model, AppError> {
+        webhook_delivery::Entity::find_by_id(id)
+            .filter(webhook_delivery::Column::WebhookId.eq(webhook_id))
+            .one(&self.app_state.db)
+            .await?
+            .ok_or_else(|| AppError::not_found("Webhook delivery"))
+    }
+}
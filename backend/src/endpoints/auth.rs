@@ -1,5 +1,7 @@
 use crate::crud::user::UserCrud;
+use crate::error::AppError;
 use crate::jwt::JwtService;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::body::Body;
 use axum::http::StatusCode;
@@ -33,6 +35,25 @@ pub struct SwitchProjectRequest {
     project_id: i32,
 }
 
+impl Validate for LoginRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.email("email", &self.email);
+    }
+}
+
+impl Validate for RegisterRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name, validation::MAX_NAME_LENGTH)
+            .email("email", &self.email);
+    }
+}
+
+impl Validate for SwitchProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.id("projectId", self.project_id);
+    }
+}
+
 pub fn auth_routes() -> Router<AppState> {
     Router::new()
         .route("/auth/login", post(login))
@@ -43,70 +64,30 @@ pub fn auth_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn login(
     State(app_state): State<AppState>,
-    Json(payload): Json<LoginRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<LoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let user_crud = UserCrud::new(app_state.clone());
-
-    let user = match user_crud.find_by_email(payload.email).await {
-        Ok(Some(user)) => user,
-        Ok(None) => return (StatusCode::NOT_FOUND, "User not found").into_response(),
-        Err(_) => return (StatusCode::INTERNAL_SERVER_ERROR, "Database error").into_response(),
-    };
+    let user = user_crud
+        .find_by_email(payload.email)
+        .await?
+        .ok_or_else(|| AppError::not_found("User"))?;
 
     create_token(app_state, user.id).await
 }
 #[axum::debug_handler]
 async fn register(
     State(app_state): State<AppState>,
-    Json(payload): Json<RegisterRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<RegisterRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let user_crud = UserCrud::new(app_state.clone());
-
-    let user = match user_crud.create(payload.name, payload.email).await {
-        Ok(user) => user,
-        Err(e) => {
-            debug!("Error creating user: {:?}", e);
-            return (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create user").into_response();
-        }
-    };
+    let user = user_crud.create(payload.name, payload.email).await?;
 
     create_token(app_state, user.id).await
 }
 
-async fn create_token(_app_state: AppState, user_id: i32) -> Response<Body> {
-    debug!("Creating JWT service instance");
-    let jwt_service = JwtService::new();
-
-    debug!(
-        "Creating JWT token for user ID: {} with no initial project",
-        user_id
-    );
+async fn create_token(_app_state: AppState, user_id: i32) -> Result<Response<Body>, AppError> {
     // Users start with no project selected - they must choose one using switch-project
-    let project_id = None;
-
-    match jwt_service.create_token(user_id, project_id) {
-        Ok(token) => {
-            debug!("JWT token created successfully for user: {}", user_id);
-            let expires_at = chrono::Utc::now()
-                .checked_add_signed(chrono::Duration::days(7))
-                .unwrap();
-
-            Json(json!({
-                "user_id": user_id,
-                "token": format!("Bearer {}", token),
-                "expires_at": expires_at,
-                "project_id": project_id
-            }))
-            .into_response()
-        }
-        Err(e) => {
-            debug!(
-                "Failed to create JWT token for user ID: {}, error: {:?}",
-                user_id, e
-            );
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create token").into_response()
-        }
-    }
+    create_token_with_project(user_id, None).await
 }
 #[axum::debug_handler]
 async fn logout(
@@ -123,7 +104,7 @@ async fn logout(
 async fn switch_project(
     State(_app_state): State<AppState>,
     req: axum::extract::Request<axum::body::Body>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     // Extract headers before consuming the body
     let auth_header = req
         .headers()
@@ -132,38 +113,30 @@ async fn switch_project(
         .map(|s| s.to_string()); // Clone the header value
 
     // Extract the JSON payload from the request body
-    let body_bytes = match axum::body::to_bytes(req.into_body(), usize::MAX).await {
-        Ok(bytes) => bytes,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Failed to read request body").into_response(),
-    };
-
-    let payload: SwitchProjectRequest = match serde_json::from_slice(&body_bytes) {
-        Ok(payload) => payload,
-        Err(_) => return (StatusCode::BAD_REQUEST, "Invalid JSON payload").into_response(),
-    };
-
-    if let Some(auth_header_value) = auth_header {
-        if let Some(token) = JwtService::extract_bearer_token(&auth_header_value) {
-            let jwt_service = JwtService::new();
-            match jwt_service.validate_token(token) {
-                Ok(claims) => {
-                    create_token_with_project(claims.user_id, Some(payload.project_id)).await
-                }
-                Err(_) => (StatusCode::UNAUTHORIZED, "Invalid token").into_response(),
-            }
-        } else {
-            (
-                StatusCode::UNAUTHORIZED,
-                "Invalid authorization header format",
-            )
-                .into_response()
-        }
-    } else {
-        (StatusCode::UNAUTHORIZED, "No authorization token provided").into_response()
-    }
+    let body_bytes = axum::body::to_bytes(req.into_body(), usize::MAX)
+        .await
+        .map_err(|_| AppError::BadRequest("Failed to read request body".to_string()))?;
+
+    let payload: SwitchProjectRequest = serde_json::from_slice(&body_bytes)
+        .map_err(|e| AppError::BadRequest(format!("Invalid JSON payload: {}", e)))?;
+    validation::validate(&payload)?;
+
+    let auth_header_value = auth_header.ok_or(AppError::Unauthorized)?;
+    let token =
+        JwtService::extract_bearer_token(&auth_header_value).ok_or(AppError::Unauthorized)?;
+    let jwt_service = JwtService::new();
+    let claims = jwt_service.validate_token(token).map_err(|e| {
+        debug!("Invalid token on switch-project: {:?}", e);
+        AppError::Unauthorized
+    })?;
+
+    create_token_with_project(claims.user_id, Some(payload.project_id)).await
 }
 
-async fn create_token_with_project(user_id: i32, project_id: Option<i32>) -> Response<Body> {
+async fn create_token_with_project(
+    user_id: i32,
+    project_id: Option<i32>,
+) -> Result<Response<Body>, AppError> {
     debug!("Creating JWT service instance");
     let jwt_service = JwtService::new();
 
@@ -171,32 +144,25 @@ async fn create_token_with_project(user_id: i32, project_id: Option<i32>) -> Res
         "Creating JWT token for user ID: {} with project ID: {:?}",
         user_id, project_id
     );
-    match jwt_service.create_token(user_id, project_id) {
-        Ok(token) => {
-            debug!(
-                "JWT token created successfully for user: {} with project: {:?}",
-                user_id, project_id
-            );
-            let expires_at = chrono::Utc::now()
-                .checked_add_signed(chrono::Duration::days(7))
-                .unwrap();
-
-            Json(json!({
-                "user_id": user_id,
-                "token": format!("Bearer {}", token),
-                "expires_at": expires_at,
-                "project_id": project_id
-            }))
-            .into_response()
-        }
-        Err(e) => {
-            debug!(
-                "Failed to create JWT token for user ID: {}, error: {:?}",
-                user_id, e
-            );
-            (StatusCode::INTERNAL_SERVER_ERROR, "Failed to create token").into_response()
-        }
-    }
+    let token = jwt_service
+        .create_token(user_id, project_id)
+        .map_err(|e| AppError::Internal(format!("failed to create JWT token: {:?}", e)))?;
+
+    debug!(
+        "JWT token created successfully for user: {} with project: {:?}",
+        user_id, project_id
+    );
+    let expires_at = chrono::Utc::now()
+        .checked_add_signed(chrono::Duration::days(7))
+        .unwrap();
+
+    Ok(Json(json!({
+        "user_id": user_id,
+        "token": format!("Bearer {}", token),
+        "expires_at": expires_at,
+        "project_id": project_id
+    }))
+    .into_response())
 }
 
 #[derive(Deserialize)]
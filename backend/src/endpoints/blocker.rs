@@ -1,4 +1,6 @@
 use crate::crud::blocker::BlockerCrud;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -9,7 +11,7 @@ use axum::{
     Json, Router,
 };
 use serde::Deserialize;
-use tracing::{debug, info};
+use tracing::debug;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +20,16 @@ pub struct CreateBlockerRequest {
     blocked_id: i32,
 }
 
+impl Validate for CreateBlockerRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.id("blockerId", self.blocker_id)
+            .id("blockedId", self.blocked_id);
+        if self.blocker_id == self.blocked_id {
+            v.error("blockedId", "an issue cannot block itself");
+        }
+    }
+}
+
 pub fn blocker_routes() -> Router<AppState> {
     Router::new()
         .route("/blockers", post(create_blocker))
@@ -32,63 +44,40 @@ pub fn blocker_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_blocker(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateBlockerRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateBlockerRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating blocker");
     let blocker_crud = BlockerCrud::new(app_state);
-    match blocker_crud
+    let blocker = blocker_crud
         .create(payload.blocker_id, payload.blocked_id)
-        .await
-    {
-        Ok(blocker) => Ok(Json(blocker)),
-        Err(e) => {
-            info!("Error creating blocker: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(blocker))
 }
 
 #[axum::debug_handler]
 async fn get_blocker_issues(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let blocker_crud = BlockerCrud::new(app_state);
-    match blocker_crud.find_by_blocker_id(id).await {
-        Ok(blockers) => Ok(Json(blockers)),
-        Err(e) => {
-            info!("Error getting blocker issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(blocker_crud.find_by_blocker_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn get_blocked_issues(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let blocker_crud = BlockerCrud::new(app_state);
-    match blocker_crud.find_by_blocked_id(id).await {
-        Ok(blockers) => Ok(Json(blockers)),
-        Err(e) => {
-            info!("Error getting blocked issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(blocker_crud.find_by_blocked_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn delete_blocker(
     Extension(app_state): Extension<AppState>,
     Path((blocker_id, blocked_id)): Path<(i32, i32)>,
-) -> StatusCode {
+) -> Result<StatusCode, AppError> {
     let blocker_crud = BlockerCrud::new(app_state);
-    match blocker_crud.delete(blocker_id, blocked_id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting blocker: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    blocker_crud.delete(blocker_id, blocked_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
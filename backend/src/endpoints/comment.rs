@@ -1,6 +1,8 @@
 use crate::crud::comment::CommentCrud;
 use crate::crud::comment_file_upload::CommentFileUploadCrud;
 use crate::entities::{comment, file_upload};
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -33,6 +35,19 @@ pub struct CommentResponse {
     pub uploads: Vec<file_upload::Model>,
 }
 
+impl Validate for CreateCommentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("content", &self.content, validation::MAX_COMMENT_LENGTH)
+            .id("issueId", self.issue_id);
+    }
+}
+
+impl Validate for UpdateCommentRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("content", &self.content, validation::MAX_COMMENT_LENGTH);
+    }
+}
+
 pub fn comment_routes() -> Router<AppState> {
     Router::new()
         .route("/comments", post(create_comment))
@@ -44,166 +59,97 @@ pub fn comment_routes() -> Router<AppState> {
         )
 }
 
+// Uploads are supplementary; a failure to load them shouldn't fail the comment itself.
+async fn with_uploads(
+    upload_crud: &CommentFileUploadCrud,
+    comment: comment::Model,
+) -> CommentResponse {
+    let uploads = match upload_crud.find_uploads_by_comment_id(comment.id).await {
+        Ok(uploads) => uploads,
+        Err(e) => {
+            debug!("Error loading uploads for comment {}: {:?}", comment.id, e);
+            vec![]
+        }
+    };
+    CommentResponse { comment, uploads }
+}
+
 #[axum::debug_handler]
 async fn create_comment(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateCommentRequest>,
-) -> impl IntoResponse {
-    let user_id = app_state.user.as_ref().unwrap().id;
+    ValidatedJson(payload): ValidatedJson<CreateCommentRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
     let comment_crud = CommentCrud::new(app_state.clone());
     let upload_crud = CommentFileUploadCrud::new(app_state);
-    match comment_crud
+    let comment = comment_crud
         .create(payload.content, payload.issue_id, user_id)
-        .await
-    {
-        Ok(comment) => match upload_crud.find_uploads_by_comment_id(comment.id).await {
-            Ok(uploads) => Ok(Json(CommentResponse { comment, uploads })),
-            Err(e) => {
-                debug!("Error loading uploads for new comment: {:?}", e);
-                Ok(Json(CommentResponse {
-                    comment,
-                    uploads: vec![],
-                }))
-            }
-        },
-        Err(e) => {
-            debug!("Error creating comment: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(with_uploads(&upload_crud, comment).await))
 }
 
 #[axum::debug_handler]
 async fn get_comment(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let comment_crud = CommentCrud::new(app_state.clone());
     let upload_crud = CommentFileUploadCrud::new(app_state);
-    match comment_crud.find_by_id(id).await {
-        Ok(Some(comment)) => match upload_crud.find_uploads_by_comment_id(comment.id).await {
-            Ok(uploads) => Ok(Json(CommentResponse { comment, uploads })),
-            Err(e) => {
-                debug!("Error loading uploads for comment {}: {:?}", id, e);
-                Ok(Json(CommentResponse {
-                    comment,
-                    uploads: vec![],
-                }))
-            }
-        },
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            debug!("Error getting comment {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let comment = comment_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Comment"))?;
+    Ok(Json(with_uploads(&upload_crud, comment).await))
 }
 
 #[axum::debug_handler]
 async fn get_comments_by_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let comment_crud = CommentCrud::new(app_state.clone());
     let upload_crud = CommentFileUploadCrud::new(app_state);
-    match comment_crud.find_by_issue_id(id).await {
-        Ok(comments) => {
-            let mut enriched: Vec<CommentResponse> = Vec::with_capacity(comments.len());
-            for c in comments {
-                match upload_crud.find_uploads_by_comment_id(c.id).await {
-                    Ok(uploads) => enriched.push(CommentResponse {
-                        comment: c,
-                        uploads,
-                    }),
-                    Err(e) => {
-                        debug!("Error loading uploads for comment {}: {:?}", c.id, e);
-                        enriched.push(CommentResponse {
-                            comment: c,
-                            uploads: vec![],
-                        });
-                    }
-                }
-            }
-            Ok(Json(enriched))
-        }
-        Err(e) => {
-            debug!("Error getting comments for issue {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    let comments = comment_crud.find_by_issue_id(id).await?;
+    let mut enriched: Vec<CommentResponse> = Vec::with_capacity(comments.len());
+    for c in comments {
+        enriched.push(with_uploads(&upload_crud, c).await);
     }
+    Ok(Json(enriched))
 }
 
 #[axum::debug_handler]
 async fn get_comments_by_user(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let comment_crud = CommentCrud::new(app_state.clone());
     let upload_crud = CommentFileUploadCrud::new(app_state);
-    match comment_crud.find_by_user_id(id).await {
-        Ok(comments) => {
-            let mut enriched: Vec<CommentResponse> = Vec::with_capacity(comments.len());
-            for c in comments {
-                match upload_crud.find_uploads_by_comment_id(c.id).await {
-                    Ok(uploads) => enriched.push(CommentResponse {
-                        comment: c,
-                        uploads,
-                    }),
-                    Err(e) => {
-                        debug!("Error loading uploads for comment {}: {:?}", c.id, e);
-                        enriched.push(CommentResponse {
-                            comment: c,
-                            uploads: vec![],
-                        });
-                    }
-                }
-            }
-            Ok(Json(enriched))
-        }
-        Err(e) => {
-            debug!("Error getting comments for user {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    let comments = comment_crud.find_by_user_id(id).await?;
+    let mut enriched: Vec<CommentResponse> = Vec::with_capacity(comments.len());
+    for c in comments {
+        enriched.push(with_uploads(&upload_crud, c).await);
     }
+    Ok(Json(enriched))
 }
 
 #[axum::debug_handler]
 async fn update_comment(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateCommentRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateCommentRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let comment_crud = CommentCrud::new(app_state.clone());
     let upload_crud = CommentFileUploadCrud::new(app_state);
-    match comment_crud.update(id, payload.content).await {
-        Ok(comment) => match upload_crud.find_uploads_by_comment_id(comment.id).await {
-            Ok(uploads) => Ok(Json(CommentResponse { comment, uploads })),
-            Err(e) => {
-                debug!("Error loading uploads for comment {}: {:?}", id, e);
-                Ok(Json(CommentResponse {
-                    comment,
-                    uploads: vec![],
-                }))
-            }
-        },
-        Err(e) => {
-            debug!("Error updating comment {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let comment = comment_crud.update(id, payload.content).await?;
+    Ok(Json(with_uploads(&upload_crud, comment).await))
 }
 
 #[axum::debug_handler]
 async fn delete_comment(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<StatusCode, AppError> {
     let comment_crud = CommentCrud::new(app_state);
-    match comment_crud.delete(id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(e) => {
-            debug!("Error deleting comment {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    comment_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
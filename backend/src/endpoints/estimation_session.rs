@@ -0,0 +1,177 @@
+use crate::crud::estimation_session::{EstimateStats, EstimationSessionCrud, SESSION_OPEN};
+use crate::entities::{estimation_session, estimation_vote};
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
+use crate::AppState;
+use axum::Extension;
+use axum::{
+    extract::Path,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VoteRequest {
+    points: i32,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitEstimateRequest {
+    points: i32,
+}
+
+impl Validate for VoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.points("points", self.points);
+    }
+}
+
+impl Validate for CommitEstimateRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.points("points", self.points);
+    }
+}
+
+#[derive(Serialize)]
+pub struct VoteResponse {
+    user_id: i32,
+    // Hidden from everyone but the voter until the round is revealed
+    points: Option<i32>,
+}
+
+#[derive(Serialize)]
+pub struct EstimationSessionResponse {
+    #[serde(flatten)]
+    session: estimation_session::Model,
+    votes: Vec<VoteResponse>,
+    stats: Option<EstimateStats>,
+}
+
+pub fn estimation_session_routes() -> Router<AppState> {
+    Router::new()
+        .route("/issues/{id}/estimation-sessions", post(open_session))
+        .route(
+            "/issues/{id}/estimation-sessions",
+            get(get_sessions_by_issue),
+        )
+        .route("/estimation-sessions/{id}", get(get_session))
+        .route("/estimation-sessions/{id}", delete(cancel_session))
+        .route("/estimation-sessions/{id}/vote", put(cast_vote))
+        .route("/estimation-sessions/{id}/reveal", put(reveal_session))
+        .route("/estimation-sessions/{id}/commit", put(commit_session))
+}
+
+async fn to_response(
+    crud: &EstimationSessionCrud,
+    app_state: &AppState,
+    session: estimation_session::Model,
+    votes: Vec<estimation_vote::Model>,
+) -> Result<EstimationSessionResponse, AppError> {
+    let current_user_id = app_state.current_user()?.id;
+    let hidden = session.state == SESSION_OPEN;
+    let stats = if hidden {
+        None
+    } else {
+        crud.stats_for(&session, &votes).await?
+    };
+    let votes = votes
+        .into_iter()
+        .map(|vote| VoteResponse {
+            user_id: vote.user_id,
+            points: (!hidden || vote.user_id == current_user_id).then_some(vote.points),
+        })
+        .collect();
+    Ok(EstimationSessionResponse {
+        session,
+        votes,
+        stats,
+    })
+}
+
+#[axum::debug_handler]
+async fn open_session(
+    Extension(app_state): Extension<AppState>,
+    Path(issue_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    debug!("Opening estimation session for issue {}", issue_id);
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let session = crud.open(issue_id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, vec![]).await?))
+}
+
+#[axum::debug_handler]
+async fn get_sessions_by_issue(
+    Extension(app_state): Extension<AppState>,
+    Path(issue_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let sessions = crud.find_by_issue_id(issue_id).await?;
+    let mut responses = Vec::with_capacity(sessions.len());
+    for session in sessions {
+        let votes = crud.find_votes(session.id).await?;
+        responses.push(to_response(&crud, &app_state, session, votes).await?);
+    }
+    Ok(Json(responses))
+}
+
+#[axum::debug_handler]
+async fn get_session(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let session = crud.find_by_id(id).await?;
+    let votes = crud.find_votes(id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, votes).await?))
+}
+
+#[axum::debug_handler]
+async fn cast_vote(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<VoteRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    crud.cast_vote(id, payload.points).await?;
+    let session = crud.find_by_id(id).await?;
+    let votes = crud.find_votes(id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, votes).await?))
+}
+
+#[axum::debug_handler]
+async fn reveal_session(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let (session, votes) = crud.reveal(id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, votes).await?))
+}
+
+#[axum::debug_handler]
+async fn commit_session(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<CommitEstimateRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let session = crud.commit(id, payload.points).await?;
+    let votes = crud.find_votes(id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, votes).await?))
+}
+
+#[axum::debug_handler]
+async fn cancel_session(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = EstimationSessionCrud::new(app_state.clone());
+    let session = crud.cancel(id).await?;
+    let votes = crud.find_votes(id).await?;
+    Ok(Json(to_response(&crud, &app_state, session, votes).await?))
+}
@@ -0,0 +1,38 @@
+use crate::crud::domain_event::{DomainEventCrud, MAX_REPLAY};
+use crate::error::AppError;
+use crate::AppState;
+use axum::Extension;
+use axum::{extract::Query, response::IntoResponse, routing::get, Json, Router};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetEventsQuery {
+    #[serde(default)]
+    after: i64,
+    #[serde(default)]
+    limit: Option<u64>,
+}
+
+pub fn event_routes() -> Router<AppState> {
+    Router::new().route("/events", get(get_events))
+}
+
+// Lets a client that was offline catch up on the current project before going live again
+#[axum::debug_handler]
+async fn get_events(
+    Extension(app_state): Extension<AppState>,
+    Query(params): Query<GetEventsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
+    let user_id = app_state.current_user()?.id;
+    let events = DomainEventCrud::new(app_state.db)
+        .find_since(
+            params.after,
+            Some(&[project_id]),
+            Some(user_id),
+            params.limit.unwrap_or(MAX_REPLAY),
+        )
+        .await?;
+    Ok(Json(events))
+}
@@ -1,17 +1,54 @@
+use crate::content_sniff;
 use crate::crud::comment_file_upload::CommentFileUploadCrud;
-use crate::crud::file_upload::FileUploadCrud;
+use crate::crud::direct_upload::DirectUploadCrud;
+use crate::crud::file_upload::{spool, FileUploadCrud, SpooledFile};
 use crate::crud::issue::IssueCrud;
 use crate::crud::project_note::ProjectNoteCrud;
+use crate::crud::project_user::ProjectUserCrud;
+use crate::crud::upload_session::{NewUploadSession, UploadSessionCrud, UploadTarget};
+use crate::entities::{direct_upload, file_upload, upload_session};
 use crate::environment;
+use crate::error::AppError;
+use crate::signed_url::{self, SignedQuery};
 use crate::AppState;
-use axum::extract::{DefaultBodyLimit, Multipart, Path};
-use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
-use axum::http::{HeaderValue, StatusCode};
-use axum::response::IntoResponse;
+use axum::body::Body;
+use axum::extract::rejection::QueryRejection;
+use axum::extract::{DefaultBodyLimit, Multipart, Path, Query, State};
+use axum::http::header::{
+    CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, LOCATION,
+    X_CONTENT_TYPE_OPTIONS,
+};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
 use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
-use std::path::PathBuf;
-use tracing::{info, warn};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use tracing::warn;
+
+// Resumable upload headers, named as in tus
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateResumableUpload {
+    filename: String,
+    mime_type: String,
+    // Total length in bytes
+    size: i64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateDirectUpload {
+    filename: String,
+    mime_type: String,
+    size: i64,
+    // Hex SHA-256 of the file
+    sha256: String,
+}
 
 pub fn file_upload_routes() -> Router<AppState> {
     Router::new()
@@ -33,6 +70,28 @@ pub fn file_upload_routes() -> Router<AppState> {
                 .layer(DefaultBodyLimit::max(environment::max_upload_size_bytes())),
         )
         .route("/project-notes/{id}/uploads", get(list_for_project_note))
+        // Resumable uploads: create, then PATCH chunks until complete
+        .route(
+            "/issues/{id}/uploads/resumable",
+            post(create_resumable_for_issue),
+        )
+        .route(
+            "/project-notes/{id}/uploads/resumable",
+            post(create_resumable_for_project_note),
+        )
+        // Direct-to-S3 uploads: presign, PUT to S3, then finalize
+        .route("/issues/{id}/uploads/direct", post(create_direct_for_issue))
+        .route(
+            "/project-notes/{id}/uploads/direct",
+            post(create_direct_for_project_note),
+        )
+        .route("/uploads/direct/{id}/finalize", post(finalize_direct))
+        .route(
+            "/uploads/resumable/{id}",
+            get(resumable_status)
+                .patch(append_resumable)
+                .delete(cancel_resumable),
+        )
         // Comment attachments (associate existing file to a comment)
         .route("/comments/{id}/uploads", get(list_for_comment))
         .route(
@@ -49,6 +108,12 @@ pub fn file_upload_routes() -> Router<AppState> {
             "/uploads/assets/{id}/{filename}",
             get(download_upload_named),
         )
+        // Preview rendered by the thumbnail job
+        .route("/uploads/thumbnails/{id}", get(download_thumbnail))
+        // Storage integrity check for the current project
+        .route("/uploads/audit", get(audit_uploads))
+        // Uploads the virus scanner flagged
+        .route("/uploads/quarantine", get(list_quarantined))
         // Single upload actions
         .route("/uploads/{id}", get(download_upload).delete(delete_upload))
 }
@@ -58,129 +123,57 @@ async fn upload_for_issue(
     Extension(app_state): Extension<AppState>,
     Path(issue_id): Path<i32>,
     mut multipart: Multipart,
-) -> impl IntoResponse {
-    let user_id = app_state
-        .user
-        .as_ref()
-        .expect("user must be set by middleware")
-        .id;
-    let project_id = app_state
-        .project
-        .as_ref()
-        .expect("project must be set by middleware")
-        .id;
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
 
     // Verify issue belongs to current project
-    let issue_crud = IssueCrud::new(app_state.clone());
-    let issue = match issue_crud.find_by_id(issue_id).await {
-        Ok(Some(i)) => i,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error loading issue {}: {:?}", issue_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-    if issue.project_id != project_id {
-        // Anti-enumeration: pretend not found if not in this project
-        return Err(StatusCode::NOT_FOUND);
-    }
+    ensure_issue_in_project(&app_state, issue_id).await?;
 
     // Extract file field
-    let (bytes, original_filename, mime_type) =
-        match extract_file_from_multipart(&mut multipart).await {
-            Ok(t) => t,
-            Err(status) => return Err(status),
-        };
-
-    // Validate allowed MIME types and non-empty
-    if bytes.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if !is_allowed_mime(&mime_type) {
-        warn!("Rejected upload for disallowed MIME type: {}", mime_type);
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    let (file, original_filename, mime_type) = extract_file_from_multipart(&mut multipart).await?;
 
     let crud = FileUploadCrud::new(app_state);
-    match crud
-        .create_for_issue_from_bytes(issue_id, user_id, original_filename, mime_type, bytes)
-        .await
-    {
-        Ok(model) => Ok(Json(model)),
-        Err(e) => {
-            warn!("Error creating file upload for issue {}: {:?}", issue_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let model = crud
+        .create_for_issue(issue_id, user_id, original_filename, mime_type, file)
+        .await?;
+    Ok(Json(model))
 }
 
 #[axum::debug_handler]
 async fn list_for_comment(
     Extension(app_state): Extension<AppState>,
     Path(comment_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let crud = CommentFileUploadCrud::new(app_state);
-    match crud.find_by_comment_id(comment_id).await {
-        Ok(mappings) => Ok(Json(mappings)),
-        Err(e) => {
-            warn!("Error listing uploads for comment {}: {:?}", comment_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.find_by_comment_id(comment_id).await?))
 }
 
 #[axum::debug_handler]
 async fn attach_upload_to_comment(
     Extension(app_state): Extension<AppState>,
     Path((comment_id, file_upload_id)): Path<(i32, i32)>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let crud = CommentFileUploadCrud::new(app_state);
-    match crud.create(comment_id, file_upload_id).await {
-        Ok(mapping) => Ok(Json(mapping)),
-        Err(e) => {
-            warn!(
-                "Error attaching upload {} to comment {}: {:?}",
-                file_upload_id, comment_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.create(comment_id, file_upload_id).await?))
 }
 
 #[axum::debug_handler]
 async fn detach_upload_from_comment(
     Extension(app_state): Extension<AppState>,
     Path((comment_id, file_upload_id)): Path<(i32, i32)>,
-) -> impl IntoResponse {
+) -> Result<StatusCode, AppError> {
     let crud = CommentFileUploadCrud::new(app_state);
-    match crud.delete(comment_id, file_upload_id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(e) => {
-            warn!(
-                "Error detaching upload {} from comment {}: {:?}",
-                file_upload_id, comment_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    crud.delete(comment_id, file_upload_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[axum::debug_handler]
 async fn file_upload_attach_to_comment(
     Extension(app_state): Extension<AppState>,
     Path((file_upload_id, comment_id)): Path<(i32, i32)>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let crud = CommentFileUploadCrud::new(app_state);
-    match crud.create(comment_id, file_upload_id).await {
-        Ok(mapping) => Ok(Json(mapping)),
-        Err(e) => {
-            warn!(
-                "Error attaching upload {} to comment {} via uploads route: {:?}",
-                file_upload_id, comment_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.create(comment_id, file_upload_id).await?))
 }
 
 #[axum::debug_handler]
@@ -188,310 +181,550 @@ async fn upload_for_project_note(
     Extension(app_state): Extension<AppState>,
     Path(note_id): Path<i32>,
     mut multipart: Multipart,
-) -> impl IntoResponse {
-    let user_id = app_state
-        .user
-        .as_ref()
-        .expect("user must be set by middleware")
-        .id;
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
     // Ensure project is selected and note exists within it
-    let project_note_crud = ProjectNoteCrud::new(app_state.clone());
-    let _note = match project_note_crud.find_by_id(note_id).await {
-        Ok(Some(n)) => n,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error loading project note {}: {:?}", note_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-
-    let (bytes, original_filename, mime_type) =
-        match extract_file_from_multipart(&mut multipart).await {
-            Ok(t) => t,
-            Err(status) => return Err(status),
-        };
+    ensure_project_note_exists(&app_state, note_id).await?;
 
-    if bytes.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
-    }
-    if !is_allowed_mime(&mime_type) {
-        warn!("Rejected upload for disallowed MIME type: {}", mime_type);
-        return Err(StatusCode::BAD_REQUEST);
-    }
+    let (file, original_filename, mime_type) = extract_file_from_multipart(&mut multipart).await?;
 
     let crud = FileUploadCrud::new(app_state);
-    match crud
-        .create_for_project_note_from_bytes(note_id, user_id, original_filename, mime_type, bytes)
-        .await
-    {
-        Ok(model) => Ok(Json(model)),
-        Err(e) => {
-            warn!(
-                "Error creating file upload for project note {}: {:?}",
-                note_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let model = crud
+        .create_for_project_note(note_id, user_id, original_filename, mime_type, file)
+        .await?;
+    Ok(Json(model))
 }
 
 #[axum::debug_handler]
 async fn list_for_issue(
     Extension(app_state): Extension<AppState>,
     Path(issue_id): Path<i32>,
-) -> impl IntoResponse {
-    // Verify issue belongs to current project
-    let project_id = app_state
-        .project
-        .as_ref()
-        .expect("project must be set by middleware")
-        .id;
-    let issue_crud = IssueCrud::new(app_state.clone());
-    let issue = match issue_crud.find_by_id(issue_id).await {
-        Ok(Some(i)) => i,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error loading issue {} for list uploads: {:?}", issue_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-    if issue.project_id != project_id {
-        return Err(StatusCode::NOT_FOUND);
-    }
-
+) -> Result<impl IntoResponse, AppError> {
+    ensure_issue_in_project(&app_state, issue_id).await?;
     let crud = FileUploadCrud::new(app_state);
-    match crud.find_by_issue_id(issue_id).await {
-        Ok(uploads) => Ok(Json(uploads)),
-        Err(e) => {
-            warn!("Error listing uploads for issue {}: {:?}", issue_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.find_by_issue_id(issue_id).await?))
 }
 
 #[axum::debug_handler]
 async fn list_unattached_for_issue(
     Extension(app_state): Extension<AppState>,
     Path(issue_id): Path<i32>,
-) -> impl IntoResponse {
-    // Verify issue belongs to current project
-    let project_id = app_state
-        .project
-        .as_ref()
-        .expect("project must be set by middleware")
-        .id;
-    let issue_crud = IssueCrud::new(app_state.clone());
-    let issue = match issue_crud.find_by_id(issue_id).await {
-        Ok(Some(i)) => i,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!(
-                "Error loading issue {} for unattached uploads: {:?}",
-                issue_id, e
-            );
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-    if issue.project_id != project_id {
-        return Err(StatusCode::NOT_FOUND);
-    }
-
+) -> Result<impl IntoResponse, AppError> {
+    ensure_issue_in_project(&app_state, issue_id).await?;
     let crud = FileUploadCrud::new(app_state);
-    match crud.find_unattached_by_issue_id(issue_id).await {
-        Ok(uploads) => Ok(Json(uploads)),
-        Err(e) => {
-            warn!(
-                "Error listing unattached uploads for issue {}: {:?}",
-                issue_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.find_unattached_by_issue_id(issue_id).await?))
 }
 
 #[axum::debug_handler]
 async fn list_for_project_note(
     Extension(app_state): Extension<AppState>,
     Path(note_id): Path<i32>,
-) -> impl IntoResponse {
-    let project_note_crud = ProjectNoteCrud::new(app_state.clone());
-    let _note = match project_note_crud.find_by_id(note_id).await {
-        Ok(Some(n)) => n,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!(
-                "Error loading project note {} for list uploads: {:?}",
-                note_id, e
-            );
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-
+) -> Result<impl IntoResponse, AppError> {
+    ensure_project_note_exists(&app_state, note_id).await?;
     let crud = FileUploadCrud::new(app_state);
-    match crud.find_by_project_note_id(note_id).await {
-        Ok(uploads) => Ok(Json(uploads)),
-        Err(e) => {
-            warn!(
-                "Error listing uploads for project note {}: {:?}",
-                note_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(crud.find_by_project_note_id(note_id).await?))
 }
 
 #[axum::debug_handler]
 async fn download_upload(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
-    // Middleware enforces auth and project selection
-    let project_id = app_state
-        .project
-        .as_ref()
-        .expect("project must be set by middleware")
-        .id;
-
+) -> Result<impl IntoResponse, AppError> {
     let crud = FileUploadCrud::new(app_state.clone());
-    let upload = match crud.find_by_id(id).await {
-        Ok(Some(u)) => u,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error loading upload {}: {:?}", id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+    let upload = crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File upload"))?;
 
     // Determine owning project via association
     if let Some(issue_id) = upload.issue_id {
-        let issue_crud = IssueCrud::new(app_state.clone());
-        match issue_crud.find_by_id(issue_id).await {
-            Ok(Some(issue)) if issue.project_id == project_id => {}
-            Ok(Some(_)) => return Err(StatusCode::NOT_FOUND), // different project
-            Ok(None) => return Err(StatusCode::NOT_FOUND),
-            Err(e) => {
-                info!("Error checking issue for upload {}: {:?}", id, e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            }
-        }
+        ensure_issue_in_project(&app_state, issue_id).await?;
     } else if let Some(note_id) = upload.project_note_id {
-        let note_crud = ProjectNoteCrud::new(app_state.clone());
-        match note_crud.find_by_id(note_id).await {
-            Ok(Some(_)) => {}
-            Ok(None) => return Err(StatusCode::NOT_FOUND),
-            Err(e) => {
-                info!("Error checking project note for upload {}: {:?}", id, e);
-                return Err(StatusCode::INTERNAL_SERVER_ERROR);
-            }
-        }
+        ensure_project_note_exists(&app_state, note_id).await?;
     } else {
         // Invalid data state
         warn!("Upload {} has no association", id);
-        return Err(StatusCode::NOT_FOUND);
+        return Err(AppError::not_found("File upload"));
     }
 
-    // Only local backend download is implemented here per current store implementation
+    store_download(&crud, &upload).await
+}
+
+// Local content is sent by the backend; S3 objects are fetched from a presigned link
+async fn store_download(
+    crud: &FileUploadCrud,
+    upload: &file_upload::Model,
+) -> Result<Response, AppError> {
     match environment::file_store_scheme() {
-        "local" => {
-            let base = match environment::base_file_path() {
-                Some(p) => p.clone(),
-                None => return Err(StatusCode::INTERNAL_SERVER_ERROR),
-            };
-            let full_path: PathBuf = base.join(&upload.path);
-            match tokio::fs::read(full_path).await {
-                Ok(bytes) => {
-                    let ct = HeaderValue::from_str(&upload.mime_type)
-                        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
-                    // Suggest inline display with a sensible filename
-                    let cd_val = format!("inline; filename=\"{}\"", upload.final_filename);
-                    let cd = HeaderValue::from_str(&cd_val)
-                        .unwrap_or(HeaderValue::from_static("inline"));
-                    Ok(([(CONTENT_TYPE, ct), (CONTENT_DISPOSITION, cd)], bytes).into_response())
-                }
-                Err(e) => {
-                    warn!("Failed to read local file for upload {}: {:?}", id, e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            }
-        }
+        "local" => local_download(crud, upload).await,
         "aws" => {
-            // AWS presign not implemented in current FileStore; return 501 for now
-            Err(StatusCode::NOT_IMPLEMENTED)
+            let url = crud.generate_browser_url(upload).await?;
+            let location = HeaderValue::from_str(&url)
+                .map_err(|e| AppError::Internal(format!("invalid presigned URL: {}", e)))?;
+            // The presigned link expires, so the redirect to it must not be cached
+            Ok((
+                StatusCode::TEMPORARY_REDIRECT,
+                [
+                    (LOCATION, location),
+                    (CACHE_CONTROL, HeaderValue::from_static("no-store")),
+                ],
+            )
+                .into_response())
         }
-        _ => Err(StatusCode::INTERNAL_SERVER_ERROR),
+        other => Err(AppError::Internal(format!(
+            "unsupported FILE_STORE_SCHEME: {}",
+            other
+        ))),
+    }
+}
+
+// Local store content, sent so that the browser never runs it as part of the app
+async fn local_download(
+    crud: &FileUploadCrud,
+    upload: &file_upload::Model,
+) -> Result<Response, AppError> {
+    let bytes = crud.read_verified(upload).await?;
+    let ct = HeaderValue::from_str(&upload.mime_type)
+        .unwrap_or(HeaderValue::from_static("application/octet-stream"));
+    let mut response = ([(CONTENT_TYPE, ct)], bytes).into_response();
+    let headers = response.headers_mut();
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    // Markup that could run script is only ever downloaded, and sandboxed if opened anyway;
+    // anything else is suggested for inline display with a sensible filename
+    let active = content_sniff::is_active_content(&upload.mime_type);
+    let disposition = if active { "attachment" } else { "inline" };
+    let cd_val = format!("{}; filename=\"{}\"", disposition, upload.final_filename);
+    let cd = HeaderValue::from_str(&cd_val).unwrap_or(HeaderValue::from_static("attachment"));
+    headers.insert(CONTENT_DISPOSITION, cd);
+    if active {
+        headers.insert(
+            CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("default-src 'none'; sandbox"),
+        );
+    }
+    Ok(response)
+}
+
+// Signed links stand in for the caller's token, so they are checked before anything is looked up
+fn check_signed_link(kind: &str, id: i32, query: &SignedQuery) -> Result<(), AppError> {
+    if signed_url::verify(kind, id, query, Utc::now().timestamp()) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(
+            "This link has expired or is not valid".to_string(),
+        ))
     }
 }
 
 #[axum::debug_handler]
 async fn download_upload_named(
-    Extension(app_state): Extension<AppState>,
+    State(app_state): State<AppState>,
     Path((id, _filename)): Path<(i32, String)>,
-) -> impl IntoResponse {
-    // Reuse the same logic as download_upload by delegating to it semantically
-    // This duplication ensures predictable handler signatures for Axum
-    download_upload(Extension(app_state), Path(id)).await
+    query: Result<Query<SignedQuery>, QueryRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(query) =
+        query.map_err(|_| AppError::Forbidden("This link is not signed".to_string()))?;
+    check_signed_link(signed_url::ASSET, id, &query)?;
+    let crud = FileUploadCrud::new(app_state);
+    let upload = crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File upload"))?;
+    store_download(&crud, &upload).await
+}
+
+#[axum::debug_handler]
+async fn download_thumbnail(
+    State(app_state): State<AppState>,
+    Path(id): Path<i32>,
+    query: Result<Query<SignedQuery>, QueryRejection>,
+) -> Result<impl IntoResponse, AppError> {
+    let Query(query) =
+        query.map_err(|_| AppError::Forbidden("This link is not signed".to_string()))?;
+    check_signed_link(signed_url::THUMBNAIL, id, &query)?;
+    let crud = FileUploadCrud::new(app_state);
+    let upload = crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("File upload"))?;
+
+    let bytes = crud.read_thumbnail(&upload).await?;
+    // The preview never changes once rendered, but the link it came from stops working
+    let max_age = (query.expires - Utc::now().timestamp()).max(0);
+    let cache_control = HeaderValue::from_str(&format!("private, max-age={}", max_age))
+        .unwrap_or(HeaderValue::from_static("no-store"));
+    Ok((
+        [
+            (CONTENT_TYPE, HeaderValue::from_static("image/png")),
+            (CACHE_CONTROL, cache_control),
+        ],
+        bytes,
+    )
+        .into_response())
 }
 
 #[axum::debug_handler]
 async fn delete_upload(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
-    let project_id = app_state
-        .project
-        .as_ref()
-        .expect("project must be set by middleware")
-        .id;
-
+) -> Result<StatusCode, AppError> {
     // Anti-enumeration: check association before deleting
     let crud = FileUploadCrud::new(app_state.clone());
-    let upload = match crud.find_by_id(id).await {
-        Ok(Some(u)) => u,
-        Ok(None) => return Ok(StatusCode::NO_CONTENT), // Idempotent per spec
-        Err(e) => {
-            info!("Error loading upload {} for delete: {:?}", id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
+    let upload = match crud.find_by_id(id).await? {
+        Some(u) => u,
+        None => return Ok(StatusCode::NO_CONTENT), // Idempotent per spec
     };
 
     // If associated to issue, verify same project; if project note, verify it exists in project
     if let Some(issue_id) = upload.issue_id {
-        let issue_crud = IssueCrud::new(app_state.clone());
-        if let Ok(Some(issue)) = issue_crud.find_by_id(issue_id).await {
-            if issue.project_id != project_id {
-                return Err(StatusCode::NOT_FOUND);
-            }
-        } else {
-            // If owning resource vanished, treat as not found
-            return Err(StatusCode::NOT_FOUND);
-        }
+        ensure_issue_in_project(&app_state, issue_id).await?;
     } else if let Some(note_id) = upload.project_note_id {
-        let note_crud = ProjectNoteCrud::new(app_state.clone());
-        if let Ok(Some(_)) = note_crud.find_by_id(note_id).await {
-            // ok, already project-filtered
-        } else {
-            return Err(StatusCode::NOT_FOUND);
-        }
+        ensure_project_note_exists(&app_state, note_id).await?;
+    }
+
+    crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn create_resumable_for_issue(
+    Extension(app_state): Extension<AppState>,
+    Path(issue_id): Path<i32>,
+    Json(payload): Json<CreateResumableUpload>,
+) -> Result<Response, AppError> {
+    ensure_issue_in_project(&app_state, issue_id).await?;
+    create_resumable(&app_state, UploadTarget::Issue(issue_id), payload).await
+}
+
+#[axum::debug_handler]
+async fn create_resumable_for_project_note(
+    Extension(app_state): Extension<AppState>,
+    Path(note_id): Path<i32>,
+    Json(payload): Json<CreateResumableUpload>,
+) -> Result<Response, AppError> {
+    ensure_project_note_exists(&app_state, note_id).await?;
+    create_resumable(&app_state, UploadTarget::ProjectNote(note_id), payload).await
+}
+
+#[axum::debug_handler]
+async fn resumable_status(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<String>,
+) -> Result<Response, AppError> {
+    let session = find_resumable(&app_state, &id).await?;
+    Ok((
+        [
+            (UPLOAD_OFFSET, session.offset_bytes.to_string()),
+            (UPLOAD_LENGTH, session.size_bytes.to_string()),
+            (CACHE_CONTROL, "no-store".to_string()),
+        ],
+        Json(session),
+    )
+        .into_response())
+}
+
+#[axum::debug_handler]
+async fn append_resumable(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, AppError> {
+    let session = find_resumable(&app_state, &id).await?;
+    let offset = headers
+        .get(UPLOAD_OFFSET)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<i64>().ok())
+        .ok_or_else(|| AppError::BadRequest("Upload-Offset header is required".to_string()))?;
+
+    let sessions = UploadSessionCrud::new(app_state.db.clone());
+    let session = sessions
+        .append(&session, offset, body.into_data_stream())
+        .await?;
+    let offset_header = [(UPLOAD_OFFSET, session.offset_bytes.to_string())];
+    if session.offset_bytes < session.size_bytes {
+        return Ok((StatusCode::NO_CONTENT, offset_header).into_response());
     }
 
-    match crud.delete(id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
+    // The last chunk is in: store it like any other upload
+    let (lease, file) = sessions.claim_completed(&session).await?;
+    let model = match complete_resumable(app_state, &session, file).await {
+        Ok(model) => model,
         Err(e) => {
-            warn!("Error deleting upload {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            // Leave the session in place so the final request can be retried
+            if let Err(release_err) = sessions.release_completed(&session.id, &lease).await {
+                warn!(
+                    "Failed to release completion of upload {}: {}",
+                    session.id, release_err
+                );
+            }
+            return Err(e);
         }
+    };
+    sessions.finish_completed(&session.id, &lease).await?;
+    Ok((StatusCode::CREATED, offset_header, Json(model)).into_response())
+}
+
+#[axum::debug_handler]
+async fn cancel_resumable(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let session = find_resumable(&app_state, &id).await?;
+    UploadSessionCrud::new(app_state.db.clone())
+        .delete(&session.id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn create_direct_for_issue(
+    Extension(app_state): Extension<AppState>,
+    Path(issue_id): Path<i32>,
+    Json(payload): Json<CreateDirectUpload>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_issue_in_project(&app_state, issue_id).await?;
+    create_direct(app_state, UploadTarget::Issue(issue_id), payload).await
+}
+
+#[axum::debug_handler]
+async fn create_direct_for_project_note(
+    Extension(app_state): Extension<AppState>,
+    Path(note_id): Path<i32>,
+    Json(payload): Json<CreateDirectUpload>,
+) -> Result<impl IntoResponse, AppError> {
+    ensure_project_note_exists(&app_state, note_id).await?;
+    create_direct(app_state, UploadTarget::ProjectNote(note_id), payload).await
+}
+
+#[axum::debug_handler]
+async fn finalize_direct(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+    let uploads = DirectUploadCrud::new(app_state.db.clone());
+    let upload = uploads
+        .find_for_user(&id, user_id, project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Upload"))?;
+    ensure_target_in_project(&app_state, &upload).await?;
+
+    let model = FileUploadCrud::new(app_state)
+        .finalize_direct_upload(&upload)
+        .await?;
+    uploads.delete(&upload.id).await?;
+    Ok((StatusCode::CREATED, Json(model)))
+}
+
+#[axum::debug_handler]
+async fn audit_uploads(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+    // The audit reads every stored object, so only the project owner may run it
+    let is_owner = ProjectUserCrud::new(app_state.clone())
+        .is_project_owner(user_id, project_id)
+        .await?;
+    if !is_owner {
+        return Err(AppError::Forbidden(
+            "Only the project owner can audit file storage".to_string(),
+        ));
     }
+    let crud = FileUploadCrud::new(app_state);
+    Ok(Json(crud.audit(project_id).await?))
+}
+
+#[axum::debug_handler]
+async fn list_quarantined(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+    let is_owner = ProjectUserCrud::new(app_state.clone())
+        .is_project_owner(user_id, project_id)
+        .await?;
+    if !is_owner {
+        return Err(AppError::Forbidden(
+            "Only the project owner can see quarantined uploads".to_string(),
+        ));
+    }
+    let crud = FileUploadCrud::new(app_state);
+    Ok(Json(crud.find_quarantined(project_id).await?))
 }
 
 // ---------------- Helpers ----------------
 
+// Anti-enumeration: an issue outside the selected project is reported as not found
+async fn ensure_issue_in_project(app_state: &AppState, issue_id: i32) -> Result<(), AppError> {
+    let project_id = app_state.current_project()?.id;
+    let issue_crud = IssueCrud::new(app_state.clone());
+    match issue_crud.find_by_id(issue_id).await? {
+        Some(issue) if issue.project_id == project_id => Ok(()),
+        _ => Err(AppError::not_found("Issue")),
+    }
+}
+
+// Project notes are already filtered by the selected project
+async fn ensure_project_note_exists(app_state: &AppState, note_id: i32) -> Result<(), AppError> {
+    let note_crud = ProjectNoteCrud::new(app_state.clone());
+    note_crud
+        .find_by_id(note_id)
+        .await?
+        .map(|_| ())
+        .ok_or_else(|| AppError::not_found("Project note"))
+}
+
+async fn create_resumable(
+    app_state: &AppState,
+    target: UploadTarget,
+    payload: CreateResumableUpload,
+) -> Result<Response, AppError> {
+    validate_mime_type(&payload.mime_type)?;
+    let session = UploadSessionCrud::new(app_state.db.clone())
+        .create(NewUploadSession {
+            project_id: app_state.current_project()?.id,
+            target,
+            uploader_user_id: app_state.current_user()?.id,
+            original_filename: payload.filename,
+            mime_type: payload.mime_type,
+            size_bytes: payload.size,
+        })
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        [
+            (LOCATION, format!("/api/uploads/resumable/{}", session.id)),
+            (UPLOAD_OFFSET, "0".to_string()),
+        ],
+        Json(session),
+    )
+        .into_response())
+}
+
+async fn create_direct(
+    app_state: AppState,
+    target: UploadTarget,
+    payload: CreateDirectUpload,
+) -> Result<impl IntoResponse, AppError> {
+    if environment::file_store_scheme() != "aws" {
+        return Err(AppError::BadRequest(
+            "Direct uploads need FILE_STORE_SCHEME=aws; upload through the API instead".to_string(),
+        ));
+    }
+    validate_mime_type(&payload.mime_type)?;
+    let upload = DirectUploadCrud::new(app_state.db.clone())
+        .create(
+            NewUploadSession {
+                project_id: app_state.current_project()?.id,
+                target,
+                uploader_user_id: app_state.current_user()?.id,
+                original_filename: payload.filename,
+                mime_type: payload.mime_type,
+                size_bytes: payload.size,
+            },
+            payload.sha256,
+        )
+        .await?;
+    let presigned = FileUploadCrud::new(app_state)
+        .presign_direct_upload(&upload)
+        .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": upload.id,
+            "uploadRequired": presigned.is_some(),
+            "upload": presigned,
+            "finalizeUrl": format!("/api/uploads/direct/{}/finalize", upload.id),
+            "expiresAt": upload.expires_at,
+        })),
+    ))
+}
+
+// The issue or note may have been deleted or moved since the upload started
+async fn ensure_target_in_project(
+    app_state: &AppState,
+    upload: &direct_upload::Model,
+) -> Result<(), AppError> {
+    match (upload.issue_id, upload.project_note_id) {
+        (Some(issue_id), _) => ensure_issue_in_project(app_state, issue_id).await,
+        (None, Some(note_id)) => ensure_project_note_exists(app_state, note_id).await,
+        (None, None) => Err(AppError::Internal(format!(
+            "upload {} has no association",
+            upload.id
+        ))),
+    }
+}
+
+// Another user's upload, or one in another project, is reported as not found
+async fn find_resumable(app_state: &AppState, id: &str) -> Result<upload_session::Model, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+    UploadSessionCrud::new(app_state.db.clone())
+        .find_for_user(id, user_id, project_id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Upload"))
+}
+
+async fn complete_resumable(
+    app_state: AppState,
+    session: &upload_session::Model,
+    file: SpooledFile,
+) -> Result<file_upload::Model, AppError> {
+    let crud = FileUploadCrud::new(app_state.clone());
+    let filename = session.original_filename.clone();
+    let mime_type = session.mime_type.clone();
+    match (session.issue_id, session.project_note_id) {
+        (Some(issue_id), _) => {
+            ensure_issue_in_project(&app_state, issue_id).await?;
+            crud.create_for_issue(
+                issue_id,
+                session.uploader_user_id,
+                filename,
+                mime_type,
+                file,
+            )
+            .await
+        }
+        (None, Some(note_id)) => {
+            ensure_project_note_exists(&app_state, note_id).await?;
+            crud.create_for_project_note(
+                note_id,
+                session.uploader_user_id,
+                filename,
+                mime_type,
+                file,
+            )
+            .await
+        }
+        (None, None) => Err(AppError::Internal(format!(
+            "upload {} has no association",
+            session.id
+        ))),
+    }
+}
+
+fn validate_mime_type(mime_type: &str) -> Result<(), AppError> {
+    if !is_allowed_mime(mime_type) {
+        warn!("Rejected upload for disallowed MIME type: {}", mime_type);
+        return Err(AppError::invalid(
+            "file",
+            format!("content type {} is not allowed", mime_type),
+        ));
+    }
+    Ok(())
+}
+
+// Streams the "file" field to a temp file rather than collecting it in memory
 async fn extract_file_from_multipart(
     multipart: &mut Multipart,
-) -> Result<(Vec<u8>, String, String), StatusCode> {
+) -> Result<(SpooledFile, String, String), AppError> {
     while let Some(field) = multipart
         .next_field()
         .await
-        .map_err(|_| StatusCode::BAD_REQUEST)?
+        .map_err(|e| AppError::BadRequest(format!("Invalid multipart body: {}", e)))?
     {
         let name = field.name().map(|s| s.to_string());
         if name.as_deref() == Some("file") {
@@ -503,15 +736,15 @@ async fn extract_file_from_multipart(
                 .content_type()
                 .map(|s| s.to_string())
                 .unwrap_or_else(|| "application/octet-stream".to_string());
-            let bytes = field
-                .bytes()
-                .await
-                .map_err(|_| StatusCode::BAD_REQUEST)?
-                .to_vec();
-            return Ok((bytes, original_filename, mime_type));
+            validate_mime_type(&mime_type)?;
+            let file = spool(field).await?;
+            if file.size_bytes == 0 {
+                return Err(AppError::invalid("file", "must not be empty"));
+            }
+            return Ok((file, original_filename, mime_type));
         }
     }
-    Err(StatusCode::BAD_REQUEST)
+    Err(AppError::invalid("file", "is required"))
 }
 
 fn is_allowed_mime(mime: &str) -> bool {
@@ -530,3 +763,150 @@ fn is_allowed_mime(mime: &str) -> bool {
         "application" | "audio" | "font" | "image" | "model" | "text" | "video"
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::event_broadcaster::EventSender;
+    use crate::entities::{issue, project_note};
+    use sea_orm::{ConnectionTrait, Database, DatabaseConnection, Schema};
+    use std::sync::Arc;
+
+    fn upload(mime_type: &str, final_filename: &str) -> file_upload::Model {
+        let now = Utc::now().into();
+        file_upload::Model {
+            id: 7,
+            issue_id: Some(1),
+            project_note_id: None,
+            uploader_user_id: 1,
+            original_filename: final_filename.to_string(),
+            final_filename: final_filename.to_string(),
+            path: "sha256/ab/cd/abcd".to_string(),
+            full_url: None,
+            mime_type: mime_type.to_string(),
+            size_bytes: 4,
+            sha256: None,
+            thumbnail_path: None,
+            thumbnail_url: None,
+            uploaded_at: now,
+        }
+    }
+
+    // Only these tests read the store settings, and they all point them at the same S3;
+    // presigning is done locally and never reaches the endpoint
+    fn use_s3_store() {
+        for (key, value) in [
+            ("FILE_STORE_SCHEME", "aws"),
+            ("S3_BUCKET", "attachments"),
+            ("AWS_REGION", "eu-central-1"),
+            ("AWS_ACCESS_KEY_ID", "test"),
+            ("AWS_SECRET_ACCESS_KEY", "test"),
+            ("S3_ENDPOINT_URL", "http://127.0.0.1:9"),
+            ("S3_FORCE_PATH_STYLE", "true"),
+        ] {
+            std::env::set_var(key, value);
+        }
+    }
+
+    // Under the S3 store, both the authenticated and the signed download routes redirect
+    // to a presigned link instead of reading the object through the backend
+    #[tokio::test]
+    async fn test_downloads_redirect_to_presigned_s3_links() {
+        use_s3_store();
+        let (tx, _) = tokio::sync::broadcast::channel(1);
+        let tx: Arc<EventSender> = Arc::new(tx);
+        let crud = FileUploadCrud::new(AppState {
+            db: DatabaseConnection::Disconnected,
+            tx,
+            user: None,
+            project: None,
+            worker: None,
+        });
+
+        let response = store_download(&crud, &upload("image/png", "a.png"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::TEMPORARY_REDIRECT);
+        assert_eq!(response.headers()[CACHE_CONTROL], "no-store");
+        let location = response.headers()[LOCATION].to_str().unwrap();
+        assert!(location.starts_with("http://127.0.0.1:9/attachments/sha256/ab/cd/abcd?"));
+        assert!(location.contains("X-Amz-Signature="));
+        assert!(!location.contains("response-content-disposition"));
+
+        // Markup is still downloaded rather than opened from the bucket's origin
+        let response = store_download(&crud, &upload("text/html", "page.html"))
+            .await
+            .unwrap();
+        let location = response.headers()[LOCATION].to_str().unwrap();
+        assert!(location.contains("response-content-disposition=attachment"));
+    }
+
+    // Content another project already has is still sent, to the upload's own key, or a
+    // client could attach that file knowing only its checksum
+    #[tokio::test]
+    async fn test_direct_upload_of_content_stored_elsewhere_needs_the_bytes() {
+        use_s3_store();
+        let db = Database::connect("sqlite::memory:").await.unwrap();
+        let backend = db.get_database_backend();
+        let schema = Schema::new(backend);
+        db.execute_unprepared("PRAGMA foreign_keys = OFF")
+            .await
+            .unwrap();
+        for table in [
+            schema.create_table_from_entity(issue::Entity),
+            schema.create_table_from_entity(project_note::Entity),
+            schema.create_table_from_entity(file_upload::Entity),
+        ] {
+            db.execute(backend.build(&table)).await.unwrap();
+        }
+        let sha256 = "ab".repeat(32);
+        db.execute_unprepared(
+            "INSERT INTO issue (id, title, priority, status, is_icebox, work_type, project_id, \
+             created_by_id, lock_version, created_at, updated_at) \
+             VALUES (1, 'Secret', 1, 0, 0, 1, 1, 1, 0, '2026-10-19T00:00:00Z', '2026-10-19T00:00:00Z')",
+        )
+        .await
+        .unwrap();
+        db.execute_unprepared(&format!(
+            "INSERT INTO file_upload (issue_id, uploader_user_id, original_filename, final_filename, \
+             path, mime_type, size_bytes, uploaded_at, sha256) \
+             VALUES (1, 1, 'secret.png', 'x.png', 'sha256/ab/ab/{0}', 'image/png', 4, \
+             '2026-10-19T00:00:00Z', '{0}')",
+            sha256
+        ))
+        .await
+        .unwrap();
+
+        let (tx, _) = tokio::sync::broadcast::channel(1);
+        let tx: Arc<EventSender> = Arc::new(tx);
+        let crud = FileUploadCrud::new(AppState {
+            db,
+            tx,
+            user: None,
+            project: None,
+            worker: None,
+        });
+        let now = Utc::now();
+        let upload = direct_upload::Model {
+            id: "elsewhere".to_string(),
+            project_id: 2,
+            issue_id: Some(2),
+            project_note_id: None,
+            uploader_user_id: 2,
+            original_filename: "guess.png".to_string(),
+            mime_type: "image/png".to_string(),
+            size_bytes: 4,
+            sha256,
+            created_at: now.into(),
+            expires_at: now.into(),
+        };
+        let presigned = crud
+            .presign_direct_upload(&upload)
+            .await
+            .unwrap()
+            .expect("the file must be uploaded");
+        assert!(presigned
+            .url
+            .starts_with("http://127.0.0.1:9/attachments/direct/elsewhere?"));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::crud::import_export::ImportExportCrud;
+use crate::error::AppError;
 use crate::AppState;
 use axum::{
     http::StatusCode,
@@ -7,7 +8,6 @@ use axum::{
     Extension, Json, Router,
 };
 use std::collections::HashMap;
-use tracing::info;
 
 pub fn import_export_routes() -> Router<AppState> {
     Router::new()
@@ -16,30 +16,19 @@ pub fn import_export_routes() -> Router<AppState> {
 }
 
 #[axum::debug_handler]
-async fn export_data(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
+async fn export_data(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
     let import_export_crud = ImportExportCrud::new(app_state);
-
-    match import_export_crud.export_all_data().await {
-        Ok(data) => Ok(Json(data)),
-        Err(e) => {
-            info!("Error exporting data: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(import_export_crud.export_all_data().await?))
 }
 
 #[axum::debug_handler]
 async fn import_data(
     Extension(app_state): Extension<AppState>,
     Json(payload): Json<HashMap<String, Vec<serde_json::Value>>>,
-) -> Result<StatusCode, StatusCode> {
+) -> Result<StatusCode, AppError> {
     let import_export_crud = ImportExportCrud::new(app_state);
-
-    match import_export_crud.import_data(payload).await {
-        Ok(_) => Ok(StatusCode::OK),
-        Err(e) => {
-            info!("Error importing data: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    import_export_crud.import_data(payload).await?;
+    Ok(StatusCode::OK)
 }
@@ -1,8 +1,12 @@
 use crate::crud::issue::IssueCrud;
+use crate::crud::recurrence;
 use crate::crud::status::{
     STATUS_ACCEPTED, STATUS_COMPLETED, STATUS_DELIVERED, STATUS_IN_PROGRESS, STATUS_REJECTED,
     STATUS_UNSTARTED,
 };
+use crate::entities::issue;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -14,7 +18,7 @@ use axum::{
 };
 use sea_orm::entity::prelude::*;
 use serde::Deserialize;
-use tracing::{debug, info, warn};
+use tracing::{debug, info};
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -26,6 +30,7 @@ pub struct CreateIssueRequest {
     is_icebox: bool,
     work_type: i32,
     target_release_at: Option<DateTimeWithTimeZone>,
+    recurrence: Option<String>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -48,12 +53,75 @@ pub struct UpdateIssueRequest {
     pub work_type: Option<i32>,
     #[serde(default)]
     pub target_release_at: Option<DateTimeWithTimeZone>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    pub recurrence: Option<Option<String>>,
 }
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct BulkPriorityUpdate {
     issue_priorities: Vec<(i32, i32)>, // (issue_id, new_priority)
 }
+impl Validate for CreateIssueRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title, validation::MAX_TITLE_LENGTH)
+            .non_negative("priority", self.priority)
+            .work_type("workType", self.work_type);
+        if let Some(description) = &self.description {
+            v.max_length(
+                "description",
+                description,
+                validation::MAX_DESCRIPTION_LENGTH,
+            );
+        }
+        if let Some(Some(points)) = self.points {
+            v.points("points", points);
+        }
+        if let Some(rule) = &self.recurrence {
+            v.recurrence("recurrence", rule);
+            if !recurrence::can_recur(self.work_type) {
+                v.error("recurrence", "only reminders and chores can recur");
+            }
+        }
+    }
+}
+
+impl Validate for UpdateIssueRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("title", self.title.as_ref(), validation::MAX_TITLE_LENGTH);
+        if let Some(description) = &self.description {
+            v.max_length(
+                "description",
+                description,
+                validation::MAX_DESCRIPTION_LENGTH,
+            );
+        }
+        if let Some(priority) = self.priority {
+            v.non_negative("priority", priority);
+        }
+        if let Some(Some(points)) = self.points {
+            v.points("points", points);
+        }
+        if let Some(status) = self.status {
+            v.status("status", status);
+        }
+        if let Some(work_type) = self.work_type {
+            v.work_type("workType", work_type);
+        }
+        if let Some(Some(rule)) = &self.recurrence {
+            v.recurrence("recurrence", rule);
+        }
+    }
+}
+
+impl Validate for BulkPriorityUpdate {
+    fn validate(&self, v: &mut Validator) {
+        for (index, (issue_id, priority)) in self.issue_priorities.iter().enumerate() {
+            let field = format!("issuePriorities[{}]", index);
+            v.id(&field, *issue_id).non_negative(&field, *priority);
+        }
+    }
+}
+
 pub fn issue_routes() -> Router<AppState> {
     Router::new()
         .route("/issues", post(create_issue))
@@ -82,7 +150,7 @@ pub fn issue_routes() -> Router<AppState> {
 async fn get_issues_by_user(
     Extension(app_state): Extension<AppState>,
     Path(user_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let project_id = match app_state.project.as_ref() {
         Some(project) => project.id,
         None => {
@@ -96,31 +164,21 @@ async fn get_issues_by_user(
         }
     };
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_by_user_id(project_id, user_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            info!("Error getting issues for user {}: {:?}", user_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(
+        issue_crud.find_all_by_user_id(project_id, user_id).await?,
+    ))
 }
 #[axum::debug_handler]
 pub async fn create_issue(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateIssueRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateIssueRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating issue");
-    let user_id = match app_state.user.as_ref() {
-        Some(user) => user.id,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
 
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud
+    let issue = issue_crud
         .create(
             payload.title,
             payload.description,
@@ -133,76 +191,53 @@ pub async fn create_issue(
             payload.target_release_at,
             user_id,
         )
-        .await
-    {
-        Ok(issue) => Ok(Json(issue)),
-        Err(e) => {
-            warn!("Error creating issue: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    let issue = match payload.recurrence {
+        Some(rule) => issue_crud.set_recurrence(issue.id, Some(rule)).await?,
+        None => issue,
+    };
+    Ok(Json(issue))
 }
 #[axum::debug_handler]
 async fn get_all_issues_for_backlog(
     Extension(app_state): Extension<AppState>,
-) -> impl IntoResponse {
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_for_backlog(project_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            warn!("Error getting all issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_crud.find_all_for_backlog(project_id).await?))
 }
 #[axum::debug_handler]
-async fn get_issues_for_me(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let user_id = match app_state.user.as_ref() {
-        Some(user) => user.id,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+async fn get_issues_for_me(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_by_user_id(project_id, user_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            warn!("Error getting all issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(
+        issue_crud.find_all_by_user_id(project_id, user_id).await?,
+    ))
 }
 #[axum::debug_handler]
 async fn get_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_by_id(id).await {
-        Ok(Some(issue)) => Ok(Json(issue)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error getting issue {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let issue = issue_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Issue"))?;
+    Ok(Json(issue))
 }
 
 #[axum::debug_handler]
 async fn update_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateIssueRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateIssueRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let issue_crud = IssueCrud::new(app_state);
-
-    match issue_crud
+    let issue = issue_crud
         .update(
             id,
             payload.title,
@@ -214,25 +249,16 @@ async fn update_issue(
             payload.work_type,
             payload.target_release_at,
             None,
+            payload.recurrence,
         )
-        .await
-    {
-        Ok(issue) => Ok(Json(issue)),
-        Err(e) => {
-            if e.to_string().contains("Issue not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error updating issue {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+        .await?;
+    Ok(Json(issue))
 }
 #[axum::debug_handler]
 async fn start_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     update_issue_status(app_state, id, STATUS_IN_PROGRESS).await
 }
 
@@ -240,7 +266,7 @@ async fn start_issue(
 async fn finish_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     update_issue_status(app_state, id, STATUS_COMPLETED).await
 }
 
@@ -248,7 +274,7 @@ async fn finish_issue(
 async fn deliver_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     update_issue_status(app_state, id, STATUS_DELIVERED).await
 }
 
@@ -256,7 +282,7 @@ async fn deliver_issue(
 async fn accept_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     update_issue_status(app_state, id, STATUS_ACCEPTED).await
 }
 
@@ -264,11 +290,15 @@ async fn accept_issue(
 async fn reject_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     update_issue_status(app_state, id, STATUS_REJECTED).await
 }
 
-async fn update_issue_status(app_state: AppState, id: i32, status: i32) -> impl IntoResponse {
+async fn update_issue_status(
+    app_state: AppState,
+    id: i32,
+    status: i32,
+) -> Result<Json<issue::Model>, AppError> {
     let issue_crud = IssueCrud::new(app_state);
     // whenever the status is updated, the is_icebox flag should be set to false
     let accepted_at = if status == STATUS_ACCEPTED {
@@ -277,7 +307,7 @@ async fn update_issue_status(app_state: AppState, id: i32, status: i32) -> impl
         None
     };
 
-    match issue_crud
+    let issue = issue_crud
         .update(
             id,
             None,
@@ -289,114 +319,69 @@ async fn update_issue_status(app_state: AppState, id: i32, status: i32) -> impl
             None,
             None,
             accepted_at,
+            None,
         )
-        .await
-    {
-        Ok(issue) => Ok(Json(issue)),
-        Err(e) => {
-            if e.to_string().contains("Issue not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error updating issue {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+        .await?;
+    Ok(Json(issue))
 }
 
 #[axum::debug_handler]
 async fn delete_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> StatusCode {
+) -> Result<StatusCode, AppError> {
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting issue {}: {:?}", id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    issue_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[axum::debug_handler]
 async fn bulk_update_priorities(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<BulkPriorityUpdate>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<BulkPriorityUpdate>,
+) -> Result<impl IntoResponse, AppError> {
     let issue_crud = IssueCrud::new(app_state);
-
-    match issue_crud
+    let updated_issues = issue_crud
         .bulk_update_priorities(payload.issue_priorities)
-        .await
-    {
-        Ok(updated_issues) => Ok(Json(updated_issues)),
-        Err(e) => {
-            info!("Error updating issue priorities: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(updated_issues))
 }
 
 #[axum::debug_handler]
 async fn get_issues_by_tag(
     Extension(app_state): Extension<AppState>,
     Path(tag_id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_by_tag_id(tag_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            info!("Error getting issues by tag {}: {:?}", tag_id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_crud.find_all_by_tag_id(tag_id).await?))
 }
 
 #[axum::debug_handler]
-async fn get_all_accepted(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+async fn get_all_accepted(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_accepted(project_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            info!("Error getting all accepted issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_crud.find_all_accepted(project_id).await?))
 }
 #[axum::debug_handler]
-async fn get_all_icebox(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+async fn get_all_icebox(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let issue_crud = IssueCrud::new(app_state);
-    match issue_crud.find_all_icebox(project_id).await {
-        Ok(issues) => Ok(Json(issues)),
-        Err(e) => {
-            info!("Error getting all icebox: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_crud.find_all_icebox(project_id).await?))
 }
 
 #[axum::debug_handler]
-async fn get_weekly_points_average(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+async fn get_weekly_points_average(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let issue_crud = IssueCrud::new(app_state);
-
-    match issue_crud.calculate_weekly_points_average(project_id).await {
-        Ok(average) => Ok(Json(average)),
-        Err(e) => {
-            info!("Error calculating weekly points average: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(
+        issue_crud
+            .calculate_weekly_points_average(project_id)
+            .await?,
+    ))
 }
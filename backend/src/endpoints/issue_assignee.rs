@@ -1,4 +1,6 @@
 use crate::crud::issue_assignee::IssueAssigneeCrud;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -9,7 +11,7 @@ use axum::{
     Json, Router,
 };
 use serde::Deserialize;
-use tracing::{debug, info};
+use tracing::debug;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +20,12 @@ pub struct CreateIssueAssigneeRequest {
     user_id: i32,
 }
 
+impl Validate for CreateIssueAssigneeRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.id("issueId", self.issue_id).id("userId", self.user_id);
+    }
+}
+
 pub fn issue_assignee_routes() -> Router<AppState> {
     Router::new()
         .route("/issue-assignees", post(create_issue_assignee))
@@ -32,63 +40,40 @@ pub fn issue_assignee_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_issue_assignee(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateIssueAssigneeRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateIssueAssigneeRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating issue assignee");
     let issue_assignee_crud = IssueAssigneeCrud::new(app_state);
-    match issue_assignee_crud
+    let issue_assignee = issue_assignee_crud
         .create(payload.issue_id, payload.user_id)
-        .await
-    {
-        Ok(issue_assignee) => Ok(Json(issue_assignee)),
-        Err(e) => {
-            info!("Error creating issue assignee: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(issue_assignee))
 }
 
 #[axum::debug_handler]
 async fn get_issue_assignees(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_assignee_crud = IssueAssigneeCrud::new(app_state);
-    match issue_assignee_crud.find_by_issue_id(id).await {
-        Ok(issue_assignees) => Ok(Json(issue_assignees)),
-        Err(e) => {
-            info!("Error getting issue assignees: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_assignee_crud.find_by_issue_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn get_user_assignees(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_assignee_crud = IssueAssigneeCrud::new(app_state);
-    match issue_assignee_crud.find_by_user_id(id).await {
-        Ok(issue_assignees) => Ok(Json(issue_assignees)),
-        Err(e) => {
-            info!("Error getting user assignees: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_assignee_crud.find_by_user_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn delete_issue_assignee(
     Extension(app_state): Extension<AppState>,
     Path((issue_id, user_id)): Path<(i32, i32)>,
-) -> StatusCode {
+) -> Result<StatusCode, AppError> {
     let issue_assignee_crud = IssueAssigneeCrud::new(app_state);
-    match issue_assignee_crud.delete(issue_id, user_id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting issue assignee: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    issue_assignee_crud.delete(issue_id, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,44 @@
+use crate::crud::issue_subscription::IssueSubscriptionCrud;
+use crate::error::AppError;
+use crate::AppState;
+use axum::Extension;
+use axum::{
+    extract::Path,
+    response::IntoResponse,
+    routing::{get, put},
+    Json, Router,
+};
+
+pub fn issue_subscription_routes() -> Router<AppState> {
+    Router::new()
+        .route("/issues/{id}/subscription", get(get_subscription))
+        .route("/issues/{id}/watch", put(watch_issue))
+        .route("/issues/{id}/unwatch", put(unwatch_issue))
+}
+
+#[axum::debug_handler]
+async fn get_subscription(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = IssueSubscriptionCrud::new(app_state);
+    Ok(Json(crud.status(id).await?))
+}
+
+#[axum::debug_handler]
+async fn watch_issue(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = IssueSubscriptionCrud::new(app_state);
+    Ok(Json(crud.watch(id).await?))
+}
+
+#[axum::debug_handler]
+async fn unwatch_issue(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = IssueSubscriptionCrud::new(app_state);
+    Ok(Json(crud.unwatch(id).await?))
+}
@@ -1,4 +1,6 @@
 use crate::crud::issue_tag::IssueTagCrud;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -9,7 +11,7 @@ use axum::{
     Json, Router,
 };
 use serde::Deserialize;
-use tracing::{debug, info};
+use tracing::debug;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -18,6 +20,12 @@ pub struct CreateIssueTagRequest {
     tag_id: i32,
 }
 
+impl Validate for CreateIssueTagRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.id("issueId", self.issue_id).id("tagId", self.tag_id);
+    }
+}
+
 pub fn issue_tag_routes() -> Router<AppState> {
     Router::new()
         .route("/issue-tags", post(create_issue_tag))
@@ -29,63 +37,40 @@ pub fn issue_tag_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_issue_tag(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateIssueTagRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateIssueTagRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating issue tag");
     let issue_tag_crud = IssueTagCrud::new(app_state);
-    match issue_tag_crud
+    let issue_tag = issue_tag_crud
         .create(payload.issue_id, payload.tag_id)
-        .await
-    {
-        Ok(issue_tag) => Ok(Json(issue_tag)),
-        Err(e) => {
-            info!("Error creating issue tag: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(issue_tag))
 }
 
 #[axum::debug_handler]
 async fn get_issue_tags(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_tag_crud = IssueTagCrud::new(app_state);
-    match issue_tag_crud.find_by_issue_id(id).await {
-        Ok(issue_tags) => Ok(Json(issue_tags)),
-        Err(e) => {
-            info!("Error getting issue tags: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_tag_crud.find_by_issue_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn get_tag_issues(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let issue_tag_crud = IssueTagCrud::new(app_state);
-    match issue_tag_crud.find_by_tag_id(id).await {
-        Ok(issue_tags) => Ok(Json(issue_tags)),
-        Err(e) => {
-            info!("Error getting tag issues: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(issue_tag_crud.find_by_tag_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn delete_issue_tag(
     Extension(app_state): Extension<AppState>,
     Path((issue_id, tag_id)): Path<(i32, i32)>,
-) -> StatusCode {
+) -> Result<StatusCode, AppError> {
     let issue_tag_crud = IssueTagCrud::new(app_state);
-    match issue_tag_crud.delete(issue_id, tag_id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting issue tag: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    issue_tag_crud.delete(issue_id, tag_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
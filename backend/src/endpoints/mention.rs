@@ -0,0 +1,33 @@
+use crate::crud::mention::MentionCrud;
+use crate::error::AppError;
+use crate::AppState;
+use axum::Extension;
+use axum::{extract::Query, response::IntoResponse, routing::get, Json, Router};
+use chrono::{DateTime, FixedOffset};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetMentionsQuery {
+    #[serde(default)]
+    pub cursor_created_at: Option<DateTime<FixedOffset>>,
+    #[serde(default)]
+    pub cursor_id: Option<i32>,
+}
+
+pub fn mention_routes() -> Router<AppState> {
+    Router::new().route("/mentions", get(get_mentions))
+}
+
+#[axum::debug_handler]
+async fn get_mentions(
+    Extension(app_state): Extension<AppState>,
+    Query(params): Query<GetMentionsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let cursor = match (params.cursor_created_at, params.cursor_id) {
+        (Some(created_at), Some(id)) => Some((created_at, id)),
+        _ => None,
+    };
+    let mention_crud = MentionCrud::new(app_state);
+    Ok(Json(mention_crud.find_for_current_user(cursor).await?))
+}
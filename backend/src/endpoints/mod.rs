@@ -1,18 +1,26 @@
 pub mod auth;
 pub mod blocker;
 pub mod comment;
+pub mod estimation_session;
+pub mod event;
 pub mod file_upload;
 pub mod history;
 pub mod import_export;
 pub mod issue;
 pub mod issue_assignee;
+pub mod issue_subscription;
 pub mod issue_tag;
+pub mod mention;
 pub mod notification;
+pub mod notification_channel;
+pub mod notification_preference;
 pub mod owner;
 pub mod project;
 pub mod project_note;
 pub mod project_note_part;
 pub mod project_note_tag;
+pub mod repository_integration;
 pub mod tag;
 pub mod task;
 pub mod user;
+pub mod webhook;
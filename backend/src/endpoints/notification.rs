@@ -1,16 +1,21 @@
-use crate::crud::notification::NotificationCrud;
+use crate::crud::notification::{NotificationCrud, NotificationFilter};
+use crate::crud::notification_preference::NotificationKind;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
     extract::{Path, Query},
     http::StatusCode,
     response::IntoResponse,
-    routing::{get, put},
+    routing::{delete, get, post, put},
     Json, Router,
 };
 use chrono::{DateTime, FixedOffset};
 use serde::Deserialize;
-use tracing::{info, warn};
+
+const BULK_ACTIONS: [&str; 3] = ["read", "archive", "delete"];
+const MAX_BULK_IDS: usize = 500;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -19,6 +24,47 @@ pub struct GetNotificationsQuery {
     pub cursor_created_at: Option<DateTime<FixedOffset>>,
     #[serde(default)]
     pub cursor_id: Option<i32>,
+    #[serde(default)]
+    pub read: Option<bool>,
+    #[serde(default)]
+    pub kind: Option<String>,
+    #[serde(default)]
+    pub issue_id: Option<i32>,
+    #[serde(default)]
+    pub archived: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MarkAllReadQuery {
+    #[serde(default)]
+    pub issue_id: Option<i32>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkNotificationRequest {
+    action: String,
+    ids: Vec<i32>,
+}
+
+impl Validate for BulkNotificationRequest {
+    fn validate(&self, v: &mut Validator) {
+        if !BULK_ACTIONS.contains(&self.action.as_str()) {
+            v.error(
+                "action",
+                format!("must be one of {}", BULK_ACTIONS.join(", ")),
+            );
+        }
+        if self.ids.is_empty() {
+            v.error("ids", "must not be empty");
+        } else if self.ids.len() > MAX_BULK_IDS {
+            v.error("ids", format!("must have at most {} ids", MAX_BULK_IDS));
+        }
+        for id in &self.ids {
+            v.id("ids", *id);
+        }
+    }
 }
 
 pub fn notification_routes() -> Router<AppState> {
@@ -28,21 +74,32 @@ pub fn notification_routes() -> Router<AppState> {
             "/notifications/count",
             get(get_notification_count_for_project),
         )
+        .route(
+            "/notifications/read-all",
+            put(mark_all_notifications_as_read),
+        )
+        .route("/notifications/bulk", post(bulk_update_notifications))
         .route("/notifications/{id}/read", put(mark_notification_as_read))
+        .route("/notifications/{id}/archive", put(archive_notification))
+        .route("/notifications/{id}", delete(delete_notification))
 }
 #[axum::debug_handler]
 async fn get_notifications_for_project(
     Extension(app_state): Extension<AppState>,
     Query(params): Query<GetNotificationsQuery>,
-) -> impl IntoResponse {
-    let user_id = match app_state.user.as_ref() {
-        Some(user) => user.id,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-    let project_id = match app_state.project.as_ref() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST), // No project selected
-    };
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+
+    if let Some(kind) = &params.kind {
+        if NotificationKind::parse(kind).is_none() {
+            let kinds: Vec<&str> = NotificationKind::ALL.iter().map(|k| k.as_str()).collect();
+            return Err(AppError::invalid(
+                "kind",
+                format!("must be one of {}", kinds.join(", ")),
+            ));
+        }
+    }
 
     let notification_crud = NotificationCrud::new(app_state);
 
@@ -50,70 +107,86 @@ async fn get_notifications_for_project(
         (Some(created_at), Some(id)) => Some((created_at, id)),
         _ => None,
     };
+    let filter = NotificationFilter {
+        read: params.read,
+        kind: params.kind,
+        issue_id: params.issue_id,
+        archived: params.archived,
+    };
 
-    match notification_crud
-        .get_all_for_project(project_id, user_id, cursor)
-        .await
-    {
-        Ok(notifications) => Ok(Json(notifications)),
-        Err(e) => {
-            warn!(
-                "Error getting notifications for project {}: {:?}",
-                project_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let notifications = notification_crud
+        .get_all_for_project(project_id, user_id, &filter, cursor)
+        .await?;
+    Ok(Json(notifications))
 }
 
 #[axum::debug_handler]
 async fn mark_notification_as_read(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let notification_crud = NotificationCrud::new(app_state);
+    Ok(Json(notification_crud.mark_as_read(id).await?))
+}
 
-    match notification_crud.mark_as_read(id).await {
-        Ok(notification) => Ok(Json(notification)),
-        Err(e) => {
-            if e.to_string().contains("Notification not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error marking notification {} as read: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+#[axum::debug_handler]
+async fn mark_all_notifications_as_read(
+    Extension(app_state): Extension<AppState>,
+    Query(params): Query<MarkAllReadQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let notification_crud = NotificationCrud::new(app_state);
+    let updated = notification_crud.mark_all_as_read(params.issue_id).await?;
+    Ok(Json(serde_json::json!({ "updated": updated })))
 }
 
 #[axum::debug_handler]
-async fn get_notification_count_for_project(
+async fn bulk_update_notifications(
     Extension(app_state): Extension<AppState>,
-) -> impl IntoResponse {
-    let user_id = match app_state.user.as_ref() {
-        Some(user) => user.id,
-        None => return Err(StatusCode::UNAUTHORIZED),
+    ValidatedJson(request): ValidatedJson<BulkNotificationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let notification_crud = NotificationCrud::new(app_state);
+    let updated = match request.action.as_str() {
+        "read" => notification_crud.mark_many_as_read(&request.ids).await?,
+        "archive" => notification_crud.archive_many(&request.ids).await?,
+        _ => notification_crud.delete_many(&request.ids).await?,
     };
+    Ok(Json(serde_json::json!({ "updated": updated })))
+}
+
+#[axum::debug_handler]
+async fn archive_notification(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let notification_crud = NotificationCrud::new(app_state);
+    Ok(Json(notification_crud.archive(id).await?))
+}
+
+#[axum::debug_handler]
+async fn delete_notification(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let notification_crud = NotificationCrud::new(app_state);
+    notification_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn get_notification_count_for_project(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
 
     // If no project is selected, return 0 count
     let project_id = match app_state.project.as_ref() {
         Some(project) => project.id,
-        None => return Ok(Json(0)), // Return 0 when no project is selected
+        None => return Ok(Json(0)),
     };
 
     let notification_crud = NotificationCrud::new(app_state);
-
-    match notification_crud
+    let count = notification_crud
         .get_unread_count_for_user_and_project(project_id, user_id)
-        .await
-    {
-        Ok(count) => Ok(Json(count)),
-        Err(e) => {
-            warn!(
-                "Error getting notification count for project {}: {:?}",
-                project_id, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(count))
 }
@@ -0,0 +1,292 @@
+use crate::crud::notification_delivery::{NotificationDeliveryCrud, DELIVERY_STATUSES};
+use crate::crud::notification_settings::NotificationSettingsCrud;
+use crate::crud::project_user::ProjectUserCrud;
+use crate::crud::user_notification_channel::UserNotificationChannelCrud;
+use crate::error::AppError;
+use crate::notifications::channel::{OutgoingNotification, CHANNELS};
+use crate::notifications::delivery::{self, deliver_all, NotificationDelivery};
+use crate::validation::{Validate, ValidatedJson, Validator};
+use crate::AppState;
+use axum::Extension;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChannelRequest {
+    channel: String,
+    config: Value,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateChannelRequest {
+    #[serde(default)]
+    config: Option<Value>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDeliveriesQuery {
+    #[serde(default)]
+    status: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+// Channel-specific config is checked by the crud layer when it builds the channel
+impl Validate for CreateChannelRequest {
+    fn validate(&self, v: &mut Validator) {
+        if !CHANNELS.contains(&self.channel.as_str()) {
+            v.error("channel", format!("must be one of {}", CHANNELS.join(", ")));
+        }
+        if !self.config.is_object() {
+            v.error("config", "must be an object");
+        }
+    }
+}
+
+impl Validate for UpdateChannelRequest {
+    fn validate(&self, v: &mut Validator) {
+        if self.config.as_ref().is_some_and(|c| !c.is_object()) {
+            v.error("config", "must be an object");
+        }
+    }
+}
+
+pub fn notification_channel_routes() -> Router<AppState> {
+    Router::new()
+        .route("/projects/notification-channels", get(get_project_channels))
+        .route(
+            "/projects/notification-channels",
+            post(create_project_channel),
+        )
+        .route(
+            "/projects/notification-channels/{id}",
+            put(update_project_channel),
+        )
+        .route(
+            "/projects/notification-channels/{id}",
+            delete(delete_project_channel),
+        )
+        .route(
+            "/projects/notification-channels/{id}/test",
+            post(test_project_channel),
+        )
+        .route(
+            "/projects/notification-deliveries",
+            get(get_project_deliveries),
+        )
+        .route(
+            "/projects/notification-deliveries/{id}/retry",
+            post(retry_project_delivery),
+        )
+        .route("/users/me/notification-channels", get(get_user_channels))
+        .route("/users/me/notification-channels", post(create_user_channel))
+        .route(
+            "/users/me/notification-channels/{id}",
+            put(update_user_channel),
+        )
+        .route(
+            "/users/me/notification-channels/{id}",
+            delete(delete_user_channel),
+        )
+        .route(
+            "/users/me/notification-channels/{id}/test",
+            post(test_user_channel),
+        )
+}
+
+// Project channels and webhooks hold shared credentials, so only the project owner manages them
+pub(crate) async fn owned_project_id(app_state: &AppState) -> Result<i32, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
+    let is_owner = ProjectUserCrud::new(app_state.clone())
+        .is_project_owner(user_id, project_id)
+        .await?;
+    if !is_owner {
+        return Err(AppError::Forbidden(
+            "Only the project owner can manage notification channels and webhooks".to_string(),
+        ));
+    }
+    Ok(project_id)
+}
+
+fn test_notification() -> OutgoingNotification {
+    OutgoingNotification::new(
+        "Phoenix Tracker test notification",
+        "This channel is set up correctly.",
+    )
+}
+
+#[axum::debug_handler]
+async fn get_project_channels(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = NotificationSettingsCrud::new(app_state);
+    Ok(Json(crud.find_all_by_project_id(project_id).await?))
+}
+
+#[axum::debug_handler]
+async fn create_project_channel(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateChannelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = NotificationSettingsCrud::new(app_state);
+    let channel = crud
+        .create_channel(project_id, payload.channel, payload.config, payload.enabled)
+        .await?;
+    Ok(Json(channel))
+}
+
+#[axum::debug_handler]
+async fn update_project_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateChannelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = NotificationSettingsCrud::new(app_state);
+    Ok(Json(
+        crud.update_channel(project_id, id, payload.config, payload.enabled)
+            .await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn delete_project_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = NotificationSettingsCrud::new(app_state);
+    crud.delete_channel(project_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn test_project_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let settings = NotificationSettingsCrud::new(app_state)
+        .find_channel(project_id, id)
+        .await?;
+    let channel =
+        delivery::project_channel(&settings).map_err(|e| AppError::invalid("config", e))?;
+    deliver_all(&[channel], &test_notification())
+        .await
+        .map_err(AppError::Upstream)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn get_project_deliveries(
+    Extension(app_state): Extension<AppState>,
+    Query(params): Query<GetDeliveriesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    if let Some(status) = &params.status {
+        if !DELIVERY_STATUSES.contains(&status.as_str()) {
+            return Err(AppError::invalid(
+                "status",
+                format!("must be one of {}", DELIVERY_STATUSES.join(", ")),
+            ));
+        }
+    }
+    let crud = NotificationDeliveryCrud::new(app_state);
+    Ok(Json(
+        crud.find_all_by_project_id(project_id, params.status.as_deref())
+            .await?,
+    ))
+}
+
+// Returns the updated record whether or not the retry went through
+#[axum::debug_handler]
+async fn retry_project_delivery(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let record = NotificationDeliveryCrud::new(app_state.clone())
+        .find_by_id(project_id, id)
+        .await?;
+    Ok(Json(
+        NotificationDelivery::new(app_state).retry(record).await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn get_user_channels(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = UserNotificationChannelCrud::new(app_state);
+    Ok(Json(crud.find_all_for_current_user().await?))
+}
+
+#[axum::debug_handler]
+async fn create_user_channel(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateChannelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = UserNotificationChannelCrud::new(app_state);
+    let channel = crud
+        .create(payload.channel, payload.config, payload.enabled)
+        .await?;
+    Ok(Json(channel))
+}
+
+#[axum::debug_handler]
+async fn update_user_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateChannelRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = UserNotificationChannelCrud::new(app_state);
+    Ok(Json(
+        crud.update(id, payload.config, payload.enabled).await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn delete_user_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let crud = UserNotificationChannelCrud::new(app_state);
+    crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn test_user_channel(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let email = app_state.current_user()?.email.clone();
+    let model = UserNotificationChannelCrud::new(app_state)
+        .find_by_id(id)
+        .await?;
+    let channel =
+        delivery::user_channel(&model, &email).map_err(|e| AppError::invalid("config", e))?;
+    deliver_all(&[channel], &test_notification())
+        .await
+        .map_err(AppError::Upstream)?;
+    Ok(StatusCode::NO_CONTENT)
+}
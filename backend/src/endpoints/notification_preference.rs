@@ -0,0 +1,145 @@
+use crate::crud::notification_digest::NotificationDigestCrud;
+use crate::crud::notification_preference::{NotificationPreferenceCrud, PreferencesUpdate};
+use crate::error::AppError;
+use crate::notifications::channel::CHANNELS;
+use crate::notifications::digest::{self, DIGEST_FREQUENCIES};
+use crate::validation::{Validate, ValidatedJson, Validator};
+use crate::AppState;
+use axum::Extension;
+use axum::{
+    response::IntoResponse,
+    routing::{get, put},
+    Json, Router,
+};
+use serde::Deserialize;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePreferencesRequest {
+    #[serde(default)]
+    comments: Option<bool>,
+    #[serde(default)]
+    status_changes: Option<bool>,
+    #[serde(default)]
+    issue_updates: Option<bool>,
+    #[serde(default)]
+    blockers: Option<bool>,
+    #[serde(default)]
+    task_updates: Option<bool>,
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    channels: Option<Option<Vec<String>>>,
+    #[serde(default)]
+    digest: Option<bool>,
+}
+
+impl Validate for UpdatePreferencesRequest {
+    fn validate(&self, v: &mut Validator) {
+        let channels = self.channels.iter().flatten().flatten();
+        if let Some(unknown) = channels
+            .into_iter()
+            .find(|c| !CHANNELS.contains(&c.as_str()))
+        {
+            v.error(
+                "channels",
+                format!(
+                    "unknown channel '{}', expected one of {}",
+                    unknown,
+                    CHANNELS.join(", ")
+                ),
+            );
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDigestRequest {
+    #[serde(default)]
+    frequency: Option<String>,
+    #[serde(default)]
+    timezone: Option<String>,
+}
+
+impl Validate for UpdateDigestRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(frequency) = &self.frequency {
+            if !DIGEST_FREQUENCIES.contains(&frequency.as_str()) {
+                v.error(
+                    "frequency",
+                    format!("must be one of {}", DIGEST_FREQUENCIES.join(", ")),
+                );
+            }
+        }
+        if let Some(Err(e)) = self.timezone.as_deref().map(digest::parse_timezone) {
+            v.error("timezone", e);
+        }
+    }
+}
+
+pub fn notification_preference_routes() -> Router<AppState> {
+    Router::new()
+        .route(
+            "/users/me/notification-preferences",
+            get(get_notification_preferences),
+        )
+        .route(
+            "/users/me/notification-preferences",
+            put(update_notification_preferences),
+        )
+        .route(
+            "/users/me/notification-digest",
+            get(get_notification_digest),
+        )
+        .route(
+            "/users/me/notification-digest",
+            put(update_notification_digest),
+        )
+}
+
+#[axum::debug_handler]
+async fn get_notification_preferences(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = NotificationPreferenceCrud::new(app_state);
+    Ok(Json(crud.find_for_current_user().await?))
+}
+
+#[axum::debug_handler]
+async fn update_notification_preferences(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<UpdatePreferencesRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = NotificationPreferenceCrud::new(app_state);
+    let preferences = crud
+        .update_for_current_user(PreferencesUpdate {
+            comments: payload.comments,
+            status_changes: payload.status_changes,
+            issue_updates: payload.issue_updates,
+            blockers: payload.blockers,
+            task_updates: payload.task_updates,
+            channels: payload.channels,
+            digest: payload.digest,
+        })
+        .await?;
+    Ok(Json(preferences))
+}
+
+#[axum::debug_handler]
+async fn get_notification_digest(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = NotificationDigestCrud::new(app_state);
+    Ok(Json(crud.find_for_current_user().await?))
+}
+
+#[axum::debug_handler]
+async fn update_notification_digest(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<UpdateDigestRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = NotificationDigestCrud::new(app_state);
+    Ok(Json(
+        crud.update_for_current_user(payload.frequency, payload.timezone)
+            .await?,
+    ))
+}
@@ -1,4 +1,6 @@
 use crate::crud::owner::OwnerCrud;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::{
     extract::{Path, State},
@@ -8,7 +10,6 @@ use axum::{
     Json, Router,
 };
 use serde::Deserialize;
-use tracing::info;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,6 +23,22 @@ pub struct UpdateOwnerRequest {
     user_id: Option<i32>,
 }
 
+impl Validate for CreateOwnerRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(user_id) = self.user_id {
+            v.id("userId", user_id);
+        }
+    }
+}
+
+impl Validate for UpdateOwnerRequest {
+    fn validate(&self, v: &mut Validator) {
+        if let Some(user_id) = self.user_id {
+            v.id("userId", user_id);
+        }
+    }
+}
+
 pub fn owner_routes() -> Router<AppState> {
     Router::new()
         .route("/owners", post(create_owner))
@@ -34,71 +51,47 @@ pub fn owner_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_owner(
     State(app_state): State<AppState>,
-    Json(payload): Json<CreateOwnerRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateOwnerRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let owner_crud = OwnerCrud::new(app_state.db);
-    match owner_crud.create(payload.user_id).await {
-        Ok(owner) => Ok(Json(owner)),
-        Err(e) => {
-            info!("Error creating owner: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(owner_crud.create(payload.user_id).await?))
 }
 
 #[axum::debug_handler]
-async fn get_all_owners(State(app_state): State<AppState>) -> impl IntoResponse {
+async fn get_all_owners(State(app_state): State<AppState>) -> Result<impl IntoResponse, AppError> {
     let owner_crud = OwnerCrud::new(app_state.db);
-    match owner_crud.find_all().await {
-        Ok(owners) => Ok(Json(owners)),
-        Err(e) => {
-            info!("Error getting all owners: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(owner_crud.find_all().await?))
 }
 
 #[axum::debug_handler]
-async fn get_owner(State(app_state): State<AppState>, Path(id): Path<i32>) -> impl IntoResponse {
+async fn get_owner(
+    State(app_state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
     let owner_crud = OwnerCrud::new(app_state.db);
-    match owner_crud.find_by_id(id).await {
-        Ok(Some(owner)) => Ok(Json(owner)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error getting owner {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let owner = owner_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Owner"))?;
+    Ok(Json(owner))
 }
 
 #[axum::debug_handler]
 async fn update_owner(
     State(app_state): State<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateOwnerRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateOwnerRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let owner_crud = OwnerCrud::new(app_state.db);
-    match owner_crud.update(id, payload.user_id).await {
-        Ok(owner) => Ok(Json(owner)),
-        Err(e) => {
-            if e.to_string().contains("Owner not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error updating owner {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+    Ok(Json(owner_crud.update(id, payload.user_id).await?))
 }
 
 #[axum::debug_handler]
-async fn delete_owner(State(app_state): State<AppState>, Path(id): Path<i32>) -> StatusCode {
+async fn delete_owner(
+    State(app_state): State<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
     let owner_crud = OwnerCrud::new(app_state.db);
-    match owner_crud.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting owner {}: {:?}", id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    owner_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -1,6 +1,9 @@
 use crate::crud::owner::OwnerCrud;
+use crate::crud::point_scale::{PointScale, POINT_SCALE_MAP};
 use crate::crud::project::ProjectCrud;
 use crate::crud::project_user::ProjectUserCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -24,12 +27,34 @@ pub struct CreateProjectRequest {
 pub struct UpdateProjectRequest {
     name: Option<String>,
     owner_id: Option<i32>,
+    point_scale: Option<String>,
+    estimate_bugs: Option<bool>,
+    estimate_chores: Option<bool>,
+}
+
+impl Validate for CreateProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name, validation::MAX_NAME_LENGTH);
+    }
+}
+
+impl Validate for UpdateProjectRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("name", self.name.as_ref(), validation::MAX_NAME_LENGTH);
+        if let Some(owner_id) = self.owner_id {
+            v.id("ownerId", owner_id);
+        }
+        if let Some(point_scale) = &self.point_scale {
+            v.point_scale("pointScale", point_scale);
+        }
+    }
 }
 
 pub fn project_routes() -> Router<AppState> {
     Router::new()
         .route("/projects", post(create_project))
         .route("/projects/user/me", get(get_all_projects_by_user_id))
+        .route("/projects/point-scales", get(get_point_scales))
         .route("/projects/{id}", get(get_project))
         .route("/projects/{id}", put(update_project))
         .route("/projects/{id}", delete(delete_project))
@@ -38,138 +63,91 @@ pub fn project_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_project(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateProjectRequest>,
-) -> impl IntoResponse {
-    let user = app_state.user.clone();
-    match user {
-        Some(user) => {
-            let owner_crud = OwnerCrud::new(app_state.db.clone());
-            let project_crud = ProjectCrud::new(app_state.clone());
-            let project_user_crud = ProjectUserCrud::new(app_state.clone());
-            match owner_crud.create(Some(user.id)).await {
-                Ok(owner) => match project_crud.create(payload.name, owner.id).await {
-                    Ok(project) => match project_user_crud.create(project.id, user.id).await {
-                        Ok(_) => Ok(Json(project)),
-                        Err(e) => {
-                            debug!("Error creating project user: {:?}", e);
-                            Err(StatusCode::INTERNAL_SERVER_ERROR)
-                        }
-                    },
-                    Err(e) => {
-                        debug!("Error creating project: {:?}", e);
-                        Err(StatusCode::INTERNAL_SERVER_ERROR)
-                    }
-                },
-                Err(e) => {
-                    debug!("Error creating owner: {:?}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            }
-        }
-        None => Err(StatusCode::UNAUTHORIZED),
-    }
+    ValidatedJson(payload): ValidatedJson<CreateProjectRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let owner_crud = OwnerCrud::new(app_state.db.clone());
+    let project_crud = ProjectCrud::new(app_state.clone());
+    let project_user_crud = ProjectUserCrud::new(app_state.clone());
+
+    let owner = owner_crud.create(Some(user_id)).await?;
+    let project = project_crud.create(payload.name, owner.id).await?;
+    project_user_crud.create(project.id, user_id).await?;
+    Ok(Json(project))
 }
 
 #[axum::debug_handler]
 async fn get_project(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let project_crud = ProjectCrud::new(app_state.clone());
-    match project_crud.find_by_id(id).await {
-        Ok(Some(project)) => Ok(Json(project)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            debug!("Error getting project {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let project = project_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project"))?;
+    Ok(Json(project))
 }
+
 #[axum::debug_handler]
 async fn get_all_projects_by_user_id(
     Extension(app_state): Extension<AppState>,
-) -> impl IntoResponse {
-    let user = app_state.user.clone();
-    match user {
-        Some(user) => {
-            let project_crud = ProjectCrud::new(app_state.clone());
-            match project_crud.find_all_projects_by_user_id(user.id).await {
-                Ok(projects) => Ok(Json(projects)),
-                Err(e) => {
-                    debug!("Error getting projects: {:?}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            }
-        }
-        None => Err(StatusCode::UNAUTHORIZED),
-    }
+) -> Result<impl IntoResponse, AppError> {
+    let user_id = app_state.current_user()?.id;
+    let project_crud = ProjectCrud::new(app_state.clone());
+    Ok(Json(
+        project_crud.find_all_projects_by_user_id(user_id).await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn get_point_scales() -> impl IntoResponse {
+    let mut scales: Vec<&PointScale> = POINT_SCALE_MAP.values().collect();
+    scales.sort_by_key(|scale| scale.name);
+    Json(scales)
 }
+
 #[axum::debug_handler]
 async fn update_project(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateProjectRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateProjectRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let project_crud = ProjectCrud::new(app_state.clone());
-    match project_crud
-        .update(id, payload.name, payload.owner_id)
-        .await
-    {
-        Ok(project) => Ok(Json(project)),
-        Err(e) => {
-            if e.to_string().contains("Project not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                debug!("Error updating project {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+    let project = project_crud
+        .update(
+            id,
+            payload.name,
+            payload.owner_id,
+            payload.point_scale,
+            payload.estimate_bugs,
+            payload.estimate_chores,
+        )
+        .await?;
+    Ok(Json(project))
 }
 
 #[axum::debug_handler]
 async fn delete_project(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> StatusCode {
+) -> Result<StatusCode, AppError> {
     let project_crud = ProjectCrud::new(app_state.clone());
-    match project_crud.delete_cascade(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            debug!("Error deleting project {}: {:?}", id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    project_crud.delete_cascade(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[axum::debug_handler]
 async fn select_project(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
-    debug!("Cloning user from app_state");
-    let user = app_state.user.clone();
-    match user {
-        Some(user) => {
-            debug!("User found: {:?}", user);
-            let project_crud = ProjectCrud::new(app_state.clone());
-            debug!("Creating ProjectCrud instance");
-            match project_crud.find_by_id(id).await {
-                Ok(project) => Ok(Json(project)),
-                Err(e) => {
-                    debug!("Error finding project by id {}: {:?}", id, e);
-                    if e.to_string().contains("Project not found") {
-                        Err(StatusCode::NOT_FOUND)
-                    } else {
-                        debug!("Error getting project {}: {:?}", id, e);
-                        Err(StatusCode::INTERNAL_SERVER_ERROR)
-                    }
-                }
-            }
-        }
-        None => {
-            debug!("No user found, returning UNAUTHORIZED");
-            Err(StatusCode::UNAUTHORIZED)
-        }
-    }
+) -> Result<impl IntoResponse, AppError> {
+    let user = app_state.current_user()?;
+    debug!("User found: {:?}", user);
+    let project_crud = ProjectCrud::new(app_state.clone());
+    let project = project_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project"))?;
+    Ok(Json(project))
 }
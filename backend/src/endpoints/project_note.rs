@@ -1,4 +1,6 @@
 use crate::crud::project_note::ProjectNoteCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -25,6 +27,22 @@ pub struct UpdateProjectNoteRequest {
     detail: Option<String>,
 }
 
+impl Validate for CreateProjectNoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title, validation::MAX_TITLE_LENGTH)
+            .max_length("detail", &self.detail, validation::MAX_NOTE_LENGTH);
+    }
+}
+
+impl Validate for UpdateProjectNoteRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("title", self.title.as_ref(), validation::MAX_TITLE_LENGTH);
+        if let Some(detail) = &self.detail {
+            v.max_length("detail", detail, validation::MAX_NOTE_LENGTH);
+        }
+    }
+}
+
 pub fn project_note_routes() -> Router<AppState> {
     Router::new()
         .route("/project-notes", post(create_project_note))
@@ -37,82 +55,56 @@ pub fn project_note_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_project_note(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateProjectNoteRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateProjectNoteRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating project note");
     let project_note_crud = ProjectNoteCrud::new(app_state);
-    match project_note_crud
+    let project_note = project_note_crud
         .create(payload.title, payload.detail)
-        .await
-    {
-        Ok(project_note) => Ok(Json(project_note)),
-        Err(e) => {
-            debug!("Error creating project note: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(project_note))
 }
 
 #[axum::debug_handler]
 async fn get_project_note(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let project_note_crud = ProjectNoteCrud::new(app_state);
-    match project_note_crud.find_by_id(id).await {
-        Ok(Some(project_note)) => Ok(Json(project_note)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            debug!("Error getting project note {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let project_note = project_note_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project note"))?;
+    Ok(Json(project_note))
 }
 
 #[axum::debug_handler]
 async fn get_project_note_by_project(
     Extension(app_state): Extension<AppState>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let project_note_crud = ProjectNoteCrud::new(app_state);
-    match project_note_crud.find_all().await {
-        Ok(project_note) => Ok(Json(project_note)),
-        Err(e) => {
-            debug!("Error getting project notes for project {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(project_note_crud.find_all().await?))
 }
 
 #[axum::debug_handler]
 async fn update_project_note(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateProjectNoteRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateProjectNoteRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let project_note_crud = ProjectNoteCrud::new(app_state);
-    match project_note_crud
+    let project_note = project_note_crud
         .update(id, payload.title, payload.detail)
-        .await
-    {
-        Ok(project_note) => Ok(Json(project_note)),
-        Err(e) => {
-            debug!("Error updating project note {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(project_note))
 }
 
 #[axum::debug_handler]
 async fn delete_project_note(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<StatusCode, AppError> {
     let project_note_crud = ProjectNoteCrud::new(app_state);
-    match project_note_crud.delete(id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(e) => {
-            debug!("Error deleting project note {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    project_note_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
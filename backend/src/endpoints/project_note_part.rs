@@ -1,9 +1,10 @@
 use crate::crud::project_note_parts::ProjectNotePartsCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing::put, Json, Router};
+use axum::{extract::Path, response::IntoResponse, routing::put, Json, Router};
 use serde::Deserialize;
-use tracing::info;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -11,6 +12,12 @@ pub struct UpdateProjectNotePartRequest {
     content: String,
 }
 
+impl Validate for UpdateProjectNotePartRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.max_length("content", &self.content, validation::MAX_NOTE_LENGTH);
+    }
+}
+
 pub fn project_note_part_routes() -> Router<AppState> {
     Router::new().route(
         "/project-note-parts/{id}/content",
@@ -22,17 +29,11 @@ pub fn project_note_part_routes() -> Router<AppState> {
 async fn update_project_note_part_content(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateProjectNotePartRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateProjectNotePartRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let project_note_parts_crud = ProjectNotePartsCrud::new(app_state);
-    match project_note_parts_crud
+    let project_note_part = project_note_parts_crud
         .update_content(id, payload.content)
-        .await
-    {
-        Ok(project_note_part) => Ok(Json(project_note_part)),
-        Err(e) => {
-            info!("Error updating project note part {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(project_note_part))
 }
@@ -1,8 +1,8 @@
 use crate::crud::project_note_tag::ProjectNoteTagCrud;
+use crate::error::AppError;
 use crate::AppState;
 use axum::Extension;
-use axum::{extract::Path, http::StatusCode, response::IntoResponse, routing::get, Json, Router};
-use tracing::debug;
+use axum::{extract::Path, response::IntoResponse, routing::get, Json, Router};
 
 pub fn project_note_tag_routes() -> Router<AppState> {
     Router::new().route(
@@ -15,28 +15,12 @@ pub fn project_note_tag_routes() -> Router<AppState> {
 async fn get_project_note_tag_by_tag_name(
     Extension(app_state): Extension<AppState>,
     Path(tag_name): Path<String>,
-) -> impl IntoResponse {
-    let project = match &app_state.project {
-        Some(p) => p,
-        None => {
-            debug!("No project found in app state");
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
-
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let project_note_tag_crud = ProjectNoteTagCrud::new(app_state.clone());
-    match project_note_tag_crud
-        .get_by_tag_name(project.id, &tag_name)
-        .await
-    {
-        Ok(Some(project_note_tag)) => Ok(Json(project_note_tag)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            debug!(
-                "Error getting project note tag by tag name {}: {:?}",
-                tag_name, e
-            );
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let project_note_tag = project_note_tag_crud
+        .get_by_tag_name(project_id, &tag_name)
+        .await?
+        .ok_or_else(|| AppError::not_found("Project note tag"))?;
+    Ok(Json(project_note_tag))
 }
@@ -0,0 +1,209 @@
+use crate::crud::issue::IssueCrud;
+use crate::crud::repository_event::{self, PROVIDERS};
+use crate::crud::repository_integration::RepositoryIntegrationCrud;
+use crate::endpoints::notification_channel::owned_project_id;
+use crate::error::AppError;
+use crate::validation::{Validate, ValidatedJson, Validator};
+use crate::AppState;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::HeaderMap;
+use axum::Extension;
+use axum::{
+    extract::Path,
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use tracing::{info, warn};
+
+const MIN_SECRET_LENGTH: usize = 16;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateIntegrationRequest {
+    provider: String,
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    merge_status: Option<i32>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateIntegrationRequest {
+    #[serde(default)]
+    secret: Option<String>,
+    // null stops closing references from changing status
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    merge_status: Option<Option<i32>>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn validate_integration(v: &mut Validator, secret: Option<&String>, merge_status: Option<i32>) {
+    if let Some(secret) = secret {
+        if secret.len() < MIN_SECRET_LENGTH {
+            v.error(
+                "secret",
+                format!("must be at least {} characters", MIN_SECRET_LENGTH),
+            );
+        }
+        v.max_length("secret", secret, 255);
+    }
+    if let Some(status) = merge_status {
+        v.status("mergeStatus", status);
+    }
+}
+
+impl Validate for CreateIntegrationRequest {
+    fn validate(&self, v: &mut Validator) {
+        if !PROVIDERS.contains(&self.provider.as_str()) {
+            v.error(
+                "provider",
+                format!("must be one of {}", PROVIDERS.join(", ")),
+            );
+        }
+        validate_integration(v, self.secret.as_ref(), self.merge_status);
+    }
+}
+
+impl Validate for UpdateIntegrationRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_integration(v, self.secret.as_ref(), self.merge_status.flatten());
+    }
+}
+
+pub fn repository_integration_routes() -> Router<AppState> {
+    Router::new()
+        .route("/projects/repository-integrations", get(get_integrations))
+        .route(
+            "/projects/repository-integrations",
+            post(create_integration),
+        )
+        .route(
+            "/projects/repository-integrations/{id}",
+            put(update_integration),
+        )
+        .route(
+            "/projects/repository-integrations/{id}",
+            delete(delete_integration),
+        )
+        .route("/issues/{id}/repository-links", get(get_issue_links))
+        .route("/hooks/{id}", post(receive_hook))
+}
+
+#[axum::debug_handler]
+async fn get_integrations(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    Ok(Json(crud.find_all_by_project_id(project_id).await?))
+}
+
+#[axum::debug_handler]
+async fn create_integration(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateIntegrationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    let integration = crud
+        .create(
+            project_id,
+            payload.provider,
+            payload.secret,
+            payload.merge_status,
+            payload.enabled,
+        )
+        .await?;
+    Ok(Json(integration))
+}
+
+#[axum::debug_handler]
+async fn update_integration(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateIntegrationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    Ok(Json(
+        crud.update(
+            project_id,
+            id,
+            payload.secret,
+            payload.merge_status,
+            payload.enabled,
+        )
+        .await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn delete_integration(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    crud.delete(project_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn get_issue_links(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
+    let issue = IssueCrud::new(app_state.clone())
+        .find_by_id(id)
+        .await?
+        .filter(|issue| issue.project_id == project_id)
+        .ok_or_else(|| AppError::not_found("Issue"))?;
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    Ok(Json(crud.find_links_by_issue_id(issue.id).await?))
+}
+
+// Called by the repository host, so it's outside JWT auth; the signature authenticates it
+#[axum::debug_handler]
+async fn receive_hook(
+    State(app_state): State<AppState>,
+    Path(id): Path<i32>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<impl IntoResponse, AppError> {
+    let crud = RepositoryIntegrationCrud::new(app_state);
+    // Unknown integrations look the same as bad signatures
+    let integration = crud.find_enabled(id).await?.ok_or(AppError::Unauthorized)?;
+    if !repository_event::verify_signature(
+        &integration.provider,
+        &integration.secret,
+        &headers,
+        &body,
+    ) {
+        warn!("Rejected repository hook {} with a bad signature", id);
+        return Err(AppError::Unauthorized);
+    }
+
+    let event = repository_event::event_name(&integration.provider, &headers).unwrap_or_default();
+    let payload: serde_json::Value = serde_json::from_slice(&body)
+        .map_err(|e| AppError::BadRequest(format!("Invalid JSON payload: {}", e)))?;
+    let activities = repository_event::parse_activities(&integration.provider, event, &payload);
+    let summary = crud.apply(&integration, &activities).await?;
+    info!(
+        "Repository hook {} '{}': {} links, {} transitions",
+        id, event, summary.linked, summary.transitioned
+    );
+    Ok(Json(summary))
+}
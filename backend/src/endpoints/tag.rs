@@ -1,5 +1,7 @@
 use crate::crud::issue::IssueCrud;
 use crate::crud::tag::TagCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -12,7 +14,6 @@ use axum::{
 use sea_orm::entity::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tracing::info;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -38,6 +39,18 @@ pub struct TagWithCount {
     updated_at: DateTimeWithTimeZone,
 }
 
+impl Validate for CreateTagRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name, validation::MAX_TAG_NAME_LENGTH);
+    }
+}
+
+impl Validate for UpdateTagRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("name", self.name.as_ref(), validation::MAX_TAG_NAME_LENGTH);
+    }
+}
+
 pub fn tag_routes() -> Router<AppState> {
     Router::new()
         .route("/tags", post(create_tag))
@@ -51,78 +64,37 @@ pub fn tag_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_tag(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateTagRequest>,
-) -> impl IntoResponse {
-    let project_id = match &app_state.project {
-        Some(project) => project.id,
-        None => {
-            info!("No project found in app state");
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
-
+    ValidatedJson(payload): ValidatedJson<CreateTagRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let tag_crud = TagCrud::new(app_state);
-    match tag_crud
+    let tag = tag_crud
         .create(project_id, payload.name, payload.is_epic)
-        .await
-    {
-        Ok(tag) => Ok(Json(tag)),
-        Err(e) => {
-            info!("Error creating tag: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(tag))
 }
 
 #[axum::debug_handler]
-async fn get_all_tags(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let project_id = match &app_state.project {
-        Some(project) => project.id,
-        None => {
-            info!("No project found in app state");
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
-
+async fn get_all_tags(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
     let tag_crud = TagCrud::new(app_state);
-    match tag_crud.find_all(project_id).await {
-        Ok(tags) => Ok(Json(tags)),
-        Err(e) => {
-            info!("Error getting all tags: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(tag_crud.find_all(project_id).await?))
 }
 
 #[axum::debug_handler]
-async fn get_tags_with_counts(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
-    let project_id = match &app_state.project {
-        Some(project) => project.id,
-        None => {
-            info!("No project found in app state");
-            return Err(StatusCode::BAD_REQUEST);
-        }
-    };
+async fn get_tags_with_counts(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = app_state.current_project()?.id;
 
     let tag_crud = TagCrud::new(app_state.clone());
     let issue_crud = IssueCrud::new(app_state);
 
-    let tags = match tag_crud.find_all(project_id).await {
-        Ok(t) => t,
-        Err(e) => {
-            info!("Error getting tags: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
-
+    let tags = tag_crud.find_all(project_id).await?;
     let tag_ids: Vec<i32> = tags.iter().map(|t| t.id).collect();
-    let counts = match issue_crud.count_issues_by_tag_ids(tag_ids).await {
-        Ok(c) => c,
-        Err(e) => {
-            info!("Error getting counts: {:?}", e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
-        }
-    };
+    let counts = issue_crud.count_issues_by_tag_ids(tag_ids).await?;
 
     let counts_map: HashMap<i32, i64> = counts.into_iter().collect();
 
@@ -133,8 +105,8 @@ async fn get_tags_with_counts(Extension(app_state): Extension<AppState>) -> impl
             name: tag.name,
             is_epic: tag.is_epic,
             count: counts_map.get(&tag.id).copied().unwrap_or(0),
-            created_at: tag.created_at.into(),
-            updated_at: tag.updated_at.into(),
+            created_at: tag.created_at,
+            updated_at: tag.updated_at,
         })
         .collect();
 
@@ -145,46 +117,32 @@ async fn get_tags_with_counts(Extension(app_state): Extension<AppState>) -> impl
 async fn get_tag(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let tag_crud = TagCrud::new(app_state);
-    match tag_crud.find_by_id(id).await {
-        Ok(Some(tag)) => Ok(Json(tag)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error getting tag {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let tag = tag_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Tag"))?;
+    Ok(Json(tag))
 }
 
 #[axum::debug_handler]
 async fn update_tag(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateTagRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateTagRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let tag_crud = TagCrud::new(app_state);
-    match tag_crud.update(id, payload.name, payload.is_epic).await {
-        Ok(tag) => Ok(Json(tag)),
-        Err(e) => {
-            if e.to_string().contains("Tag not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error updating tag {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+    let tag = tag_crud.update(id, payload.name, payload.is_epic).await?;
+    Ok(Json(tag))
 }
 
 #[axum::debug_handler]
-async fn delete_tag(Extension(app_state): Extension<AppState>, Path(id): Path<i32>) -> StatusCode {
+async fn delete_tag(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
     let tag_crud = TagCrud::new(app_state);
-    match tag_crud.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting tag {}: {:?}", id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    tag_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
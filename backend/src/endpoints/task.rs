@@ -1,4 +1,6 @@
 use crate::crud::task::TaskCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::Extension;
 use axum::{
@@ -28,6 +30,23 @@ pub struct UpdateTaskRequest {
     percent: Option<f32>,
 }
 
+impl Validate for CreateTaskRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("title", &self.title, validation::MAX_TITLE_LENGTH)
+            .id("issueId", self.issue_id)
+            .percent("percent", self.percent);
+    }
+}
+
+impl Validate for UpdateTaskRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("title", self.title.as_ref(), validation::MAX_TITLE_LENGTH);
+        if let Some(percent) = self.percent {
+            v.percent("percent", percent);
+        }
+    }
+}
+
 pub fn task_routes() -> Router<AppState> {
     Router::new()
         .route("/tasks", post(create_task))
@@ -40,88 +59,62 @@ pub fn task_routes() -> Router<AppState> {
 #[axum::debug_handler]
 async fn create_task(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateTaskRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateTaskRequest>,
+) -> Result<impl IntoResponse, AppError> {
     debug!("Creating task");
     let task_crud = TaskCrud::new(app_state);
-    match task_crud
+    let task = task_crud
         .create(
             payload.title,
             payload.issue_id,
             payload.completed,
             payload.percent,
         )
-        .await
-    {
-        Ok(task) => Ok(Json(task)),
-        Err(e) => {
-            debug!("Error creating task: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(task))
 }
 
 #[axum::debug_handler]
 async fn get_task(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let task_crud = TaskCrud::new(app_state);
-    match task_crud.find_by_id(id).await {
-        Ok(Some(task)) => Ok(Json(task)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            debug!("Error getting task {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let task = task_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("Task"))?;
+    Ok(Json(task))
 }
 
 #[axum::debug_handler]
 async fn get_tasks_by_issue(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let task_crud = TaskCrud::new(app_state);
-    match task_crud.find_by_issue_id(id).await {
-        Ok(tasks) => Ok(Json(tasks)),
-        Err(e) => {
-            debug!("Error getting tasks for issue {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(task_crud.find_by_issue_id(id).await?))
 }
 
 #[axum::debug_handler]
 async fn update_task(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateTaskRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateTaskRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let task_crud = TaskCrud::new(app_state);
-    match task_crud
+    let task = task_crud
         .update(id, payload.title, payload.completed, payload.percent)
-        .await
-    {
-        Ok(task) => Ok(Json(task)),
-        Err(e) => {
-            debug!("Error updating task {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+        .await?;
+    Ok(Json(task))
 }
 
 #[axum::debug_handler]
 async fn delete_task(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<StatusCode, AppError> {
     let task_crud = TaskCrud::new(app_state);
-    match task_crud.delete(id).await {
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
-        Err(e) => {
-            debug!("Error deleting task {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    task_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -1,5 +1,7 @@
 use crate::crud::project_user::ProjectUserCrud;
 use crate::crud::user::UserCrud;
+use crate::error::AppError;
+use crate::validation::{self, Validate, ValidatedJson, Validator};
 use crate::AppState;
 use axum::extract::Query;
 use axum::Extension;
@@ -34,6 +36,26 @@ pub struct InviteUserRequest {
     email: String,
 }
 
+impl Validate for CreateUserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.text("name", &self.name, validation::MAX_NAME_LENGTH)
+            .email("email", &self.email);
+    }
+}
+
+impl Validate for UpdateUserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.optional_text("name", self.name.as_ref(), validation::MAX_NAME_LENGTH)
+            .optional_email("email", self.email.as_ref());
+    }
+}
+
+impl Validate for InviteUserRequest {
+    fn validate(&self, v: &mut Validator) {
+        v.email("email", &self.email);
+    }
+}
+
 pub fn user_routes() -> Router<AppState> {
     Router::new()
         .route("/users", post(create_user))
@@ -50,99 +72,64 @@ pub fn user_routes() -> Router<AppState> {
 async fn remove_user(
     Extension(app_state): Extension<AppState>,
     Path(user_id): Path<i32>,
-) -> impl IntoResponse {
-    let current_user = match app_state.user.clone() {
-        Some(user) => user,
-        None => return StatusCode::UNAUTHORIZED,
-    };
-
-    let project_id = match app_state.project.clone() {
-        Some(project) => project.id,
-        None => return StatusCode::BAD_REQUEST,
-    };
+) -> Result<StatusCode, AppError> {
+    let current_user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
 
     let project_user_crud = ProjectUserCrud::new(app_state.clone());
-
-    match project_user_crud
-        .is_project_owner(current_user.id, project_id)
-        .await
+    if !project_user_crud
+        .is_project_owner(current_user_id, project_id)
+        .await?
     {
-        Ok(true) => match project_user_crud.delete(project_id, user_id).await {
-            Ok(_) => StatusCode::NO_CONTENT,
-            Err(e) => {
-                info!("Error removing user {}: {:?}", user_id, e);
-                StatusCode::INTERNAL_SERVER_ERROR
-            }
-        },
-        Ok(false) => StatusCode::FORBIDDEN,
-        Err(e) => {
-            info!("Error checking project owner: {:?}", e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
+        return Err(AppError::Forbidden(
+            "Only the project owner can remove users".to_string(),
+        ));
     }
+
+    project_user_crud.delete(project_id, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[axum::debug_handler]
 async fn invite_user(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<InviteUserRequest>,
-) -> impl IntoResponse {
-    let user_crud = UserCrud::new(app_state.clone());
-    let current_user = match app_state.user.clone() {
-        Some(user) => user,
-        None => return Err(StatusCode::UNAUTHORIZED),
-    };
-
-    let project_id = match app_state.project.clone() {
-        Some(project) => project.id,
-        None => return Err(StatusCode::BAD_REQUEST),
-    };
+    ValidatedJson(payload): ValidatedJson<InviteUserRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let current_user_id = app_state.current_user()?.id;
+    let project_id = app_state.current_project()?.id;
 
+    let user_crud = UserCrud::new(app_state.clone());
     let project_user_crud = ProjectUserCrud::new(app_state.clone());
-
-    match project_user_crud
-        .is_project_owner(current_user.id, project_id)
-        .await
+    if !project_user_crud
+        .is_project_owner(current_user_id, project_id)
+        .await?
     {
-        Ok(true) => match user_crud.find_by_email(payload.email).await {
-            Ok(Some(user)) => match project_user_crud.create(project_id, user.id).await {
-                Ok(project_user) => Ok(Json(project_user)),
-                Err(e) => {
-                    info!("Error creating project user association: {:?}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            },
-            Ok(None) => Err(StatusCode::NOT_FOUND),
-            Err(e) => {
-                info!("Error finding user by email: {:?}", e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        },
-        Ok(false) => Err(StatusCode::FORBIDDEN),
-        Err(e) => {
-            info!("Error checking project owner: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+        return Err(AppError::Forbidden(
+            "Only the project owner can invite users".to_string(),
+        ));
     }
+
+    let user = user_crud
+        .find_by_email(payload.email)
+        .await?
+        .ok_or_else(|| AppError::not_found("User"))?;
+    let project_user = project_user_crud.create(project_id, user.id).await?;
+    Ok(Json(project_user))
 }
 
 #[axum::debug_handler]
 async fn create_user(
     Extension(app_state): Extension<AppState>,
-    Json(payload): Json<CreateUserRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<CreateUserRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let user_crud = UserCrud::new(app_state);
-    match user_crud.create(payload.name, payload.email).await {
-        Ok(user) => Ok(Json(user)),
-        Err(e) => {
-            info!("Error creating user: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Ok(Json(user_crud.create(payload.name, payload.email).await?))
 }
 
 #[axum::debug_handler]
-async fn get_all_users(Extension(app_state): Extension<AppState>) -> impl IntoResponse {
+async fn get_all_users(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
     let project_id = match app_state.project.clone() {
         Some(project) => project.id,
         None => {
@@ -154,95 +141,64 @@ async fn get_all_users(Extension(app_state): Extension<AppState>) -> impl IntoRe
     };
 
     let project_users_crud = ProjectUserCrud::new(app_state.clone());
-    let project_users = project_users_crud.get_users_for_project(project_id).await;
-
-    match project_users {
-        Ok(project_users) => {
-            let user_ids = project_users
-                .iter()
-                .map(|project_user| project_user.user_id)
-                .collect::<Vec<_>>();
-            let user_crud = UserCrud::new(app_state);
-            match user_crud.find_all(project_id, user_ids).await {
-                Ok(users) => Ok(Json(users)),
-                Err(e) => {
-                    info!("Error getting all users: {:?}", e);
-                    Err(StatusCode::INTERNAL_SERVER_ERROR)
-                }
-            }
-        }
-        Err(e) => {
-            info!("Error getting project users: {:?}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let project_users = project_users_crud.get_users_for_project(project_id).await?;
+    let user_ids = project_users
+        .iter()
+        .map(|project_user| project_user.user_id)
+        .collect::<Vec<_>>();
+
+    let user_crud = UserCrud::new(app_state);
+    Ok(Json(user_crud.find_all(project_id, user_ids).await?))
 }
 
 #[axum::debug_handler]
 async fn get_user(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-) -> impl IntoResponse {
+) -> Result<impl IntoResponse, AppError> {
     let user_crud = UserCrud::new(app_state);
-    match user_crud.find_by_id(id).await {
-        Ok(Some(user)) => Ok(Json(user)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error getting user {}: {:?}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let user = user_crud
+        .find_by_id(id)
+        .await?
+        .ok_or_else(|| AppError::not_found("User"))?;
+    Ok(Json(user))
 }
 
 #[axum::debug_handler]
 async fn get_user_by_email(
     Extension(app_state): Extension<AppState>,
     Query(params): Query<HashMap<String, String>>,
-) -> impl IntoResponse {
-    let email = match params.get("email") {
-        Some(email) => email,
-        None => return Err(StatusCode::BAD_REQUEST),
-    };
+) -> Result<impl IntoResponse, AppError> {
+    let email = params
+        .get("email")
+        .ok_or_else(|| AppError::invalid("email", "is required"))?;
 
     let user_crud = UserCrud::new(app_state);
-    match user_crud.find_by_email(email.to_string()).await {
-        Ok(Some(user)) => Ok(Json(user)),
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            info!("Error getting user by email {}: {:?}", email, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    let user = user_crud
+        .find_by_email(email.to_string())
+        .await?
+        .ok_or_else(|| AppError::not_found("User"))?;
+    Ok(Json(user))
 }
 
 #[axum::debug_handler]
 async fn update_user(
     Extension(app_state): Extension<AppState>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateUserRequest>,
-) -> impl IntoResponse {
+    ValidatedJson(payload): ValidatedJson<UpdateUserRequest>,
+) -> Result<impl IntoResponse, AppError> {
     let user_crud = UserCrud::new(app_state);
-    match user_crud.update(id, payload.name, payload.email).await {
-        Ok(user) => Ok(Json(user)),
-        Err(e) => {
-            if e.to_string().contains("User not found") {
-                Err(StatusCode::NOT_FOUND)
-            } else {
-                info!("Error updating user {}: {:?}", id, e);
-                Err(StatusCode::INTERNAL_SERVER_ERROR)
-            }
-        }
-    }
+    Ok(Json(
+        user_crud.update(id, payload.name, payload.email).await?,
+    ))
 }
 
 #[axum::debug_handler]
-async fn delete_user(Extension(app_state): Extension<AppState>, Path(id): Path<i32>) -> StatusCode {
+async fn delete_user(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
     let user_crud = UserCrud::new(app_state);
-    match user_crud.delete(id).await {
-        Ok(_) => StatusCode::NO_CONTENT,
-        Err(e) => {
-            info!("Error deleting user {}: {:?}", id, e);
-            StatusCode::INTERNAL_SERVER_ERROR
-        }
-    }
+    user_crud.delete(id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
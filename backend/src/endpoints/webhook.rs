@@ -0,0 +1,230 @@
+use crate::crud::event_broadcaster::WEBHOOK_EVENTS;
+use crate::crud::webhook::WebhookCrud;
+use crate::crud::webhook_delivery::{WebhookDeliveryCrud, WEBHOOK_STATUSES};
+use crate::endpoints::notification_channel::owned_project_id;
+use crate::error::AppError;
+use crate::notifications::destination::{check_url, Destination};
+use crate::notifications::project_webhook;
+use crate::validation::{Validate, ValidatedJson, Validator};
+use crate::AppState;
+use axum::Extension;
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::IntoResponse,
+    routing::{delete, get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+
+const MIN_SECRET_LENGTH: usize = 16;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    url: String,
+    #[serde(default)]
+    secret: Option<String>,
+    #[serde(default)]
+    events: Option<Vec<String>>,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookRequest {
+    #[serde(default)]
+    url: Option<String>,
+    #[serde(default)]
+    secret: Option<String>,
+    // null clears the filter so every event is sent
+    #[serde(default, with = "::serde_with::rust::double_option")]
+    events: Option<Option<Vec<String>>>,
+    #[serde(default)]
+    enabled: Option<bool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetDeliveriesQuery {
+    #[serde(default)]
+    status: Option<String>,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn validate_webhook(
+    v: &mut Validator,
+    url: Option<&String>,
+    secret: Option<&String>,
+    events: Option<&Vec<String>>,
+) {
+    if let Some(url) = url {
+        if let Err(e) = check_url(url, Destination::Public) {
+            v.error("url", e);
+        }
+        v.max_length("url", url, 2048);
+    }
+    if let Some(secret) = secret {
+        if secret.len() < MIN_SECRET_LENGTH {
+            v.error(
+                "secret",
+                format!("must be at least {} characters", MIN_SECRET_LENGTH),
+            );
+        }
+        v.max_length("secret", secret, 255);
+    }
+    if let Some(unknown) = events
+        .into_iter()
+        .flatten()
+        .find(|e| !WEBHOOK_EVENTS.contains(&e.as_str()))
+    {
+        v.error(
+            "events",
+            format!(
+                "'{}' is not an event; must be one of {}",
+                unknown,
+                WEBHOOK_EVENTS.join(", ")
+            ),
+        );
+    }
+}
+
+impl Validate for CreateWebhookRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_webhook(
+            v,
+            Some(&self.url),
+            self.secret.as_ref(),
+            self.events.as_ref(),
+        );
+    }
+}
+
+impl Validate for UpdateWebhookRequest {
+    fn validate(&self, v: &mut Validator) {
+        validate_webhook(
+            v,
+            self.url.as_ref(),
+            self.secret.as_ref(),
+            self.events.as_ref().and_then(Option::as_ref),
+        );
+    }
+}
+
+pub fn webhook_routes() -> Router<AppState> {
+    Router::new()
+        .route("/projects/webhooks", get(get_webhooks))
+        .route("/projects/webhooks", post(create_webhook))
+        .route("/projects/webhooks/{id}", put(update_webhook))
+        .route("/projects/webhooks/{id}", delete(delete_webhook))
+        .route("/projects/webhooks/{id}/deliveries", get(get_deliveries))
+        .route(
+            "/projects/webhooks/{id}/deliveries/{delivery_id}/redeliver",
+            post(redeliver),
+        )
+}
+
+#[axum::debug_handler]
+async fn get_webhooks(
+    Extension(app_state): Extension<AppState>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = WebhookCrud::new(app_state);
+    Ok(Json(crud.find_all_by_project_id(project_id).await?))
+}
+
+#[axum::debug_handler]
+async fn create_webhook(
+    Extension(app_state): Extension<AppState>,
+    ValidatedJson(payload): ValidatedJson<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = WebhookCrud::new(app_state);
+    let webhook = crud
+        .create(
+            project_id,
+            payload.url,
+            payload.secret,
+            payload.events,
+            payload.enabled,
+        )
+        .await?;
+    Ok(Json(webhook))
+}
+
+#[axum::debug_handler]
+async fn update_webhook(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    ValidatedJson(payload): ValidatedJson<UpdateWebhookRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = WebhookCrud::new(app_state);
+    Ok(Json(
+        crud.update(
+            project_id,
+            id,
+            payload.url,
+            payload.secret,
+            payload.events,
+            payload.enabled,
+        )
+        .await?,
+    ))
+}
+
+#[axum::debug_handler]
+async fn delete_webhook(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let crud = WebhookCrud::new(app_state);
+    crud.delete(project_id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[axum::debug_handler]
+async fn get_deliveries(
+    Extension(app_state): Extension<AppState>,
+    Path(id): Path<i32>,
+    Query(params): Query<GetDeliveriesQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    if let Some(status) = &params.status {
+        if !WEBHOOK_STATUSES.contains(&status.as_str()) {
+            return Err(AppError::invalid(
+                "status",
+                format!("must be one of {}", WEBHOOK_STATUSES.join(", ")),
+            ));
+        }
+    }
+    let webhook = WebhookCrud::new(app_state.clone())
+        .find_by_id(project_id, id)
+        .await?;
+    let crud = WebhookDeliveryCrud::new(app_state);
+    Ok(Json(
+        crud.find_all_by_webhook_id(webhook.id, params.status.as_deref())
+            .await?,
+    ))
+}
+
+// Queues the original payload again and returns the new delivery
+#[axum::debug_handler]
+async fn redeliver(
+    Extension(app_state): Extension<AppState>,
+    Path((id, delivery_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    let project_id = owned_project_id(&app_state).await?;
+    let webhook = WebhookCrud::new(app_state.clone())
+        .find_by_id(project_id, id)
+        .await?;
+    let delivery = WebhookDeliveryCrud::new(app_state.clone())
+        .find_by_id(webhook.id, delivery_id)
+        .await?;
+    Ok(Json(project_webhook::redeliver(app_state, delivery).await?))
+}
@@ -0,0 +1,26 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "direct_upload")]
+pub struct Model {
+    // Random token naming the upload in the finalize URL
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub project_id: i32,
+    pub issue_id: Option<i32>,
+    pub project_note_id: Option<i32>,
+    pub uploader_user_id: i32,
+    pub original_filename: String,
+    pub mime_type: String,
+    pub size_bytes: i64,
+    // Hex SHA-256 the client declared; S3 rejects a body that doesn't match it
+    pub sha256: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -0,0 +1,36 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "estimation_session")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub issue_id: i32,
+    pub project_id: i32,
+    pub created_by_id: i32,
+    pub state: String,
+    pub final_points: Option<i32>,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::issue::Entity",
+        from = "Column::IssueId",
+        to = "super::issue::Column::Id"
+    )]
+    Issue,
+    #[sea_orm(has_many = "super::estimation_vote::Entity")]
+    EstimationVote,
+}
+
+impl Related<super::estimation_vote::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EstimationVote.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
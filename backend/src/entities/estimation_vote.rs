@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "estimation_vote")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub session_id: i32,
+    pub user_id: i32,
+    pub points: i32,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::estimation_session::Entity",
+        from = "Column::SessionId",
+        to = "super::estimation_session::Column::Id"
+    )]
+    EstimationSession,
+}
+
+impl Related<super::estimation_session::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::EstimationSession.def()
+    }
+}
+
+impl ActiveModelBehavior for ActiveModel {}
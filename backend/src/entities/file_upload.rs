@@ -15,10 +15,18 @@ pub struct Model {
     pub mime_type: String,
     pub size_bytes: i64,
     pub uploaded_at: DateTimeWithTimeZone,
+    // Hex SHA-256 of the content; None for uploads stored before checksums were kept
+    pub sha256: Option<String>,
+    // Derived PNG preview in the same store; None until the thumbnail job has rendered one
+    pub thumbnail_path: Option<String>,
 
     // Full browser-accessible URL (local: backend download route; aws: presigned S3 URL)
     #[sea_orm(ignore)]
     pub full_url: Option<String>,
+
+    // Browser URL of the preview, built the same way as full_url
+    #[sea_orm(ignore)]
+    pub thumbnail_url: Option<String>,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
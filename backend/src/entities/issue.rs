@@ -17,6 +17,8 @@ pub struct Model {
     pub target_release_at: Option<DateTimeWithTimeZone>,
     pub lock_version: i32,
     pub accepted_at: Option<DateTimeWithTimeZone>,
+    // RRULE-style rule; moves to the next occurrence once this one fires or is accepted
+    pub recurrence: Option<String>,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
     #[sea_orm(ignore)]
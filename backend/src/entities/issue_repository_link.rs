@@ -0,0 +1,32 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "issue_repository_link")]
+pub struct Model {
+    #[sea_orm(primary_key)]
+    pub id: i32,
+    pub issue_id: i32,
+    pub integration_id: i32,
+    // "commit" or "pull_request"
+    pub kind: String,
+    pub external_id: String,
+    pub title: String,
+    pub url: Option<String>,
+    // "pushed" for commits; "open", "merged" or "closed" for pull requests
+    pub state: String,
+    pub created_at: DateTimeWithTimeZone,
+    pub updated_at: DateTimeWithTimeZone,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::issue::Entity",
+        from = "Column::IssueId",
+        to = "super::issue::Column::Id"
+    )]
+    Issue,
+}
+
+impl ActiveModelBehavior for ActiveModel {}
@@ -13,6 +13,9 @@ pub struct Model {
     pub initiated_by_user_id: i32,
     pub targeted_user_id: i32,
     pub read: bool,
+    pub kind: String,
+    // Hidden from the inbox unless asked for; archiving also marks it read
+    pub archived: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
@@ -9,6 +9,10 @@ pub struct Model {
     pub project_id: i32,
     pub application_id: i32,
     pub token: String,
+    pub channel: String,
+    // Channel-specific JSON (urls, topics, recipients); Gotify keeps using `token`
+    pub config: Option<String>,
+    pub enabled: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
 }
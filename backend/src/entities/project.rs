@@ -8,6 +8,12 @@ pub struct Model {
     pub id: i32,
     pub name: String,
     pub owner_id: i32,
+    #[serde(default = "default_point_scale")]
+    pub point_scale: String,
+    #[serde(default)]
+    pub estimate_bugs: bool,
+    #[serde(default)]
+    pub estimate_chores: bool,
     pub created_at: DateTimeWithTimeZone,
     pub updated_at: DateTimeWithTimeZone,
     #[sea_orm(ignore)]
@@ -24,3 +30,8 @@ pub enum Relation {
 }
 
 impl ActiveModelBehavior for ActiveModel {}
+
+// Exports written before point scales existed don't carry the column
+fn default_point_scale() -> String {
+    crate::crud::point_scale::POINT_SCALE_FIBONACCI.to_string()
+}
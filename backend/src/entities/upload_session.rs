@@ -0,0 +1,30 @@
+use sea_orm::entity::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, DeriveEntityModel, Serialize, Deserialize)]
+#[sea_orm(table_name = "upload_session")]
+pub struct Model {
+    // Random token; knowing it is not enough to continue someone else's upload
+    #[sea_orm(primary_key, auto_increment = false)]
+    pub id: String,
+    pub project_id: i32,
+    pub issue_id: Option<i32>,
+    pub project_note_id: Option<i32>,
+    pub uploader_user_id: i32,
+    pub original_filename: String,
+    pub mime_type: String,
+    // Total length declared when the upload was created
+    pub size_bytes: i64,
+    // Bytes received so far; the next chunk must start here
+    pub offset_bytes: i64,
+    pub created_at: DateTimeWithTimeZone,
+    pub expires_at: DateTimeWithTimeZone,
+    // Token of the request currently writing to the part file, until it expires
+    pub write_lease: Option<String>,
+    pub write_lease_expires_at: Option<DateTimeWithTimeZone>,
+}
+
+#[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
+pub enum Relation {}
+
+impl ActiveModelBehavior for ActiveModel {}
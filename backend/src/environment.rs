@@ -10,6 +10,13 @@ lazy_static! {
     // Security
     static ref JWT_SECRET: String = env::var("JWT_SECRET")
         .unwrap_or_else(|_| "default_secret_key_change_in_production".to_string());
+    // Signs the short-lived file links handed out for the local store
+    static ref ASSET_URL_SECRET: Option<String> = env::var("ASSET_URL_SECRET").ok();
+    static ref ASSET_URL_TTL_SECONDS: i64 = env::var("ASSET_URL_TTL_SECONDS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|s| *s > 0)
+        .unwrap_or(900);
 
     // Logging
     static ref LOG_LEVEL: Level = env::var("LOG_LEVEL")
@@ -29,6 +36,10 @@ lazy_static! {
         .parse::<usize>()
         .unwrap_or(1000);
 
+    // How events reach other instances: memory (this process only) or postgres (LISTEN/NOTIFY)
+    static ref EVENT_FANOUT: String = env::var("EVENT_FANOUT")
+        .unwrap_or_else(|_| "memory".to_string());
+
     // Database
     static ref DATABASE_URL: String = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
 
@@ -55,6 +66,34 @@ lazy_static! {
         .and_then(|v| v.parse::<i64>().ok())
         .unwrap_or(10);
 
+    // Where uploads are spooled before they are stored and resumable uploads keep their parts.
+    // Defaults to BASE_FILE_PATH/.tmp for the local store so finished files are moved, not copied.
+    static ref UPLOAD_TMP_DIR: PathBuf = env::var("UPLOAD_TMP_DIR")
+        .ok()
+        .map(PathBuf::from)
+        .unwrap_or_else(|| match (FILE_STORE_SCHEME.as_str(), BASE_FILE_PATH.as_ref()) {
+            ("local", Some(base)) => base.join(".tmp"),
+            _ => env::temp_dir().join("phoenix-uploads"),
+        });
+
+    // Hours an unfinished resumable upload is kept after its last chunk
+    static ref UPLOAD_SESSION_TTL_HOURS: i64 = env::var("UPLOAD_SESSION_TTL_HOURS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|h| *h > 0)
+        .unwrap_or(24);
+
+    // Scans new uploads before they are stored: none or clamav
+    static ref UPLOAD_SCANNER: String =
+        env::var("UPLOAD_SCANNER").unwrap_or_else(|_| "none".to_string());
+    // clamd address as host:port, or the path of its Unix socket
+    static ref CLAMAV_ADDRESS: String =
+        env::var("CLAMAV_ADDRESS").unwrap_or_else(|_| "127.0.0.1:3310".to_string());
+
+    // Renders the first page of a PDF for its preview; poppler's pdftoppm or anything taking the same arguments
+    static ref PDF_PREVIEW_COMMAND: String =
+        env::var("PDF_PREVIEW_COMMAND").unwrap_or_else(|_| "pdftoppm".to_string());
+
     // AWS S3 configuration (used when FILE_STORE_SCHEME=aws)
     static ref S3_BUCKET: Option<String> = env::var("S3_BUCKET").ok();
     static ref AWS_REGION: Option<String> = env::var("AWS_REGION").ok();
@@ -74,6 +113,31 @@ lazy_static! {
         .unwrap_or_else(|_| "http://gotify:80".to_string());
     static ref GOTIFY_DEFAULTUSER_NAME: Option<String> = env::var("GOTIFY_DEFAULTUSER_NAME").ok();
     static ref GOTIFY_DEFAULTUSER_PASS: Option<String> = env::var("GOTIFY_DEFAULTUSER_PASS").ok();
+
+    // Outgoing email (MAILER=smtp|maildir); maildir is meant for development and tests
+    static ref MAILER: String = env::var("MAILER").unwrap_or_else(|_| "smtp".to_string());
+    static ref SMTP_URL: Option<String> = env::var("SMTP_URL").ok();
+    static ref MAIL_FROM: String = env::var("MAIL_FROM")
+        .unwrap_or_else(|_| "Phoenix Tracker <noreply@localhost>".to_string());
+    static ref MAILDIR_PATH: Option<PathBuf> = env::var("MAILDIR_PATH").ok().map(PathBuf::from);
+
+    // Default server for ntfy channels that don't name their own
+    static ref NTFY_URL: String = env::var("NTFY_URL")
+        .unwrap_or_else(|_| "https://ntfy.sh".to_string());
+
+    // Local hour (0-23, in each user's timezone) at which email digests go out
+    static ref DIGEST_HOUR: u32 = env::var("DIGEST_HOUR")
+        .ok()
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|h| *h < 24)
+        .unwrap_or(8);
+
+    // Days to keep events in the outbox; clients further behind than this reload instead
+    static ref EVENT_RETENTION_DAYS: i64 = env::var("EVENT_RETENTION_DAYS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .filter(|d| *d > 0)
+        .unwrap_or(30);
 }
 
 // ---- Public accessors (static-style) ----
@@ -82,6 +146,14 @@ pub fn jwt_secret() -> &'static str {
     &JWT_SECRET
 }
 
+pub fn asset_url_secret() -> Option<&'static str> {
+    ASSET_URL_SECRET.as_deref()
+}
+
+pub fn asset_url_ttl_seconds() -> i64 {
+    *ASSET_URL_TTL_SECONDS
+}
+
 pub fn log_level() -> Level {
     *LOG_LEVEL
 }
@@ -90,6 +162,14 @@ pub fn websocket_buffer_size() -> usize {
     *WEBSOCKET_BUFFER_SIZE
 }
 
+pub fn event_fanout() -> &'static str {
+    &EVENT_FANOUT
+}
+
+pub fn event_retention_days() -> i64 {
+    *EVENT_RETENTION_DAYS
+}
+
 pub fn database_url() -> &'static str {
     &DATABASE_URL
 }
@@ -122,6 +202,26 @@ pub fn max_upload_size_bytes() -> usize {
     max_upload_size_mb() as usize * 1024 * 1024
 }
 
+pub fn upload_tmp_dir() -> &'static PathBuf {
+    &UPLOAD_TMP_DIR
+}
+
+pub fn upload_session_ttl_hours() -> i64 {
+    *UPLOAD_SESSION_TTL_HOURS
+}
+
+pub fn pdf_preview_command() -> &'static str {
+    PDF_PREVIEW_COMMAND.as_str()
+}
+
+pub fn upload_scanner() -> &'static str {
+    UPLOAD_SCANNER.as_str()
+}
+
+pub fn clamav_address() -> &'static str {
+    CLAMAV_ADDRESS.as_str()
+}
+
 // ---- S3-specific accessors ----
 
 pub fn s3_bucket() -> Option<&'static str> {
@@ -161,3 +261,29 @@ pub fn gotify_defaultuser_name() -> Option<&'static str> {
 pub fn gotify_defaultuser_pass() -> Option<&'static str> {
     GOTIFY_DEFAULTUSER_PASS.as_deref()
 }
+
+// ---- Email and channel accessors ----
+
+pub fn mailer() -> &'static str {
+    &MAILER
+}
+
+pub fn smtp_url() -> Option<&'static str> {
+    SMTP_URL.as_deref()
+}
+
+pub fn mail_from() -> &'static str {
+    &MAIL_FROM
+}
+
+pub fn maildir_path() -> Option<&'static PathBuf> {
+    MAILDIR_PATH.as_ref()
+}
+
+pub fn ntfy_url() -> &'static str {
+    &NTFY_URL
+}
+
+pub fn digest_hour() -> u32 {
+    *DIGEST_HOUR
+}
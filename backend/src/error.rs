@@ -0,0 +1,212 @@
+use axum::http::header::CONTENT_TYPE;
+use axum::http::{HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use sea_orm::DbErr;
+use serde::Serialize;
+use tracing::{error, info};
+
+// Domain error shared by the crud layer and the HTTP handlers.
+// Handlers return `Result<_, AppError>` and rely on `IntoResponse` to render an
+// RFC 7807 problem-details body, so business failures never have to be detected
+// by matching on error strings.
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum AppError {
+    NotFound(String),
+    Conflict(String),
+    OptimisticLock,
+    Unauthorized,
+    Forbidden(String),
+    BadRequest(String),
+    Validation(Vec<FieldError>),
+    Upstream(String),
+    Database(DbErr),
+    Internal(String),
+}
+
+impl AppError {
+    pub fn not_found(entity: &str) -> Self {
+        AppError::NotFound(format!("{} not found", entity))
+    }
+
+    pub fn no_project_selected() -> Self {
+        AppError::BadRequest("No project selected".to_string())
+    }
+
+    pub fn invalid(field: impl Into<String>, message: impl Into<String>) -> Self {
+        AppError::Validation(vec![FieldError::new(field, message)])
+    }
+
+    pub fn status(&self) -> StatusCode {
+        match self {
+            AppError::NotFound(_) => StatusCode::NOT_FOUND,
+            AppError::Conflict(_) | AppError::OptimisticLock => StatusCode::CONFLICT,
+            AppError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::BadRequest(_) => StatusCode::BAD_REQUEST,
+            AppError::Validation(_) => StatusCode::UNPROCESSABLE_ENTITY,
+            AppError::Upstream(_) => StatusCode::BAD_GATEWAY,
+            AppError::Database(_) | AppError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn problem_type(&self) -> &'static str {
+        match self {
+            AppError::NotFound(_) => "not-found",
+            AppError::Conflict(_) => "conflict",
+            AppError::OptimisticLock => "optimistic-lock",
+            AppError::Unauthorized => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::BadRequest(_) => "bad-request",
+            AppError::Validation(_) => "validation",
+            AppError::Upstream(_) => "upstream",
+            AppError::Database(_) | AppError::Internal(_) => "internal",
+        }
+    }
+
+    // Server-side failures are logged with full detail but only a generic message is
+    // returned to the client.
+    fn detail(&self) -> String {
+        match self {
+            AppError::NotFound(m)
+            | AppError::Conflict(m)
+            | AppError::Forbidden(m)
+            | AppError::BadRequest(m)
+            | AppError::Upstream(m) => m.clone(),
+            AppError::OptimisticLock => {
+                "The resource was modified by someone else; reload and try again".to_string()
+            }
+            AppError::Unauthorized => "Authentication is required".to_string(),
+            AppError::Validation(_) => "One or more fields are invalid".to_string(),
+            AppError::Database(_) | AppError::Internal(_) => {
+                "An unexpected error occurred".to_string()
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for AppError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppError::Database(e) => write!(f, "database error: {}", e),
+            AppError::Internal(m) => write!(f, "internal error: {}", m),
+            AppError::Validation(errors) => write!(f, "validation failed: {:?}", errors),
+            other => write!(f, "{}", other.detail()),
+        }
+    }
+}
+
+impl std::error::Error for AppError {}
+
+impl From<DbErr> for AppError {
+    fn from(e: DbErr) -> Self {
+        match e {
+            DbErr::RecordNotFound(m) => AppError::NotFound(m),
+            other => AppError::Database(other),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ProblemDetails {
+    #[serde(rename = "type")]
+    problem_type: String,
+    title: String,
+    status: u16,
+    detail: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<FieldError>,
+}
+
+impl IntoResponse for AppError {
+    fn into_response(self) -> Response {
+        let status = self.status();
+        if status.is_server_error() {
+            error!("Request failed: {}", self);
+        } else {
+            info!("Request rejected: {}", self);
+        }
+
+        let body = ProblemDetails {
+            problem_type: format!("/problems/{}", self.problem_type()),
+            title: status.canonical_reason().unwrap_or("Error").to_string(),
+            status: status.as_u16(),
+            detail: self.detail(),
+            errors: match self {
+                AppError::Validation(errors) => errors,
+                _ => Vec::new(),
+            },
+        };
+
+        let mut response = (status, Json(body)).into_response();
+        response.headers_mut().insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/problem+json"),
+        );
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_status_mapping() {
+        assert_eq!(AppError::not_found("Issue").status(), StatusCode::NOT_FOUND);
+        assert_eq!(AppError::OptimisticLock.status(), StatusCode::CONFLICT);
+        assert_eq!(
+            AppError::invalid("title", "must not be empty").status(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+        assert_eq!(
+            AppError::from(DbErr::Custom("boom".into())).status(),
+            StatusCode::INTERNAL_SERVER_ERROR
+        );
+    }
+
+    #[tokio::test]
+    async fn test_problem_details_body() {
+        let response = AppError::invalid("email", "is not a valid email address").into_response();
+        assert_eq!(response.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.headers().get(CONTENT_TYPE).unwrap(),
+            "application/problem+json"
+        );
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["type"], "/problems/validation");
+        assert_eq!(body["status"], 422);
+        assert_eq!(body["errors"][0]["field"], "email");
+    }
+
+    #[tokio::test]
+    async fn test_internal_details_are_hidden() {
+        let response = AppError::Internal("secret connection string".into()).into_response();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["detail"], "An unexpected error occurred");
+        assert!(body.get("errors").is_none());
+    }
+}
@@ -0,0 +1,186 @@
+use crate::crud::domain_event::{DomainEventCrud, OutboxReader, GAP_RECHECK, MAX_REPLAY};
+use crate::crud::event_broadcaster::EventSender;
+use crate::environment;
+use crate::error::AppError;
+use crate::websocket::{EventRouter, PresenceAnnouncement, PRESENCE_CHANNEL};
+use sea_orm::sqlx::postgres::PgListener;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+
+pub const FANOUT_MEMORY: &str = "memory";
+pub const FANOUT_POSTGRES: &str = "postgres";
+
+const NOTIFY_CHANNEL: &str = "phoenix_events";
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+// Wakes this instance's forwarder in `memory` mode
+static COMMITTED: Notify = Notify::const_new();
+
+/// How published events reach the live channel of every running instance.
+/// `memory` only reaches this process; `postgres` goes through LISTEN/NOTIFY so that
+/// replicas behind a load balancer, and workers on other nodes, all see each other's events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventFanout {
+    Memory,
+    Postgres,
+}
+
+impl EventFanout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            FANOUT_MEMORY => Some(EventFanout::Memory),
+            FANOUT_POSTGRES => Some(EventFanout::Postgres),
+            _ => None,
+        }
+    }
+
+    /// The configured backend; anything unrecognised falls back to `memory`.
+    pub fn configured() -> Self {
+        Self::parse(environment::event_fanout()).unwrap_or(EventFanout::Memory)
+    }
+
+    /// Tells every instance that the event with this sequence has been committed. The
+    /// notification only carries the sequence; each forwarder reads events from the outbox.
+    pub async fn announce(self, db: &DatabaseConnection, sequence: i64) -> Result<(), AppError> {
+        match self {
+            EventFanout::Memory => COMMITTED.notify_one(),
+            EventFanout::Postgres => {
+                db.execute(Statement::from_sql_and_values(
+                    DbBackend::Postgres,
+                    "SELECT pg_notify($1, $2)",
+                    [NOTIFY_CHANNEL.into(), sequence.to_string().into()],
+                ))
+                .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Feeds committed events into this process's live channel, in sequence order. With
+/// `postgres` it listens for events announced by any instance, reconnecting on its own and
+/// catching up from the outbox on everything published while it was away; presence from
+/// other instances goes straight to the router.
+pub fn spawn_listener(
+    fanout: EventFanout,
+    db: DatabaseConnection,
+    tx: Arc<EventSender>,
+    router: Arc<EventRouter>,
+) {
+    tokio::spawn(async move {
+        let cursor = match DomainEventCrud::new(db.clone()).latest_sequence().await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                warn!("Failed to read the latest event, starting from 0: {}", e);
+                0
+            }
+        };
+        let mut reader = OutboxReader::new(db.clone(), cursor);
+        match fanout {
+            EventFanout::Memory => loop {
+                if let Err(e) = forward(&tx, &mut reader).await {
+                    warn!("Failed to forward events: {}", e);
+                }
+                wait_for(&reader, COMMITTED.notified()).await;
+            },
+            EventFanout::Postgres => loop {
+                if let Err(e) = listen(&db, &tx, &router, &mut reader).await {
+                    warn!(
+                        "Event listener disconnected, retrying in {:?}: {}",
+                        RECONNECT_DELAY, e
+                    );
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            },
+        }
+    });
+}
+
+// Waits for the next announcement, or only briefly while a gap may still fill
+async fn wait_for<F: std::future::Future>(
+    reader: &OutboxReader,
+    announced: F,
+) -> Option<F::Output> {
+    if reader.waiting() {
+        tokio::time::timeout(GAP_RECHECK, announced).await.ok()
+    } else {
+        Some(announced.await)
+    }
+}
+
+async fn listen(
+    db: &DatabaseConnection,
+    tx: &EventSender,
+    router: &EventRouter,
+    reader: &mut OutboxReader,
+) -> Result<(), AppError> {
+    let sqlx_error = |e: sea_orm::sqlx::Error| AppError::Internal(e.to_string());
+    let mut listener = PgListener::connect_with(db.get_postgres_connection_pool())
+        .await
+        .map_err(sqlx_error)?;
+    listener
+        .listen_all([NOTIFY_CHANNEL, PRESENCE_CHANNEL])
+        .await
+        .map_err(sqlx_error)?;
+    info!("Listening for events on '{}'", NOTIFY_CHANNEL);
+    forward(tx, reader).await?;
+
+    loop {
+        // None means the connection dropped; the listener reconnects on the next call
+        // and anything announced in between is picked up from the outbox
+        let Some(notification) = wait_for(reader, listener.try_recv()).await else {
+            forward(tx, reader).await?;
+            continue;
+        };
+        let notification = notification.map_err(sqlx_error)?;
+        if let Some(presence) = notification
+            .as_ref()
+            .filter(|n| n.channel() == PRESENCE_CHANNEL)
+        {
+            match serde_json::from_str::<PresenceAnnouncement>(presence.payload()) {
+                Ok(announcement) => router.apply_remote(announcement),
+                Err(e) => warn!("Ignoring unreadable presence announcement: {}", e),
+            }
+            continue;
+        }
+        let announced = notification.and_then(|n| n.payload().parse::<i64>().ok());
+        if announced.is_some_and(|sequence| sequence <= reader.cursor) {
+            continue;
+        }
+        forward(tx, reader).await?;
+    }
+}
+
+async fn forward(tx: &EventSender, reader: &mut OutboxReader) -> Result<(), AppError> {
+    loop {
+        let batch = reader.next_batch().await?;
+        let complete = (batch.len() as u64) < MAX_REPLAY;
+        for envelope in batch {
+            debug!(
+                "Forwarding '{}' event #{} for project {}",
+                envelope.event.event_type(),
+                envelope.sequence,
+                envelope.project_id
+            );
+            let _ = tx.send(Arc::new(envelope));
+        }
+        if complete {
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!(EventFanout::parse("postgres"), Some(EventFanout::Postgres));
+        assert_eq!(EventFanout::parse("Memory"), Some(EventFanout::Memory));
+        assert_eq!(EventFanout::parse("redis"), None);
+    }
+}
@@ -81,8 +81,8 @@ impl JwtService {
 
     /// Extract bearer token from Authorization header (alias for compatibility)
     pub fn extract_bearer_token(auth_header: &str) -> Option<&str> {
-        if auth_header.starts_with("Bearer ") {
-            Some(&auth_header[7..])
+        if let Some(token) = auth_header.strip_prefix("Bearer ") {
+            Some(token)
         } else {
             // Accept raw JWT tokens without the "Bearer " prefix as well
             // Heuristic: JWTs have three segments separated by dots
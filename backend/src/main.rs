@@ -1,5 +1,9 @@
+use crate::crud::domain_event::EventEnvelope;
+use crate::crud::event_broadcaster::EventSender;
 use crate::crud::project::ProjectCrud;
 use crate::crud::user::UserCrud;
+use crate::error::AppError;
+use crate::event_fanout::{spawn_listener, EventFanout, FANOUT_MEMORY, FANOUT_POSTGRES};
 use crate::jwt::JwtService;
 use axum::body::Body;
 use axum::extract::Request;
@@ -7,7 +11,7 @@ use axum::http::header::AUTHORIZATION;
 use axum::http::StatusCode;
 use axum::middleware::Next;
 use axum::response::Response;
-use axum::{extract::State, middleware};
+use axum::{extract::State, middleware, Extension};
 use axum::{
     http::{HeaderName, HeaderValue, Method},
     routing::get,
@@ -15,16 +19,20 @@ use axum::{
 };
 use endpoints::{
     auth::auth_routes, blocker::blocker_routes, comment::comment_routes,
+    estimation_session::estimation_session_routes, event::event_routes,
     file_upload::file_upload_routes, history::history_routes, import_export::import_export_routes,
-    issue::issue_routes, issue_assignee::issue_assignee_routes, issue_tag::issue_tag_routes,
-    notification::notification_routes, owner::owner_routes, project::project_routes,
-    project_note::project_note_routes, project_note_part::project_note_part_routes,
-    project_note_tag::project_note_tag_routes, tag::tag_routes, task::task_routes,
-    user::user_routes,
+    issue::issue_routes, issue_assignee::issue_assignee_routes,
+    issue_subscription::issue_subscription_routes, issue_tag::issue_tag_routes,
+    mention::mention_routes, notification::notification_routes,
+    notification_channel::notification_channel_routes,
+    notification_preference::notification_preference_routes, owner::owner_routes,
+    project::project_routes, project_note::project_note_routes,
+    project_note_part::project_note_part_routes, project_note_tag::project_note_tag_routes,
+    repository_integration::repository_integration_routes, tag::tag_routes, task::task_routes,
+    user::user_routes, webhook::webhook_routes,
 };
 use graphile_worker::WorkerOptions;
-use sea_orm::{Database, DatabaseConnection};
-use serde::Deserialize;
+use sea_orm::{ConnectionTrait, Database, DatabaseConnection, DbBackend};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use std::time::Instant;
@@ -35,24 +43,41 @@ use tower_http::services::ServeDir;
 use tower_http::services::ServeFile;
 use tracing::{debug, info, warn};
 
+mod content_sniff;
 mod crud;
 mod endpoints;
 mod entities;
 mod environment;
+mod error;
+mod event_fanout;
 mod jwt;
 mod notifications;
+mod signed_url;
+mod upload_scanner;
+mod validation;
 mod websocket;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: DatabaseConnection,
-    pub tx: Arc<broadcast::Sender<String>>,
+    pub tx: Arc<EventSender>,
     pub user: Option<entities::user::Model>,
     pub project: Option<entities::project::Model>,
-    pub bearer_token: Option<String>,
     pub worker: Option<Arc<graphile_worker::Worker>>,
 }
 
+impl AppState {
+    pub fn current_user(&self) -> Result<&entities::user::Model, AppError> {
+        self.user.as_ref().ok_or(AppError::Unauthorized)
+    }
+
+    pub fn current_project(&self) -> Result<&entities::project::Model, AppError> {
+        self.project
+            .as_ref()
+            .ok_or_else(AppError::no_project_selected)
+    }
+}
+
 // Debug-friendly wrappers for non-Debug types we want to share with the worker
 #[derive(Clone)]
 pub struct DbConn(pub DatabaseConnection);
@@ -63,7 +88,7 @@ impl std::fmt::Debug for DbConn {
 }
 
 #[derive(Clone)]
-pub struct BroadcastTx(pub Arc<broadcast::Sender<String>>);
+pub struct BroadcastTx(pub Arc<EventSender>);
 impl std::fmt::Debug for BroadcastTx {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_str("BroadcastTx(..)")
@@ -94,57 +119,42 @@ async fn logging_middleware(req: Request<Body>, next: Next) -> Result<Response,
     Ok(response)
 }
 async fn auth_middleware(
-    State(mut app_state): State<AppState>,
+    State(app_state): State<AppState>,
     mut req: Request<Body>,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, AppError> {
     let path = req.uri().path();
 
     // Allow unauthenticated routes
-    if path.starts_with("/api/auth") || path.starts_with("/ws") {
+    // Repository hooks authenticate with their own signatures, and file links with theirs
+    if path.starts_with("/api/auth")
+        || path.starts_with("/ws")
+        || path.starts_with("/api/hooks/")
+        || path.starts_with("/api/uploads/assets/")
+        || path.starts_with("/api/uploads/thumbnails/")
+    {
         return Ok(next.run(req).await);
     }
 
     // All other routes require a valid JWT. We set the user for all, and set project if present in claims.
-    let mut auth_header: Option<String> = req
+    let auth_header: Option<String> = req
         .headers()
         .get(AUTHORIZATION)
         .and_then(|value| value.to_str().ok())
         .map(|s| s.to_string());
 
-    if path.starts_with("/api/uploads/assets") {
-        let query = req.uri().query();
-        if let Some(query_str) = query {
-            #[derive(Deserialize)]
-            struct QueryParams {
-                token: Option<String>,
-            }
-
-            let params: QueryParams =
-                serde_urlencoded::from_str(query_str).map_err(|_| StatusCode::UNAUTHORIZED)?;
-
-            auth_header = Some(format!("Bearer {}", params.token.unwrap()));
-        }
-    }
-
     if let Some(auth_header_value) = auth_header {
-        debug!("Authorization header value: {}", auth_header_value);
-
         if let Some(token) = JwtService::extract_bearer_token(&auth_header_value) {
-            debug!("Authorization header value: {}", auth_header_value);
-
             let jwt_service = JwtService::new();
             match jwt_service.validate_token(token) {
                 Ok(claims) => {
                     debug!("Valid JWT found, user_id: {}", claims.user_id);
 
-                    app_state.bearer_token = Some(token.to_string());
-
                     // Load user
                     let user_crud = UserCrud::new(app_state.clone());
                     let user = match user_crud.find_by_id(claims.user_id).await {
                         Ok(Some(u)) => u,
-                        _ => return Err(StatusCode::UNAUTHORIZED),
+                        _ => return Err(AppError::Unauthorized),
                     };
 
                     // Attach to request extensions with user
@@ -170,14 +180,14 @@ async fn auth_middleware(
                 }
                 Err(e) => {
                     warn!("Invalid JWT token: {:?}", e);
-                    return Err(StatusCode::UNAUTHORIZED);
+                    return Err(AppError::Unauthorized);
                 }
             }
         }
     }
 
     warn!("No authorization token provided");
-    Err(StatusCode::UNAUTHORIZED)
+    Err(AppError::Unauthorized)
 }
 
 fn main() {
@@ -197,7 +207,7 @@ fn main() {
         "Initializing broadcast channel with buffer size: {}",
         buffer_size
     );
-    let (tx, _rx) = broadcast::channel::<String>(buffer_size);
+    let (tx, _rx) = broadcast::channel::<Arc<EventEnvelope>>(buffer_size);
     let tx = std::sync::Arc::new(tx);
 
     let rt = tokio::runtime::Runtime::new().unwrap();
@@ -206,16 +216,54 @@ fn main() {
         let database_url = environment::database_url();
         let conn = Database::connect(database_url).await.unwrap();
 
+        let event_router = websocket::EventRouter::spawn(&tx, &conn);
+        if environment::asset_url_secret().is_none() {
+            warn!("ASSET_URL_SECRET is not set; file links are signed with a key derived from JWT_SECRET");
+        }
+        if EventFanout::parse(environment::event_fanout()).is_none() {
+            warn!(
+                "Unknown EVENT_FANOUT '{}', using '{}'",
+                environment::event_fanout(),
+                FANOUT_MEMORY
+            );
+        }
+        let fanout = EventFanout::configured();
+        match fanout {
+            EventFanout::Postgres if conn.get_database_backend() != DbBackend::Postgres => {
+                panic!(
+                    "EVENT_FANOUT={} requires a Postgres database",
+                    FANOUT_POSTGRES
+                )
+            }
+            EventFanout::Postgres => {}
+            EventFanout::Memory => info!("Events are only broadcast within this instance"),
+        }
+        spawn_listener(fanout, conn.clone(), tx.clone(), event_router.clone());
+
         // Initialize Graphile worker synchronously so we can store it in AppState, then run it in background
         let worker_ext = WorkerAppState {
             db: DbConn(conn.clone()),
             tx: BroadcastTx(tx.clone()),
         };
         let worker_arc_opt: Option<Arc<graphile_worker::Worker>> = match WorkerOptions::default()
-            .database_url(&database_url)
+            .database_url(database_url)
             .schema("graphile_worker")
             .add_extension(worker_ext)
             .define_job::<crate::notifications::push_notification::PushNotification>()
+            .define_job::<crate::notifications::gotify_provisioning::ProvisionGotify>()
+            .define_job::<crate::notifications::email_digest::EmailDigest>()
+            .define_job::<crate::notifications::project_webhook::DeliverWebhook>()
+            .define_job::<crate::notifications::event_retention::PruneDomainEvents>()
+            .define_job::<crate::notifications::upload_session_cleanup::PruneUploadSessions>()
+            .define_job::<crate::crud::thumbnail::GenerateThumbnail>()
+            .with_crontab(crate::notifications::email_digest::EMAIL_DIGEST_CRONTAB)
+            .expect("email digest crontab is valid")
+            .with_crontab(crate::notifications::event_retention::PRUNE_DOMAIN_EVENTS_CRONTAB)
+            .expect("event retention crontab is valid")
+            .with_crontab(
+                crate::notifications::upload_session_cleanup::PRUNE_UPLOAD_SESSIONS_CRONTAB,
+            )
+            .expect("upload session cleanup crontab is valid")
             .init()
             .await
         {
@@ -256,9 +304,13 @@ fn main() {
             tx: tx.clone(),
             user: None,
             project: None,
-            bearer_token: None,
             worker: worker_arc_opt,
         };
+        if app_state.worker.is_some() {
+            crate::notifications::project_webhook::spawn_dispatcher(app_state.clone());
+        } else {
+            warn!("Background worker is not available, webhooks will not be delivered");
+        }
 
         let api_routes = Router::new()
             .merge(auth_routes())
@@ -270,12 +322,20 @@ fn main() {
             .merge(tag_routes())
             .merge(issue_tag_routes())
             .merge(issue_assignee_routes())
+            .merge(issue_subscription_routes())
             .merge(task_routes())
+            .merge(estimation_session_routes())
             .merge(blocker_routes())
             .merge(import_export_routes())
             .merge(file_upload_routes())
             .merge(history_routes())
+            .merge(event_routes())
             .merge(notification_routes())
+            .merge(mention_routes())
+            .merge(notification_channel_routes())
+            .merge(webhook_routes())
+            .merge(repository_integration_routes())
+            .merge(notification_preference_routes())
             .merge(project_note_part_routes())
             .merge(project_note_tag_routes())
             .merge(project_note_routes());
@@ -284,7 +344,10 @@ fn main() {
 
         let api_router = Router::new()
             .nest("/api", api_routes)
-            .route("/ws", get(websocket::ws_handler))
+            .route(
+                "/ws",
+                get(websocket::ws_handler).layer(Extension(event_router)),
+            )
             .layer(middleware::from_fn(logging_middleware))
             .layer(middleware::from_fn_with_state(
                 app_state.clone(),
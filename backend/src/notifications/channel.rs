@@ -0,0 +1,447 @@
+use crate::environment;
+use crate::notifications::destination::{check_destination, check_url, Destination};
+use crate::notifications::email::EmailChannel;
+use crate::notifications::gotify::GotifyChannel;
+use crate::notifications::mailer;
+use crate::notifications::ntfy::NtfyChannel;
+use crate::notifications::webhook::{ChatFlavor, ChatWebhookChannel, WebhookChannel};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::warn;
+
+pub const CHANNEL_GOTIFY: &str = "gotify";
+pub const CHANNEL_EMAIL: &str = "email";
+pub const CHANNEL_WEBHOOK: &str = "webhook";
+pub const CHANNEL_NTFY: &str = "ntfy";
+pub const CHANNEL_SLACK: &str = "slack";
+pub const CHANNEL_MATRIX: &str = "matrix";
+
+pub const CHANNELS: [&str; 6] = [
+    CHANNEL_GOTIFY,
+    CHANNEL_EMAIL,
+    CHANNEL_WEBHOOK,
+    CHANNEL_NTFY,
+    CHANNEL_SLACK,
+    CHANNEL_MATRIX,
+];
+
+pub const DEFAULT_PRIORITY: i32 = 5;
+
+// Receivers' error bodies are logged, never shown to the channel's owner
+const MAX_ERROR_BODY: usize = 500;
+
+/// What every channel delivers. Priority uses Gotify's 0-10 scale; channels with their
+/// own scale map onto it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutgoingNotification {
+    pub title: String,
+    pub message: String,
+    pub priority: i32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub issue_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl OutgoingNotification {
+    pub fn new(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            message: message.into(),
+            priority: DEFAULT_PRIORITY,
+            project_id: None,
+            issue_id: None,
+            link: None,
+        }
+    }
+
+    pub fn for_issue(mut self, project_id: i32, issue_id: i32) -> Self {
+        self.project_id = Some(project_id);
+        self.issue_id = Some(issue_id);
+        self.link = Some(format!(
+            "{}/issues/{}",
+            environment::frontend_url().trim_end_matches('/'),
+            issue_id
+        ));
+        self
+    }
+}
+
+#[async_trait]
+pub trait NotificationChannel: Send + Sync {
+    fn kind(&self) -> &'static str;
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String>;
+}
+
+#[derive(Deserialize)]
+struct GotifyConfig {
+    token: String,
+    url: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct EmailConfig {
+    #[serde(default)]
+    to: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct WebhookConfig {
+    url: String,
+    #[serde(default)]
+    headers: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct NtfyConfig {
+    url: Option<String>,
+    topic: String,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ChatWebhookConfig {
+    url: String,
+}
+
+fn parse_config<T: for<'de> Deserialize<'de>>(kind: &str, config: &Value) -> Result<T, String> {
+    serde_json::from_value(config.clone())
+        .map_err(|e| format!("invalid {} channel config: {}", kind, e))
+}
+
+// A URL from the channel config was typed in by a user; the default is the operator's
+fn channel_url(url: Option<String>, default: &str) -> (String, Destination) {
+    match url {
+        Some(url) => (url, Destination::Public),
+        None => (default.to_string(), Destination::Configured),
+    }
+}
+
+/// Builds a channel from its stored kind and JSON config. Email channels without
+/// explicit recipients are addressed by the caller, which knows the user's address.
+pub fn build_channel(kind: &str, config: &Value) -> Result<Box<dyn NotificationChannel>, String> {
+    match kind {
+        CHANNEL_GOTIFY => {
+            let config: GotifyConfig = parse_config(kind, config)?;
+            let (url, destination) = channel_url(config.url, environment::gotify_url());
+            check_url(&url, destination)?;
+            Ok(Box::new(GotifyChannel::new(url, config.token, destination)))
+        }
+        CHANNEL_EMAIL => {
+            let config: EmailConfig = parse_config(kind, config)?;
+            if config.to.is_empty() {
+                return Err("email channel needs at least one recipient in 'to'".to_string());
+            }
+            Ok(Box::new(EmailChannel::new(
+                mailer::default_mailer()?,
+                config.to,
+            )))
+        }
+        CHANNEL_WEBHOOK => {
+            let config: WebhookConfig = parse_config(kind, config)?;
+            check_url(&config.url, Destination::Public)?;
+            Ok(Box::new(WebhookChannel::new(
+                config.url,
+                config.headers,
+                Destination::Public,
+            )))
+        }
+        CHANNEL_NTFY => {
+            let config: NtfyConfig = parse_config(kind, config)?;
+            let (url, destination) = channel_url(config.url, environment::ntfy_url());
+            check_url(&url, destination)?;
+            if config.topic.trim().is_empty() {
+                return Err("ntfy channel needs a topic".to_string());
+            }
+            Ok(Box::new(NtfyChannel::new(
+                url,
+                config.topic,
+                config.token,
+                destination,
+            )))
+        }
+        CHANNEL_SLACK | CHANNEL_MATRIX => {
+            let config: ChatWebhookConfig = parse_config(kind, config)?;
+            check_url(&config.url, Destination::Public)?;
+            let flavor = if kind == CHANNEL_SLACK {
+                ChatFlavor::Slack
+            } else {
+                ChatFlavor::Matrix
+            };
+            Ok(Box::new(ChatWebhookChannel::new(
+                config.url,
+                flavor,
+                Destination::Public,
+            )))
+        }
+        other => Err(format!(
+            "unknown channel '{}', expected one of {}",
+            other,
+            CHANNELS.join(", ")
+        )),
+    }
+}
+
+/// `build_channel` plus a lookup of the URL the config names, for when a channel is saved
+pub async fn check_channel(kind: &str, config: &Value) -> Result<(), String> {
+    build_channel(kind, config)?;
+    match config.get("url").and_then(Value::as_str) {
+        Some(url) => check_destination(url, Destination::Public).await,
+        None => Ok(()),
+    }
+}
+
+// Shared by the HTTP-based channels: anything but a 2xx is a failed delivery
+pub(crate) async fn check_response(
+    kind: &str,
+    result: Result<reqwest::Response, reqwest::Error>,
+) -> Result<(), String> {
+    let response = result.map_err(|e| format!("{} request failed: {}", kind, e))?;
+    if response.status().is_success() {
+        return Ok(());
+    }
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let body: String = body.chars().take(MAX_ERROR_BODY).collect();
+    warn!("{} channel responded with {}: {}", kind, status, body);
+    Err(format!("{} responded with {}", kind, status))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::mailer::{Email, Mailer};
+    use axum::{
+        body::Bytes,
+        extract::State,
+        http::{header, HeaderMap, StatusCode},
+        routing::post,
+        Router,
+    };
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Captured {
+        requests: Arc<Mutex<Vec<(String, HeaderMap, Bytes)>>>,
+    }
+
+    async fn capture(
+        State(captured): State<Captured>,
+        uri: axum::http::Uri,
+        headers: HeaderMap,
+        body: Bytes,
+    ) -> &'static str {
+        captured
+            .requests
+            .lock()
+            .unwrap()
+            .push((uri.path().to_string(), headers, body));
+        "{\"id\":1,\"message\":\"m\",\"title\":\"t\",\"priority\":5,\"date\":\"now\"}"
+    }
+
+    // Local stand-in for Gotify/ntfy/webhook receivers that records every request. It
+    // listens on loopback, so channels pointed at it are built as operator-configured.
+    async fn mock_server() -> (String, Captured) {
+        let captured = Captured::default();
+        let app = Router::new()
+            .fallback(post(capture))
+            .with_state(captured.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, captured)
+    }
+
+    fn notification() -> OutgoingNotification {
+        let mut n = OutgoingNotification::new("Rotate secrets", "It's the last Friday");
+        n.issue_id = Some(7);
+        n.link = Some("http://localhost:3000/issues/7".to_string());
+        n
+    }
+
+    fn json_body(captured: &Captured) -> Value {
+        let requests = captured.requests.lock().unwrap();
+        serde_json::from_slice(&requests[0].2).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_gotify_channel_posts_message_with_key() {
+        let (url, captured) = mock_server().await;
+        let channel = GotifyChannel::new(url, "abc".to_string(), Destination::Configured);
+        channel.send(&notification()).await.unwrap();
+
+        let requests = captured.requests.lock().unwrap();
+        assert_eq!(requests[0].0, "/message");
+        assert_eq!(requests[0].1["x-gotify-key"], "abc");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_channel_posts_json_with_headers() {
+        let (url, captured) = mock_server().await;
+        let channel = WebhookChannel::new(
+            format!("{}/hook", url),
+            HashMap::from([("X-Secret".to_string(), "s3".to_string())]),
+            Destination::Configured,
+        );
+        channel.send(&notification()).await.unwrap();
+
+        let body = json_body(&captured);
+        assert_eq!(body["title"], "Rotate secrets");
+        assert_eq!(body["issue_id"], 7);
+        assert_eq!(captured.requests.lock().unwrap()[0].1["x-secret"], "s3");
+    }
+
+    #[tokio::test]
+    async fn test_ntfy_channel_publishes_to_topic() {
+        let (url, captured) = mock_server().await;
+        let channel = NtfyChannel::new(
+            url,
+            "ops".to_string(),
+            Some("tk".to_string()),
+            Destination::Configured,
+        );
+        channel.send(&notification()).await.unwrap();
+
+        let requests = captured.requests.lock().unwrap();
+        let (path, headers, body) = &requests[0];
+        assert_eq!(path, "/ops");
+        assert_eq!(headers["title"], "Rotate secrets");
+        assert_eq!(headers["priority"], "3");
+        assert_eq!(headers["authorization"], "Bearer tk");
+        assert_eq!(body.as_ref(), b"It's the last Friday");
+    }
+
+    #[tokio::test]
+    async fn test_chat_webhooks_use_their_payload_shape() {
+        let (url, captured) = mock_server().await;
+        ChatWebhookChannel::new(url, ChatFlavor::Slack, Destination::Configured)
+            .send(&notification())
+            .await
+            .unwrap();
+        let body = json_body(&captured);
+        assert!(body["text"]
+            .as_str()
+            .unwrap()
+            .starts_with("*Rotate secrets*"));
+
+        let (url, captured) = mock_server().await;
+        ChatWebhookChannel::new(url, ChatFlavor::Matrix, Destination::Configured)
+            .send(&notification())
+            .await
+            .unwrap();
+        let body = json_body(&captured);
+        assert!(body["html"]
+            .as_str()
+            .unwrap()
+            .starts_with("<strong>Rotate secrets</strong>"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_delivery_is_an_error() {
+        let channel = WebhookChannel::new(
+            "http://127.0.0.1:9/unreachable".to_string(),
+            HashMap::new(),
+            Destination::Configured,
+        );
+        assert!(channel.send(&notification()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_channels_to_internal_addresses_are_rejected() {
+        for (kind, config) in [
+            (
+                CHANNEL_WEBHOOK,
+                serde_json::json!({"url": "http://127.0.0.1/hook"}),
+            ),
+            (
+                CHANNEL_SLACK,
+                serde_json::json!({"url": "http://127.0.0.1/hook"}),
+            ),
+            (
+                CHANNEL_MATRIX,
+                serde_json::json!({"url": "http://[::1]/hook"}),
+            ),
+            (
+                CHANNEL_NTFY,
+                serde_json::json!({"url": "http://127.0.0.1", "topic": "ops"}),
+            ),
+            (
+                CHANNEL_GOTIFY,
+                serde_json::json!({"url": "http://169.254.169.254", "token": "abc"}),
+            ),
+            (
+                CHANNEL_WEBHOOK,
+                serde_json::json!({"url": "http://localhost/hook"}),
+            ),
+        ] {
+            assert!(build_channel(kind, &config).is_err(), "{}", config);
+            assert!(check_channel(kind, &config).await.is_err(), "{}", config);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_public_channels_refuse_internal_addresses_on_send() {
+        let (url, captured) = mock_server().await;
+        let channel = WebhookChannel::new(url, HashMap::new(), Destination::Public);
+        assert!(channel.send(&notification()).await.is_err());
+        assert!(captured.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_redirects_are_not_followed_and_bodies_are_not_reported() {
+        let (target, captured) = mock_server().await;
+        let app = Router::new().fallback(post(move || async move {
+            (
+                StatusCode::TEMPORARY_REDIRECT,
+                [(header::LOCATION, format!("{}/internal", target))],
+                "internal details",
+            )
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let channel = WebhookChannel::new(url, HashMap::new(), Destination::Configured);
+        let error = channel.send(&notification()).await.unwrap_err();
+        assert_eq!(error, "webhook responded with 307 Temporary Redirect");
+        assert!(captured.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_email_channel_writes_to_maildir() {
+        let dir = std::env::temp_dir().join(format!("phoenix-maildir-{}", rand::random::<u64>()));
+        let mailer = Arc::new(Mailer::maildir(
+            dir.clone(),
+            "Phoenix <noreply@example.com>".parse().unwrap(),
+        ));
+        let channel = EmailChannel::new(mailer.clone(), vec!["dev@example.com".to_string()]);
+        channel.send(&notification()).await.unwrap();
+
+        let mut entries = std::fs::read_dir(dir.join("new")).unwrap();
+        let message = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+        assert!(message.contains("Subject: Rotate secrets"));
+        assert!(message.contains("To: dev@example.com"));
+
+        let empty = Email {
+            to: vec![],
+            subject: "x".to_string(),
+            text_body: "x".to_string(),
+            html_body: None,
+        };
+        assert!(mailer.send(&empty).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalid_configs_are_rejected() {
+        assert!(build_channel("pager", &serde_json::json!({})).is_err());
+        assert!(build_channel(CHANNEL_WEBHOOK, &serde_json::json!({"url": "ftp://x"})).is_err());
+        assert!(build_channel(CHANNEL_NTFY, &serde_json::json!({"topic": " "})).is_err());
+        assert!(build_channel(CHANNEL_GOTIFY, &serde_json::json!({})).is_err());
+        assert!(build_channel(CHANNEL_EMAIL, &serde_json::json!({"to": []})).is_err());
+    }
+}
@@ -0,0 +1,294 @@
+use crate::crud::notification_delivery::{
+    DeliveryChannel, NotificationDeliveryCrud, SOURCE_PROJECT, SOURCE_USER,
+};
+use crate::crud::notification_preference::Preferences;
+use crate::crud::notification_settings::NotificationSettingsCrud;
+use crate::crud::user::UserCrud;
+use crate::crud::user_notification_channel::UserNotificationChannelCrud;
+use crate::entities::{notification_delivery, notification_settings, user_notification_channel};
+use crate::error::AppError;
+use crate::notifications::channel::{
+    build_channel, NotificationChannel, OutgoingNotification, CHANNEL_EMAIL, CHANNEL_GOTIFY,
+};
+use crate::AppState;
+use serde_json::Value;
+use tracing::{info, warn};
+
+fn object_or_empty(config: Option<&Value>) -> Value {
+    config
+        .cloned()
+        .filter(Value::is_object)
+        .unwrap_or_else(|| serde_json::json!({}))
+}
+
+/// Fills in what a personal channel's config leaves out. Personal email channels only
+/// ever go to the owning user's address, whatever the stored config says; mailing
+/// anyone else needs a project channel, which only project owners can set up.
+pub fn resolve_user_config(kind: &str, config: Option<&Value>, email: &str) -> Value {
+    let mut config = object_or_empty(config);
+    if kind == CHANNEL_EMAIL {
+        config["to"] = serde_json::json!([email]);
+    }
+    config
+}
+
+/// Rejects personal email channels that name recipients other than their owner
+pub fn check_user_config(kind: &str, config: &Value, email: &str) -> Result<(), String> {
+    if kind != CHANNEL_EMAIL {
+        return Ok(());
+    }
+    let others = config
+        .get("to")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .any(|to| to.as_str().is_none_or(|to| !to.eq_ignore_ascii_case(email)));
+    if others {
+        return Err(
+            "personal email channels can only send to your own address; \
+             ask a project owner to add a project channel for other recipients"
+                .to_string(),
+        );
+    }
+    Ok(())
+}
+
+fn parse_stored(config: &Option<String>) -> Option<Value> {
+    config.as_deref().and_then(|c| serde_json::from_str(c).ok())
+}
+
+pub fn project_channel(
+    settings: &notification_settings::Model,
+) -> Result<Box<dyn NotificationChannel>, String> {
+    let mut config = object_or_empty(parse_stored(&settings.config).as_ref());
+    // Provisioned Gotify applications keep their token in its own column
+    if settings.channel == CHANNEL_GOTIFY && config.get("token").is_none() {
+        config["token"] = Value::String(settings.token.clone());
+    }
+    build_channel(&settings.channel, &config)
+}
+
+pub fn user_channel(
+    channel: &user_notification_channel::Model,
+    email: &str,
+) -> Result<Box<dyn NotificationChannel>, String> {
+    let config = resolve_user_config(
+        &channel.channel,
+        parse_stored(&channel.config).as_ref(),
+        email,
+    );
+    build_channel(&channel.channel, &config)
+}
+
+/// Sends to every channel and succeeds if at least one delivery went through, so a single
+/// broken integration doesn't cause the others to be retried and repeat themselves.
+pub async fn deliver_all(
+    channels: &[Box<dyn NotificationChannel>],
+    notification: &OutgoingNotification,
+) -> Result<usize, String> {
+    if channels.is_empty() {
+        return Err("No notification channels are configured".to_string());
+    }
+    let mut results = Vec::new();
+    for channel in channels {
+        results.push((channel.kind(), channel.send(notification).await));
+    }
+    summarize(&results, notification)
+}
+
+fn summarize(
+    results: &[(&'static str, Result<(), String>)],
+    notification: &OutgoingNotification,
+) -> Result<usize, String> {
+    let mut failures = Vec::new();
+    for (kind, result) in results {
+        if let Err(e) = result {
+            warn!("{} delivery failed: {}", kind, e);
+            failures.push(format!("{}: {}", kind, e));
+        }
+    }
+    let delivered = results.len() - failures.len();
+    if delivered == 0 {
+        return Err(failures.join("; "));
+    }
+    info!(
+        "Delivered '{}' to {} of {} channels",
+        notification.title,
+        delivered,
+        results.len()
+    );
+    Ok(delivered)
+}
+
+/// A built channel together with the stored row it came from, so each attempt can be
+/// recorded and retried later.
+pub struct DeliveryTarget {
+    pub source: &'static str,
+    pub channel_id: i32,
+    pub user_id: Option<i32>,
+    pub channel: Box<dyn NotificationChannel>,
+}
+
+impl DeliveryTarget {
+    fn describe(&self) -> DeliveryChannel<'_> {
+        DeliveryChannel {
+            source: self.source,
+            channel_id: self.channel_id,
+            channel: self.channel.kind(),
+            user_id: self.user_id,
+        }
+    }
+}
+
+pub struct NotificationDelivery {
+    app_state: AppState,
+}
+
+impl NotificationDelivery {
+    pub fn new(app_state: AppState) -> Self {
+        Self { app_state }
+    }
+
+    // Rows with a config that no longer builds are skipped rather than failing the rest
+    pub async fn project_channels(&self, project_id: i32) -> Result<Vec<DeliveryTarget>, AppError> {
+        let settings = NotificationSettingsCrud::new(self.app_state.clone())
+            .find_enabled_by_project_id(project_id)
+            .await?;
+        Ok(settings
+            .iter()
+            .filter_map(|s| {
+                project_channel(s)
+                    .inspect_err(|e| warn!("Skipping project channel {}: {}", s.id, e))
+                    .ok()
+                    .map(|channel| DeliveryTarget {
+                        source: SOURCE_PROJECT,
+                        channel_id: s.id,
+                        user_id: None,
+                        channel,
+                    })
+            })
+            .collect())
+    }
+
+    pub async fn user_channels(&self, user_id: i32) -> Result<Vec<DeliveryTarget>, AppError> {
+        let Some(user) = UserCrud::new(self.app_state.clone())
+            .find_by_id(user_id)
+            .await?
+        else {
+            return Ok(vec![]);
+        };
+        let channels = UserNotificationChannelCrud::new(self.app_state.clone())
+            .find_all_by_user_id(user_id)
+            .await?;
+        Ok(channels
+            .iter()
+            .filter(|c| c.enabled)
+            .filter_map(|c| {
+                user_channel(c, &user.email)
+                    .inspect_err(|e| warn!("Skipping user channel {}: {}", c.id, e))
+                    .ok()
+                    .map(|channel| DeliveryTarget {
+                        source: SOURCE_USER,
+                        channel_id: c.id,
+                        user_id: Some(user_id),
+                        channel,
+                    })
+            })
+            .collect())
+    }
+
+    /// Like `deliver_all`, but records every attempt against the project the
+    /// notification belongs to, so failures can be reviewed and retried.
+    pub async fn deliver(
+        &self,
+        targets: &[DeliveryTarget],
+        notification: &OutgoingNotification,
+    ) -> Result<usize, String> {
+        if targets.is_empty() {
+            return Err("No notification channels are configured".to_string());
+        }
+        let payload = serde_json::to_string(notification).map_err(|e| e.to_string())?;
+        let delivery_crud = NotificationDeliveryCrud::new(self.app_state.clone());
+        let mut results = Vec::new();
+        for target in targets {
+            let result = target.channel.send(notification).await;
+            if let Some(project_id) = notification.project_id {
+                // Losing the record shouldn't turn a delivered notification into a failure
+                if let Err(e) = delivery_crud
+                    .record(project_id, target.describe(), payload.clone(), &result)
+                    .await
+                {
+                    warn!("Failed to record {} delivery: {}", target.channel.kind(), e);
+                }
+            }
+            results.push((target.channel.kind(), result));
+        }
+        summarize(&results, notification)
+    }
+
+    /// Sends a recorded attempt again through the same channel, which has to still exist.
+    pub async fn retry(
+        &self,
+        delivery: notification_delivery::Model,
+    ) -> Result<notification_delivery::Model, AppError> {
+        let notification: OutgoingNotification = serde_json::from_str(&delivery.payload)
+            .map_err(|e| AppError::Internal(format!("Unreadable delivery payload: {}", e)))?;
+        let targets = match delivery.user_id {
+            Some(user_id) if delivery.source == SOURCE_USER => self.user_channels(user_id).await?,
+            _ => self.project_channels(delivery.project_id).await?,
+        };
+        let target = targets
+            .iter()
+            .find(|t| t.source == delivery.source && t.channel_id == delivery.channel_id)
+            .ok_or_else(|| AppError::not_found("Enabled notification channel"))?;
+
+        let result = target.channel.send(&notification).await;
+        if let Err(e) = &result {
+            warn!("Retry of delivery {} failed: {}", delivery.id, e);
+        }
+        NotificationDeliveryCrud::new(self.app_state.clone())
+            .record_retry(delivery, &result)
+            .await
+    }
+
+    // Users without channels simply get nothing pushed; the in-app notification remains
+    pub async fn send_to_user(
+        &self,
+        user_id: i32,
+        preferences: &Preferences,
+        notification: &OutgoingNotification,
+    ) -> Result<usize, AppError> {
+        let mut targets = self.user_channels(user_id).await?;
+        targets.retain(|t| preferences.allows_channel(t.channel.kind()));
+        if targets.is_empty() {
+            return Ok(0);
+        }
+        self.deliver(&targets, notification)
+            .await
+            .map_err(AppError::Upstream)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_personal_email_channels_only_reach_their_owner() {
+        let me = "me@example.com";
+        assert!(check_user_config(CHANNEL_EMAIL, &json!({}), me).is_ok());
+        assert!(check_user_config(CHANNEL_EMAIL, &json!({"to": ["Me@Example.com"]}), me).is_ok());
+        assert!(
+            check_user_config(CHANNEL_EMAIL, &json!({"to": [me, "x@example.com"]}), me).is_err()
+        );
+        assert!(check_user_config(CHANNEL_EMAIL, &json!({"to": [42]}), me).is_err());
+
+        // Rows stored before the check still only go to the owner
+        let stored = json!({"to": ["x@example.com"]});
+        assert_eq!(
+            resolve_user_config(CHANNEL_EMAIL, Some(&stored), me),
+            json!({"to": [me]})
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use reqwest::redirect::Policy;
+use reqwest::Url;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+// One unreachable or slow endpoint must not hold up delivery to the others
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Who chose where a request goes. The operator's own services (GOTIFY_URL, NTFY_URL) may
+/// live on internal addresses; anything a user or project owner typed in must be public,
+/// or the server could be used to reach services that are not meant to be exposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    Configured,
+    Public,
+}
+
+fn is_public_ipv4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        || a == 0
+        // Carrier-grade NAT, 100.64.0.0/10
+        || (a == 100 && (64..128).contains(&b))
+        // Reserved, 240.0.0.0/4
+        || a >= 240)
+}
+
+fn is_public_ipv6(ip: Ipv6Addr) -> bool {
+    if let Some(v4) = ip.to_ipv4_mapped() {
+        return is_public_ipv4(v4);
+    }
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_unique_local()
+        || ip.is_unicast_link_local()
+        || ip.is_multicast())
+}
+
+pub fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_ipv4(ip),
+        IpAddr::V6(ip) => is_public_ipv6(ip),
+    }
+}
+
+// An IP literal host, or the normalised domain name otherwise
+fn host_ip(host: &str) -> Result<IpAddr, String> {
+    let bare = host.trim_start_matches('[').trim_end_matches(']');
+    bare.parse()
+        .map_err(|_| bare.trim_end_matches('.').to_ascii_lowercase())
+}
+
+// Every address a name resolves to must be public, so it cannot be pointed at both
+fn check_addrs(host: &str, addrs: &[SocketAddr]) -> Result<(), String> {
+    if addrs.is_empty() {
+        return Err(format!("'{}' does not resolve to any address", host));
+    }
+    if addrs.iter().any(|addr| !is_public_ip(addr.ip())) {
+        return Err(format!("'{}' resolves to a non-public address", host));
+    }
+    Ok(())
+}
+
+/// Checks what can be told without a DNS lookup: the scheme, and that an IP literal or
+/// `localhost` is not used for a public destination.
+pub fn check_url(url: &str, destination: Destination) -> Result<Url, String> {
+    let parsed = Url::parse(url).map_err(|_| format!("'{}' must be an http(s) URL", url))?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err(format!("'{}' must be an http(s) URL", url));
+    }
+    if destination == Destination::Configured {
+        return Ok(parsed);
+    }
+    let not_public = match parsed.host_str().map(host_ip) {
+        Some(Ok(ip)) => !is_public_ip(ip),
+        Some(Err(domain)) => domain == "localhost" || domain.ends_with(".localhost"),
+        None => true,
+    };
+    if not_public {
+        return Err(format!("'{}' is not a public address", url));
+    }
+    Ok(parsed)
+}
+
+/// `check_url` plus a lookup of the host, for when a destination is saved and before
+/// each request to it. The client's resolver repeats the check when it connects, so a
+/// name that changes its answer in between is still refused.
+pub async fn check_destination(url: &str, destination: Destination) -> Result<(), String> {
+    let parsed = check_url(url, destination)?;
+    if destination == Destination::Configured {
+        return Ok(());
+    }
+    if let Some(Err(domain)) = parsed.host_str().map(host_ip) {
+        let port = parsed.port_or_known_default().unwrap_or(80);
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((domain.as_str(), port))
+            .await
+            .map_err(|e| format!("could not resolve '{}': {}", domain, e))?
+            .collect();
+        check_addrs(&domain, &addrs)?;
+    }
+    Ok(())
+}
+
+// Refuses to connect to names that resolve to non-public addresses
+struct PublicResolver;
+
+impl Resolve for PublicResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            check_addrs(&host, &addrs)?;
+            Ok(Box::new(addrs.into_iter()) as Addrs)
+        })
+    }
+}
+
+/// Client for outgoing notification requests. Redirects are never followed, since they
+/// could lead a public destination to an internal one.
+pub fn http_client(destination: Destination) -> reqwest::Client {
+    let builder = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .timeout(REQUEST_TIMEOUT)
+        .redirect(Policy::none());
+    let builder = match destination {
+        Destination::Configured => builder,
+        Destination::Public => builder.dns_resolver(Arc::new(PublicResolver)),
+    };
+    builder.build().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_addresses_are_not_public() {
+        for url in [
+            "http://127.0.0.1/hook",
+            "http://127.0.0.1:8080/hook",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.0.0.5/",
+            "http://172.16.0.1/",
+            "http://192.168.1.1/",
+            "http://100.64.0.1/",
+            "http://0.0.0.0/",
+            "http://[::1]/",
+            "http://[fd00::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://localhost:3001/",
+            "http://api.localhost/",
+        ] {
+            assert!(check_url(url, Destination::Public).is_err(), "{}", url);
+        }
+        assert!(check_url("ftp://example.com", Destination::Public).is_err());
+        assert!(check_url("https://93.184.215.14/hook", Destination::Public).is_ok());
+        assert!(check_url("https://hooks.example.com/x", Destination::Public).is_ok());
+        // The operator's own services may be internal
+        assert!(check_url("http://127.0.0.1:8080", Destination::Configured).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_names_resolving_to_internal_addresses_are_refused() {
+        assert!(check_destination("http://localhost/", Destination::Public)
+            .await
+            .is_err());
+        let addrs = [SocketAddr::from(([127, 0, 0, 1], 80))];
+        assert!(check_addrs("internal.example.com", &addrs).is_err());
+        let mixed = [
+            SocketAddr::from(([93, 184, 215, 14], 80)),
+            SocketAddr::from(([10, 0, 0, 1], 80)),
+        ];
+        assert!(check_addrs("rebind.example.com", &mixed).is_err());
+        assert!(check_addrs("nowhere.example.com", &[]).is_err());
+    }
+}
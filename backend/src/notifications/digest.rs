@@ -0,0 +1,358 @@
+use crate::entities::notification;
+use crate::environment;
+use crate::notifications::mailer::Email;
+use crate::notifications::template;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::Serialize;
+use std::collections::HashMap;
+
+pub const DIGEST_OFF: &str = "off";
+pub const DIGEST_DAILY: &str = "daily";
+pub const DIGEST_WEEKLY: &str = "weekly";
+pub const DIGEST_FREQUENCIES: [&str; 3] = [DIGEST_OFF, DIGEST_DAILY, DIGEST_WEEKLY];
+
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+// Keeps a long-ignored inbox from producing an unreadable email
+pub const MAX_DIGEST_NOTIFICATIONS: u64 = 200;
+
+pub fn parse_timezone(name: &str) -> Result<Tz, String> {
+    name.parse::<Tz>()
+        .map_err(|_| format!("unknown timezone '{}'", name))
+}
+
+fn period(frequency: &str) -> Option<Duration> {
+    match frequency {
+        DIGEST_DAILY => Some(Duration::days(1)),
+        DIGEST_WEEKLY => Some(Duration::weeks(1)),
+        _ => None,
+    }
+}
+
+// `hour` on `date` in the user's timezone; a time skipped by a DST change moves an hour on
+fn local_time(tz: Tz, date: NaiveDate, hour: u32) -> Option<DateTime<Utc>> {
+    let naive = date.and_hms_opt(hour, 0, 0)?;
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(naive + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|at| at.with_timezone(&Utc))
+}
+
+/// The most recent time a digest fell due: `hour` o'clock in the user's timezone, every
+/// day or every Monday. None when digests are off.
+pub fn last_due_at(
+    frequency: &str,
+    tz: Tz,
+    hour: u32,
+    now: DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let period = period(frequency)?;
+    let today = now.with_timezone(&tz).date_naive();
+    let start = match frequency {
+        DIGEST_WEEKLY => today - Duration::days(today.weekday().num_days_from_monday() as i64),
+        _ => today,
+    };
+    let due = local_time(tz, start, hour)?;
+    if due <= now {
+        Some(due)
+    } else {
+        local_time(tz, start - period, hour)
+    }
+}
+
+// Running the job more often than the digest period is harmless: a user is only due
+// once per slot, however many times the job checks.
+pub fn is_due(
+    frequency: &str,
+    tz: Tz,
+    hour: u32,
+    last_sent_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> bool {
+    match last_due_at(frequency, tz, hour, now) {
+        Some(due) => last_sent_at.is_none_or(|sent| sent < due),
+        None => false,
+    }
+}
+
+// Only what arrived since the last digest; the first one looks back a single period
+pub fn window_start(
+    frequency: &str,
+    last_sent_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> DateTime<Utc> {
+    last_sent_at.unwrap_or_else(|| now - period(frequency).unwrap_or_else(|| Duration::days(1)))
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestEntry {
+    pub title: String,
+    pub description: String,
+    // Already formatted in the recipient's timezone
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestIssue {
+    pub issue_id: i32,
+    pub title: String,
+    pub link: String,
+    pub notifications: Vec<DigestEntry>,
+    #[serde(skip)]
+    pub last_activity: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DigestProject {
+    pub project_id: i32,
+    pub name: String,
+    pub issues: Vec<DigestIssue>,
+}
+
+/// Groups notifications by project (by name) and issue (most recently active first),
+/// keeping each issue's notifications in the order they happened.
+pub fn group_notifications(
+    notifications: &[notification::Model],
+    project_names: &HashMap<i32, String>,
+    issue_titles: &HashMap<i32, String>,
+    tz: Tz,
+) -> Vec<DigestProject> {
+    let mut sorted: Vec<&notification::Model> = notifications.iter().collect();
+    sorted.sort_by_key(|n| (n.created_at, n.id));
+
+    let mut projects: Vec<DigestProject> = Vec::new();
+    for n in sorted {
+        let project_index = match projects.iter().position(|p| p.project_id == n.project_id) {
+            Some(index) => index,
+            None => {
+                projects.push(DigestProject {
+                    project_id: n.project_id,
+                    name: project_names
+                        .get(&n.project_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("Project #{}", n.project_id)),
+                    issues: Vec::new(),
+                });
+                projects.len() - 1
+            }
+        };
+        let issues = &mut projects[project_index].issues;
+        let issue_index = match issues.iter().position(|i| i.issue_id == n.issue_id) {
+            Some(index) => index,
+            None => {
+                issues.push(DigestIssue {
+                    issue_id: n.issue_id,
+                    title: issue_titles
+                        .get(&n.issue_id)
+                        .cloned()
+                        .unwrap_or_else(|| format!("Issue #{}", n.issue_id)),
+                    link: format!(
+                        "{}/issues/{}",
+                        environment::frontend_url().trim_end_matches('/'),
+                        n.issue_id
+                    ),
+                    notifications: Vec::new(),
+                    last_activity: n.created_at.with_timezone(&Utc),
+                });
+                issues.len() - 1
+            }
+        };
+        issues[issue_index].last_activity = n.created_at.with_timezone(&Utc);
+        issues[issue_index].notifications.push(DigestEntry {
+            title: n.title.clone(),
+            description: n.description.clone(),
+            created_at: n
+                .created_at
+                .with_timezone(&tz)
+                .format("%a %b %-d, %H:%M")
+                .to_string(),
+        });
+    }
+
+    projects.sort_by_key(|p| p.name.to_lowercase());
+    for project in &mut projects {
+        project
+            .issues
+            .sort_by_key(|i| std::cmp::Reverse(i.last_activity));
+    }
+    projects
+}
+
+pub fn render_digest(
+    to: &str,
+    user_name: &str,
+    frequency: &str,
+    projects: &[DigestProject],
+) -> Result<Email, String> {
+    let total: usize = projects
+        .iter()
+        .flat_map(|p| &p.issues)
+        .map(|i| i.notifications.len())
+        .sum();
+    let period = if frequency == DIGEST_WEEKLY {
+        "week"
+    } else {
+        "day"
+    };
+    let context = serde_json::json!({
+        "user_name": user_name,
+        "total": total,
+        "period": period,
+        "projects": projects,
+        "app_link": environment::frontend_url(),
+    });
+
+    Ok(Email {
+        to: vec![to.to_string()],
+        subject: format!(
+            "Your {} Phoenix Tracker digest: {} unread notification{}",
+            frequency,
+            total,
+            if total == 1 { "" } else { "s" }
+        ),
+        text_body: template::render("email/digest.txt", &context)?,
+        html_body: Some(template::render("email/digest.html", &context)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::mailer::Mailer;
+
+    fn utc(value: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn notification(id: i32, project_id: i32, issue_id: i32, at: &str) -> notification::Model {
+        notification::Model {
+            id,
+            title: format!("Update {}", id),
+            description: "<b>changed</b>".to_string(),
+            project_id,
+            issue_id,
+            initiated_by_user_id: 1,
+            targeted_user_id: 2,
+            read: false,
+            kind: "issue_update".to_string(),
+            archived: false,
+            created_at: utc(at).into(),
+            updated_at: utc(at).into(),
+        }
+    }
+
+    #[test]
+    fn test_daily_digest_is_due_at_the_local_hour() {
+        let tz = parse_timezone("America/New_York").unwrap();
+        // 8:00 in New York on 19 Oct 2026 (EDT) is 12:00 UTC
+        let now = utc("2026-10-19T12:30:00Z");
+        assert_eq!(
+            last_due_at(DIGEST_DAILY, tz, 8, now),
+            Some(utc("2026-10-19T12:00:00Z"))
+        );
+        assert_eq!(
+            last_due_at(DIGEST_DAILY, tz, 8, utc("2026-10-19T11:00:00Z")),
+            Some(utc("2026-10-18T12:00:00Z"))
+        );
+
+        assert!(is_due(DIGEST_DAILY, tz, 8, None, now));
+        assert!(is_due(
+            DIGEST_DAILY,
+            tz,
+            8,
+            Some(utc("2026-10-18T12:00:00Z")),
+            now
+        ));
+        assert!(!is_due(
+            DIGEST_DAILY,
+            tz,
+            8,
+            Some(utc("2026-10-19T12:05:00Z")),
+            now
+        ));
+        assert!(!is_due(DIGEST_OFF, tz, 8, None, now));
+    }
+
+    #[test]
+    fn test_weekly_digest_is_due_on_monday() {
+        let tz = parse_timezone("Europe/Berlin").unwrap();
+        // Thursday 22 Oct; the slot is Monday 19 Oct at 08:00 CEST
+        assert_eq!(
+            last_due_at(DIGEST_WEEKLY, tz, 8, utc("2026-10-22T09:00:00Z")),
+            Some(utc("2026-10-19T06:00:00Z"))
+        );
+        // Early Monday morning still belongs to the previous week
+        assert_eq!(
+            last_due_at(DIGEST_WEEKLY, tz, 8, utc("2026-10-19T05:00:00Z")),
+            Some(utc("2026-10-12T06:00:00Z"))
+        );
+        assert!(parse_timezone("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_notifications_are_grouped_by_project_and_issue() {
+        let projects = HashMap::from([(1, "Website".to_string()), (2, "API".to_string())]);
+        let issues = HashMap::from([(10, "Fix login".to_string()), (11, "Dark mode".to_string())]);
+        let notifications = [
+            notification(1, 1, 10, "2026-10-19T09:00:00Z"),
+            notification(2, 1, 11, "2026-10-19T10:00:00Z"),
+            notification(3, 2, 20, "2026-10-19T11:00:00Z"),
+            notification(4, 1, 10, "2026-10-19T12:00:00Z"),
+        ];
+
+        let grouped = group_notifications(&notifications, &projects, &issues, Tz::UTC);
+        assert_eq!(grouped.len(), 2);
+        assert_eq!(grouped[0].name, "API");
+        assert_eq!(grouped[0].issues[0].title, "Issue #20");
+
+        let website = &grouped[1];
+        assert_eq!(website.issues[0].title, "Fix login");
+        assert_eq!(website.issues[0].notifications.len(), 2);
+        assert_eq!(website.issues[0].notifications[1].title, "Update 4");
+        assert_eq!(
+            website.issues[0].notifications[0].created_at,
+            "Mon Oct 19, 09:00"
+        );
+        assert_eq!(website.issues[1].title, "Dark mode");
+    }
+
+    #[tokio::test]
+    async fn test_digest_is_rendered_and_delivered_to_maildir() {
+        let projects = HashMap::from([(1, "Website".to_string())]);
+        let issues = HashMap::from([(10, "Fix login".to_string())]);
+        let grouped = group_notifications(
+            &[notification(1, 1, 10, "2026-10-19T09:00:00Z")],
+            &projects,
+            &issues,
+            Tz::UTC,
+        );
+        let email = render_digest("dev@example.com", "Dana", DIGEST_DAILY, &grouped).unwrap();
+        assert_eq!(
+            email.subject,
+            "Your daily Phoenix Tracker digest: 1 unread notification"
+        );
+        assert!(email.text_body.contains("== Website =="));
+        assert!(email.text_body.contains("Update 1: <b>changed</b>"));
+        let html = email.html_body.as_deref().unwrap();
+        assert!(html.contains("&lt;b&gt;changed"));
+        assert!(!html.contains("<b>changed"));
+
+        let dir = std::env::temp_dir().join(format!("phoenix-digest-{}", rand::random::<u64>()));
+        let mailer = Mailer::maildir(
+            dir.clone(),
+            "Phoenix <noreply@example.com>".parse().unwrap(),
+        );
+        mailer.send(&email).await.unwrap();
+        let mut entries = std::fs::read_dir(dir.join("new")).unwrap();
+        let message = std::fs::read_to_string(entries.next().unwrap().unwrap().path()).unwrap();
+        assert!(message.contains("To: dev@example.com"));
+        assert!(message.contains("multipart/alternative"));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,37 @@
+use crate::notifications::channel::{NotificationChannel, OutgoingNotification, CHANNEL_EMAIL};
+use crate::notifications::mailer::{Email, Mailer};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+pub struct EmailChannel {
+    mailer: Arc<Mailer>,
+    to: Vec<String>,
+}
+
+impl EmailChannel {
+    pub fn new(mailer: Arc<Mailer>, to: Vec<String>) -> Self {
+        Self { mailer, to }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for EmailChannel {
+    fn kind(&self) -> &'static str {
+        CHANNEL_EMAIL
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String> {
+        let mut text_body = notification.message.clone();
+        if let Some(link) = &notification.link {
+            text_body.push_str(&format!("\n\n{}", link));
+        }
+        self.mailer
+            .send(&Email {
+                to: self.to.clone(),
+                subject: notification.title.clone(),
+                text_body,
+                html_body: None,
+            })
+            .await
+    }
+}
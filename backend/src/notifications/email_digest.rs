@@ -0,0 +1,170 @@
+use crate::crud::notification::NotificationCrud;
+use crate::crud::notification_digest::NotificationDigestCrud;
+use crate::crud::notification_preference::NotificationPreferenceCrud;
+use crate::crud::user::UserCrud;
+use crate::entities::{issue, project, user};
+use crate::environment;
+use crate::notifications::digest::{self, MAX_DIGEST_NOTIFICATIONS};
+use crate::notifications::mailer::default_mailer;
+use crate::{AppState, WorkerAppState};
+use chrono::{DateTime, Utc};
+use graphile_worker::{IntoTaskHandlerResult, TaskHandler, WorkerContext};
+use sea_orm::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use tracing::{error, info, warn};
+
+// Checked hourly; each user is only sent one digest per daily or weekly slot
+pub const EMAIL_DIGEST_CRONTAB: &str = "0 * * * * email_digest ?max=3 {}";
+
+#[derive(Deserialize, Serialize)]
+pub struct EmailDigest {}
+
+impl TaskHandler for EmailDigest {
+    const IDENTIFIER: &'static str = "email_digest";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+        let app_state = AppState {
+            db: worker_state.db.0.clone(),
+            tx: worker_state.tx.0.clone(),
+            user: None,
+            project: None,
+            worker: None,
+        };
+
+        let user_ids = NotificationCrud::new(app_state.clone())
+            .find_user_ids_with_unread()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let now = Utc::now();
+        let mut sent = 0;
+        for user_id in user_ids {
+            // One user's bad address or timezone shouldn't hold up everyone else's digest
+            match send_digest(&app_state, user_id, now).await {
+                Ok(true) => sent += 1,
+                Ok(false) => {}
+                Err(e) => warn!("Failed to send digest to user {}: {}", user_id, e),
+            }
+        }
+        if sent > 0 {
+            info!("Sent {} email digests", sent);
+        }
+        Ok::<(), String>(())
+    }
+}
+
+async fn send_digest(
+    app_state: &AppState,
+    user_id: i32,
+    now: DateTime<Utc>,
+) -> Result<bool, String> {
+    let digest_crud = NotificationDigestCrud::new(app_state.clone());
+    let settings = digest_crud
+        .find_by_user_id(user_id)
+        .await
+        .map_err(|e| e.to_string())?;
+    let tz = digest::parse_timezone(&settings.timezone)?;
+    let last_sent_at = settings.last_sent_at.map(|at| at.with_timezone(&Utc));
+    if !digest::is_due(
+        &settings.frequency,
+        tz,
+        environment::digest_hour(),
+        last_sent_at,
+        now,
+    ) {
+        return Ok(false);
+    }
+
+    let Some(user) = UserCrud::new(app_state.clone())
+        .find_by_id(user_id)
+        .await
+        .map_err(|e| e.to_string())?
+    else {
+        return Ok(false);
+    };
+
+    let since = digest::window_start(&settings.frequency, last_sent_at, now);
+    let mut notifications = NotificationCrud::new(app_state.clone())
+        .find_unread_for_user_since(user_id, since.into(), MAX_DIGEST_NOTIFICATIONS)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    // Projects the user left out of the digest keep their notifications in-app only
+    let preference_crud = NotificationPreferenceCrud::new(app_state.clone());
+    let mut excluded = HashSet::new();
+    for project_id in notifications
+        .iter()
+        .map(|n| n.project_id)
+        .collect::<HashSet<_>>()
+    {
+        let preferences = preference_crud
+            .find(user_id, project_id)
+            .await
+            .map_err(|e| e.to_string())?;
+        if !preferences.digest {
+            excluded.insert(project_id);
+        }
+    }
+    notifications.retain(|n| !excluded.contains(&n.project_id));
+
+    if !notifications.is_empty() {
+        deliver(app_state, &user, &settings.frequency, tz, &notifications).await?;
+    }
+
+    // Advanced even when there was nothing to send so the window keeps moving
+    digest_crud
+        .mark_sent(user_id, now)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(!notifications.is_empty())
+}
+
+async fn deliver(
+    app_state: &AppState,
+    user: &user::Model,
+    frequency: &str,
+    tz: chrono_tz::Tz,
+    notifications: &[crate::entities::notification::Model],
+) -> Result<(), String> {
+    let project_ids: Vec<i32> = notifications.iter().map(|n| n.project_id).collect();
+    let issue_ids: Vec<i32> = notifications.iter().map(|n| n.issue_id).collect();
+
+    let project_names: HashMap<i32, String> = project::Entity::find()
+        .filter(project::Column::Id.is_in(project_ids))
+        .all(&app_state.db)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+    let issue_titles: HashMap<i32, String> = issue::Entity::find()
+        .filter(issue::Column::Id.is_in(issue_ids))
+        .all(&app_state.db)
+        .await
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|i| (i.id, i.title))
+        .collect();
+
+    let projects = digest::group_notifications(notifications, &project_names, &issue_titles, tz);
+    let email = digest::render_digest(&user.email, &user.name, frequency, &projects)?;
+    let mailer = default_mailer().inspect_err(|e| error!("Mailer unavailable: {}", e))?;
+    mailer.send(&email).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crontab_is_valid() {
+        assert!(graphile_worker::WorkerOptions::default()
+            .with_crontab(EMAIL_DIGEST_CRONTAB)
+            .is_ok());
+    }
+}
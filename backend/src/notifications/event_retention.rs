@@ -0,0 +1,46 @@
+use crate::crud::domain_event::DomainEventCrud;
+use crate::environment;
+use crate::WorkerAppState;
+use chrono::{Duration, Utc};
+use graphile_worker::{IntoTaskHandlerResult, TaskHandler, WorkerContext};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+pub const PRUNE_DOMAIN_EVENTS_CRONTAB: &str = "30 3 * * * prune_domain_events ?max=1 {}";
+
+/// Drops outbox events older than `EVENT_RETENTION_DAYS`.
+#[derive(Deserialize, Serialize)]
+pub struct PruneDomainEvents {}
+
+impl TaskHandler for PruneDomainEvents {
+    const IDENTIFIER: &'static str = "prune_domain_events";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+
+        let cutoff = Utc::now() - Duration::days(environment::event_retention_days());
+        let deleted = DomainEventCrud::new(worker_state.db.0.clone())
+            .delete_older_than(cutoff)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if deleted > 0 {
+            info!("Pruned {} events older than {}", deleted, cutoff);
+        }
+        Ok::<(), String>(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crontab_is_valid() {
+        assert!(graphile_worker::WorkerOptions::default()
+            .with_crontab(PRUNE_DOMAIN_EVENTS_CRONTAB)
+            .is_ok());
+    }
+}
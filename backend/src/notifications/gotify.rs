@@ -1,4 +1,9 @@
 use crate::environment;
+use crate::notifications::channel::{
+    check_response, NotificationChannel, OutgoingNotification, CHANNEL_GOTIFY,
+};
+use crate::notifications::destination::{check_destination, http_client, Destination};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use tracing::{error, info, warn};
 
@@ -12,16 +17,6 @@ pub struct GotifyMessage {
     pub extras: Option<serde_json::Value>,
 }
 
-#[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-pub struct GotifyResponse {
-    pub id: i32,
-    pub message: String,
-    pub title: String,
-    pub priority: i32,
-    pub date: String,
-}
-
 #[derive(Debug, Serialize)]
 pub struct CreateApplicationRequest {
     pub name: String,
@@ -48,81 +43,16 @@ pub struct GotifyClient {
 
 impl GotifyClient {
     pub fn new() -> Self {
-        let base_url = environment::gotify_url().to_string();
-
-        Self {
-            base_url,
-            client: reqwest::Client::new(),
-        }
+        Self::with_base_url(environment::gotify_url())
     }
 
-    pub async fn send_notification(
-        &self,
-        token: String,
-        title: impl Into<String>,
-        message: impl Into<String>,
-        priority: Option<i32>,
-    ) -> Result<GotifyResponse, String> {
-        let url = format!("{}/message", self.base_url);
-
-        let payload = GotifyMessage {
-            title: title.into(),
-            message: message.into(),
-            priority,
-            extras: None,
-        };
-
-        info!("Sending Gotify notification to: {}", url);
-
-        match self
-            .client
-            .post(&url)
-            .header("X-Gotify-Key", token)
-            .json(&payload)
-            .send()
-            .await
-        {
-            Ok(response) => {
-                if response.status().is_success() {
-                    match response.json::<GotifyResponse>().await {
-                        Ok(gotify_response) => {
-                            info!(
-                                "Gotify notification sent successfully: {:?}",
-                                gotify_response
-                            );
-                            Ok(gotify_response)
-                        }
-                        Err(e) => {
-                            error!("Failed to parse Gotify response: {:?}", e);
-                            Err(format!("Failed to parse response: {}", e))
-                        }
-                    }
-                } else {
-                    let status = response.status();
-                    let body = response
-                        .text()
-                        .await
-                        .unwrap_or_else(|_| "Could not read response body".to_string());
-                    error!("Gotify request failed with status {}: {}", status, body);
-                    Err(format!("Request failed with status {}: {}", status, body))
-                }
-            }
-            Err(e) => {
-                error!("Failed to send Gotify notification: {:?}", e);
-                Err(format!("Network error: {}", e))
-            }
+    pub fn with_base_url(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            client: http_client(Destination::Configured),
         }
     }
 
-    pub async fn send_simple_notification(
-        &self,
-        token: String,
-        title: impl Into<String>,
-        message: impl Into<String>,
-    ) -> Result<GotifyResponse, String> {
-        self.send_notification(token, title, message, Some(5)).await
-    }
-
     pub async fn create_application_with_basic_auth(
         &self,
         name: impl Into<String>,
@@ -254,3 +184,46 @@ impl Default for GotifyClient {
         Self::new()
     }
 }
+
+/// Delivers through a Gotify application token, to the operator's server or one the
+/// channel's owner configured.
+pub struct GotifyChannel {
+    base_url: String,
+    token: String,
+    destination: Destination,
+    client: reqwest::Client,
+}
+
+impl GotifyChannel {
+    pub fn new(base_url: String, token: String, destination: Destination) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+            destination,
+            client: http_client(destination),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for GotifyChannel {
+    fn kind(&self) -> &'static str {
+        CHANNEL_GOTIFY
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String> {
+        check_destination(&self.base_url, self.destination).await?;
+        let payload = GotifyMessage {
+            title: notification.title.clone(),
+            message: notification.message.clone(),
+            priority: Some(notification.priority),
+            extras: None,
+        };
+        let request = self
+            .client
+            .post(format!("{}/message", self.base_url))
+            .header("X-Gotify-Key", &self.token)
+            .json(&payload);
+        check_response(self.kind(), request.send().await).await
+    }
+}
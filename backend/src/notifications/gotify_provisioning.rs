@@ -0,0 +1,105 @@
+use crate::crud::notification_settings::NotificationSettingsCrud;
+use crate::notifications::channel::CHANNEL_GOTIFY;
+use crate::notifications::gotify::GotifyClient;
+use crate::{AppState, WorkerAppState};
+use graphile_worker::{
+    IntoTaskHandlerResult, JobKeyMode, JobSpec, JobSpecBuilder, TaskHandler, WorkerContext,
+};
+use serde::{Deserialize, Serialize};
+use tracing::{error, info, warn};
+
+// Graphile backs off exponentially between attempts; ten of them span several hours
+const MAX_PROVISIONING_ATTEMPTS: i16 = 10;
+
+/// Gives a project without any notification channel its own Gotify application.
+/// Safe to run more than once: a project that already has one is left alone.
+#[derive(Deserialize, Serialize)]
+pub struct ProvisionGotify {
+    pub project_id: i32,
+}
+
+// One pending job per project however many reminders are created while Gotify is down
+pub fn provisioning_job_spec(project_id: i32) -> JobSpec {
+    JobSpecBuilder::new()
+        .job_key(format!("provision_gotify:{}", project_id))
+        .job_key_mode(JobKeyMode::PreserveRunAt)
+        .max_attempts(MAX_PROVISIONING_ATTEMPTS)
+        .build()
+}
+
+impl TaskHandler for ProvisionGotify {
+    const IDENTIFIER: &'static str = "provision_gotify";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+        let app_state = AppState {
+            db: worker_state.db.0.clone(),
+            tx: worker_state.tx.0.clone(),
+            user: None,
+            project: None,
+            worker: None,
+        };
+        let settings_crud = NotificationSettingsCrud::new(app_state);
+
+        let existing = settings_crud
+            .find_all_by_project_id(self.project_id)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if existing.iter().any(|s| s.channel == CHANNEL_GOTIFY) {
+            info!(
+                "Project {} already has a Gotify application",
+                self.project_id
+            );
+            return Ok::<(), String>(());
+        }
+
+        let gotify_client = GotifyClient::new();
+        let application = gotify_client
+            .create_application_with_basic_auth(
+                format!("Phoenix Tracker - Project {}", self.project_id),
+                Some("Notification application for Phoenix Tracker project".to_string()),
+            )
+            .await
+            .inspect_err(|e| {
+                warn!(
+                    "Gotify provisioning for project {} failed, will retry: {}",
+                    self.project_id, e
+                )
+            })?;
+
+        if let Err(e) = settings_crud
+            .create(self.project_id, application.token, application.id)
+            .await
+        {
+            // Don't leave an application behind that the retry would duplicate
+            if let Err(cleanup) = gotify_client
+                .delete_application(application.id as i64)
+                .await
+            {
+                error!(
+                    "Failed to remove Gotify application {}: {}",
+                    application.id, cleanup
+                );
+            }
+            return Err(format!("Failed to save Gotify application: {}", e));
+        }
+
+        info!("Provisioned Gotify for project {}", self.project_id);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provisioning_is_keyed_per_project() {
+        let spec = provisioning_job_spec(3);
+        assert_eq!(spec.job_key.as_deref(), Some("provision_gotify:3"));
+        assert_eq!(spec.max_attempts, Some(MAX_PROVISIONING_ATTEMPTS));
+    }
+}
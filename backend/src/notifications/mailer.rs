@@ -0,0 +1,143 @@
+use crate::environment;
+use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::info;
+
+pub const MAILER_SMTP: &str = "smtp";
+pub const MAILER_MAILDIR: &str = "maildir";
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+/// Sends mail over SMTP, or drops it into a local maildir (`new/`) for development and tests.
+pub enum Mailer {
+    Smtp {
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        from: Mailbox,
+    },
+    Maildir {
+        path: PathBuf,
+        from: Mailbox,
+    },
+}
+
+static DEFAULT_MAILER: OnceLock<Result<Arc<Mailer>, String>> = OnceLock::new();
+
+// Built once from the environment so SMTP connections are pooled across sends
+pub fn default_mailer() -> Result<Arc<Mailer>, String> {
+    DEFAULT_MAILER
+        .get_or_init(|| Mailer::from_env().map(Arc::new))
+        .clone()
+}
+
+fn parse_mailbox(address: &str) -> Result<Mailbox, String> {
+    address
+        .parse::<Mailbox>()
+        .map_err(|e| format!("Invalid email address '{}': {}", address, e))
+}
+
+impl Mailer {
+    pub fn from_env() -> Result<Self, String> {
+        let from = parse_mailbox(environment::mail_from())?;
+        match environment::mailer() {
+            MAILER_MAILDIR => {
+                let path = environment::maildir_path()
+                    .ok_or_else(|| "MAILDIR_PATH must be set when MAILER=maildir".to_string())?;
+                Ok(Self::maildir(path.clone(), from))
+            }
+            MAILER_SMTP => {
+                let url = environment::smtp_url()
+                    .ok_or_else(|| "SMTP_URL must be set to send email".to_string())?;
+                let transport = AsyncSmtpTransport::<Tokio1Executor>::from_url(url)
+                    .map_err(|e| format!("Invalid SMTP_URL: {}", e))?
+                    .build();
+                Ok(Self::Smtp { transport, from })
+            }
+            other => Err(format!("Unknown MAILER '{}'", other)),
+        }
+    }
+
+    pub fn maildir(path: PathBuf, from: Mailbox) -> Self {
+        Self::Maildir { path, from }
+    }
+
+    fn from(&self) -> &Mailbox {
+        match self {
+            Self::Smtp { from, .. } | Self::Maildir { from, .. } => from,
+        }
+    }
+
+    fn build_message(&self, email: &Email) -> Result<Message, String> {
+        let mut builder = Message::builder()
+            .from(self.from().clone())
+            .subject(email.subject.clone());
+        for to in &email.to {
+            builder = builder.to(parse_mailbox(to)?);
+        }
+
+        let result = match &email.html_body {
+            Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+                email.text_body.clone(),
+                html.clone(),
+            )),
+            None => builder.singlepart(
+                SinglePart::builder()
+                    .header(ContentType::TEXT_PLAIN)
+                    .body(email.text_body.clone()),
+            ),
+        };
+        result.map_err(|e| format!("Failed to build email: {}", e))
+    }
+
+    pub async fn send(&self, email: &Email) -> Result<(), String> {
+        if email.to.is_empty() {
+            return Err("Email has no recipients".to_string());
+        }
+        let message = self.build_message(email)?;
+
+        match self {
+            Self::Smtp { transport, .. } => {
+                transport
+                    .send(message)
+                    .await
+                    .map_err(|e| format!("SMTP delivery failed: {}", e))?;
+            }
+            Self::Maildir { path, .. } => {
+                // Written to tmp/ first and renamed so readers never see a partial message
+                let unique = format!(
+                    "{}.{}.phoenix",
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_nanos())
+                        .unwrap_or_default(),
+                    rand::random::<u32>()
+                );
+                let tmp_dir = path.join("tmp");
+                let new_dir = path.join("new");
+                tokio::fs::create_dir_all(&tmp_dir)
+                    .await
+                    .map_err(|e| format!("Failed to create maildir: {}", e))?;
+                tokio::fs::create_dir_all(&new_dir)
+                    .await
+                    .map_err(|e| format!("Failed to create maildir: {}", e))?;
+                tokio::fs::write(tmp_dir.join(&unique), message.formatted())
+                    .await
+                    .map_err(|e| format!("Failed to write email: {}", e))?;
+                tokio::fs::rename(tmp_dir.join(&unique), new_dir.join(&unique))
+                    .await
+                    .map_err(|e| format!("Failed to deliver email: {}", e))?;
+            }
+        }
+
+        info!("Sent email '{}' to {:?}", email.subject, email.to);
+        Ok(())
+    }
+}
@@ -1,2 +1,16 @@
+pub mod channel;
+pub mod delivery;
+pub mod destination;
+pub mod digest;
+pub mod email;
+pub mod email_digest;
+pub mod event_retention;
 pub mod gotify;
+pub mod gotify_provisioning;
+pub mod mailer;
+pub mod ntfy;
+pub mod project_webhook;
 pub mod push_notification;
+pub mod template;
+pub mod upload_session_cleanup;
+pub mod webhook;
@@ -0,0 +1,66 @@
+use crate::notifications::channel::{
+    check_response, NotificationChannel, OutgoingNotification, CHANNEL_NTFY,
+};
+use crate::notifications::destination::{check_destination, http_client, Destination};
+use async_trait::async_trait;
+
+/// Publishes to an ntfy topic (https://ntfy.sh or a self-hosted server).
+pub struct NtfyChannel {
+    url: String,
+    topic: String,
+    token: Option<String>,
+    destination: Destination,
+    client: reqwest::Client,
+}
+
+impl NtfyChannel {
+    pub fn new(
+        url: String,
+        topic: String,
+        token: Option<String>,
+        destination: Destination,
+    ) -> Self {
+        Self {
+            url: url.trim_end_matches('/').to_string(),
+            topic,
+            token,
+            destination,
+            client: http_client(destination),
+        }
+    }
+}
+
+// ntfy priorities run 1 (min) to 5 (max); Gotify's 0-10 scale is folded onto them
+fn ntfy_priority(priority: i32) -> i32 {
+    match priority {
+        p if p >= 8 => 5,
+        p if p >= 6 => 4,
+        p if p >= 4 => 3,
+        p if p >= 2 => 2,
+        _ => 1,
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for NtfyChannel {
+    fn kind(&self) -> &'static str {
+        CHANNEL_NTFY
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String> {
+        check_destination(&self.url, self.destination).await?;
+        let mut request = self
+            .client
+            .post(format!("{}/{}", self.url, self.topic))
+            .header("Title", &notification.title)
+            .header("Priority", ntfy_priority(notification.priority).to_string())
+            .body(notification.message.clone());
+        if let Some(link) = &notification.link {
+            request = request.header("Click", link);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        check_response(self.kind(), request.send().await).await
+    }
+}
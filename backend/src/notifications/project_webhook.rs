@@ -0,0 +1,375 @@
+use crate::crud::domain_event::{
+    DomainEventCrud, EventEnvelope, OutboxReader, GAP_RECHECK, MAX_REPLAY,
+};
+use crate::crud::event_broadcaster::WEBHOOK_EVENTS;
+use crate::crud::webhook::WebhookCrud;
+use crate::crud::webhook_delivery::{WebhookAttempt, WebhookDeliveryCrud};
+use crate::entities::{webhook, webhook_delivery};
+use crate::error::AppError;
+use crate::notifications::destination::{check_destination, http_client, Destination};
+use crate::{AppState, WorkerAppState};
+use graphile_worker::{IntoTaskHandlerResult, JobSpec, JobSpecBuilder, TaskHandler, WorkerContext};
+use hmac::{Hmac, Mac};
+use sea_orm::EntityTrait;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, error, info, warn};
+
+// Graphile backs off exponentially, so eight attempts cover roughly an hour and a half
+const MAX_DELIVERY_ATTEMPTS: i16 = 8;
+// Enough of a failing response body to tell what went wrong
+const MAX_ERROR_BODY: usize = 500;
+
+// Outbox cursor the dispatcher resumes from
+const DISPATCHER_CONSUMER: &str = "webhooks";
+
+pub const SIGNATURE_HEADER: &str = "X-Phoenix-Signature";
+pub const EVENT_HEADER: &str = "X-Phoenix-Event";
+pub const DELIVERY_HEADER: &str = "X-Phoenix-Delivery";
+
+/// `sha256=<hex HMAC-SHA256 of the body>`, which receivers recompute with their secret.
+pub fn sign(secret: &str, body: &str) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(body.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+fn payload(envelope: &EventEnvelope) -> String {
+    serde_json::json!({
+        "event": envelope.event.event_type(),
+        "sequence": envelope.sequence,
+        "project_id": envelope.project_id,
+        "data": envelope.event.data(),
+        "created_at": envelope.created_at.to_rfc3339(),
+    })
+    .to_string()
+}
+
+fn delivery_job_spec() -> JobSpec {
+    JobSpecBuilder::new()
+        .max_attempts(MAX_DELIVERY_ATTEMPTS)
+        .build()
+}
+
+async fn enqueue(app_state: &AppState, delivery_id: i32) -> Result<(), String> {
+    let worker = app_state
+        .worker
+        .clone()
+        .ok_or_else(|| "Background worker is not available".to_string())?;
+    worker
+        .create_utils()
+        .add_job(DeliverWebhook { delivery_id }, delivery_job_spec())
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+/// Forwards project events to the webhooks subscribed to them. Each match gets a delivery
+/// record and a job, so a slow receiver never holds up the rest. Progress is kept as a
+/// consumer cursor, so events published while the server was down go out on the next start.
+pub fn spawn_dispatcher(app_state: AppState) {
+    let mut rx = app_state.tx.subscribe();
+    tokio::spawn(async move {
+        let events = DomainEventCrud::new(app_state.db.clone());
+        let cursor = match events.find_cursor(DISPATCHER_CONSUMER).await {
+            Ok(Some(cursor)) => Ok(cursor),
+            // A first start only forwards what happens from now on
+            Ok(None) => match events.latest_sequence().await {
+                Ok(latest) => events
+                    .save_cursor(DISPATCHER_CONSUMER, latest)
+                    .await
+                    .map(|_| latest),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        let mut cursor = match cursor {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                error!("Webhook dispatcher could not load its cursor: {}", e);
+                return;
+            }
+        };
+        catch_up(&app_state, &events, &mut cursor).await;
+
+        loop {
+            match rx.recv().await {
+                Ok(envelope) if envelope.sequence > cursor => {
+                    forward(&app_state, &events, &envelope, &mut cursor).await
+                }
+                // Already sent while catching up
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!(
+                        "Webhook dispatcher fell behind by {} events, catching up from the outbox",
+                        skipped
+                    );
+                    catch_up(&app_state, &events, &mut cursor).await;
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+async fn catch_up(app_state: &AppState, events: &DomainEventCrud, cursor: &mut i64) {
+    let mut reader = OutboxReader::new(app_state.db.clone(), *cursor);
+    loop {
+        let batch = match reader.next_batch().await {
+            Ok(batch) => batch,
+            Err(e) => {
+                error!("Failed to read events after {}: {}", cursor, e);
+                return;
+            }
+        };
+        for envelope in &batch {
+            forward(app_state, events, envelope, cursor).await;
+        }
+        // Later events arrive live, so a gap that may still fill is waited out here
+        if reader.waiting() {
+            tokio::time::sleep(GAP_RECHECK).await;
+        } else if (batch.len() as u64) < MAX_REPLAY {
+            return;
+        }
+    }
+}
+
+// Every instance hears every event; whichever claims it first queues the deliveries.
+// A failed dispatch is logged and passed over rather than holding up every later event.
+async fn forward(
+    app_state: &AppState,
+    events: &DomainEventCrud,
+    envelope: &EventEnvelope,
+    cursor: &mut i64,
+) {
+    *cursor = envelope.sequence;
+    match events.claim(DISPATCHER_CONSUMER, envelope.sequence).await {
+        Ok(true) => {}
+        Ok(false) => return,
+        Err(e) => {
+            warn!(
+                "Failed to claim event {} for webhooks: {}",
+                envelope.sequence, e
+            );
+            return;
+        }
+    }
+    if let Err(e) = dispatch(app_state, envelope).await {
+        error!(
+            "Failed to queue webhook deliveries for event {}: {}",
+            envelope.sequence, e
+        );
+    }
+}
+
+async fn dispatch(app_state: &AppState, envelope: &EventEnvelope) -> Result<(), AppError> {
+    // Events addressed to a single user aren't project activity
+    if envelope.user_id.is_some() {
+        return Ok(());
+    }
+    let event_type = envelope.event.event_type();
+    if !WEBHOOK_EVENTS.contains(&event_type) {
+        return Ok(());
+    }
+
+    let webhooks = WebhookCrud::new(app_state.clone())
+        .find_subscribed(envelope.project_id, event_type)
+        .await?;
+    if webhooks.is_empty() {
+        return Ok(());
+    }
+
+    let body = payload(envelope);
+    let delivery_crud = WebhookDeliveryCrud::new(app_state.clone());
+    for webhook in webhooks {
+        let delivery = delivery_crud
+            .create(webhook.id, event_type, body.clone())
+            .await?;
+        enqueue(app_state, delivery.id)
+            .await
+            .map_err(AppError::Internal)?;
+        debug!(
+            "Queued '{}' delivery {} for webhook {}",
+            event_type, delivery.id, webhook.id
+        );
+    }
+    Ok(())
+}
+
+/// Sends a delivery's payload again as a new delivery, leaving the original in the log.
+pub async fn redeliver(
+    app_state: AppState,
+    delivery: webhook_delivery::Model,
+) -> Result<webhook_delivery::Model, AppError> {
+    let redelivery = WebhookDeliveryCrud::new(app_state.clone())
+        .create(delivery.webhook_id, &delivery.event_type, delivery.payload)
+        .await?;
+    enqueue(&app_state, redelivery.id)
+        .await
+        .map_err(AppError::Internal)?;
+    Ok(redelivery)
+}
+
+async fn post(webhook: &webhook::Model, delivery: &webhook_delivery::Model) -> WebhookAttempt {
+    // Checked again here, since a name's addresses can change after the webhook is saved
+    if let Err(e) = check_destination(&webhook.url, Destination::Public).await {
+        return WebhookAttempt {
+            response_status: None,
+            error: Some(format!("Refused: {}", e)),
+        };
+    }
+    let result = http_client(Destination::Public)
+        .post(&webhook.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(reqwest::header::USER_AGENT, "PhoenixTracker-Webhook")
+        .header(EVENT_HEADER, &delivery.event_type)
+        .header(DELIVERY_HEADER, delivery.id.to_string())
+        .header(SIGNATURE_HEADER, sign(&webhook.secret, &delivery.payload))
+        .body(delivery.payload.clone())
+        .send()
+        .await;
+
+    match result {
+        Err(e) => WebhookAttempt {
+            response_status: None,
+            error: Some(format!("Request failed: {}", e)),
+        },
+        Ok(response) => {
+            let status = response.status();
+            let error = if status.is_success() {
+                None
+            } else {
+                let body = response.text().await.unwrap_or_default();
+                let body: String = body.chars().take(MAX_ERROR_BODY).collect();
+                Some(format!("Responded with {}: {}", status, body))
+            };
+            WebhookAttempt {
+                response_status: Some(status.as_u16() as i32),
+                error,
+            }
+        }
+    }
+}
+
+/// Posts one webhook delivery. Failures are returned so graphile retries with backoff.
+#[derive(Deserialize, Serialize)]
+pub struct DeliverWebhook {
+    pub delivery_id: i32,
+}
+
+impl TaskHandler for DeliverWebhook {
+    const IDENTIFIER: &'static str = "deliver_webhook";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+        let app_state = AppState {
+            db: worker_state.db.0.clone(),
+            tx: worker_state.tx.0.clone(),
+            user: None,
+            project: None,
+            worker: None,
+        };
+
+        let found = webhook_delivery::Entity::find_by_id(self.delivery_id)
+            .find_also_related(webhook::Entity)
+            .one(&app_state.db)
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        let Some((delivery, Some(webhook))) = found else {
+            info!(
+                "Webhook delivery {} no longer exists, skipping",
+                self.delivery_id
+            );
+            return Ok::<(), String>(());
+        };
+
+        let attempt = if webhook.enabled {
+            post(&webhook, &delivery).await
+        } else {
+            WebhookAttempt {
+                response_status: None,
+                error: Some("Webhook is disabled".to_string()),
+            }
+        };
+        let error = attempt.error.clone();
+        // Nothing is coming for a disabled webhook, so its delivery fails straight away
+        let final_attempt = !webhook.enabled || ctx.job().attempts() >= ctx.job().max_attempts();
+        WebhookDeliveryCrud::new(app_state)
+            .record_attempt(delivery, attempt, final_attempt)
+            .await
+            .map_err(|e| format!("Failed to record webhook delivery: {}", e))?;
+
+        match error {
+            None => {
+                info!(
+                    "Delivered webhook {} (delivery {})",
+                    webhook.id, self.delivery_id
+                );
+                Ok(())
+            }
+            Some(_) if !webhook.enabled => Ok(()),
+            Some(e) => {
+                warn!(
+                    "Webhook {} delivery {} failed: {}",
+                    webhook.id, self.delivery_id, e
+                );
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signature_matches_known_hmac() {
+        // RFC 4231 test case 2
+        assert_eq!(
+            sign("Jefe", "what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_to_internal_address_is_refused() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let now = chrono::Utc::now();
+        let webhook = webhook::Model {
+            id: 1,
+            project_id: 1,
+            url: format!("http://{}/hook", listener.local_addr().unwrap()),
+            secret: "s".repeat(16),
+            events: None,
+            enabled: true,
+            created_at: now.into(),
+            updated_at: now.into(),
+        };
+        let delivery = webhook_delivery::Model {
+            id: 1,
+            webhook_id: 1,
+            event_type: "issue_created".to_string(),
+            payload: "{}".to_string(),
+            status: "pending".to_string(),
+            attempts: 0,
+            response_status: None,
+            last_error: None,
+            delivered_at: None,
+            created_at: now.into(),
+            updated_at: now.into(),
+        };
+
+        let attempt = post(&webhook, &delivery).await;
+        assert_eq!(attempt.response_status, None);
+        assert!(attempt.error.unwrap().starts_with("Refused"));
+        let accepted =
+            tokio::time::timeout(std::time::Duration::from_millis(100), listener.accept()).await;
+        assert!(accepted.is_err());
+    }
+}
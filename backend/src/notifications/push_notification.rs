@@ -1,24 +1,50 @@
-use crate::crud::event_broadcaster::EventBroadcaster;
-use crate::crud::event_broadcaster::REMINDER_DISPATCHED;
+use crate::crud::event_broadcaster::{DomainEvent, EventBroadcaster};
 use crate::crud::issue::IssueCrud;
-use crate::crud::notification_settings::NotificationSettingsCrud;
 use crate::crud::project::ProjectCrud;
 use crate::crud::status::STATUS_ACCEPTED;
 use crate::crud::status::STATUS_UNSTARTED;
 use crate::crud::user::UserCrud;
 use crate::crud::work_type::WORK_TYPE_REMINDER;
-use crate::notifications::gotify::GotifyClient;
+use crate::entities::issue;
+use crate::notifications::channel::OutgoingNotification;
+use crate::notifications::delivery::NotificationDelivery;
 use crate::{AppState, WorkerAppState};
 use chrono::Utc;
-use graphile_worker::{IntoTaskHandlerResult, TaskHandler, WorkerContext};
+use graphile_worker::{
+    IntoTaskHandlerResult, JobKeyMode, JobSpec, JobSpecBuilder, TaskHandler, WorkerContext,
+    WorkerContextExt,
+};
+use sea_orm::prelude::DateTimeWithTimeZone;
 use serde::{Deserialize, Serialize};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 
 #[derive(Deserialize, Serialize)]
 pub struct PushNotification {
     pub issue_id: i32,
 }
 
+// One key per issue, so scheduling again moves the pending job instead of adding another
+pub fn reminder_job_key(issue_id: i32) -> String {
+    format!("reminder:{}", issue_id)
+}
+
+pub fn reminder_job_spec(issue_id: i32, run_at: DateTimeWithTimeZone) -> JobSpec {
+    JobSpecBuilder::new()
+        .run_at(run_at)
+        .job_key(reminder_job_key(issue_id))
+        .job_key_mode(JobKeyMode::Replace)
+        .build()
+}
+
+/// When the reminder should fire, or None if it shouldn't fire at all: only unstarted
+/// reminders with a target date are sent.
+pub fn reminder_due_at(issue: &issue::Model) -> Option<DateTimeWithTimeZone> {
+    if issue.work_type != WORK_TYPE_REMINDER || issue.status != STATUS_UNSTARTED {
+        return None;
+    }
+    issue.target_release_at
+}
+
 impl TaskHandler for PushNotification {
     const IDENTIFIER: &'static str = "push_notification";
 
@@ -33,14 +59,13 @@ impl TaskHandler for PushNotification {
             tx: worker_state.tx.0.clone(),
             user: None,
             project: None,
-            bearer_token: None,
             worker: None,
         };
         let issue_crud = IssueCrud::new(app_state.clone());
 
         match issue_crud.find_by_id(self.issue_id).await {
             Ok(Some(issue)) => {
-                if issue.work_type != WORK_TYPE_REMINDER || issue.status != STATUS_UNSTARTED {
+                if reminder_due_at(&issue).is_none() {
                     return Ok(());
                 }
 
@@ -79,82 +104,97 @@ impl TaskHandler for PushNotification {
                     tx: worker_state.tx.0.clone(),
                     user: Some(user),
                     project: Some(project),
-                    bearer_token: None,
                     worker: None,
                 };
                 let issue_crud_with_user = IssueCrud::new(app_state_with_user.clone());
 
-                // Create a new GotifyClient instance
-                let gotify_client = GotifyClient::new();
-
-                // Get notification settings by project_id
-                let notification_crud = NotificationSettingsCrud::new(app_state);
-                match notification_crud.find_by_project_id(issue.project_id).await {
-                    Ok(Some(settings)) => {
-                        // Send notification with issue title and description
-                        let description =
-                            issue.description.clone().unwrap_or_else(|| "".to_string());
-                        let message = if description.is_empty() {
-                            issue.title.clone()
-                        } else {
-                            description
-                        };
-
-                        let broadcaster = EventBroadcaster::new(app_state_with_user.tx.clone());
-                        broadcaster.broadcast_event(
-                            issue.project_id,
-                            REMINDER_DISPATCHED,
-                            serde_json::json!(issue),
-                        );
-
-                        match gotify_client
-                            .send_simple_notification(settings.token, issue.title.clone(), message)
+                // Collect every enabled channel configured for the project
+                let delivery = NotificationDelivery::new(app_state);
+                let channels = match delivery.project_channels(issue.project_id).await {
+                    Ok(channels) => channels,
+                    Err(e) => {
+                        error!("Failed to retrieve notification channels: {}", e);
+                        return Err(format!("Database error: {}", e));
+                    }
+                };
+                if channels.is_empty() {
+                    error!(
+                        "No notification channels configured for project: {}",
+                        issue.project_id
+                    );
+                    return Err(format!(
+                        "No notification channels for project {}",
+                        issue.project_id
+                    ));
+                }
+
+                // Send notification with issue title and description
+                let description = issue.description.clone().unwrap_or_else(|| "".to_string());
+                let message = if description.is_empty() {
+                    issue.title.clone()
+                } else {
+                    description
+                };
+
+                EventBroadcaster::new(&app_state_with_user)
+                    .publish(
+                        issue.project_id,
+                        DomainEvent::ReminderDispatched(issue.clone()),
+                    )
+                    .await;
+
+                // A recurring reminder hands its rule to the next occurrence as it fires
+                match issue_crud_with_user.spawn_next_occurrence(issue.id).await {
+                    Ok(Some(next)) => {
+                        if let Some(run_at) = reminder_due_at(&next) {
+                            let job_spec = reminder_job_spec(next.id, run_at);
+                            if let Err(e) = ctx
+                                .add_job(PushNotification { issue_id: next.id }, job_spec)
+                                .await
+                            {
+                                error!("Failed to schedule reminder {}: {:?}", next.id, e);
+                            }
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => warn!(
+                        "Failed to create next occurrence of reminder {}: {}",
+                        issue.id, e
+                    ),
+                }
+
+                let notification = OutgoingNotification::new(issue.title.clone(), message)
+                    .for_issue(issue.project_id, issue.id);
+                match delivery.deliver(&channels, &notification).await {
+                    Ok(_) => {
+                        // Update issue status to ACCEPTED and set accepted_at
+                        let now = Utc::now();
+                        match issue_crud_with_user
+                            .update(
+                                issue.id,
+                                None,
+                                None,
+                                None,
+                                None,
+                                Some(STATUS_ACCEPTED),
+                                None,
+                                None,
+                                None,
+                                Some(now.into()),
+                                None,
+                            )
                             .await
                         {
-                            Ok(_) => {
-                                // Update issue status to ACCEPTED and set accepted_at
-                                let now = Utc::now();
-                                match issue_crud_with_user
-                                    .update(
-                                        issue.id,
-                                        None,
-                                        None,
-                                        None,
-                                        None,
-                                        Some(STATUS_ACCEPTED),
-                                        None,
-                                        None,
-                                        None,
-                                        Some(now.into()),
-                                    )
-                                    .await
-                                {
-                                    Ok(_) => Ok::<(), String>(()),
-                                    Err(e) => {
-                                        error!("Failed to update issue status to ACCEPTED: {}", e);
-                                        Err(format!("Failed to update issue status: {}", e))
-                                    }
-                                }
-                            }
+                            Ok(_) => Ok::<(), String>(()),
                             Err(e) => {
-                                error!("Failed to send Gotify notification: {}", e);
-                                Err(format!("Failed to send notification: {}", e))
+                                error!("Failed to update issue status to ACCEPTED: {}", e);
+                                Err(format!("Failed to update issue status: {}", e))
                             }
                         }
                     }
-                    Ok(None) => {
-                        error!(
-                            "No notification settings found for project: {}",
-                            issue.project_id
-                        );
-                        Err(format!(
-                            "No notification settings for project {}",
-                            issue.project_id
-                        ))
-                    }
                     Err(e) => {
-                        error!("Failed to retrieve notification settings: {}", e);
-                        Err(format!("Database error: {}", e))
+                        error!("Failed to send reminder notification: {}", e);
+                        Err(format!("Failed to send notification: {}", e))
                     }
                 }
             }
@@ -163,3 +203,55 @@ impl TaskHandler for PushNotification {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::work_type::WORK_TYPE_FEATURE;
+
+    fn reminder(status: i32, target_release_at: Option<DateTimeWithTimeZone>) -> issue::Model {
+        issue::Model {
+            id: 7,
+            title: "Water the plants".to_string(),
+            description: None,
+            priority: 0,
+            points: None,
+            status,
+            is_icebox: false,
+            work_type: WORK_TYPE_REMINDER,
+            project_id: 1,
+            created_by_id: 1,
+            target_release_at,
+            lock_version: 0,
+            accepted_at: None,
+            recurrence: None,
+            created_at: Utc::now().into(),
+            updated_at: Utc::now().into(),
+            issue_tag_ids: vec![],
+            scheduled_at: None,
+            issue_assignee_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn test_only_unstarted_reminders_with_a_date_are_due() {
+        let at: DateTimeWithTimeZone = Utc::now().into();
+        assert_eq!(
+            reminder_due_at(&reminder(STATUS_UNSTARTED, Some(at))),
+            Some(at)
+        );
+        assert_eq!(reminder_due_at(&reminder(STATUS_UNSTARTED, None)), None);
+        assert_eq!(reminder_due_at(&reminder(STATUS_ACCEPTED, Some(at))), None);
+
+        let mut feature = reminder(STATUS_UNSTARTED, Some(at));
+        feature.work_type = WORK_TYPE_FEATURE;
+        assert_eq!(reminder_due_at(&feature), None);
+    }
+
+    #[test]
+    fn test_rescheduling_replaces_the_queued_job() {
+        let spec = reminder_job_spec(7, Utc::now().into());
+        assert_eq!(spec.job_key.as_deref(), Some("reminder:7"));
+        assert!(matches!(spec.job_key_mode, Some(JobKeyMode::Replace)));
+    }
+}
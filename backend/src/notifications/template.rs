@@ -0,0 +1,30 @@
+use minijinja::Environment;
+use serde::Serialize;
+use std::sync::OnceLock;
+
+static TEMPLATES: OnceLock<Environment<'static>> = OnceLock::new();
+
+// Templates are compiled into the binary; names ending in .html are auto-escaped
+fn templates() -> &'static Environment<'static> {
+    TEMPLATES.get_or_init(|| {
+        let mut env = Environment::new();
+        env.add_template(
+            "email/digest.txt",
+            include_str!("../../templates/email/digest.txt"),
+        )
+        .expect("digest.txt template is valid");
+        env.add_template(
+            "email/digest.html",
+            include_str!("../../templates/email/digest.html"),
+        )
+        .expect("digest.html template is valid");
+        env
+    })
+}
+
+pub fn render<S: Serialize>(name: &str, context: S) -> Result<String, String> {
+    templates()
+        .get_template(name)
+        .and_then(|template| template.render(context))
+        .map_err(|e| format!("Failed to render {}: {}", name, e))
+}
@@ -0,0 +1,63 @@
+use crate::crud::direct_upload::DirectUploadCrud;
+use crate::crud::file_upload::remove_staged_direct_upload;
+use crate::crud::upload_session::UploadSessionCrud;
+use crate::WorkerAppState;
+use graphile_worker::{IntoTaskHandlerResult, TaskHandler, WorkerContext};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+pub const PRUNE_UPLOAD_SESSIONS_CRONTAB: &str = "15 * * * * prune_upload_sessions ?max=1 {}";
+
+/// Drops resumable uploads that went `UPLOAD_SESSION_TTL_HOURS` without a chunk, along
+/// with the bytes they had received, and direct uploads that were never finalized.
+#[derive(Deserialize, Serialize)]
+pub struct PruneUploadSessions {}
+
+impl TaskHandler for PruneUploadSessions {
+    const IDENTIFIER: &'static str = "prune_upload_sessions";
+
+    async fn run(self, ctx: WorkerContext) -> impl IntoTaskHandlerResult {
+        let worker_state = ctx
+            .get_ext::<WorkerAppState>()
+            .ok_or_else(|| "Missing WorkerAppState extension".to_string())?
+            .clone();
+
+        let deleted = UploadSessionCrud::new(worker_state.db.0.clone())
+            .delete_expired()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        if deleted > 0 {
+            info!("Pruned {} expired resumable uploads", deleted);
+        }
+
+        let expired = DirectUploadCrud::new(worker_state.db.0.clone())
+            .delete_expired()
+            .await
+            .map_err(|e| format!("Database error: {}", e))?;
+        for upload in &expired {
+            // The client may have put the object in S3 and never finalized it
+            if let Err(e) = remove_staged_direct_upload(&upload.id).await {
+                warn!(
+                    "Failed to remove object of direct upload {}: {}",
+                    upload.id, e
+                );
+            }
+        }
+        if !expired.is_empty() {
+            info!("Pruned {} unfinished direct uploads", expired.len());
+        }
+        Ok::<(), String>(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crontab_is_valid() {
+        assert!(graphile_worker::WorkerOptions::default()
+            .with_crontab(PRUNE_UPLOAD_SESSIONS_CRONTAB)
+            .is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+use crate::notifications::channel::{
+    check_response, NotificationChannel, OutgoingNotification, CHANNEL_MATRIX, CHANNEL_SLACK,
+    CHANNEL_WEBHOOK,
+};
+use crate::notifications::destination::{check_destination, http_client, Destination};
+use async_trait::async_trait;
+use std::collections::HashMap;
+
+/// Posts the notification as JSON to an arbitrary URL, with optional extra headers
+/// (e.g. a shared secret the receiver checks).
+pub struct WebhookChannel {
+    url: String,
+    headers: HashMap<String, String>,
+    destination: Destination,
+    client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    pub fn new(url: String, headers: HashMap<String, String>, destination: Destination) -> Self {
+        Self {
+            url,
+            headers,
+            destination,
+            client: http_client(destination),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationChannel for WebhookChannel {
+    fn kind(&self) -> &'static str {
+        CHANNEL_WEBHOOK
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String> {
+        check_destination(&self.url, self.destination).await?;
+        let mut request = self.client.post(&self.url).json(notification);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        check_response(self.kind(), request.send().await).await
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatFlavor {
+    Slack,
+    // Matrix hookshot-style generic webhooks, which take plain text plus optional HTML
+    Matrix,
+}
+
+/// Incoming-webhook integrations for chat rooms (Slack, Mattermost, Matrix hookshot).
+pub struct ChatWebhookChannel {
+    url: String,
+    flavor: ChatFlavor,
+    destination: Destination,
+    client: reqwest::Client,
+}
+
+impl ChatWebhookChannel {
+    pub fn new(url: String, flavor: ChatFlavor, destination: Destination) -> Self {
+        Self {
+            url,
+            flavor,
+            destination,
+            client: http_client(destination),
+        }
+    }
+
+    fn payload(&self, notification: &OutgoingNotification) -> serde_json::Value {
+        match self.flavor {
+            ChatFlavor::Slack => {
+                let mut text = format!("*{}*\n{}", notification.title, notification.message);
+                if let Some(link) = &notification.link {
+                    text.push_str(&format!("\n<{}|Open in Phoenix Tracker>", link));
+                }
+                serde_json::json!({ "text": text })
+            }
+            ChatFlavor::Matrix => {
+                let mut text = format!("{}\n{}", notification.title, notification.message);
+                let mut html = format!(
+                    "<strong>{}</strong><br>{}",
+                    escape_html(&notification.title),
+                    escape_html(&notification.message)
+                );
+                if let Some(link) = &notification.link {
+                    text.push_str(&format!("\n{}", link));
+                    html.push_str(&format!(
+                        "<br><a href=\"{}\">Open in Phoenix Tracker</a>",
+                        escape_html(link)
+                    ));
+                }
+                serde_json::json!({ "text": text, "html": html, "username": "Phoenix Tracker" })
+            }
+        }
+    }
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[async_trait]
+impl NotificationChannel for ChatWebhookChannel {
+    fn kind(&self) -> &'static str {
+        match self.flavor {
+            ChatFlavor::Slack => CHANNEL_SLACK,
+            ChatFlavor::Matrix => CHANNEL_MATRIX,
+        }
+    }
+
+    async fn send(&self, notification: &OutgoingNotification) -> Result<(), String> {
+        check_destination(&self.url, self.destination).await?;
+        let request = self
+            .client
+            .post(&self.url)
+            .json(&self.payload(notification));
+        check_response(self.kind(), request.send().await).await
+    }
+}
@@ -0,0 +1,100 @@
+use crate::environment;
+use hmac::{Hmac, Mac};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use sha2::Sha256;
+
+// Short-lived links to files in the local store, minted once the caller has been authorized,
+// the way S3 presigns its URLs. Each covers one file and variant until it expires.
+
+pub const ASSET: &str = "asset";
+pub const THUMBNAIL: &str = "thumbnail";
+
+lazy_static! {
+    // Without ASSET_URL_SECRET, a key is derived from JWT_SECRET rather than reusing it,
+    // so nothing signed for a link can be passed off as signed for a token
+    static ref SIGNING_KEY: Vec<u8> = match environment::asset_url_secret() {
+        Some(secret) => secret.as_bytes().to_vec(),
+        None => derive_key(environment::jwt_secret()),
+    };
+}
+
+fn derive_key(jwt_secret: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(jwt_secret.as_bytes())
+        .expect("HMAC accepts keys of any size");
+    mac.update(b"asset-url");
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SignedQuery {
+    // Unix timestamp the link stops working at
+    pub expires: i64,
+    pub signature: String,
+}
+
+/// The query string granting access to `kind` of the upload until `expires`.
+pub fn query(kind: &str, upload_id: i32, expires: i64) -> String {
+    format!(
+        "expires={}&signature={}",
+        expires,
+        hex::encode(mac(kind, upload_id, expires).finalize().into_bytes())
+    )
+}
+
+/// Whether the query was minted for this file and hasn't expired yet.
+pub fn verify(kind: &str, upload_id: i32, query: &SignedQuery, now: i64) -> bool {
+    let Ok(signature) = hex::decode(&query.signature) else {
+        return false;
+    };
+    query.expires >= now
+        && mac(kind, upload_id, query.expires)
+            .verify_slice(&signature)
+            .is_ok()
+}
+
+fn mac(kind: &str, upload_id: i32, expires: i64) -> Hmac<Sha256> {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(&SIGNING_KEY).expect("HMAC accepts keys of any size");
+    mac.update(format!("{}:{}:{}", kind, upload_id, expires).as_bytes());
+    mac
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(query: &str) -> SignedQuery {
+        serde_urlencoded::from_str(query).unwrap()
+    }
+
+    #[test]
+    fn test_links_cover_one_file_until_they_expire() {
+        let signed = parse(&query(ASSET, 7, 1_000));
+        assert!(verify(ASSET, 7, &signed, 1_000));
+        assert!(!verify(ASSET, 7, &signed, 1_001));
+        assert!(!verify(ASSET, 8, &signed, 1_000));
+        assert!(!verify(THUMBNAIL, 7, &signed, 1_000));
+
+        let extended = SignedQuery {
+            expires: 2_000,
+            signature: signed.signature.clone(),
+        };
+        assert!(!verify(ASSET, 7, &extended, 1_000));
+        assert!(!verify(
+            ASSET,
+            7,
+            &parse("expires=1000&signature=zz"),
+            1_000
+        ));
+    }
+
+    #[test]
+    fn test_the_fallback_key_is_not_the_jwt_secret() {
+        let key = derive_key("secret");
+        assert_ne!(key, b"secret".to_vec());
+        assert_eq!(key.len(), 32);
+        assert_eq!(key, derive_key("secret"));
+        assert_ne!(key, derive_key("other"));
+    }
+}
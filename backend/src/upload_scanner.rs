@@ -0,0 +1,172 @@
+use crate::environment;
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+pub const SCANNER_NONE: &str = "none";
+pub const SCANNER_CLAMAV: &str = "clamav";
+
+// clamd accepts chunks of any size up to its StreamMaxLength in total
+const CHUNK_SIZE: usize = 64 * 1024;
+const SCAN_TIMEOUT: Duration = Duration::from_secs(120);
+
+pub type ScanInput = Box<dyn AsyncRead + Send + Unpin>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanVerdict {
+    Clean,
+    // The signature the scanner matched
+    Infected(String),
+}
+
+/// Checks new upload content before it is stored. Errors mean the content could not be
+/// checked, and the upload is refused rather than stored unscanned.
+#[async_trait]
+pub trait UploadScanner: Send + Sync {
+    fn kind(&self) -> &'static str;
+    async fn scan(&self, content: ScanInput) -> Result<ScanVerdict, String>;
+}
+
+/// The scanner UPLOAD_SCANNER selects, or None when uploads aren't scanned.
+pub fn from_env() -> Result<Option<Box<dyn UploadScanner>>, String> {
+    match environment::upload_scanner() {
+        SCANNER_NONE => Ok(None),
+        SCANNER_CLAMAV => Ok(Some(Box::new(ClamdScanner::new(
+            environment::clamav_address().to_string(),
+        )))),
+        other => Err(format!(
+            "Unsupported UPLOAD_SCANNER: {} (expected '{}' or '{}')",
+            other, SCANNER_NONE, SCANNER_CLAMAV
+        )),
+    }
+}
+
+/// Streams content to a ClamAV daemon (or anything speaking clamd's INSTREAM command), over
+/// TCP as host:port or over a Unix socket given as an absolute path.
+pub struct ClamdScanner {
+    address: String,
+}
+
+impl ClamdScanner {
+    pub fn new(address: String) -> Self {
+        Self { address }
+    }
+
+    async fn scan_over<S>(&self, stream: S, content: ScanInput) -> Result<ScanVerdict, String>
+    where
+        S: AsyncRead + AsyncWrite + Unpin,
+    {
+        tokio::time::timeout(SCAN_TIMEOUT, instream(stream, content))
+            .await
+            .map_err(|_| format!("clamd at {} timed out", self.address))?
+            .map_err(|e| format!("clamd at {}: {}", self.address, e))
+    }
+}
+
+#[async_trait]
+impl UploadScanner for ClamdScanner {
+    fn kind(&self) -> &'static str {
+        SCANNER_CLAMAV
+    }
+
+    async fn scan(&self, content: ScanInput) -> Result<ScanVerdict, String> {
+        let connect_err =
+            |e: std::io::Error| format!("could not reach clamd at {}: {}", self.address, e);
+        #[cfg(unix)]
+        if self.address.starts_with('/') {
+            let stream = tokio::net::UnixStream::connect(&self.address)
+                .await
+                .map_err(connect_err)?;
+            return self.scan_over(stream, content).await;
+        }
+        let stream = TcpStream::connect(&self.address)
+            .await
+            .map_err(connect_err)?;
+        self.scan_over(stream, content).await
+    }
+}
+
+// zINSTREAM: length-prefixed chunks ended by an empty one, then a NUL-terminated reply
+async fn instream<S>(mut stream: S, mut content: ScanInput) -> Result<ScanVerdict, String>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let io_err = |e: std::io::Error| e.to_string();
+    stream.write_all(b"zINSTREAM\0").await.map_err(io_err)?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    loop {
+        let read = content.read(&mut buf).await.map_err(io_err)?;
+        stream
+            .write_all(&(read as u32).to_be_bytes())
+            .await
+            .map_err(io_err)?;
+        if read == 0 {
+            break;
+        }
+        stream.write_all(&buf[..read]).await.map_err(io_err)?;
+    }
+    stream.flush().await.map_err(io_err)?;
+
+    let mut reply = Vec::new();
+    BufReader::new(stream)
+        .read_until(b'\0', &mut reply)
+        .await
+        .map_err(io_err)?;
+    parse_reply(&String::from_utf8_lossy(&reply))
+}
+
+// "stream: OK", "stream: <signature> FOUND" or "<message> ERROR"
+fn parse_reply(reply: &str) -> Result<ScanVerdict, String> {
+    let reply = reply.trim_end_matches(['\0', '\n']).trim();
+    let result = reply.strip_prefix("stream:").unwrap_or(reply).trim();
+    if result == "OK" {
+        Ok(ScanVerdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(ScanVerdict::Infected(signature.trim().to_string()))
+    } else {
+        Err(format!("unexpected reply: {}", reply))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reply() {
+        assert_eq!(parse_reply("stream: OK\0"), Ok(ScanVerdict::Clean));
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0"),
+            Ok(ScanVerdict::Infected("Win.Test.EICAR_HDB-1".to_string()))
+        );
+        assert!(parse_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_instream_sends_length_prefixed_chunks() {
+        let (client, mut daemon) = tokio::io::duplex(1024);
+        let daemon = tokio::spawn(async move {
+            let mut command = [0u8; 10];
+            daemon.read_exact(&mut command).await.unwrap();
+            let mut received = Vec::new();
+            loop {
+                let len = daemon.read_u32().await.unwrap() as usize;
+                if len == 0 {
+                    break;
+                }
+                let mut chunk = vec![0u8; len];
+                daemon.read_exact(&mut chunk).await.unwrap();
+                received.extend(chunk);
+            }
+            daemon.write_all(b"stream: OK\0").await.unwrap();
+            (command, received)
+        });
+
+        let verdict = instream(client, Box::new(&b"hello clamd"[..])).await;
+        let (command, received) = daemon.await.unwrap();
+        assert_eq!(verdict, Ok(ScanVerdict::Clean));
+        assert_eq!(&command, b"zINSTREAM\0");
+        assert_eq!(received, b"hello clamd");
+    }
+}
@@ -0,0 +1,256 @@
+use crate::crud::point_scale::POINT_SCALE_MAP;
+use crate::crud::recurrence::Recurrence;
+use crate::crud::status::STATUS_MAP;
+use crate::crud::work_type::WORK_TYPE_MAP;
+use crate::error::{AppError, FieldError};
+use axum::extract::rejection::JsonRejection;
+use axum::extract::{FromRequest, Request};
+use axum::Json;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+
+// Limits shared by every request payload so the same field is held to the same rules
+// no matter which endpoint it arrives through.
+pub const MAX_TITLE_LENGTH: usize = 255;
+pub const MAX_NAME_LENGTH: usize = 100;
+pub const MAX_EMAIL_LENGTH: usize = 254;
+pub const MAX_TAG_NAME_LENGTH: usize = 50;
+pub const MAX_DESCRIPTION_LENGTH: usize = 50_000;
+pub const MAX_COMMENT_LENGTH: usize = 20_000;
+pub const MAX_NOTE_LENGTH: usize = 200_000;
+
+lazy_static! {
+    static ref EMAIL_REGEX: Regex =
+        Regex::new(r"^[^\s@]+@[^\s@]+\.[^\s@]+$").expect("email regex is valid");
+}
+
+/// Implemented by request payloads; each impl lists its field rules against a `Validator`.
+pub trait Validate {
+    fn validate(&self, v: &mut Validator);
+}
+
+/// Collects every failing rule so the client gets all field errors in one response.
+#[derive(Default)]
+pub struct Validator {
+    errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn error(&mut self, field: &str, message: impl Into<String>) -> &mut Self {
+        self.errors.push(FieldError::new(field, message));
+        self
+    }
+
+    // Required text: not blank and no longer than `max` characters
+    pub fn text(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.trim().is_empty() {
+            return self.error(field, "must not be empty");
+        }
+        self.max_length(field, value, max)
+    }
+
+    // Text that may be omitted, but when present follows the same rules as `text`
+    pub fn optional_text(&mut self, field: &str, value: Option<&String>, max: usize) -> &mut Self {
+        match value {
+            Some(value) => self.text(field, value, max),
+            None => self,
+        }
+    }
+
+    // Free-form text that may be empty but is still bounded
+    pub fn max_length(&mut self, field: &str, value: &str, max: usize) -> &mut Self {
+        if value.chars().count() > max {
+            return self.error(field, format!("must be at most {} characters", max));
+        }
+        self
+    }
+
+    pub fn email(&mut self, field: &str, value: &str) -> &mut Self {
+        if value.len() > MAX_EMAIL_LENGTH {
+            return self.error(
+                field,
+                format!("must be at most {} characters", MAX_EMAIL_LENGTH),
+            );
+        }
+        if !EMAIL_REGEX.is_match(value.trim()) {
+            return self.error(field, "must be a valid email address");
+        }
+        self
+    }
+
+    pub fn optional_email(&mut self, field: &str, value: Option<&String>) -> &mut Self {
+        match value {
+            Some(value) => self.email(field, value),
+            None => self,
+        }
+    }
+
+    pub fn id(&mut self, field: &str, value: i32) -> &mut Self {
+        if value <= 0 {
+            return self.error(field, "must be a positive id");
+        }
+        self
+    }
+
+    pub fn non_negative(&mut self, field: &str, value: i32) -> &mut Self {
+        if value < 0 {
+            return self.error(field, "must not be negative");
+        }
+        self
+    }
+
+    pub fn percent(&mut self, field: &str, value: f32) -> &mut Self {
+        if !(0.0..=100.0).contains(&value) {
+            return self.error(field, "must be between 0 and 100");
+        }
+        self
+    }
+
+    // Only the range is checked here; membership in the project's scale is enforced by
+    // the crud layer, which knows which project the issue belongs to.
+    pub fn points(&mut self, field: &str, value: i32) -> &mut Self {
+        self.non_negative(field, value)
+    }
+
+    pub fn point_scale(&mut self, field: &str, value: &str) -> &mut Self {
+        if !POINT_SCALE_MAP.contains_key(value) {
+            let mut names: Vec<&str> = POINT_SCALE_MAP.keys().copied().collect();
+            names.sort();
+            return self.error(field, format!("must be one of {}", names.join(", ")));
+        }
+        self
+    }
+
+    pub fn work_type(&mut self, field: &str, value: i32) -> &mut Self {
+        if !WORK_TYPE_MAP.contains_key(&value) {
+            return self.error(field, format!("unknown work type {}", value));
+        }
+        self
+    }
+
+    pub fn status(&mut self, field: &str, value: i32) -> &mut Self {
+        if !STATUS_MAP.contains_key(&value) {
+            return self.error(field, format!("unknown status {}", value));
+        }
+        self
+    }
+
+    pub fn recurrence(&mut self, field: &str, value: &str) -> &mut Self {
+        if let Err(e) = Recurrence::parse(value) {
+            return self.error(field, e);
+        }
+        self
+    }
+
+    pub fn finish(&mut self) -> Result<(), AppError> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(AppError::Validation(std::mem::take(&mut self.errors)))
+        }
+    }
+}
+
+pub fn validate<T: Validate>(payload: &T) -> Result<(), AppError> {
+    let mut v = Validator::new();
+    payload.validate(&mut v);
+    v.finish()
+}
+
+/// Drop-in replacement for `Json<T>` that rejects malformed bodies and payloads that
+/// fail `Validate` with a problem-details response.
+pub struct ValidatedJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) = Json::<T>::from_request(req, state)
+            .await
+            .map_err(|rejection| match rejection {
+                // Well-formed JSON with missing or mistyped fields
+                JsonRejection::JsonDataError(e) => AppError::invalid("body", e.body_text()),
+                other => AppError::BadRequest(other.body_text()),
+            })?;
+        validate(&payload)?;
+        Ok(ValidatedJson(payload))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Sample {
+        title: String,
+        email: String,
+        points: Option<i32>,
+    }
+
+    impl Validate for Sample {
+        fn validate(&self, v: &mut Validator) {
+            v.text("title", &self.title, MAX_TITLE_LENGTH)
+                .email("email", &self.email);
+            if let Some(points) = self.points {
+                v.points("points", points);
+            }
+        }
+    }
+
+    fn field_errors(result: Result<(), AppError>) -> Vec<String> {
+        match result {
+            Err(AppError::Validation(errors)) => errors.into_iter().map(|e| e.field).collect(),
+            Err(other) => panic!("unexpected error: {:?}", other),
+            Ok(()) => vec![],
+        }
+    }
+
+    #[test]
+    fn test_valid_payload_passes() {
+        let sample = Sample {
+            title: "Fix login".to_string(),
+            email: "alice@example.com".to_string(),
+            points: Some(3),
+        };
+        assert!(validate(&sample).is_ok());
+    }
+
+    #[test]
+    fn test_all_field_errors_are_reported() {
+        let sample = Sample {
+            title: "   ".to_string(),
+            email: "not-an-email".to_string(),
+            points: Some(-1),
+        };
+        assert_eq!(
+            field_errors(validate(&sample)),
+            vec!["title", "email", "points"]
+        );
+    }
+
+    #[test]
+    fn test_length_limits_count_characters() {
+        let mut v = Validator::new();
+        v.text("title", &"é".repeat(MAX_TITLE_LENGTH), MAX_TITLE_LENGTH);
+        assert!(v.finish().is_ok());
+
+        v.text("title", &"a".repeat(MAX_TITLE_LENGTH + 1), MAX_TITLE_LENGTH);
+        assert_eq!(field_errors(v.finish()), vec!["title"]);
+    }
+
+    #[test]
+    fn test_unknown_work_type_and_status() {
+        let mut v = Validator::new();
+        v.work_type("workType", 99).status("status", 42);
+        assert_eq!(field_errors(v.finish()), vec!["workType", "status"]);
+    }
+}
@@ -1,265 +1,582 @@
+mod presence;
+mod protocol;
+mod router;
+
+use crate::crud::domain_event::{DomainEvent, DomainEventCrud, EventEnvelope, MAX_REPLAY};
 use crate::crud::project::ProjectCrud;
+use crate::crud::project_user::ProjectUserCrud;
+use crate::crud::user::UserCrud;
+use crate::error::AppError;
 use crate::jwt::JwtService;
 use crate::AppState;
 use axum::extract::Query;
 use axum::{
     extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
+        ws::{CloseFrame, Message, WebSocket, WebSocketUpgrade},
         State,
     },
     http::StatusCode,
     response::IntoResponse,
+    Extension,
+};
+use protocol::{
+    parse_client_message, parse_version, render, ClientMessage, ErrorCode, EventFilter,
+    ProtocolError, ServerMessage, CLOSE_ACCESS_REVOKED, CLOSE_TOKEN_EXPIRED, PROTOCOL_V2,
 };
+pub use router::{EventRouter, PresenceAnnouncement, PRESENCE_CHANNEL};
+use router::{Routed, RoutedConnection};
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::sync::broadcast;
-use tokio::sync::Mutex;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 
-#[allow(dead_code)]
-struct WebSocketState {
-    subscribed_projects: HashSet<i32>,
-    user_id: i32,
-    last_ping_time: std::time::Instant,
-}
+// Maximum time allowed between pings (in seconds)
+const MAX_PING_INTERVAL_SECS: u64 = 120;
+// How often the user's account and project memberships are checked again
+const REVALIDATE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+// How long before the token expires the client is asked for a fresh one
+const TOKEN_REFRESH_WINDOW_SECS: usize = 300;
 
-#[derive(serde::Deserialize, Debug)]
-struct SocketCommandWrapper {
-    command: String,
-    project_id: Option<i32>,
+struct Subscription {
+    filter: EventFilter,
+    // Last sequence delivered, so replays never repeat an event
+    last_sequence: i64,
 }
 
-#[derive(Debug)]
-enum SocketCommand {
-    Subscribe { project_id: i32 },
-    Unsubscribe { project_id: i32 },
+struct WebSocketState {
+    version: u8,
+    user_id: i32,
+    subscriptions: HashMap<i32, Subscription>,
+    last_ping_time: std::time::Instant,
+    // Unix time the token expires; moved forward by authenticate
+    token_expires_at: usize,
+    expiry_warned: bool,
+    last_revalidated: std::time::Instant,
+    // Set when a membership_revoked event arrives, to check before the next interval
+    revalidate_now: bool,
 }
 
-#[allow(dead_code)]
-#[derive(serde::Deserialize, Debug)]
-struct ProjectEvent {
-    project_id: i32,
-    event_type: String,
-    data: serde_json::Value,
-}
+// The socket went away; stop serving it
+struct Closed;
 
 pub async fn ws_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Extension(router): Extension<Arc<EventRouter>>,
     Query(params): Query<HashMap<String, String>>,
 ) -> impl IntoResponse {
-    let token = params.get("token");
-
-    match token {
-        Some(token_str) => {
-            debug!("token = {}", token_str);
-            let rx = state.tx.subscribe();
+    let Some(version) = parse_version(params.get("protocol").map(String::as_str)) else {
+        debug!("WebSocket requested an unsupported protocol");
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let Some(token_str) = params.get("token") else {
+        debug!("WebSocket token missing");
+        return StatusCode::UNAUTHORIZED.into_response();
+    };
 
-            // Extract Bearer token if present
-            let jwt_token = if token_str.starts_with("Bearer ") {
-                &token_str[7..]
-            } else {
-                token_str
-            };
+    // Extract Bearer token if present
+    let jwt_token = token_str.strip_prefix("Bearer ").unwrap_or(token_str);
 
-            let jwt_service = JwtService::new();
-            match jwt_service.validate_token(jwt_token) {
-                Ok(claims) => {
-                    debug!("WebSocket connection upgraded for user: {}", claims.user_id);
-                    ws.on_upgrade(move |socket| handle_socket(socket, rx, state, claims.user_id))
-                }
-                Err(e) => {
-                    debug!("WebSocket JWT validation failed: {:?}", e);
-                    StatusCode::UNAUTHORIZED.into_response()
-                }
-            }
+    let jwt_service = JwtService::new();
+    match jwt_service.validate_token(jwt_token) {
+        Ok(claims) => {
+            debug!(
+                "WebSocket connection upgraded for user {} (protocol {})",
+                claims.user_id, version
+            );
+            let connection = router.connect(claims.user_id);
+            ws.on_upgrade(move |socket| {
+                handle_socket(
+                    socket,
+                    connection,
+                    state,
+                    claims.user_id,
+                    claims.exp,
+                    version,
+                )
+            })
         }
-        None => {
-            debug!("WebSocket token missing");
+        Err(e) => {
+            debug!("WebSocket JWT validation failed: {:?}", e);
             StatusCode::UNAUTHORIZED.into_response()
         }
     }
 }
 
-// Maximum time allowed between pings (in seconds)
-const MAX_PING_INTERVAL_SECS: u64 = 120;
-
-pub async fn handle_socket(
+async fn handle_socket(
     mut socket: WebSocket,
-    mut rx: broadcast::Receiver<String>,
+    mut connection: RoutedConnection,
     state: AppState,
     user_id: i32,
+    token_expires_at: usize,
+    version: u8,
 ) {
-    // Initialize the WebSocket state with the current time
-    let web_socket_state = Arc::new(Mutex::new(WebSocketState {
-        subscribed_projects: HashSet::new(),
+    let mut ws_state = WebSocketState {
+        version,
         user_id,
+        subscriptions: HashMap::new(),
         last_ping_time: std::time::Instant::now(),
-    }));
+        token_expires_at,
+        expiry_warned: false,
+        last_revalidated: std::time::Instant::now(),
+        revalidate_now: false,
+    };
 
     // Create a ticker to check for stale connections periodically
     let mut interval = tokio::time::interval(std::time::Duration::from_secs(30));
 
+    let welcome = ServerMessage::Welcome { version, user_id };
+    if send(&mut socket, &ws_state, &welcome).await.is_err() {
+        return;
+    }
+
     loop {
-        tokio::select! {
-            // Check for stale connections periodically
+        let result = tokio::select! {
             _ = interval.tick() => {
-                // Check if the connection is stale
-                let is_stale = {
-                    let ws_state = web_socket_state.lock().await;
-                    let elapsed = ws_state.last_ping_time.elapsed().as_secs();
-                    elapsed > MAX_PING_INTERVAL_SECS
-                };
-
-                // If the connection is stale, close it
-                if is_stale {
+                let elapsed = ws_state.last_ping_time.elapsed().as_secs();
+                if elapsed > MAX_PING_INTERVAL_SECS {
                     debug!("WebSocket connection for user {} is stale (no ping in {} seconds), closing",
                            user_id, MAX_PING_INTERVAL_SECS);
                     break;
                 }
+                match check_access(&mut socket, &state, &connection, &mut ws_state).await {
+                    Ok(()) => catch_up_if_lagged(&mut socket, &state, &connection, &mut ws_state).await,
+                    Err(closed) => Err(closed),
+                }
             }
 
-            // Handle incoming WebSocket messages
-            Some(msg_result) = socket.recv() => {
+            msg_result = socket.recv() => {
                 match msg_result {
-                    Ok(msg) => {
-                        match msg {
-                            Message::Text(text) => {
-                                debug!("Received message: {}", text);
-
-                                // Handle ping messages
-                                if text == "ping" {
-                                    debug!("Received ping, updating last_ping_time");
-                                    {
-                                        let mut ws_state = web_socket_state.lock().await;
-                                        ws_state.last_ping_time = std::time::Instant::now();
-                                    }
-                                    if let Err(_) = socket.send(Message::Text("pong".to_string().into())).await {
-                                        break;
-                                    }
-                                    continue;
-                                }
-
-                                // Handle regular commands
-                                if let Ok(wrapper) = serde_json::from_str::<SocketCommandWrapper>(&text) {
-                                    // Update last ping time on any valid command
-                                    {
-                                        let mut ws_state = web_socket_state.lock().await;
-                                        ws_state.last_ping_time = std::time::Instant::now();
-                                    }
-
-                                    let command = match wrapper.command.as_str() {
-                                        "subscribe" => {
-                                            if let Some(project_id) = wrapper.project_id {
-                                                Some(SocketCommand::Subscribe { project_id })
-                                            } else {
-                                                None
-                                            }
-                                        },
-                                        "unsubscribe" => {
-                                            if let Some(project_id) = wrapper.project_id {
-                                                Some(SocketCommand::Unsubscribe { project_id })
-                                            } else {
-                                                None
-                                            }
-                                        },
-                                        _ => None,
-                                    };
-
-                                    debug!("Received command: {:?}", command);
-                                    if let Some(cmd) = command {
-                                        match cmd {
-                                            SocketCommand::Subscribe { project_id } => {
-                                                // Verify user has access to this project
-                                                let project_crud = ProjectCrud::new(state.clone());
-                                                if let Ok(project_users) = project_crud.find_users_by_project_id(project_id).await {
-                                                    if project_users.iter().any(|pu| pu.user_id == user_id) {
-                                                        let mut ws_state = web_socket_state.lock().await;
-                                                        ws_state.subscribed_projects.insert(project_id);
-                                                        debug!("Subscribed to project {}", project_id);
-                                                        let response = format!("{{\"event\": \"subscribed\", \"project_id\": {}}}", project_id);
-                                                        if let Err(_) = socket.send(Message::Text(response.into())).await {
-                                                            break;
-                                                        }
-                                                    } else {
-                                                        debug!("User {} does not have access to project {}", user_id, project_id);
-                                                        let response = format!("{{\"event\": \"error\", \"message\": \"Access denied to project {}\"}}", project_id);
-                                                        if let Err(_) = socket.send(Message::Text(response.into())).await {
-                                                            break;
-                                                        }
-                                                    }
-                                                }
-                                            },
-                                            SocketCommand::Unsubscribe { project_id } => {
-                                                let mut ws_state = web_socket_state.lock().await;
-                                                ws_state.subscribed_projects.remove(&project_id);
-                                                debug!("Unsubscribed from project {}", project_id);
-                                                let response = format!("{{\"event\": \"unsubscribed\", \"project_id\": {}}}", project_id);
-                                                if let Err(_) = socket.send(Message::Text(response.into())).await {
-                                                    break;
-                                                }
-                                            }
-                                        }
-                                    }
-                                }
-                            },
-                            Message::Close(_) => {
-                                debug!("WebSocket closed, clearing all subscriptions");
-                                break;
-                            },
-                            _ => {}
-                        }
-                    },
-                    Err(e) => {
+                    Some(Ok(Message::Text(text))) => {
+                        debug!("Received message: {}", text);
+                        handle_message(&mut socket, &state, &connection, &mut ws_state, &text).await
+                    }
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => Ok(()),
+                    Some(Err(e)) => {
                         debug!("WebSocket error: {:?}", e);
                         break;
                     }
                 }
             }
 
-            // Handle broadcast messages from other parts of the application
-            result = rx.recv() => {
-                match result {
-                    Ok(broadcast_msg) => {
-                        debug!("Received broadcast message for user {}: {}", user_id, broadcast_msg);
-                        if let Ok(event) = serde_json::from_str::<ProjectEvent>(&broadcast_msg) {
-                            let ws_state = web_socket_state.lock().await;
-                            if ws_state.subscribed_projects.contains(&event.project_id) {
-                                info!("Forwarding {} event to user {} for project {}",
-                                      event.event_type, user_id, event.project_id);
-
-                                if let Err(send_err) = socket.send(Message::Text(broadcast_msg.into())).await {
-                                    error!("Failed to send event to user {}: {:?}", user_id, send_err);
-                                    break;
-                                }
-                            } else {
-                                debug!("User {} not subscribed to project {}, skipping event {}",
-                                       user_id, event.project_id, event.event_type);
-                            }
-                        } else {
-                            warn!("Failed to parse broadcast message for user {}: {}", user_id, broadcast_msg);
+            Some(routed) = connection.recv() => {
+                match routed {
+                    Routed::Event(envelope) => match deliver(&mut socket, &mut ws_state, &envelope).await {
+                        Ok(()) if ws_state.revalidate_now => {
+                            check_access(&mut socket, &state, &connection, &mut ws_state).await
                         }
+                        Ok(()) => catch_up_if_lagged(&mut socket, &state, &connection, &mut ws_state).await,
+                        Err(closed) => Err(closed),
                     },
-                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
-                        warn!("WebSocket receiver for user {} lagged and skipped {} messages", user_id, skipped);
-                        // Continue processing, don't break the connection
-                        continue;
-                    },
-                    Err(broadcast::error::RecvError::Closed) => {
-                        error!("Broadcast channel closed for user {}, terminating WebSocket", user_id);
-                        break;
+                    Routed::Presence(change) => {
+                        send(&mut socket, &ws_state, &ServerMessage::PresenceChanged(&change)).await
                     }
                 }
             }
+        };
+        if result.is_err() {
+            break;
         }
     }
 
-    // Clean up any resources when WebSocket closes
+    // Dropping the connection takes it out of the router
     debug!("WebSocket connection closed for user {}", user_id);
+}
 
-    // Clear all subscriptions
-    if let Ok(mut ws_state) = web_socket_state.try_lock() {
-        ws_state.subscribed_projects.clear();
+async fn handle_message(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection: &RoutedConnection,
+    ws_state: &mut WebSocketState,
+    text: &str,
+) -> Result<(), Closed> {
+    let message = match parse_client_message(ws_state.version, text) {
+        Ok(message) => message,
+        Err(error) => {
+            debug!(
+                "Invalid message from user {}: {}",
+                ws_state.user_id, error.message
+            );
+            return send(socket, ws_state, &ServerMessage::Error { id: None, error }).await;
+        }
     };
+    // Any valid message shows the client is still there
+    ws_state.last_ping_time = std::time::Instant::now();
+
+    match message {
+        ClientMessage::Ping { id } => send(socket, ws_state, &ServerMessage::Pong { id }).await,
+        ClientMessage::Subscribe {
+            id,
+            project_id,
+            after,
+            filter,
+        } => match subscribe(state, connection, ws_state, project_id, after, filter).await {
+            Ok(sequence) => {
+                debug!("Subscribed to project {}", project_id);
+                let ack = ServerMessage::Subscribed {
+                    id,
+                    project_id,
+                    sequence,
+                };
+                send(socket, ws_state, &ack).await?;
+                let users = connection.users(project_id);
+                send(
+                    socket,
+                    ws_state,
+                    &ServerMessage::Presence { project_id, users },
+                )
+                .await?;
+                replay(socket, state, ws_state, project_id).await
+            }
+            Err(error) => send(socket, ws_state, &ServerMessage::Error { id, error }).await,
+        },
+        ClientMessage::Unsubscribe { id, project_id } => {
+            connection.unsubscribe(project_id);
+            ws_state.subscriptions.remove(&project_id);
+            debug!("Unsubscribed from project {}", project_id);
+            send(
+                socket,
+                ws_state,
+                &ServerMessage::Unsubscribed {
+                    id,
+                    project_id,
+                    reason: None,
+                },
+            )
+            .await
+        }
+        ClientMessage::Authenticate { id, token } => {
+            authenticate(socket, ws_state, id, &token).await
+        }
+        ClientMessage::Focus {
+            id,
+            project_id,
+            target,
+        } => {
+            if let Err(error) = ensure_subscribed(ws_state, project_id) {
+                return send(socket, ws_state, &ServerMessage::Error { id, error }).await;
+            }
+            connection.focus(project_id, target);
+            let ack = ServerMessage::Focused {
+                id,
+                project_id,
+                target,
+            };
+            send(socket, ws_state, &ack).await
+        }
+        ClientMessage::Edit {
+            id,
+            project_id,
+            target,
+            typing,
+        } => {
+            if let Err(error) = ensure_subscribed(ws_state, project_id) {
+                return send(socket, ws_state, &ServerMessage::Error { id, error }).await;
+            }
+            let locked_by = connection.edit(project_id, target, typing);
+            let ack = ServerMessage::Editing {
+                id,
+                project_id,
+                target,
+                typing: typing && target.is_some(),
+                locked_by,
+            };
+            send(socket, ws_state, &ack).await
+        }
+    }
+}
+
+async fn authenticate(
+    socket: &mut WebSocket,
+    ws_state: &mut WebSocketState,
+    id: Option<u64>,
+    token: &str,
+) -> Result<(), Closed> {
+    let token = token.strip_prefix("Bearer ").unwrap_or(token);
+    let error = match JwtService::new().validate_token(token) {
+        Ok(claims) if claims.user_id == ws_state.user_id => {
+            ws_state.token_expires_at = claims.exp;
+            ws_state.expiry_warned = false;
+            let reply = ServerMessage::Authenticated {
+                id,
+                expires_at: claims.exp,
+            };
+            return send(socket, ws_state, &reply).await;
+        }
+        Ok(_) => ProtocolError::new(
+            ErrorCode::Forbidden,
+            "The token belongs to a different user; open a new connection instead",
+        ),
+        Err(e) => ProtocolError::new(ErrorCode::Unauthorized, e.to_string()),
+    };
+    send(socket, ws_state, &ServerMessage::Error { id, error }).await
+}
+
+// Closes the socket once the token expires or the user is gone, and drops the
+// subscriptions of projects the user no longer belongs to
+async fn check_access(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection: &RoutedConnection,
+    ws_state: &mut WebSocketState,
+) -> Result<(), Closed> {
+    let now = chrono::Utc::now().timestamp().max(0) as usize;
+    if now >= ws_state.token_expires_at {
+        info!(
+            "Token for user {} expired, closing WebSocket",
+            ws_state.user_id
+        );
+        return close(socket, CLOSE_TOKEN_EXPIRED, "Token expired").await;
+    }
+    if !ws_state.expiry_warned && now + TOKEN_REFRESH_WINDOW_SECS >= ws_state.token_expires_at {
+        ws_state.expiry_warned = true;
+        let warning = ServerMessage::TokenExpiring {
+            expires_at: ws_state.token_expires_at,
+        };
+        send(socket, ws_state, &warning).await?;
+    }
+
+    if !ws_state.revalidate_now && ws_state.last_revalidated.elapsed() < REVALIDATE_INTERVAL {
+        return Ok(());
+    }
+    ws_state.revalidate_now = false;
+    ws_state.last_revalidated = std::time::Instant::now();
+
+    // A failed check is retried on the next interval rather than closing the socket
+    match UserCrud::new(state.clone())
+        .find_by_id(ws_state.user_id)
+        .await
+    {
+        Ok(Some(_)) => {}
+        Ok(None) => {
+            info!(
+                "User {} no longer exists, closing WebSocket",
+                ws_state.user_id
+            );
+            return close(socket, CLOSE_ACCESS_REVOKED, "Access revoked").await;
+        }
+        Err(e) => {
+            warn!("Failed to revalidate user {}: {}", ws_state.user_id, e);
+            return Ok(());
+        }
+    }
+
+    let project_user_crud = ProjectUserCrud::new(state.clone());
+    let projects: Vec<i32> = ws_state.subscriptions.keys().copied().collect();
+    for project_id in projects {
+        let is_member = match project_user_crud
+            .is_member(ws_state.user_id, project_id)
+            .await
+        {
+            Ok(is_member) => is_member,
+            // The project was deleted
+            Err(AppError::NotFound(_)) => false,
+            Err(e) => {
+                warn!(
+                    "Failed to revalidate access to project {}: {}",
+                    project_id, e
+                );
+                continue;
+            }
+        };
+        if is_member {
+            continue;
+        }
+        info!(
+            "User {} lost access to project {}, dropping subscription",
+            ws_state.user_id, project_id
+        );
+        connection.unsubscribe(project_id);
+        ws_state.subscriptions.remove(&project_id);
+        let notice = ServerMessage::Unsubscribed {
+            id: None,
+            project_id,
+            reason: Some("access_revoked"),
+        };
+        send(socket, ws_state, &notice).await?;
+    }
+    Ok(())
+}
+
+async fn close(socket: &mut WebSocket, code: u16, reason: &'static str) -> Result<(), Closed> {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+    Err(Closed)
+}
+
+// Presence is only shared within projects the socket is subscribed to
+fn ensure_subscribed(ws_state: &WebSocketState, project_id: i32) -> Result<(), ProtocolError> {
+    if ws_state.subscriptions.contains_key(&project_id) {
+        Ok(())
+    } else {
+        Err(ProtocolError::new(
+            ErrorCode::InvalidMessage,
+            format!("Subscribe to project {} first", project_id),
+        ))
+    }
+}
+
+// Returns the sequence the subscription starts after
+async fn subscribe(
+    state: &AppState,
+    connection: &RoutedConnection,
+    ws_state: &mut WebSocketState,
+    project_id: i32,
+    after: Option<i64>,
+    filter: EventFilter,
+) -> Result<i64, ProtocolError> {
+    filter.validate()?;
+    let internal = |e: crate::error::AppError| {
+        warn!("Failed to subscribe to project {}: {}", project_id, e);
+        ProtocolError::new(ErrorCode::Internal, "Could not subscribe, try again")
+    };
+
+    let project_users = ProjectCrud::new(state.clone())
+        .find_users_by_project_id(project_id)
+        .await
+        .map_err(internal)?;
+    if !project_users
+        .iter()
+        .any(|pu| pu.user_id == ws_state.user_id)
+    {
+        debug!(
+            "User {} does not have access to project {}",
+            ws_state.user_id, project_id
+        );
+        return Err(ProtocolError::new(
+            ErrorCode::Forbidden,
+            format!("Access denied to project {}", project_id),
+        ));
+    }
+
+    // Routed before reading the latest sequence so nothing slips in between
+    connection.subscribe(project_id);
+    let last_sequence = match after {
+        Some(after) => after,
+        None => DomainEventCrud::new(state.db.clone())
+            .latest_sequence_in_project(project_id)
+            .await
+            .map_err(internal)?,
+    };
+    ws_state.subscriptions.insert(
+        project_id,
+        Subscription {
+            filter,
+            last_sequence,
+        },
+    );
+    Ok(last_sequence)
+}
+
+async fn send(
+    socket: &mut WebSocket,
+    ws_state: &WebSocketState,
+    message: &ServerMessage<'_>,
+) -> Result<(), Closed> {
+    let Some(text) = render(ws_state.version, message) else {
+        return Ok(());
+    };
+    socket.send(Message::Text(text.into())).await.map_err(|e| {
+        debug!("Failed to send to user {}: {:?}", ws_state.user_id, e);
+        Closed
+    })
+}
+
+// Sends an event unless the client already has it or filtered it out
+async fn deliver(
+    socket: &mut WebSocket,
+    ws_state: &mut WebSocketState,
+    envelope: &EventEnvelope,
+) -> Result<(), Closed> {
+    let Some(subscription) = ws_state.subscriptions.get_mut(&envelope.project_id) else {
+        return Ok(());
+    };
+    if envelope.sequence <= subscription.last_sequence {
+        return Ok(());
+    }
+    subscription.last_sequence = envelope.sequence;
+    // Handled here rather than passed on; the membership itself decides
+    if let DomainEvent::MembershipRevoked { .. } = envelope.event {
+        ws_state.revalidate_now = true;
+        return Ok(());
+    }
+    if !subscription.filter.matches(envelope) {
+        return Ok(());
+    }
+    info!(
+        "Forwarding {} event to user {} for project {}",
+        envelope.event.event_type(),
+        ws_state.user_id,
+        envelope.project_id
+    );
+    send(socket, ws_state, &ServerMessage::Event(envelope)).await
+}
+
+async fn catch_up_if_lagged(
+    socket: &mut WebSocket,
+    state: &AppState,
+    connection: &RoutedConnection,
+    ws_state: &mut WebSocketState,
+) -> Result<(), Closed> {
+    if !connection.take_lagged() {
+        return Ok(());
+    }
+    warn!(
+        "WebSocket for user {} fell behind, replaying from the outbox",
+        ws_state.user_id
+    );
+    let projects: Vec<i32> = ws_state.subscriptions.keys().copied().collect();
+    for project_id in projects {
+        replay(socket, state, ws_state, project_id).await?;
+    }
+    Ok(())
+}
+
+// Sends the project's stored events after the last one this client saw. A failed read
+// is only logged; the client can still catch up with GET /api/events.
+async fn replay(
+    socket: &mut WebSocket,
+    state: &AppState,
+    ws_state: &mut WebSocketState,
+    project_id: i32,
+) -> Result<(), Closed> {
+    let Some(after) = ws_state
+        .subscriptions
+        .get(&project_id)
+        .map(|s| s.last_sequence)
+    else {
+        return Ok(());
+    };
+    let events = match DomainEventCrud::new(state.db.clone())
+        .find_since(
+            after,
+            Some(&[project_id]),
+            Some(ws_state.user_id),
+            MAX_REPLAY,
+        )
+        .await
+    {
+        Ok(events) => events,
+        Err(e) => {
+            warn!("Failed to replay events for project {}: {}", project_id, e);
+            return Ok(());
+        }
+    };
+    for envelope in &events {
+        deliver(socket, ws_state, envelope).await?;
+    }
+    if events.len() as u64 >= MAX_REPLAY && ws_state.version == PROTOCOL_V2 {
+        let error = ProtocolError::new(
+            ErrorCode::ResyncRequired,
+            format!(
+                "More than {} events were missed in project {}; reload and subscribe again",
+                MAX_REPLAY, project_id
+            ),
+        );
+        send(socket, ws_state, &ServerMessage::Error { id: None, error }).await?;
+    }
+    Ok(())
 }
@@ -0,0 +1,333 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// A typing indicator clears unless the client keeps sending edits
+pub const TYPING_TIMEOUT: Duration = Duration::from_secs(10);
+// An edit lock is released after this long without an edit, matching the stale-ping limit
+pub const EDIT_TIMEOUT: Duration = Duration::from_secs(120);
+// Connections on other instances are dropped unless re-announced within this time
+pub const REMOTE_TTL: Duration = Duration::from_secs(75);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TargetKind {
+    Issue,
+    ProjectNote,
+}
+
+/// What a user is looking at or editing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Target {
+    pub kind: TargetKind,
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Presence {
+    pub viewing: Option<Target>,
+    // A soft lock: others are told, nothing is refused
+    pub editing: Option<Target>,
+    pub typing: bool,
+}
+
+/// A user's presence in a project, merged over all their connections.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UserPresence {
+    pub user_id: i32,
+    #[serde(flatten)]
+    pub presence: Presence,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PresenceChange {
+    pub project_id: i32,
+    pub user_id: i32,
+    // None once the user has left
+    pub presence: Option<Presence>,
+}
+
+/// A connection, on this instance or another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ConnectionKey {
+    pub instance: u64,
+    pub connection: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    user_id: i32,
+    presence: Presence,
+    updated_at: Instant,
+    edited_at: Instant,
+    // Only set for other instances' connections
+    expires_at: Option<Instant>,
+}
+
+/// Who is in each project and what they're doing. Every change that alters what
+/// others see comes back as a `PresenceChange` to broadcast.
+#[derive(Debug, Default)]
+pub struct PresenceRegistry {
+    projects: HashMap<i32, HashMap<ConnectionKey, Entry>>,
+}
+
+impl PresenceRegistry {
+    pub fn users(&self, project_id: i32) -> Vec<UserPresence> {
+        let mut user_ids: Vec<i32> = self
+            .projects
+            .get(&project_id)
+            .into_iter()
+            .flat_map(|entries| entries.values().map(|e| e.user_id))
+            .collect();
+        user_ids.sort_unstable();
+        user_ids.dedup();
+        user_ids
+            .into_iter()
+            .filter_map(|user_id| {
+                self.merged(project_id, user_id)
+                    .map(|presence| UserPresence { user_id, presence })
+            })
+            .collect()
+    }
+
+    /// Other users editing `target` right now.
+    pub fn editors(&self, project_id: i32, target: Target, except_user_id: i32) -> Vec<i32> {
+        let mut editors: Vec<i32> = self
+            .users(project_id)
+            .into_iter()
+            .filter(|u| u.user_id != except_user_id && u.presence.editing == Some(target))
+            .map(|u| u.user_id)
+            .collect();
+        editors.dedup();
+        editors
+    }
+
+    pub fn local_presence(&self, key: ConnectionKey, project_id: i32) -> Option<Presence> {
+        self.projects
+            .get(&project_id)
+            .and_then(|entries| entries.get(&key))
+            .map(|entry| entry.presence.clone())
+    }
+
+    /// Records `presence` for a connection, or removes the connection with None.
+    /// `expires_at` is for connections on other instances.
+    pub fn set(
+        &mut self,
+        key: ConnectionKey,
+        project_id: i32,
+        user_id: i32,
+        presence: Option<Presence>,
+        now: Instant,
+        expires_at: Option<Instant>,
+    ) -> Option<PresenceChange> {
+        let before = self.merged(project_id, user_id);
+        let entries = self.projects.entry(project_id).or_default();
+        match presence {
+            Some(presence) => {
+                let edited_at = match entries.get(&key) {
+                    Some(entry)
+                        if entry.presence.editing == presence.editing && !presence.typing =>
+                    {
+                        entry.edited_at
+                    }
+                    _ => now,
+                };
+                entries.insert(
+                    key,
+                    Entry {
+                        user_id,
+                        presence,
+                        updated_at: now,
+                        edited_at,
+                        expires_at,
+                    },
+                );
+            }
+            None => {
+                entries.remove(&key);
+                if entries.is_empty() {
+                    self.projects.remove(&project_id);
+                }
+            }
+        }
+        self.change(project_id, user_id, before)
+    }
+
+    /// Clears stale typing indicators and edit locks, and drops other instances'
+    /// connections that stopped being announced. Returns the changed local connections
+    /// alongside the changes to broadcast.
+    pub fn sweep(&mut self, now: Instant) -> (Vec<(ConnectionKey, i32)>, Vec<PresenceChange>) {
+        let mut touched = Vec::new();
+        let mut affected = Vec::new();
+        for (project_id, entries) in &self.projects {
+            for (key, entry) in entries {
+                let expired = entry.expires_at.is_some_and(|at| at <= now);
+                let typing_over =
+                    entry.presence.typing && now.duration_since(entry.updated_at) >= TYPING_TIMEOUT;
+                let editing_over = entry.presence.editing.is_some()
+                    && now.duration_since(entry.edited_at) >= EDIT_TIMEOUT;
+                if expired || typing_over || editing_over {
+                    affected.push((*project_id, *key, entry.user_id, expired));
+                }
+            }
+        }
+
+        let mut changes = Vec::new();
+        for (project_id, key, user_id, expired) in affected {
+            let before = self.merged(project_id, user_id);
+            if expired {
+                if let Some(entries) = self.projects.get_mut(&project_id) {
+                    entries.remove(&key);
+                    if entries.is_empty() {
+                        self.projects.remove(&project_id);
+                    }
+                }
+            } else if let Some(entry) = self
+                .projects
+                .get_mut(&project_id)
+                .and_then(|entries| entries.get_mut(&key))
+            {
+                if now.duration_since(entry.updated_at) >= TYPING_TIMEOUT {
+                    entry.presence.typing = false;
+                }
+                if now.duration_since(entry.edited_at) >= EDIT_TIMEOUT {
+                    entry.presence.editing = None;
+                    entry.presence.typing = false;
+                }
+                touched.push((key, project_id));
+            }
+            changes.extend(self.change(project_id, user_id, before));
+        }
+        (touched, changes)
+    }
+
+    /// Every connection of `instance`, for re-announcing them to other instances.
+    pub fn connections_of(&self, instance: u64) -> Vec<(ConnectionKey, i32, i32, Presence)> {
+        self.projects
+            .iter()
+            .flat_map(|(project_id, entries)| {
+                entries
+                    .iter()
+                    .filter(|(key, _)| key.instance == instance)
+                    .map(|(key, e)| (*key, *project_id, e.user_id, e.presence.clone()))
+            })
+            .collect()
+    }
+
+    // The most recent thing any of the user's connections is viewing or editing
+    fn merged(&self, project_id: i32, user_id: i32) -> Option<Presence> {
+        let mut entries: Vec<&Entry> = self
+            .projects
+            .get(&project_id)?
+            .values()
+            .filter(|e| e.user_id == user_id)
+            .collect();
+        if entries.is_empty() {
+            return None;
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.updated_at));
+        Some(Presence {
+            viewing: entries.iter().find_map(|e| e.presence.viewing),
+            editing: entries.iter().find_map(|e| e.presence.editing),
+            typing: entries.iter().any(|e| e.presence.typing),
+        })
+    }
+
+    fn change(
+        &self,
+        project_id: i32,
+        user_id: i32,
+        before: Option<Presence>,
+    ) -> Option<PresenceChange> {
+        let after = self.merged(project_id, user_id);
+        (after != before).then_some(PresenceChange {
+            project_id,
+            user_id,
+            presence: after,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(connection: u64) -> ConnectionKey {
+        ConnectionKey {
+            instance: 1,
+            connection,
+        }
+    }
+
+    const NOTE: Target = Target {
+        kind: TargetKind::ProjectNote,
+        id: 4,
+    };
+
+    #[test]
+    fn test_join_focus_and_leave() {
+        let mut registry = PresenceRegistry::default();
+        let now = Instant::now();
+
+        let joined = registry.set(key(1), 7, 10, Some(Presence::default()), now, None);
+        assert_eq!(joined.unwrap().presence, Some(Presence::default()));
+        // A second tab for the same user changes nothing others can see
+        assert!(registry
+            .set(key(2), 7, 10, Some(Presence::default()), now, None)
+            .is_none());
+
+        let editing = Presence {
+            viewing: Some(NOTE),
+            editing: Some(NOTE),
+            typing: true,
+        };
+        let later = now + Duration::from_secs(1);
+        let change = registry.set(key(2), 7, 10, Some(editing.clone()), later, None);
+        assert_eq!(change.unwrap().presence, Some(editing));
+        registry.set(key(3), 7, 11, Some(Presence::default()), later, None);
+        assert_eq!(registry.editors(7, NOTE, 11), vec![10]);
+        assert!(registry.editors(7, NOTE, 10).is_empty());
+
+        assert!(registry.set(key(2), 7, 10, None, later, None).is_some());
+        let left = registry.set(key(1), 7, 10, None, later, None);
+        assert_eq!(left.unwrap().presence, None);
+        assert_eq!(registry.users(7).len(), 1);
+    }
+
+    #[test]
+    fn test_sweep_expires_typing_locks_and_remote_connections() {
+        let mut registry = PresenceRegistry::default();
+        let now = Instant::now();
+        let typing = Presence {
+            viewing: None,
+            editing: Some(NOTE),
+            typing: true,
+        };
+        registry.set(key(1), 7, 10, Some(typing), now, None);
+        let remote = ConnectionKey {
+            instance: 2,
+            connection: 1,
+        };
+        registry.set(
+            remote,
+            7,
+            11,
+            Some(Presence::default()),
+            now,
+            Some(now + REMOTE_TTL),
+        );
+
+        let (touched, changes) = registry.sweep(now + TYPING_TIMEOUT);
+        assert_eq!(touched, vec![(key(1), 7)]);
+        assert_eq!(changes[0].presence.as_ref().unwrap().editing, Some(NOTE));
+        assert!(!changes[0].presence.as_ref().unwrap().typing);
+
+        let (_, changes) = registry.sweep(now + EDIT_TIMEOUT);
+        assert!(changes
+            .iter()
+            .any(|c| c.user_id == 10 && c.presence == Some(Presence::default())));
+        assert!(changes
+            .iter()
+            .any(|c| c.user_id == 11 && c.presence.is_none()));
+    }
+}
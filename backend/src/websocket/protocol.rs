@@ -0,0 +1,398 @@
+use super::presence::{PresenceChange, Target, UserPresence};
+use crate::crud::domain_event::EventEnvelope;
+use crate::crud::event_broadcaster::{NOTIFICATION_COUNT_UPDATED, WEBHOOK_EVENTS};
+use serde::{Deserialize, Serialize};
+
+/// The original protocol: `"ping"` text and `{"command": ...}` objects. Still the default
+/// so existing clients keep working.
+pub const PROTOCOL_V1: u8 = 1;
+/// Typed messages tagged with `type`, request ids echoed in acks, filters and errors.
+pub const PROTOCOL_V2: u8 = 2;
+
+// Close codes, in the range reserved for applications
+pub const CLOSE_TOKEN_EXPIRED: u16 = 4001;
+pub const CLOSE_ACCESS_REVOKED: u16 = 4003;
+
+pub fn parse_version(value: Option<&str>) -> Option<u8> {
+    match value {
+        None | Some("1") => Some(PROTOCOL_V1),
+        Some("2") => Some(PROTOCOL_V2),
+        _ => None,
+    }
+}
+
+/// Narrows a subscription to some issues and/or event types. Unset means everything;
+/// with `issue_ids` set, events that aren't about an issue are left out.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct EventFilter {
+    #[serde(default)]
+    pub issue_ids: Option<Vec<i32>>,
+    #[serde(default)]
+    pub event_types: Option<Vec<String>>,
+}
+
+impl EventFilter {
+    pub fn validate(&self) -> Result<(), ProtocolError> {
+        let unknown = self.event_types.iter().flatten().find(|event_type| {
+            !WEBHOOK_EVENTS.contains(&event_type.as_str())
+                && event_type.as_str() != NOTIFICATION_COUNT_UPDATED
+        });
+        match unknown {
+            Some(event_type) => Err(ProtocolError::new(
+                ErrorCode::InvalidMessage,
+                format!("Unknown event type '{}'", event_type),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub fn matches(&self, envelope: &EventEnvelope) -> bool {
+        let event_type = envelope.event.event_type();
+        let type_matches = self
+            .event_types
+            .as_ref()
+            .is_none_or(|types| types.iter().any(|t| t == event_type));
+        let issue_matches = self.issue_ids.as_ref().is_none_or(|ids| {
+            envelope
+                .event
+                .issue_id()
+                .is_some_and(|issue_id| ids.contains(&issue_id))
+        });
+        type_matches && issue_matches
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    Ping {
+        #[serde(default)]
+        id: Option<u64>,
+    },
+    Subscribe {
+        #[serde(default)]
+        id: Option<u64>,
+        project_id: i32,
+        // Sequence of the last event the client saw, to replay what it missed
+        #[serde(default)]
+        after: Option<i64>,
+        #[serde(default)]
+        filter: EventFilter,
+    },
+    Unsubscribe {
+        #[serde(default)]
+        id: Option<u64>,
+        project_id: i32,
+    },
+    // Swaps in a fresh token before the current one expires
+    Authenticate {
+        #[serde(default)]
+        id: Option<u64>,
+        token: String,
+    },
+    // What the user is looking at; null when they navigate away
+    Focus {
+        #[serde(default)]
+        id: Option<u64>,
+        project_id: i32,
+        target: Option<Target>,
+    },
+    // Sent when editing starts and with each keystroke batch; null when done
+    Edit {
+        #[serde(default)]
+        id: Option<u64>,
+        project_id: i32,
+        target: Option<Target>,
+        #[serde(default)]
+        typing: bool,
+    },
+}
+
+#[derive(Deserialize)]
+struct LegacyCommand {
+    command: String,
+    project_id: Option<i32>,
+    after: Option<i64>,
+    token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    InvalidMessage,
+    Unauthorized,
+    Forbidden,
+    // More events were missed than one replay covers; reload and subscribe again
+    ResyncRequired,
+    Internal,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProtocolError {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl ProtocolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+pub fn parse_client_message(version: u8, text: &str) -> Result<ClientMessage, ProtocolError> {
+    let invalid =
+        |e: serde_json::Error| ProtocolError::new(ErrorCode::InvalidMessage, e.to_string());
+    if version == PROTOCOL_V2 {
+        return serde_json::from_str(text).map_err(invalid);
+    }
+
+    if text == "ping" {
+        return Ok(ClientMessage::Ping { id: None });
+    }
+    let command: LegacyCommand = serde_json::from_str(text).map_err(invalid)?;
+    if command.command == "authenticate" {
+        let token = command
+            .token
+            .ok_or_else(|| ProtocolError::new(ErrorCode::InvalidMessage, "token is required"))?;
+        return Ok(ClientMessage::Authenticate { id: None, token });
+    }
+    let project_id = command
+        .project_id
+        .ok_or_else(|| ProtocolError::new(ErrorCode::InvalidMessage, "project_id is required"))?;
+    match command.command.as_str() {
+        "subscribe" => Ok(ClientMessage::Subscribe {
+            id: None,
+            project_id,
+            after: command.after,
+            filter: EventFilter::default(),
+        }),
+        "unsubscribe" => Ok(ClientMessage::Unsubscribe {
+            id: None,
+            project_id,
+        }),
+        other => Err(ProtocolError::new(
+            ErrorCode::InvalidMessage,
+            format!("Unknown command '{}'", other),
+        )),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage<'a> {
+    Welcome {
+        version: u8,
+        user_id: i32,
+    },
+    Pong {
+        id: Option<u64>,
+    },
+    Subscribed {
+        id: Option<u64>,
+        project_id: i32,
+        // Events after this one follow, replayed ones first
+        sequence: i64,
+    },
+    Unsubscribed {
+        id: Option<u64>,
+        project_id: i32,
+        // Set when the server ended the subscription, e.g. "access_revoked"
+        #[serde(skip_serializing_if = "Option::is_none")]
+        reason: Option<&'static str>,
+    },
+    Authenticated {
+        id: Option<u64>,
+        // Unix time the new token expires
+        expires_at: usize,
+    },
+    // Sent once shortly before the token expires; answer with authenticate
+    TokenExpiring {
+        expires_at: usize,
+    },
+    Event(&'a EventEnvelope),
+    // Everyone in a project, sent after subscribing
+    Presence {
+        project_id: i32,
+        users: Vec<UserPresence>,
+    },
+    PresenceChanged(&'a PresenceChange),
+    Focused {
+        id: Option<u64>,
+        project_id: i32,
+        target: Option<Target>,
+    },
+    Editing {
+        id: Option<u64>,
+        project_id: i32,
+        target: Option<Target>,
+        typing: bool,
+        // Other users editing the same thing; a warning, not a refusal
+        locked_by: Vec<i32>,
+    },
+    Error {
+        id: Option<u64>,
+        #[serde(flatten)]
+        error: ProtocolError,
+    },
+}
+
+#[derive(Serialize)]
+struct LegacyReply<'a> {
+    event: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    project_id: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    message: Option<&'a str>,
+}
+
+/// The text frame for `message` in the socket's protocol version, if that version has one.
+pub fn render(version: u8, message: &ServerMessage) -> Option<String> {
+    if version == PROTOCOL_V2 {
+        return serde_json::to_string(message).ok();
+    }
+
+    let reply = match message {
+        ServerMessage::Welcome { .. }
+        | ServerMessage::Presence { .. }
+        | ServerMessage::PresenceChanged(_)
+        | ServerMessage::Focused { .. }
+        | ServerMessage::Editing { .. } => return None,
+        ServerMessage::Pong { .. } => return Some("pong".to_string()),
+        ServerMessage::Event(envelope) => return serde_json::to_string(envelope).ok(),
+        ServerMessage::Subscribed { project_id, .. } => LegacyReply {
+            event: "subscribed",
+            project_id: Some(*project_id),
+            message: None,
+        },
+        ServerMessage::Unsubscribed { project_id, .. } => LegacyReply {
+            event: "unsubscribed",
+            project_id: Some(*project_id),
+            message: None,
+        },
+        ServerMessage::Authenticated { .. } => LegacyReply {
+            event: "authenticated",
+            project_id: None,
+            message: None,
+        },
+        ServerMessage::TokenExpiring { .. } => LegacyReply {
+            event: "token_expiring",
+            project_id: None,
+            message: None,
+        },
+        ServerMessage::Error { error, .. } => LegacyReply {
+            event: "error",
+            project_id: None,
+            message: Some(&error.message),
+        },
+    };
+    serde_json::to_string(&reply).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crud::domain_event::DomainEvent;
+    use chrono::Utc;
+
+    fn envelope(event: DomainEvent) -> EventEnvelope {
+        EventEnvelope {
+            sequence: 7,
+            project_id: 1,
+            user_id: None,
+            event,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_both_versions_parse_to_the_same_messages() {
+        let legacy = parse_client_message(
+            PROTOCOL_V1,
+            r#"{"command": "subscribe", "project_id": 3, "after": 40}"#,
+        );
+        let typed = parse_client_message(
+            PROTOCOL_V2,
+            r#"{"type": "subscribe", "project_id": 3, "after": 40}"#,
+        );
+        assert_eq!(legacy, typed);
+        assert_eq!(
+            parse_client_message(PROTOCOL_V1, "ping"),
+            Ok(ClientMessage::Ping { id: None })
+        );
+        let error = parse_client_message(PROTOCOL_V1, r#"{"command": "subscribe"}"#);
+        assert_eq!(error.unwrap_err().code, ErrorCode::InvalidMessage);
+        assert_eq!(
+            parse_client_message(
+                PROTOCOL_V1,
+                r#"{"command": "authenticate", "token": "Bearer abc"}"#
+            ),
+            parse_client_message(
+                PROTOCOL_V2,
+                r#"{"type": "authenticate", "token": "Bearer abc"}"#
+            )
+        );
+    }
+
+    #[test]
+    fn test_filters() {
+        let filter = EventFilter {
+            issue_ids: Some(vec![9]),
+            event_types: Some(vec!["estimation_committed".to_string()]),
+        };
+        assert!(filter.validate().is_ok());
+        let committed = |issue_id| {
+            envelope(DomainEvent::EstimationCommitted {
+                session_id: 1,
+                issue_id,
+                points: 3,
+            })
+        };
+        assert!(filter.matches(&committed(9)));
+        assert!(!filter.matches(&committed(10)));
+        assert!(!filter.matches(&envelope(DomainEvent::TagDeleted { id: 9 })));
+        assert!(EventFilter::default().matches(&envelope(DomainEvent::TagDeleted { id: 9 })));
+
+        let unknown = EventFilter {
+            issue_ids: None,
+            event_types: Some(vec!["issue_exploded".to_string()]),
+        };
+        assert!(unknown.validate().is_err());
+    }
+
+    #[test]
+    fn test_replies_per_version() {
+        let subscribed = ServerMessage::Subscribed {
+            id: Some(4),
+            project_id: 3,
+            sequence: 40,
+        };
+        assert_eq!(
+            render(PROTOCOL_V1, &subscribed).unwrap(),
+            r#"{"event":"subscribed","project_id":3}"#
+        );
+        assert_eq!(
+            render(PROTOCOL_V2, &subscribed).unwrap(),
+            r#"{"type":"subscribed","id":4,"project_id":3,"sequence":40}"#
+        );
+
+        let event = envelope(DomainEvent::TagDeleted { id: 9 });
+        let typed: serde_json::Value =
+            serde_json::from_str(&render(PROTOCOL_V2, &ServerMessage::Event(&event)).unwrap())
+                .unwrap();
+        assert_eq!(typed["type"], "event");
+        assert_eq!(typed["event_type"], "tag_deleted");
+        assert_eq!(typed["data"]["id"], 9);
+        assert_eq!(typed["sequence"], 7);
+        assert!(render(
+            PROTOCOL_V1,
+            &ServerMessage::Welcome {
+                version: 1,
+                user_id: 1
+            }
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,403 @@
+use super::presence::{
+    ConnectionKey, Presence, PresenceChange, PresenceRegistry, Target, UserPresence, REMOTE_TTL,
+};
+use crate::crud::domain_event::EventEnvelope;
+use crate::crud::event_broadcaster::EventSender;
+use crate::event_fanout::EventFanout;
+use sea_orm::{ConnectionTrait, DatabaseConnection, DbBackend, Statement};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+// Events a socket may have queued before it counts as behind and replays instead
+const CONNECTION_BUFFER: usize = 256;
+// How often typing indicators and edit locks are checked for timeouts
+const SWEEP_INTERVAL: Duration = Duration::from_secs(5);
+// How often this instance's connections are re-announced to the others
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(30);
+
+pub const PRESENCE_CHANNEL: &str = "phoenix_presence";
+
+/// Something for a socket to pass on.
+pub enum Routed {
+    Event(Arc<EventEnvelope>),
+    Presence(Arc<PresenceChange>),
+}
+
+/// A connection's presence as sent between instances over Postgres.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PresenceAnnouncement {
+    key: ConnectionKey,
+    project_id: i32,
+    user_id: i32,
+    presence: Option<Presence>,
+}
+
+struct Connection {
+    user_id: i32,
+    tx: mpsc::Sender<Routed>,
+    lagged: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct Routes {
+    connections: HashMap<u64, Connection>,
+    projects: HashMap<i32, HashSet<u64>>,
+}
+
+/// Hands each event only to the sockets subscribed to its project (and, for personal
+/// events, belonging to its user), so sockets never look at other projects' traffic.
+/// Also keeps track of who is in each project.
+pub struct EventRouter {
+    routes: RwLock<Routes>,
+    presence: Mutex<PresenceRegistry>,
+    next_id: AtomicU64,
+    // Tells connections apart from those of other instances
+    instance: u64,
+    // Set when presence is shared with other instances
+    db: Option<DatabaseConnection>,
+}
+
+impl EventRouter {
+    /// Starts routing everything sent on `tx`, and timing out presence.
+    pub fn spawn(tx: &EventSender, db: &DatabaseConnection) -> Arc<Self> {
+        let router = Arc::new(Self {
+            routes: RwLock::default(),
+            presence: Mutex::default(),
+            next_id: AtomicU64::new(0),
+            instance: rand::random(),
+            db: (EventFanout::configured() == EventFanout::Postgres).then(|| db.clone()),
+        });
+
+        let mut rx = tx.subscribe();
+        let routing = router.clone();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(envelope) => routing.route(envelope),
+                    Err(RecvError::Lagged(skipped)) => {
+                        warn!("Event router fell behind by {} events", skipped);
+                        routing.mark_all_lagged();
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+
+        let sweeping = router.clone();
+        tokio::spawn(async move {
+            let mut sweep = tokio::time::interval(SWEEP_INTERVAL);
+            let mut heartbeat = tokio::time::interval(HEARTBEAT_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = sweep.tick() => sweeping.sweep(),
+                    _ = heartbeat.tick() => sweeping.heartbeat(),
+                }
+            }
+        });
+        router
+    }
+
+    pub fn connect(self: &Arc<Self>, user_id: i32) -> RoutedConnection {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = mpsc::channel(CONNECTION_BUFFER);
+        let lagged = Arc::new(AtomicBool::new(false));
+        self.write().connections.insert(
+            id,
+            Connection {
+                user_id,
+                tx,
+                lagged: lagged.clone(),
+            },
+        );
+        RoutedConnection {
+            router: self.clone(),
+            id,
+            user_id,
+            rx,
+            lagged,
+        }
+    }
+
+    /// Applies a presence announcement from another instance.
+    pub fn apply_remote(&self, announcement: PresenceAnnouncement) {
+        if announcement.key.instance == self.instance {
+            return;
+        }
+        let now = Instant::now();
+        let change = self.registry().set(
+            announcement.key,
+            announcement.project_id,
+            announcement.user_id,
+            announcement.presence,
+            now,
+            Some(now + REMOTE_TTL),
+        );
+        self.route_presence(change);
+    }
+
+    fn route(&self, envelope: Arc<EventEnvelope>) {
+        let routes = self.read();
+        let Some(ids) = routes.projects.get(&envelope.project_id) else {
+            return;
+        };
+        for connection in ids.iter().filter_map(|id| routes.connections.get(id)) {
+            if !envelope.visible_to(connection.user_id) {
+                continue;
+            }
+            if let Err(mpsc::error::TrySendError::Full(_)) =
+                connection.tx.try_send(Routed::Event(envelope.clone()))
+            {
+                debug!("Socket for user {} is behind", connection.user_id);
+                connection.lagged.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+
+    // Presence is only ever current, so a socket too far behind just misses a change
+    fn route_presence(&self, changes: impl IntoIterator<Item = PresenceChange>) {
+        let routes = self.read();
+        for change in changes {
+            let change = Arc::new(change);
+            let Some(ids) = routes.projects.get(&change.project_id) else {
+                continue;
+            };
+            for connection in ids.iter().filter_map(|id| routes.connections.get(id)) {
+                let _ = connection.tx.try_send(Routed::Presence(change.clone()));
+            }
+        }
+    }
+
+    fn update_presence(
+        &self,
+        connection: u64,
+        project_id: i32,
+        user_id: i32,
+        presence: Option<Presence>,
+    ) {
+        let key = self.key(connection);
+        let change = self.registry().set(
+            key,
+            project_id,
+            user_id,
+            presence.clone(),
+            Instant::now(),
+            None,
+        );
+        self.route_presence(change);
+        self.announce(vec![PresenceAnnouncement {
+            key,
+            project_id,
+            user_id,
+            presence,
+        }]);
+    }
+
+    fn sweep(&self) {
+        let (touched, changes) = self.registry().sweep(Instant::now());
+        self.route_presence(changes);
+        let announcements = {
+            let registry = self.registry();
+            touched
+                .into_iter()
+                .filter(|(key, _)| key.instance == self.instance)
+                .filter_map(|(key, project_id)| {
+                    let presence = registry.local_presence(key, project_id)?;
+                    let user_id = self.read().connections.get(&key.connection)?.user_id;
+                    Some(PresenceAnnouncement {
+                        key,
+                        project_id,
+                        user_id,
+                        presence: Some(presence),
+                    })
+                })
+                .collect()
+        };
+        self.announce(announcements);
+    }
+
+    fn heartbeat(&self) {
+        if self.db.is_none() {
+            return;
+        }
+        let announcements = self
+            .registry()
+            .connections_of(self.instance)
+            .into_iter()
+            .map(
+                |(key, project_id, user_id, presence)| PresenceAnnouncement {
+                    key,
+                    project_id,
+                    user_id,
+                    presence: Some(presence),
+                },
+            )
+            .collect();
+        self.announce(announcements);
+    }
+
+    fn announce(&self, announcements: Vec<PresenceAnnouncement>) {
+        let Some(db) = self.db.clone() else {
+            return;
+        };
+        if announcements.is_empty() {
+            return;
+        }
+        tokio::spawn(async move {
+            for announcement in announcements {
+                let Ok(payload) = serde_json::to_string(&announcement) else {
+                    continue;
+                };
+                let notify = db
+                    .execute(Statement::from_sql_and_values(
+                        DbBackend::Postgres,
+                        "SELECT pg_notify($1, $2)",
+                        [PRESENCE_CHANNEL.into(), payload.into()],
+                    ))
+                    .await;
+                if let Err(e) = notify {
+                    warn!("Failed to announce presence: {}", e);
+                }
+            }
+        });
+    }
+
+    fn mark_all_lagged(&self) {
+        for connection in self.read().connections.values() {
+            connection.lagged.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn key(&self, connection: u64) -> ConnectionKey {
+        ConnectionKey {
+            instance: self.instance,
+            connection,
+        }
+    }
+
+    // A panic while holding a lock can't leave the maps half-updated, so carry on
+    fn read(&self) -> std::sync::RwLockReadGuard<'_, Routes> {
+        self.routes.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> std::sync::RwLockWriteGuard<'_, Routes> {
+        self.routes.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn registry(&self) -> std::sync::MutexGuard<'_, PresenceRegistry> {
+        self.presence.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// One socket's registration. Unregisters itself, and leaves every project, when dropped.
+pub struct RoutedConnection {
+    router: Arc<EventRouter>,
+    id: u64,
+    user_id: i32,
+    rx: mpsc::Receiver<Routed>,
+    lagged: Arc<AtomicBool>,
+}
+
+impl RoutedConnection {
+    /// Starts routing the project's events here and marks the user present in it.
+    pub fn subscribe(&self, project_id: i32) {
+        self.router
+            .write()
+            .projects
+            .entry(project_id)
+            .or_default()
+            .insert(self.id);
+        let presence = self.presence(project_id).unwrap_or_default();
+        self.router
+            .update_presence(self.id, project_id, self.user_id, Some(presence));
+    }
+
+    pub fn unsubscribe(&self, project_id: i32) {
+        {
+            let mut routes = self.router.write();
+            if let Some(ids) = routes.projects.get_mut(&project_id) {
+                ids.remove(&self.id);
+                if ids.is_empty() {
+                    routes.projects.remove(&project_id);
+                }
+            }
+        }
+        self.router
+            .update_presence(self.id, project_id, self.user_id, None);
+    }
+
+    pub fn users(&self, project_id: i32) -> Vec<UserPresence> {
+        self.router.registry().users(project_id)
+    }
+
+    pub fn focus(&self, project_id: i32, viewing: Option<Target>) {
+        let presence = Presence {
+            viewing,
+            ..self.presence(project_id).unwrap_or_default()
+        };
+        self.router
+            .update_presence(self.id, project_id, self.user_id, Some(presence));
+    }
+
+    /// Records what this connection is editing and returns the other users editing it.
+    pub fn edit(&self, project_id: i32, editing: Option<Target>, typing: bool) -> Vec<i32> {
+        let presence = Presence {
+            editing,
+            typing: typing && editing.is_some(),
+            ..self.presence(project_id).unwrap_or_default()
+        };
+        self.router
+            .update_presence(self.id, project_id, self.user_id, Some(presence));
+        match editing {
+            Some(target) => self
+                .router
+                .registry()
+                .editors(project_id, target, self.user_id),
+            None => Vec::new(),
+        }
+    }
+
+    pub async fn recv(&mut self) -> Option<Routed> {
+        self.rx.recv().await
+    }
+
+    /// Whether events were dropped since the last call; the socket should replay.
+    pub fn take_lagged(&self) -> bool {
+        self.lagged.swap(false, Ordering::Relaxed)
+    }
+
+    fn presence(&self, project_id: i32) -> Option<Presence> {
+        self.router
+            .registry()
+            .local_presence(self.router.key(self.id), project_id)
+    }
+}
+
+impl Drop for RoutedConnection {
+    fn drop(&mut self) {
+        let projects: Vec<i32> = {
+            let mut routes = self.router.write();
+            routes.connections.remove(&self.id);
+            let projects = routes
+                .projects
+                .iter()
+                .filter(|(_, ids)| ids.contains(&self.id))
+                .map(|(project_id, _)| *project_id)
+                .collect();
+            routes.projects.retain(|_, ids| {
+                ids.remove(&self.id);
+                !ids.is_empty()
+            });
+            projects
+        };
+        for project_id in projects {
+            self.router
+                .update_presence(self.id, project_id, self.user_id, None);
+        }
+    }
+}